@@ -0,0 +1,201 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 画像解析関連の処理をまとめたモジュール
+//!
+
+use image::{GrayImage, Primitive, RgbaImage};
+
+use crate::pixel::{WorkImage, WorkPixel};
+
+///
+/// 平均勾配がこの値以上の画像を最も複雑な画像として扱う
+///
+const SATURATED_GRADIENT: f32 = 24.0;
+
+///
+/// 領域の平均輝度の算出で一辺あたりに調べる画素数の目安
+///
+const LUMA_SAMPLES: u32 = 64;
+
+///
+/// ピクセルの輝度値の算出
+///
+/// # 引数
+/// * `pixel` - 対象のピクセル値(RGBまたはRGBAのチャネルの値)
+///
+/// # 戻り値
+/// ITU-R BT.601の係数で算出した輝度値を返す(チャネルの値と同じ尺度)
+///
+pub(crate) fn luma<T>(pixel: &[T]) -> f32
+where
+    T: Copy + Into<f32>,
+{
+    0.299 * pixel[0].into() + 0.587 * pixel[1].into() + 0.114 * pixel[2].into()
+}
+
+///
+/// 作業用のピクセル形式のピクセルの輝度値の算出
+///
+/// # 引数
+/// * `pixel` - 対象のピクセル値
+///
+/// # 戻り値
+/// 8ビットの範囲(0.0〜255.0)に換算した輝度値を返す。
+///
+pub(crate) fn pixel_luma<T>(pixel: &T) -> f32
+where
+    T: WorkPixel,
+{
+    let max: f32 = T::Subpixel::DEFAULT_MAX_VALUE.into();
+    let rgb = pixel.to_rgb().0.map(|value| {
+        let value: f32 = value.into();
+        value / max * 255.0
+    });
+
+    luma(&rgb)
+}
+
+///
+/// 画像の平均輝度の算出
+///
+/// # 引数
+/// * `image` - 対象の画像データ(ロゴ画像など)
+///
+/// # 戻り値
+/// アルファ値で重み付けした平均輝度(0.0〜255.0)を返す。完全に透明な画像の
+/// 場合は`None`を返す。
+///
+pub(crate) fn mean_luma(image: &RgbaImage) -> Option<f32> {
+    let mut total = 0.0f64;
+    let mut weight = 0.0f64;
+
+    for pixel in image.pixels() {
+        let alpha = pixel[3] as f64;
+
+        total += luma(&pixel.0) as f64 * alpha;
+        weight += alpha;
+    }
+
+    (weight > 0.0).then(|| (total / weight) as f32)
+}
+
+///
+/// 画像の矩形領域の平均輝度の算出
+///
+/// # 引数
+/// * `image` - 対象の画像データ
+/// * `(x, y, width, height)` - 領域の左上の座標と大きさ(画像からはみ出す部
+///   分は無視する)
+///
+/// # 戻り値
+/// 領域の平均輝度(0.0〜255.0)を返す。領域が画像に掛からない場合は`None`を
+/// 返す。
+///
+/// # 注記
+/// 大きな領域では一辺あたり`LUMA_SAMPLES`画素程度に間引いて調べる。
+///
+pub(crate) fn region_luma<T>(
+    image: &WorkImage<T>,
+    (x, y, width, height): (i64, i64, u32, u32),
+) -> Option<f32>
+where
+    T: WorkPixel,
+{
+    let left = x.clamp(0, image.width() as i64) as u32;
+    let top = y.clamp(0, image.height() as i64) as u32;
+    let right = (x + width as i64).clamp(0, image.width() as i64) as u32;
+    let bottom = (y + height as i64).clamp(0, image.height() as i64) as u32;
+
+    if left >= right || top >= bottom {
+        return None;
+    }
+
+    let step = ((right - left).max(bottom - top) / LUMA_SAMPLES).max(1);
+    let mut total = 0.0f64;
+    let mut count = 0usize;
+
+    for y in (top..bottom).step_by(step as usize) {
+        for x in (left..right).step_by(step as usize) {
+            total += pixel_luma(image.get_pixel(x, y)) as f64;
+            count += 1;
+        }
+    }
+
+    Some((total / count as f64) as f32)
+}
+
+///
+/// 画像の複雑さの推定
+///
+/// # 引数
+/// * `image` - 解析対象の画像データ
+///
+/// # 戻り値
+/// 画像の複雑さを0.0(平坦)〜1.0(高精細・高ノイズ)の範囲で返す。
+///
+/// # 注記
+/// 隣接画素間の輝度差の平均値を複雑さの指標として用いる。ベタ塗りのグラフィ
+/// ックスでは小さく、細部の多い写真やノイズの多い画像では大きな値となる。
+///
+pub(crate) fn estimate_complexity(image: &RgbaImage) -> f32 {
+    complexity(image.width(), image.height(), |x, y| {
+        luma(&image.get_pixel(x, y).0)
+    })
+}
+
+///
+/// グレースケール画像の複雑さの推定
+///
+/// # 引数
+/// * `image` - 解析対象の画像データ
+///
+/// # 戻り値
+/// 画像の複雑さを0.0(平坦)〜1.0(高精細・高ノイズ)の範囲で返す。
+///
+pub(crate) fn estimate_gray_complexity(image: &GrayImage) -> f32 {
+    complexity(image.width(), image.height(), |x, y| {
+        image.get_pixel(x, y).0[0] as f32
+    })
+}
+
+///
+/// 輝度値の取得関数を用いた複雑さの算出
+///
+/// # 引数
+/// * `width` - 画像の幅
+/// * `height` - 画像の高さ
+/// * `luma_at` - 指定座標の輝度値を返す関数
+///
+/// # 戻り値
+/// 画像の複雑さを0.0(平坦)〜1.0(高精細・高ノイズ)の範囲で返す。
+///
+fn complexity<F>(width: u32, height: u32, luma_at: F) -> f32
+where
+    F: Fn(u32, u32) -> f32
+{
+    if width < 2 || height < 2 {
+        return 0.0;
+    }
+
+    let mut total = 0.0f64;
+
+    for y in 0..(height - 1) {
+        for x in 0..(width - 1) {
+            let center = luma_at(x, y);
+            let right = luma_at(x + 1, y);
+            let below = luma_at(x, y + 1);
+
+            total += ((center - right).abs() + (center - below).abs()) as f64;
+        }
+    }
+
+    let count = ((width - 1) * (height - 1)) as f64;
+    let gradient = (total / count / 2.0) as f32;
+
+    (gradient / SATURATED_GRADIENT).clamp(0.0, 1.0)
+}
@@ -0,0 +1,69 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! クリップボードとの画像の受け渡し処理をまとめたモジュール
+//!
+
+use std::borrow::Cow;
+
+use anyhow::{anyhow, Result};
+use arboard::{Clipboard, ImageData};
+use image::RgbaImage;
+
+#[cfg(target_os = "linux")]
+use arboard::SetExtLinux;
+
+///
+/// クリップボードからの画像の読み込み
+///
+/// # 戻り値
+/// 読み込みに成功した場合は画像データを`Ok()`でラップして返す。クリップボー
+/// ドに画像が無い場合や読み込みに失敗した場合はエラー情報を`Err()`でラップし
+/// て返す。
+///
+pub(crate) fn read_image() -> Result<RgbaImage> {
+    let data = Clipboard::new()?.get_image()?;
+
+    RgbaImage::from_raw(
+        data.width as u32,
+        data.height as u32,
+        data.bytes.into_owned()
+    ).ok_or_else(|| anyhow!("invalid clipboard image"))
+}
+
+///
+/// クリップボードへの画像の書き込み
+///
+/// # 引数
+/// * `image` - 書き込む画像データ
+///
+/// # 戻り値
+/// 書き込みに成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を
+/// `Err()`でラップして返す。
+///
+/// # 注記
+/// Linux(X11/Wayland)ではクリップボードの内容は書き込んだプロセスが保持する
+/// ので、他のアプリケーションが内容を受け取る(クリップボードが置き換えられ
+/// る)まで戻らない。
+///
+pub(crate) fn write_image(image: RgbaImage) -> Result<()> {
+    let data = ImageData {
+        width: image.width() as usize,
+        height: image.height() as usize,
+        bytes: Cow::Owned(image.into_raw()),
+    };
+
+    let mut clipboard = Clipboard::new()?;
+
+    #[cfg(target_os = "linux")]
+    clipboard.set().wait().image(data)?;
+
+    #[cfg(not(target_os = "linux"))]
+    clipboard.set_image(data)?;
+
+    Ok(())
+}
@@ -12,11 +12,33 @@ use std::str::FromStr;
 use std::path::Path;
 use std::path::PathBuf;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Deserializer};
+use toml::{Table, Value};
 
+use super::Align;
+use super::Backdrop;
+use super::ChromaSubsampling;
+use super::Color;
+use super::ColorDepth;
+use super::Length;
+use super::LogoOffset;
+use super::LogoScale;
+use super::OutputFormat;
+use super::PngCompression;
+use super::PostFailure;
 use super::Position;
+use super::Quality;
+use super::QualityPreset;
+use super::QualityRange;
 use super::Resolution;
+use super::Spacing;
+use super::TintMode;
+use super::watermark::{OrientationRules, PlacementOverride, WatermarkSpec};
+use crate::hdr::HdrGrouping;
+use crate::lut::ToneCurve;
+use crate::pixel::Dither;
+use crate::sharpen::SharpenMode;
 
 ///
 /// デシリアライズ用の&strからenumへの変換の為の中継関数
@@ -36,13 +58,106 @@ where
     }
 }
 
+///
+/// 出力品質のデシリアライズ
+///
+/// # 注記
+/// 数値(1〜100)と文字列("auto"または数値)のいずれでも指定できるようにする。
+///
+fn quality_from_value<'de, D>(deserializer: D)
+    -> Result<Option<Quality>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::Integer(quality)) => {
+            Quality::from_str(&quality.to_string())
+                .map(Some)
+                .map_err(serde::de::Error::custom)
+        }
+
+        Some(Value::String(s)) => {
+            Quality::from_str(&s).map(Some).map_err(serde::de::Error::custom)
+        }
+
+        Some(value) => Err(serde::de::Error::custom(
+            format!("品質の指定が無効: {}", value)
+        )),
+
+        None => Ok(None),
+    }
+}
+
+///
+/// 出力画像の寸法の揃え方のデシリアライズ
+///
+/// # 注記
+/// 数値(8または16)と文字列("even"など)のいずれでも指定できるようにする。
+///
+fn align_from_value<'de, D>(deserializer: D)
+    -> Result<Option<Align>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::Integer(multiple)) => {
+            Align::from_str(&multiple.to_string())
+                .map(Some)
+                .map_err(serde::de::Error::custom)
+        }
+
+        Some(Value::String(s)) => {
+            Align::from_str(&s).map(Some).map_err(serde::de::Error::custom)
+        }
+
+        Some(value) => Err(serde::de::Error::custom(
+            format!("寸法の揃え方の指定が無効: {}", value)
+        )),
+
+        None => Ok(None),
+    }
+}
+
+///
+/// ロゴ関連の設定のデシリアライズ
+///
+/// # 注記
+/// `[logo]`テーブル1つと`[[logo]]`によるテーブルの配列のいずれでも指定でき
+/// るようにする。
+///
+fn logo_from_value<'de, D>(deserializer: D)
+    -> Result<Vec<LogoInfo>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        Some(value @ Value::Table(_)) => {
+            LogoInfo::deserialize(value)
+                .map(|logo| vec![logo])
+                .map_err(serde::de::Error::custom)
+        }
+
+        Some(value @ Value::Array(_)) => {
+            Vec::<LogoInfo>::deserialize(value)
+                .map_err(serde::de::Error::custom)
+        }
+
+        Some(value) => Err(serde::de::Error::custom(
+            format!("ロゴの設定が無効: {}", value)
+        )),
+
+        None => Ok(Vec::new()),
+    }
+}
+
 ///
 /// コンフィギュレーションデータを集約する構造体
 ///
 #[derive(Debug, Deserialize)]
 pub(super) struct Config {
-    /// ロゴ関連の設定情報の格納先
-    logo: Option<LogoInfo>,
+    /// ロゴ関連の設定情報の格納先(先頭以外は追加の透かし)
+    #[serde(default, deserialize_with = "logo_from_value")]
+    logo: Vec<LogoInfo>,
 
     /// レンダリング関連の設定情報の格納先
     output: Option<OutputInfo>,
@@ -54,38 +169,659 @@ impl Config {
     //
     pub(super) fn logo_file_path(&self) -> Option<PathBuf> {
         self.logo
+            .first()
+            .and_then(|logo| logo.file_path.as_ref())
+            .cloned()
+    }
+
+    ///
+    /// 明るい背景用のロゴで使用するファイルへのパスへのアクセサ
+    ///
+    pub(super) fn logo_dark_file_path(&self) -> Option<PathBuf> {
+        self.logo.first().and_then(|logo| logo.dark_file_path.clone())
+    }
+
+    ///
+    /// 透かしとして描画する文字列へのアクセサ
+    ///
+    pub(super) fn text(&self) -> Option<String> {
+        self.logo.first().and_then(|logo| logo.text.clone())
+    }
+
+    ///
+    /// 文字列の描画に使用するフォントへのアクセサ
+    ///
+    pub(super) fn font(&self) -> Option<PathBuf> {
+        self.logo.first().and_then(|logo| logo.font.clone())
+    }
+
+    ///
+    /// 文字列の描画サイズへのアクセサ
+    ///
+    pub(super) fn text_size(&self) -> Option<f32> {
+        self.logo.first().and_then(|logo| logo.text_size)
+    }
+
+    ///
+    /// 文字列の色へのアクセサ
+    ///
+    pub(super) fn text_color(&self) -> Option<Color> {
+        self.logo.first().and_then(|logo| logo.text_color)
+    }
+
+    ///
+    /// ロゴの展開位置へのアクセサ
+    ///
+    pub(super) fn logo_position(&self) -> Option<Position> {
+        self.logo
+            .first()
+            .and_then(|logo| logo.position.as_ref())
+            .cloned()
+    }
+
+    ///
+    /// ロゴの不透明度へのアクセサ
+    ///
+    pub(super) fn logo_opacity(&self) -> Option<f32> {
+        self.logo.first().and_then(|logo| logo.opacity)
+    }
+
+    ///
+    /// ロゴの大きさの指定へのアクセサ
+    ///
+    pub(super) fn logo_scale(&self) -> Option<LogoScale> {
+        self.logo.first().and_then(|logo| logo.scale)
+    }
+
+    ///
+    /// ロゴの大きさの上限へのアクセサ
+    ///
+    pub(super) fn logo_max_coverage(&self) -> Option<f32> {
+        self.logo.first().and_then(|logo| logo.max_coverage)
+    }
+
+    ///
+    /// 縮小したロゴのシャープ化の強度へのアクセサ
+    ///
+    pub(super) fn logo_sharpen(&self) -> Option<f32> {
+        self.logo.first().and_then(|logo| logo.sharpen)
+    }
+
+    ///
+    /// ロゴの回転角へのアクセサ
+    ///
+    pub(super) fn logo_rotation(&self) -> Option<f32> {
+        self.logo.first().and_then(|logo| logo.rotation)
+    }
+
+    ///
+    /// ロゴの縁取りの幅へのアクセサ
+    ///
+    pub(super) fn logo_outline(&self) -> Option<u32> {
+        self.logo.first().and_then(|logo| logo.outline)
+    }
+
+    ///
+    /// ロゴの影のぼかしの半径へのアクセサ
+    ///
+    pub(super) fn logo_shadow(&self) -> Option<u32> {
+        self.logo.first().and_then(|logo| logo.shadow)
+    }
+
+    ///
+    /// ロゴの縁取りと影の色へのアクセサ
+    ///
+    pub(super) fn logo_halo_color(&self) -> Option<Color> {
+        self.logo.first().and_then(|logo| logo.halo_color)
+    }
+
+    ///
+    /// ロゴの背後に描画する背景へのアクセサ
+    ///
+    pub(super) fn logo_backdrop(&self) -> Option<Backdrop> {
+        self.logo.first().and_then(|logo| logo.backdrop)
+    }
+
+    ///
+    /// ロゴの背後に描画する背景を広げる幅へのアクセサ
+    ///
+    pub(super) fn logo_backdrop_padding(&self) -> Option<u32> {
+        self.logo.first().and_then(|logo| logo.backdrop_padding)
+    }
+
+    ///
+    /// ロゴの背後に描画する背景の角の丸めの半径へのアクセサ
+    ///
+    pub(super) fn logo_backdrop_radius(&self) -> Option<u32> {
+        self.logo.first().and_then(|logo| logo.backdrop_radius)
+    }
+
+    ///
+    /// ロゴの色付けに用いる色へのアクセサ
+    ///
+    pub(super) fn logo_tint(&self) -> Option<Color> {
+        self.logo.first().and_then(|logo| logo.tint)
+    }
+
+    ///
+    /// ロゴの色付けの方法へのアクセサ
+    ///
+    pub(super) fn logo_tint_mode(&self) -> Option<TintMode> {
+        self.logo.first().and_then(|logo| logo.tint_mode)
+    }
+
+    ///
+    /// ロゴを無彩色にするか否かへのアクセサ
+    ///
+    pub(super) fn logo_grayscale(&self) -> bool {
+        self.logo
+            .first()
+            .and_then(|logo| logo.grayscale)
+            .unwrap_or(false)
+    }
+
+    ///
+    /// ロゴの余白へのアクセサ
+    ///
+    pub(super) fn logo_margin(&self) -> Option<Spacing> {
+        self.logo.first().and_then(|logo| logo.margin)
+    }
+
+    ///
+    /// ロゴの座標の指定へのアクセサ
+    ///
+    pub(super) fn logo_offset(&self) -> Option<LogoOffset> {
+        self.logo.first().and_then(|logo| logo.offset)
+    }
+
+    ///
+    /// ロゴをずらす範囲へのアクセサ
+    ///
+    pub(super) fn logo_jitter(&self) -> Option<Spacing> {
+        self.logo.first().and_then(|logo| logo.jitter)
+    }
+
+    ///
+    /// 出力画像の向きごとのロゴの配置の指定へのアクセサ
+    ///
+    pub(super) fn logo_orientations(&self) -> OrientationRules {
+        self.logo.first().map(LogoInfo::orientations).unwrap_or_default()
+    }
+
+    ///
+    /// ロゴを小数点以下の精度で配置するか否かへのアクセサ
+    ///
+    pub(super) fn logo_subpixel(&self) -> bool {
+        self.logo
+            .first()
+            .and_then(|logo| logo.subpixel)
+            .unwrap_or(false)
+    }
+
+    ///
+    /// ロゴを敷き詰めるか否かへのアクセサ
+    ///
+    pub(super) fn tile(&self) -> bool {
+        self.logo
+            .first()
+            .and_then(|logo| logo.tile)
+            .unwrap_or(false)
+    }
+
+    ///
+    /// ロゴを敷き詰める際の角度へのアクセサ
+    ///
+    pub(super) fn tile_angle(&self) -> Option<f32> {
+        self.logo.first().and_then(|logo| logo.tile_angle)
+    }
+
+    ///
+    /// ロゴを敷き詰める際の間隔へのアクセサ
+    ///
+    pub(super) fn tile_spacing(&self) -> Option<Spacing> {
+        self.logo.first().and_then(|logo| logo.tile_spacing)
+    }
+
+    ///
+    /// パノラマ画像でロゴを繰り返す縦横比へのアクセサ
+    ///
+    pub(super) fn panorama_ratio(&self) -> Option<f32> {
+        self.logo.first().and_then(|logo| logo.panorama_ratio)
+    }
+
+    ///
+    /// パノラマ画像でロゴを繰り返す間隔へのアクセサ
+    ///
+    pub(super) fn panorama_interval(&self) -> Option<Length> {
+        self.logo.first().and_then(|logo| logo.panorama_interval)
+    }
+
+    ///
+    /// 追加の透かしの指定へのアクセサ
+    ///
+    /// # 注記
+    /// `[[logo]]`の2つ目以降のエントリを、記述された順に返す。
+    ///
+    pub(super) fn extra_logos(&self) -> Vec<WatermarkSpec> {
+        self.logo.iter().skip(1).map(LogoInfo::to_spec).collect()
+    }
+
+    ///
+    /// 出力解像度へのアクセサ
+    ///
+    pub(super) fn output_resolution(&self) -> Option<Resolution> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.resolution.as_ref())
+            .cloned()
+    }
+
+    ///
+    /// 出力先へのアクセサ
+    ///
+    pub(super) fn output_path(&self) -> Option<PathBuf> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.output_path.as_ref())
+            .cloned()
+    }
+
+    ///
+    /// 入力ファイルの隣に作成する出力フォルダの名前へのアクセサ
+    ///
+    pub(super) fn output_beside_input(&self) -> Option<String> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.output_beside_input.as_ref())
+            .cloned()
+    }
+
+    ///
+    /// キャンバスサイズへのアクセサ
+    ///
+    pub(super) fn canvas(&self) -> Option<Resolution> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.canvas.as_ref())
+            .cloned()
+    }
+
+    ///
+    /// キャンバスの背景色へのアクセサ
+    ///
+    pub(super) fn canvas_color(&self) -> Option<Color> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.canvas_color.as_ref())
+            .cloned()
+    }
+
+    ///
+    /// キャンバス上での画像の配置位置へのアクセサ
+    ///
+    pub(super) fn canvas_gravity(&self) -> Option<Position> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.canvas_gravity.as_ref())
+            .cloned()
+    }
+
+    ///
+    /// 縁の幅へのアクセサ
+    ///
+    pub(super) fn border(&self) -> Option<u32> {
+        self.output.as_ref().and_then(|output| output.border)
+    }
+
+    ///
+    /// 縁の色へのアクセサ
+    ///
+    pub(super) fn border_color(&self) -> Option<Color> {
+        self.output.as_ref().and_then(|output| output.border_color)
+    }
+
+    ///
+    /// 出力画像の寸法の揃え方へのアクセサ
+    ///
+    pub(super) fn align(&self) -> Option<Align> {
+        self.output.as_ref().and_then(|output| output.align)
+    }
+
+    ///
+    /// 出力品質へのアクセサ
+    ///
+    pub(super) fn quality(&self) -> Option<Quality> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.quality)
+    }
+
+    ///
+    /// 出力設定のプリセットへのアクセサ
+    ///
+    pub(super) fn quality_preset(&self) -> Option<QualityPreset> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.quality_preset)
+    }
+
+    ///
+    /// 品質自動選択の可否へのアクセサ
+    ///
+    pub(super) fn adaptive_quality(&self) -> bool {
+        self.output
+            .as_ref()
+            .and_then(|output| output.adaptive_quality)
+            .unwrap_or(false)
+    }
+
+    ///
+    /// 品質自動選択時の品質範囲へのアクセサ
+    ///
+    pub(super) fn quality_range(&self) -> Option<QualityRange> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.quality_range.as_ref())
+            .cloned()
+    }
+
+    ///
+    /// 出力形式へのアクセサ
+    ///
+    pub(super) fn output_format(&self) -> Option<OutputFormat> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.format)
+    }
+
+    ///
+    /// プログレッシブJPEGで出力するか否かへのアクセサ
+    ///
+    pub(super) fn progressive(&self) -> bool {
+        self.output
+            .as_ref()
+            .and_then(|output| output.progressive)
+            .unwrap_or(false)
+    }
+
+    ///
+    /// WebP出力時の品質へのアクセサ
+    ///
+    pub(super) fn webp_quality(&self) -> Option<u8> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.webp_quality)
+    }
+
+    ///
+    /// JPEG出力時のクロマサブサンプリングへのアクセサ
+    ///
+    pub(super) fn subsampling(&self) -> Option<ChromaSubsampling> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.subsampling)
+    }
+
+    ///
+    /// グレースケールの入力をグレースケールのまま処理するか否かへのアクセサ
+    ///
+    pub(super) fn keep_grayscale(&self) -> bool {
+        self.output
+            .as_ref()
+            .and_then(|output| output.keep_grayscale)
+            .unwrap_or(false)
+    }
+
+    ///
+    /// WebPを可逆圧縮で出力するか否かへのアクセサ
+    ///
+    pub(super) fn webp_lossless(&self) -> bool {
+        self.output
+            .as_ref()
+            .and_then(|output| output.webp_lossless)
+            .unwrap_or(false)
+    }
+
+    ///
+    /// AVIF出力時のエンコード速度へのアクセサ
+    ///
+    pub(super) fn avif_speed(&self) -> Option<u8> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.avif_speed)
+    }
+
+    ///
+    /// PNG出力時の圧縮レベルへのアクセサ
+    ///
+    pub(super) fn png_compression(&self) -> Option<PngCompression> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.png_compression)
+    }
+
+    ///
+    /// 実行記録の書き込み先へのアクセサ
+    ///
+    pub(super) fn run_log(&self) -> Option<PathBuf> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.run_log.as_ref())
+            .cloned()
+    }
+
+    ///
+    /// 前処理コマンドへのアクセサ
+    ///
+    pub(super) fn pre_filter(&self) -> Option<String> {
+        self.output.as_ref().and_then(|output| output.pre_filter.clone())
+    }
+
+    ///
+    /// 後処理コマンドへのアクセサ
+    ///
+    pub(super) fn post_cmd(&self) -> Option<String> {
+        self.output.as_ref().and_then(|output| output.post_cmd.clone())
+    }
+
+    ///
+    /// 同時に実行する後処理コマンドの数の上限へのアクセサ
+    ///
+    pub(super) fn post_jobs(&self) -> Option<usize> {
+        self.output.as_ref().and_then(|output| output.post_jobs)
+    }
+
+    ///
+    /// 後処理コマンドが失敗した場合の扱いへのアクセサ
+    ///
+    pub(super) fn post_failure(&self) -> Option<PostFailure> {
+        self.output.as_ref().and_then(|output| output.post_failure)
+    }
+
+    ///
+    /// 作業用の色深度へのアクセサ
+    ///
+    pub(super) fn depth(&self) -> Option<ColorDepth> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.depth)
+    }
+
+    ///
+    /// ディザリング方式へのアクセサ
+    ///
+    pub(super) fn dither(&self) -> Option<Dither> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.dither)
+    }
+
+    ///
+    /// HDR合成のためのブラケットのまとめ方へのアクセサ
+    ///
+    pub(super) fn hdr_merge(&self) -> Option<HdrGrouping> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.hdr_merge)
+    }
+
+    ///
+    /// タイルに分割する際のタイルの一辺へのアクセサ
+    ///
+    pub(super) fn tile_output(&self) -> Option<u32> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.tile_output)
+    }
+
+    ///
+    /// タイルに分割する際の重なりへのアクセサ
+    ///
+    pub(super) fn tile_overlap(&self) -> Option<u32> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.tile_overlap)
+    }
+
+    ///
+    /// Web向けの出力一式を書き出すか否かへのアクセサ
+    ///
+    pub(super) fn web_bundle(&self) -> bool {
+        self.output
+            .as_ref()
+            .and_then(|output| output.web_bundle)
+            .unwrap_or(false)
+    }
+
+    ///
+    /// Web向けの出力一式に含める縮小版の幅へのアクセサ
+    ///
+    pub(super) fn web_widths(&self) -> Option<Vec<u32>> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.web_widths.clone())
+    }
+
+    ///
+    /// パレットを抽出するか否かへのアクセサ
+    ///
+    pub(super) fn palette(&self) -> bool {
+        self.output
+            .as_ref()
+            .and_then(|output| output.palette)
+            .unwrap_or(false)
+    }
+
+    ///
+    /// キャプションの対応表へのパスへのアクセサ
+    ///
+    pub(super) fn captions(&self) -> Option<PathBuf> {
+        self.output.as_ref().and_then(|output| output.captions.clone())
+    }
+
+    ///
+    /// ロゴをずらす量を決める乱数の種へのアクセサ
+    ///
+    pub(super) fn jitter_seed(&self) -> Option<u64> {
+        self.output.as_ref().and_then(|output| output.jitter_seed)
+    }
+
+    ///
+    /// 代替テキストのテンプレートへのアクセサ
+    ///
+    pub(super) fn alt_text(&self) -> Option<String> {
+        self.output.as_ref().and_then(|output| output.alt_text.clone())
+    }
+
+    ///
+    /// テンプレートの日時に用いるタイムゾーンへのアクセサ
+    ///
+    pub(super) fn timezone(&self) -> Option<String> {
+        self.output.as_ref().and_then(|output| output.timezone.clone())
+    }
+
+    ///
+    /// 代替テキストの対応表へのパスへのアクセサ
+    ///
+    pub(super) fn alt_text_csv(&self) -> Option<PathBuf> {
+        self.output.as_ref().and_then(|output| output.alt_text_csv.clone())
+    }
+
+    ///
+    /// 3D LUTのパスへのアクセサ
+    ///
+    pub(super) fn lut_file(&self) -> Option<PathBuf> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.lut.as_ref())
+            .cloned()
+    }
+
+    ///
+    /// トーンカーブへのアクセサ
+    ///
+    pub(super) fn tone_curve(&self) -> Option<ToneCurve> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.tone_curve.as_ref())
+            .cloned()
+    }
+
+    ///
+    /// シャープ化の強度へのアクセサ
+    ///
+    pub(super) fn sharpen(&self) -> Option<f32> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.sharpen)
+    }
+
+    ///
+    /// シャープ化を適用するチャネルへのアクセサ
+    ///
+    pub(super) fn sharpen_mode(&self) -> Option<SharpenMode> {
+        self.output
+            .as_ref()
+            .and_then(|output| output.sharpen_mode)
+    }
+
+    ///
+    /// ディレクトリ構造を維持するか否かへのアクセサ
+    ///
+    pub(super) fn mirror_tree(&self) -> bool {
+        self.output
             .as_ref()
-            .and_then(|logo| logo.file_path.as_ref())
-            .cloned()
+            .and_then(|output| output.mirror_tree)
+            .unwrap_or(false)
     }
 
     ///
-    /// ロゴの展開位置へのアクセサ
+    /// 付随ファイルをコピーするか否かへのアクセサ
     ///
-    pub(super) fn logo_position(&self) -> Option<Position> {
-        self.logo
+    pub(super) fn copy_sidecars(&self) -> bool {
+        self.output
             .as_ref()
-            .and_then(|logo| logo.position.as_ref())
-            .cloned()
+            .and_then(|output| output.copy_sidecars)
+            .unwrap_or(false)
     }
 
     ///
-    /// 出力解像度へのアクセサ
+    /// 小さい画像をそのままコピーするか否かへのアクセサ
     ///
-    pub(super) fn output_resolution(&self) -> Option<Resolution> {
+    pub(super) fn keep_resolution_if_smaller(&self) -> bool {
         self.output
             .as_ref()
-            .and_then(|output| output.resolution.as_ref())
-            .cloned()
+            .and_then(|output| output.keep_resolution_if_smaller)
+            .unwrap_or(false)
     }
 
     ///
-    /// 出力先へのアクセサ
+    /// 処理対象とする画像サイズの下限へのアクセサ
     ///
-    pub(super) fn output_path(&self) -> Option<PathBuf> {
+    pub(super) fn only_if_larger_than(&self) -> Option<Resolution> {
         self.output
             .as_ref()
-            .and_then(|output| output.output_path.as_ref())
+            .and_then(|output| output.only_if_larger_than.as_ref())
             .cloned()
     }
 }
@@ -98,8 +834,195 @@ pub struct LogoInfo {
     /// ロゴに使用する画像ファイル(PNG)へのパス
     file_path: Option<PathBuf>,
 
+    /// 明るい背景用のロゴ(暗い色のロゴ)に使用する画像ファイルへのパス
+    dark_file_path: Option<PathBuf>,
+
+    /// ロゴ画像の代わりに描画する文字列
+    text: Option<String>,
+
+    /// 文字列の描画に使用するフォントファイルへのパス
+    font: Option<PathBuf>,
+
+    /// 文字列の描画サイズ(ピクセル数)
+    text_size: Option<f32>,
+
+    /// 文字列の色(色名または#RRGGBB形式)
+    #[serde(default, deserialize_with = "from_str")]
+    text_color: Option<Color>,
+
+    /// ロゴを配置する場所
+    position: Option<Position>,
+
+    /// ロゴの不透明度(0.0〜1.0)
+    opacity: Option<f32>,
+
+    /// 出力画像の大きさに対するロゴの大きさ("10%"や"8%h"の形式)
+    #[serde(default, deserialize_with = "from_str")]
+    scale: Option<LogoScale>,
+
+    /// 出力画像の大きさに対するロゴの大きさの上限(0.0〜1.0)
+    max_coverage: Option<f32>,
+
+    /// 大きく縮小したロゴに適用するシャープ化の強度
+    sharpen: Option<f32>,
+
+    /// ロゴの回転角(度、反時計回り)
+    rotation: Option<f32>,
+
+    /// ロゴの輪郭に沿った縁取りの幅(ピクセル数)
+    outline: Option<u32>,
+
+    /// ロゴの影のぼかしの半径(ピクセル数)
+    shadow: Option<u32>,
+
+    /// 縁取りと影の色(色名または#RRGGBB形式)
+    #[serde(default, deserialize_with = "from_str")]
+    halo_color: Option<Color>,
+
+    /// ロゴの背後に描画する背景("black@0.4"の形式)
+    #[serde(default, deserialize_with = "from_str")]
+    backdrop: Option<Backdrop>,
+
+    /// 背景をロゴの周囲に広げる幅(ピクセル数)
+    backdrop_padding: Option<u32>,
+
+    /// 背景の角の丸めの半径(ピクセル数)
+    backdrop_radius: Option<u32>,
+
+    /// ロゴの色付けに用いる色(色名または#RRGGBB形式)
+    #[serde(default, deserialize_with = "from_str")]
+    tint: Option<Color>,
+
+    /// ロゴの色付けの方法("multiply"または"replace")
+    tint_mode: Option<TintMode>,
+
+    /// ロゴを読み込む際に無彩色にするか否か
+    grayscale: Option<bool>,
+
+    /// ロゴと画像の端との間の余白("16"、"2%"や"24px,2%"の形式)
+    #[serde(default, deserialize_with = "from_str")]
+    margin: Option<Spacing>,
+
+    /// ロゴの左上の座標("40,30"や"-5%,-5%"の形式)
+    #[serde(default, deserialize_with = "from_str")]
+    offset: Option<LogoOffset>,
+
+    /// ロゴをファイルごとにずらす範囲("5%"や"40,20"の形式)
+    #[serde(default, deserialize_with = "from_str")]
+    jitter: Option<Spacing>,
+
+    /// 出力画像が縦長の場合に置き換える配置の指定([logo.portrait])
+    portrait: Option<PlacementInfo>,
+
+    /// 出力画像が横長の場合に置き換える配置の指定([logo.landscape])
+    landscape: Option<PlacementInfo>,
+
+    /// 出力画像が正方形の場合に置き換える配置の指定([logo.square])
+    square: Option<PlacementInfo>,
+
+    /// ロゴの大きさと位置を小数点以下の精度で扱うか否か
+    subpixel: Option<bool>,
+
+    /// ロゴを斜めに繰り返し敷き詰めるか否か
+    tile: Option<bool>,
+
+    /// 敷き詰める際の角度(度)
+    tile_angle: Option<f32>,
+
+    /// 敷き詰める際のロゴ同士の間隔("80"や"10%,5%"の形式)
+    #[serde(default, deserialize_with = "from_str")]
+    tile_spacing: Option<Spacing>,
+
+    /// パノラマ画像でロゴを水平方向に繰り返す縦横比(幅÷高さ)
+    panorama_ratio: Option<f32>,
+
+    /// パノラマ画像でロゴを繰り返す間隔("2400"や"25%"の形式)
+    #[serde(default, deserialize_with = "from_str")]
+    panorama_interval: Option<Length>,
+}
+
+/// LogoInfoに対する実装
+impl LogoInfo {
+    ///
+    /// 出力画像の向きごとの配置の指定への変換
+    ///
+    fn orientations(&self) -> OrientationRules {
+        OrientationRules {
+            portrait: self.portrait.as_ref().map(PlacementInfo::to_override),
+            landscape: self.landscape.as_ref().map(PlacementInfo::to_override),
+            square: self.square.as_ref().map(PlacementInfo::to_override),
+        }
+    }
+
+    ///
+    /// 透かしの指定への変換
+    ///
+    fn to_spec(&self) -> WatermarkSpec {
+        WatermarkSpec {
+            file_path: self.file_path.clone(),
+            dark_file_path: self.dark_file_path.clone(),
+            text: self.text.clone(),
+            font: self.font.clone(),
+            text_size: self.text_size,
+            text_color: self.text_color,
+            position: self.position,
+            opacity: self.opacity,
+            scale: self.scale,
+            max_coverage: self.max_coverage,
+            sharpen: self.sharpen,
+            rotation: self.rotation,
+            outline: self.outline,
+            shadow: self.shadow,
+            halo_color: self.halo_color,
+            backdrop: self.backdrop,
+            backdrop_padding: self.backdrop_padding,
+            backdrop_radius: self.backdrop_radius,
+            tint: self.tint,
+            tint_mode: self.tint_mode,
+            grayscale: self.grayscale.unwrap_or(false),
+            margin: self.margin,
+            offset: self.offset,
+            jitter: self.jitter,
+            orientations: self.orientations(),
+            subpixel: self.subpixel.unwrap_or(false),
+            tile: self.tile.unwrap_or(false),
+            tile_angle: self.tile_angle,
+            tile_spacing: self.tile_spacing,
+            panorama_ratio: self.panorama_ratio,
+            panorama_interval: self.panorama_interval,
+        }
+    }
+}
+
+///
+/// 出力画像の向きに応じて置き換えるロゴの配置の設定を格納する構造体
+///
+#[derive(Debug, Deserialize)]
+pub struct PlacementInfo {
     /// ロゴを配置する場所
     position: Option<Position>,
+
+    /// 出力画像の大きさに対するロゴの大きさ("10%"や"8%h"の形式)
+    #[serde(default, deserialize_with = "from_str")]
+    scale: Option<LogoScale>,
+
+    /// ロゴと画像の端との間の余白("16"、"2%"や"24px,2%"の形式)
+    #[serde(default, deserialize_with = "from_str")]
+    margin: Option<Spacing>,
+}
+
+/// PlacementInfoに対する実装
+impl PlacementInfo {
+    ///
+    /// 配置の指定への変換
+    ///
+    fn to_override(&self) -> PlacementOverride {
+        PlacementOverride {
+            position: self.position,
+            scale: self.scale,
+            margin: self.margin,
+        }
+    }
 }
 
 ///
@@ -108,19 +1031,666 @@ pub struct LogoInfo {
 #[derive(Debug, Deserialize)]
 pub struct OutputInfo {
     /// 出力解像度(プリセット名またはWxH形式)
-    #[serde(deserialize_with = "from_str")]
+    #[serde(default, deserialize_with = "from_str")]
     resolution: Option<Resolution>,
 
     /// 出力先
     output_path: Option<PathBuf>,
+
+    /// 入力ファイルの隣に作成する出力フォルダの名前
+    output_beside_input: Option<String>,
+
+    /// キャンバスサイズ(プリセット名またはWxH形式)
+    #[serde(default, deserialize_with = "from_str")]
+    canvas: Option<Resolution>,
+
+    /// キャンバスの背景色(色名または#RRGGBB形式)
+    #[serde(default, deserialize_with = "from_str")]
+    canvas_color: Option<Color>,
+
+    /// キャンバス上での画像の配置位置
+    canvas_gravity: Option<Position>,
+
+    /// 出力画像の周囲に付ける縁の幅(ピクセル数)
+    border: Option<u32>,
+
+    /// 縁の色(色名または#RRGGBB形式)
+    #[serde(default, deserialize_with = "from_str")]
+    border_color: Option<Color>,
+
+    /// 出力画像の寸法の揃え方("even"、8または16)
+    #[serde(default, deserialize_with = "align_from_value")]
+    align: Option<Align>,
+
+    /// 出力品質(1〜100または"auto")
+    #[serde(default, deserialize_with = "quality_from_value")]
+    quality: Option<Quality>,
+
+    /// 用途に合わせた出力設定のプリセット("screenshot")
+    quality_preset: Option<QualityPreset>,
+
+    /// 画像の複雑さに応じてJPEG品質を自動選択するか否か
+    adaptive_quality: Option<bool>,
+
+    /// 品質自動選択時の品質範囲(MIN-MAX形式)
+    #[serde(default, deserialize_with = "from_str")]
+    quality_range: Option<QualityRange>,
+
+    /// 出力形式
+    format: Option<OutputFormat>,
+
+    /// JPEGをプログレッシブ形式で出力するか否か
+    progressive: Option<bool>,
+
+    /// JPEG出力時のクロマサブサンプリング(444, 422, 420のいずれか)
+    subsampling: Option<ChromaSubsampling>,
+
+    /// グレースケールのJPEG入力をグレースケールのまま処理するか否か
+    keep_grayscale: Option<bool>,
+
+    /// WebP出力時の品質(0〜100)
+    webp_quality: Option<u8>,
+
+    /// WebPを可逆圧縮で出力するか否か
+    webp_lossless: Option<bool>,
+
+    /// AVIF出力時のエンコード速度(1〜10)
+    avif_speed: Option<u8>,
+
+    /// PNG出力時の圧縮レベル
+    png_compression: Option<PngCompression>,
+
+    /// 作業用の色深度(8または16)
+    depth: Option<ColorDepth>,
+
+    /// 16ビットから8ビットへの変換時のディザリング方式
+    dither: Option<Dither>,
+
+    /// HDR合成のためのブラケットのまとめ方("exif"または"filename")
+    hdr_merge: Option<HdrGrouping>,
+
+    /// タイルに分割する際のタイルの一辺(ピクセル数)
+    tile_output: Option<u32>,
+
+    /// タイルに分割する際に隣のタイルと重ねるピクセル数
+    tile_overlap: Option<u32>,
+
+    /// Web向けの出力一式を書き出すか否か
+    web_bundle: Option<bool>,
+
+    /// Web向けの出力一式に含める縮小版の幅のリスト
+    web_widths: Option<Vec<u32>>,
+
+    /// 出力画像の支配的な色とパレットを抽出するか否か
+    palette: Option<bool>,
+
+    /// ファイル名とキャプションの対応を記述したCSVファイルのパス
+    captions: Option<PathBuf>,
+
+    /// テンプレートの日時に用いるタイムゾーン("Asia/Tokyo"や"+09:00")
+    timezone: Option<String>,
+
+    /// ロゴをずらす量を決める乱数の種
+    jitter_seed: Option<u64>,
+
+    /// 出力画像の代替テキストのテンプレート
+    alt_text: Option<String>,
+
+    /// ファイル名と代替テキストの対応を記述したCSVファイルのパス
+    alt_text_csv: Option<PathBuf>,
+
+    /// 適用する3D LUT(.cube形式)のパス
+    lut: Option<PathBuf>,
+
+    /// 適用するトーンカーブ("0:10,128:140,255:245"の形式)
+    #[serde(default, deserialize_with = "from_str")]
+    tone_curve: Option<ToneCurve>,
+
+    /// リサイズ後のシャープ化の強度(0.0〜5.0)
+    sharpen: Option<f32>,
+
+    /// シャープ化を適用するチャネル("rgb"または"luma")
+    sharpen_mode: Option<SharpenMode>,
+
+    /// 入力ディレクトリの構造を出力先に再現するか否か
+    mirror_tree: Option<bool>,
+
+    /// JPEG以外の付随ファイルも出力先にコピーするか否か(mirror_treeが有効な
+    /// 場合のみ)
+    copy_sidecars: Option<bool>,
+
+    /// 入力画像が出力解像度以下の場合にそのままコピーするか否か
+    keep_resolution_if_smaller: Option<bool>,
+
+    /// この大きさ以下の入力画像はそのままコピーする(プリセット名またはWxH形
+    /// 式)
+    #[serde(default, deserialize_with = "from_str")]
+    only_if_larger_than: Option<Resolution>,
+
+    /// 監査用の実行記録を追記するJSON Linesファイルのパス
+    run_log: Option<PathBuf>,
+
+    /// 入力ファイルごとに処理するか否かを決めるコマンド
+    pre_filter: Option<String>,
+
+    /// 出力ファイルを書き込むたびに実行するコマンド
+    post_cmd: Option<String>,
+
+    /// 同時に実行する後処理コマンドの数の上限
+    post_jobs: Option<usize>,
+
+    /// 後処理コマンドが失敗した場合の扱い("abort"または"warn")
+    post_failure: Option<PostFailure>,
+}
+
+///
+/// テーブルの再帰的なマージ
+///
+/// # 引数
+/// * `base` - マージ先のテーブル
+/// * `overlay` - 上書きする内容を持つテーブル
+///
+/// # 注記
+/// 両方にテーブルとして存在するキーは再帰的にマージし、それ以外のキーは
+/// `overlay`側の値で上書きする。
+///
+fn merge(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(value)) => {
+                merge(base, value);
+            }
+
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+///
+/// インクルードを展開したテーブルの読み込み
+///
+/// # 引数
+/// * `path` - 読み込むファイルへのパス
+/// * `stack` - 読み込み中のファイルのリスト(循環参照の検出用)
+///
+/// # 戻り値
+/// 読み込みに成功した場合はインクルードを展開したテーブルを`Ok()`でラップし
+/// て返す。失敗した場合はエラー情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// `include`に列挙されたファイルを先頭から順にマージし、最後に読み込んだファ
+/// イル自身の内容をマージする。つまり後に列挙したファイルほど優先され、読み
+/// 込んだファイル自身の設定が最も優先される。相対パスは読み込んだファイルの
+/// あるディレクトリを基準に解決する。
+///
+fn read_table(path: &Path, stack: &mut Vec<PathBuf>) -> Result<Table> {
+    let canonical = path.canonicalize()
+        .map_err(|err| anyhow!("{}: {}", path.display(), err))?;
+
+    if stack.contains(&canonical) {
+        return Err(anyhow!(
+            "circular include detected at \"{}\"",
+            path.display()
+        ));
+    }
+
+    let mut table: Table = toml::from_str(&std::fs::read_to_string(path)?)?;
+
+    let includes = match table.remove("include") {
+        Some(Value::Array(includes)) => includes,
+        Some(Value::String(include)) => vec![Value::String(include)],
+        Some(_) => {
+            return Err(anyhow!(
+                "\"include\" in \"{}\" must be a string or an array of \
+                 strings",
+                path.display()
+            ));
+        }
+        None => Vec::new(),
+    };
+
+    stack.push(canonical);
+
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    let mut merged = Table::new();
+
+    for include in includes {
+        let Value::String(include) = include else {
+            return Err(anyhow!(
+                "\"include\" in \"{}\" must contain only strings",
+                path.display()
+            ));
+        };
+
+        merge(&mut merged, read_table(&base_dir.join(include), stack)?);
+    }
+
+    stack.pop();
+    merge(&mut merged, table);
+
+    Ok(merged)
+}
+
+///
+/// ユーザ定義のプリセット解像度の登録
+///
+/// # 引数
+/// * `table` - インクルードを展開したテーブル
+/// * `path` - 読み込んだファイルへのパス(エラー表示用)
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を`Err()`で
+/// ラップして返す。
+///
+/// # 注記
+/// `[presets]`テーブルの`名前 = "WxH"`の形式のエントリを登録する。小さい画
+/// 像をそのままコピーするか否かをプリセット毎に指定する場合は`名前 = {
+/// resolution = "WxH", keep_resolution_if_smaller = true }`の形式で記述す
+/// る。解像度の指定の解析より前に登録しておく必要があるため、デシリアライ
+/// ズとは別に処理する。
+///
+fn register_presets(table: &Table, path: &Path) -> Result<()> {
+    let presets = match table.get("presets") {
+        Some(Value::Table(presets)) => presets,
+        Some(_) => {
+            return Err(anyhow!(
+                "\"presets\" in \"{}\" must be a table",
+                path.display()
+            ));
+        }
+        None => return Ok(()),
+    };
+
+    for (name, value) in presets {
+        let (value, keep) = match value {
+            Value::String(value) => (value, None),
+            Value::Table(entry) => preset_entry(entry).ok_or_else(|| {
+                anyhow!(
+                    "preset \"{}\" in \"{}\" must have a string \
+                     \"resolution\" and an optional boolean \
+                     \"keep_resolution_if_smaller\"",
+                    name,
+                    path.display()
+                )
+            })?,
+            _ => {
+                return Err(anyhow!(
+                    "preset \"{}\" in \"{}\" must be a string or a table",
+                    name,
+                    path.display()
+                ));
+            }
+        };
+
+        super::register_user_preset(name, value, keep).map_err(|err| {
+            anyhow!("{} (preset \"{}\" in \"{}\")", err, name, path.display())
+        })?;
+    }
+
+    Ok(())
+}
+
+///
+/// テーブル形式のプリセットの解析
+///
+/// # 引数
+/// * `entry` - プリセットのテーブル
+///
+/// # 戻り値
+/// 解像度の文字列と小さい画像をそのままコピーするか否かの指定をパックした
+/// タプルを返す。形式が不正な場合は`None`を返す。
+///
+fn preset_entry(entry: &Table) -> Option<(&String, Option<bool>)> {
+    let mut resolution = None;
+    let mut keep = None;
+
+    for (key, value) in entry {
+        match (key.as_str(), value) {
+            ("resolution", Value::String(value)) => resolution = Some(value),
+            ("keep_resolution_if_smaller", Value::Boolean(value)) => {
+                keep = Some(*value);
+            }
+            _ => return None,
+        }
+    }
+
+    Some((resolution?, keep))
+}
+
+///
+/// 名前付きのスタイルの展開
+///
+/// # 引数
+/// * `table` - インクルードを展開したテーブル
+/// * `path` - 読み込んだファイルへのパス(エラー表示用)
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を`Err()`で
+/// ラップして返す。
+///
+/// # 注記
+/// `[styles.名前]`テーブルに記述した設定を、`style = "名前"`で参照する
+/// `[logo]`、`[[logo]]`および向きごとの配置のテーブル(`[logo.portrait]`な
+/// ど)に取り込む。`style = ["名前", ...]`で複数のスタイルを参照した場合は後
+/// に列挙したものほど優先され、参照したテーブル自身の設定が最も優先される。
+///
+fn expand_styles(table: &mut Table, path: &Path) -> Result<()> {
+    let styles = match table.remove("styles") {
+        Some(Value::Table(styles)) => styles,
+        Some(_) => {
+            return Err(anyhow!(
+                "\"styles\" in \"{}\" must be a table",
+                path.display()
+            ));
+        }
+        None => Table::new(),
+    };
+
+    for (name, style) in &styles {
+        match style {
+            Value::Table(style) if style.contains_key("style") => {
+                return Err(anyhow!(
+                    "style \"{}\" in \"{}\" cannot refer to another style",
+                    name,
+                    path.display()
+                ));
+            }
+
+            Value::Table(_) => {}
+
+            _ => {
+                return Err(anyhow!(
+                    "style \"{}\" in \"{}\" must be a table",
+                    name,
+                    path.display()
+                ));
+            }
+        }
+    }
+
+    let logos = match table.get_mut("logo") {
+        Some(Value::Table(logo)) => vec![logo],
+        Some(Value::Array(logos)) => logos.iter_mut()
+            .filter_map(|logo| match logo {
+                Value::Table(logo) => Some(logo),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    for logo in logos {
+        apply_style(logo, &styles, path)?;
+
+        for key in ["portrait", "landscape", "square"] {
+            if let Some(Value::Table(placement)) = logo.get_mut(key) {
+                apply_style(placement, &styles, path)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+///
+/// テーブルが参照するスタイルの取り込み
+///
+/// # 引数
+/// * `entry` - `style`キーを持つ可能性のあるテーブル
+/// * `styles` - `[styles]`テーブルの内容
+/// * `path` - 読み込んだファイルへのパス(エラー表示用)
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を`Err()`で
+/// ラップして返す。
+///
+fn apply_style(entry: &mut Table, styles: &Table, path: &Path) -> Result<()> {
+    let names = match entry.remove("style") {
+        Some(Value::String(name)) => vec![Value::String(name)],
+        Some(Value::Array(names)) => names,
+        Some(_) => {
+            return Err(anyhow!(
+                "\"style\" in \"{}\" must be a string or an array of \
+                 strings",
+                path.display()
+            ));
+        }
+        None => return Ok(()),
+    };
+
+    let mut merged = Table::new();
+
+    for name in names {
+        let Value::String(name) = name else {
+            return Err(anyhow!(
+                "\"style\" in \"{}\" must contain only strings",
+                path.display()
+            ));
+        };
+
+        let Some(Value::Table(style)) = styles.get(&name) else {
+            return Err(anyhow!(
+                "unknown style \"{}\" in \"{}\" (defined: {})",
+                name,
+                path.display(),
+                if styles.is_empty() {
+                    "none".to_string()
+                } else {
+                    styles.keys().cloned().collect::<Vec<_>>().join(", ")
+                }
+            ));
+        };
+
+        merge(&mut merged, style.clone());
+    }
+
+    merge(&mut merged, std::mem::take(entry));
+    *entry = merged;
+
+    Ok(())
+}
+
+///
+/// ユーザ定義のプリセット解像度の事前読み込み
+///
+/// # 引数
+/// * `path` - コンフィギュレーションファイルへのパス
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を`Err()`で
+/// ラップして返す。
+///
+/// # 注記
+/// コマンドラインオプションの解析前に呼び出すためのもので、ファイルが存在し
+/// ない場合は何もしない(存在しない場合のエラーは`read()`の呼び出し側で報告
+/// する)。
+///
+pub(super) fn load_presets(path: &Path) -> Result<()> {
+    if !path.is_file() {
+        return Ok(());
+    }
+
+    register_presets(&read_table(path, &mut Vec::new())?, path)
 }
 
 ///
 /// コンフィギュレーションファイルの読み込み
 ///
+/// # 注記
+/// `include = ["base.toml"]`で他のコンフィギュレーションファイルを取り込むこ
+/// とができる(優先順位は`read_table()`を参照)。`[styles.名前]`で定義したス
+/// タイルはインクルードを展開した後に取り込む(`expand_styles()`を参照)。
+///
 pub(super) fn read<P>(path: P) -> Result<Config>
 where 
     P: AsRef<Path>
 {
-     Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+    let mut table = read_table(path.as_ref(), &mut Vec::new())?;
+
+    register_presets(&table, path.as_ref())?;
+    expand_styles(&mut table, path.as_ref())?;
+    Ok(Config::deserialize(Value::Table(table))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::cmd_args::Options;
+
+    ///
+    /// 指定した引数で解析したオプションの小さい画像のコピーの指定の取得
+    ///
+    fn keep_if_smaller(args: &[&str]) -> bool {
+        Options::try_parse_from(
+            ["watermarker"].iter().chain(args).chain(&["input.jpg"])
+        )
+        .unwrap()
+        .is_keep_resolution_if_smaller()
+    }
+
+    #[test]
+    fn preset_overrides_keep_resolution_if_smaller() {
+        let table: Table = toml::from_str(r#"
+            [presets.test-keep]
+            resolution = "320x240"
+            keep_resolution_if_smaller = true
+
+            [presets.test-resize]
+            resolution = "320x240"
+            keep_resolution_if_smaller = false
+
+            [presets]
+            test-plain = "320x240"
+        "#).unwrap();
+
+        register_presets(&table, Path::new("test.toml")).unwrap();
+
+        assert!(keep_if_smaller(&["-r", "test-keep"]));
+        assert!(!keep_if_smaller(
+            &["-r", "test-resize", "--keep-resolution-if-smaller"]
+        ));
+        assert!(keep_if_smaller(
+            &["-r", "test-plain", "--keep-resolution-if-smaller"]
+        ));
+        assert!(!keep_if_smaller(&["-r", "test-plain"]));
+    }
+
+    #[test]
+    fn preset_table_rejects_unknown_keys() {
+        let table: Table = toml::from_str(r#"
+            [presets]
+            test-bad = { resolution = "320x240", keep = true }
+        "#).unwrap();
+
+        assert!(register_presets(&table, Path::new("test.toml")).is_err());
+    }
+
+    #[test]
+    fn quality_preset_fills_unset_options() {
+        let mut opts = Options::try_parse_from([
+            "watermarker", "--quality-preset", "screenshot", "--border", "4",
+            "input.png",
+        ]).unwrap();
+
+        opts.apply_quality_preset();
+
+        assert_eq!(opts.border(), Some(4));
+        assert_eq!(opts.output_format(), OutputFormat::Png);
+        assert_eq!(
+            opts.text().as_deref(),
+            Some(QualityPreset::SCREENSHOT_TEXT)
+        );
+        assert_eq!(opts.resolution().scaled_size(3200, 1000), (1600, 500));
+        assert_eq!(opts.resolution().scaled_size(800, 600), (800, 600));
+    }
+
+    #[test]
+    fn quality_preset_keeps_logo_file() {
+        let mut opts = Options::try_parse_from([
+            "watermarker", "--quality-preset", "screenshot", "-l", "logo.png",
+            "input.png",
+        ]).unwrap();
+
+        opts.apply_quality_preset();
+
+        assert_eq!(opts.text(), None);
+        assert_eq!(opts.logo_position(), Position::BottomRight);
+    }
+
+    #[test]
+    fn styles_are_expanded_into_logo_tables() {
+        let mut table: Table = toml::from_str(r#"
+            [styles.caption]
+            text_size = 24.0
+            position = "bottom-left"
+            margin = "16"
+
+            [styles.corner-logo]
+            position = "top-right"
+            opacity = 0.8
+
+            [[logo]]
+            style = "caption"
+            text = "caption"
+            margin = "8"
+
+            [logo.portrait]
+            style = "corner-logo"
+
+            [[logo]]
+            style = ["caption", "corner-logo"]
+            file_path = "logo.png"
+        "#).unwrap();
+
+        expand_styles(&mut table, Path::new("test.toml")).unwrap();
+
+        let config = Config::deserialize(Value::Table(table)).unwrap();
+        let extra = &config.logo[1];
+
+        assert_eq!(config.text_size(), Some(24.0));
+        assert_eq!(config.logo_position(), Some(Position::BottomLeft));
+        assert_eq!(config.logo_margin(), "8".parse().ok());
+        assert_eq!(
+            config.logo[0].portrait.as_ref().and_then(|p| p.position),
+            Some(Position::TopRight)
+        );
+        assert_eq!(extra.position, Some(Position::TopRight));
+        assert_eq!(extra.opacity, Some(0.8));
+        assert_eq!(extra.text_size, Some(24.0));
+    }
+
+    #[test]
+    fn styles_reject_unknown_names() {
+        let mut table: Table = toml::from_str(r#"
+            [styles.caption]
+            text_size = 24.0
+
+            [logo]
+            style = "captoin"
+        "#).unwrap();
+
+        let err = expand_styles(&mut table, Path::new("test.toml"))
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("unknown style \"captoin\""), "{}", err);
+        assert!(err.contains("(defined: caption)"), "{}", err);
+    }
+
+    #[test]
+    fn styles_cannot_refer_to_styles() {
+        let mut table: Table = toml::from_str(r#"
+            [styles.base]
+            opacity = 0.5
+
+            [styles.derived]
+            style = "base"
+        "#).unwrap();
+
+        assert!(expand_styles(&mut table, Path::new("test.toml")).is_err());
+    }
 }
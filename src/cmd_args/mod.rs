@@ -9,18 +9,41 @@
 //!
 
 mod config;
+mod suggest;
+mod watermark;
 
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
 use std::fmt::Display;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::str::FromStr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use anyhow::{anyhow, Result};
-use clap::{Parser, ValueEnum};
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
 use directories::BaseDirs;
-use image::RgbaImage;
+use image::codecs::png::CompressionType;
+use image::{ImageFormat, Rgba};
 use serde::Deserialize;
 
+use crate::hdr::HdrGrouping;
+use crate::layer_cache::LayerCache;
+use crate::lut::{CubeLut, ToneCurve};
+use crate::pixel::Dither;
+use crate::datetime::TimeZone;
+use crate::placement;
+use crate::pre_filter::FileOverrides;
+use crate::sharpen::SharpenMode;
+use crate::template::{self, Sequence};
+use crate::text_table::TextTable;
+use crate::web_bundle;
+use watermark::{
+    DEFAULT_HALO_COLOR, DEFAULT_PANORAMA_RATIO, DEFAULT_TEXT_SIZE,
+    DEFAULT_TILE_ANGLE, OrientationRules, PlacementOverride, WatermarkSpec,
+};
+
+pub(crate) use watermark::{Orientation, Watermark};
+
 ///
 /// デフォルトのコンフィグレーションファイルのパス情報を生成
 ///
@@ -38,9 +61,9 @@ fn default_config_path() -> PathBuf {
 ///
 /// ロゴ画像の配置位置
 ///
-#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum, Deserialize)]
 #[clap(rename_all = "SCREAMING_SNAKE_CASE")]
-#[serde(rename_all = "SCREAMING-KEBAB-CASE")]
+#[serde(try_from = "String")]
 pub enum Position {
     /// 左上
     TopLeft,
@@ -56,6 +79,62 @@ pub enum Position {
 
     /// 画像中央
     Center,
+
+    /// 上端中央
+    TopCenter,
+
+    /// 下端中央
+    BottomCenter,
+
+    /// 左端中央
+    LeftCenter,
+
+    /// 右端中央
+    RightCenter,
+
+    /// 画像ごとに最も細部の少ない位置(ロゴの配置位置でのみ有効)
+    #[value(alias = "auto")]
+    Auto,
+}
+
+// FromStrトレイトの実装
+impl FromStr for Position {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const NAMES: &[&str] = &[
+            "TOP-LEFT", "TOP-RIGHT", "BOTTOM-LEFT", "BOTTOM-RIGHT", "CENTER",
+            "TOP-CENTER", "BOTTOM-CENTER", "LEFT-CENTER", "RIGHT-CENTER",
+            "AUTO",
+        ];
+
+        match s.to_uppercase().replace('_', "-").as_str() {
+            "TOP-LEFT" => Ok(Self::TopLeft),
+            "TOP-RIGHT" => Ok(Self::TopRight),
+            "BOTTOM-LEFT" => Ok(Self::BottomLeft),
+            "BOTTOM-RIGHT" => Ok(Self::BottomRight),
+            "CENTER" => Ok(Self::Center),
+            "TOP-CENTER" => Ok(Self::TopCenter),
+            "BOTTOM-CENTER" => Ok(Self::BottomCenter),
+            "LEFT-CENTER" => Ok(Self::LeftCenter),
+            "RIGHT-CENTER" => Ok(Self::RightCenter),
+            "AUTO" => Ok(Self::Auto),
+            _ => Err(format!(
+                "配置位置の指定が無効: {}{}",
+                s,
+                suggest::did_you_mean(s, NAMES)
+            )),
+        }
+    }
+}
+
+// TryFromトレイトの実装
+impl TryFrom<String> for Position {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        <Self as FromStr>::from_str(&s)
+    }
 }
 
 // Displayトレイトの実装
@@ -67,6 +146,11 @@ impl Display for Position {
             Self::BottomLeft => "BOTTOM-LEFT",
             Self::BottomRight => "BOTTOM-RIGHT",
             Self::Center => "CENTER",
+            Self::TopCenter => "TOP-CENTER",
+            Self::BottomCenter => "BOTTOM-CENTER",
+            Self::LeftCenter => "LEFT-CENTER",
+            Self::RightCenter => "RIGHT-CENTER",
+            Self::Auto => "AUTO",
         })
     }
 }
@@ -74,6 +158,7 @@ impl Display for Position {
 ///
 /// プリセット解像度の定義
 ///
+#[allow(clippy::upper_case_acronyms)]
 pub enum PresetResolution {
     /// QVGA (320x240)
     QVGA,
@@ -92,6 +177,24 @@ pub enum PresetResolution {
 
     /// FullHD (1920x1080)
     FullHD,
+
+    /// QHD (2560x1440)
+    QHD,
+
+    /// UHD (3840x2160)
+    UHD,
+
+    /// 1080pの正方形 (1080x1080)
+    Square1080,
+}
+
+/// PresetResolutionに対する実装
+impl PresetResolution {
+    /// プリセット名のリスト
+    const NAMES: &[&str] = &[
+        "QVGA", "VGA", "SVGA", "HD", "QuadVGA", "FullHD", "QHD", "UHD",
+        "square-1080", "720p", "1080p", "1440p", "4k",
+    ];
 }
 
 // FromStrトレイトの実装
@@ -103,28 +206,95 @@ impl FromStr for PresetResolution {
             "qvga" => Ok(PresetResolution::QVGA),
             "vga" => Ok(PresetResolution::VGA),
             "svga" => Ok(PresetResolution::SVGA),
-            "hd" => Ok(PresetResolution::HD),
+            "hd" | "720p" => Ok(PresetResolution::HD),
             "quadvga" => Ok(PresetResolution::QuadVGA),
-            "fullhd" => Ok(PresetResolution::FullHD),
-            _ => Err(format!("該当する解像度無し:{}", s)),
+            "fullhd" | "1080p" => Ok(PresetResolution::FullHD),
+            "qhd" | "1440p" => Ok(PresetResolution::QHD),
+            "uhd" | "4k" => Ok(PresetResolution::UHD),
+            "square-1080" => Ok(PresetResolution::Square1080),
+            _ => {
+                let users = user_preset_names();
+                let candidates = Self::NAMES.iter()
+                    .copied()
+                    .chain(users.iter().map(String::as_str))
+                    .collect::<Vec<_>>();
+
+                Err(format!(
+                    "該当する解像度無し: {}{}",
+                    s,
+                    suggest::did_you_mean(s, &candidates)
+                ))
+            }
         }
     }
 }
 
-// Intoトレイトの実装
-impl Into<Resolution> for PresetResolution {
-    fn into(self) -> Resolution {
-        match self {
+// Fromトレイトの実装
+impl From<PresetResolution> for Resolution {
+    fn from(preset: PresetResolution) -> Self {
+        match preset {
             PresetResolution::QVGA => Resolution::new(320, 240),
             PresetResolution::VGA => Resolution::new(640, 480),
             PresetResolution::SVGA => Resolution::new(800, 600),
             PresetResolution::HD => Resolution::new(1280, 720),
             PresetResolution::QuadVGA => Resolution::new(1280, 960),
             PresetResolution::FullHD => Resolution::new(1920, 1080),
+            PresetResolution::QHD => Resolution::new(2560, 1440),
+            PresetResolution::UHD => Resolution::new(3840, 2160),
+            PresetResolution::Square1080 => Resolution::new(1080, 1080),
         }
     }
 }
 
+///
+/// ユーザ定義のプリセット解像度の登録表(キーは小文字化したプリセット名)
+///
+static USER_PRESETS: RwLock<BTreeMap<String, Resolution>> =
+    RwLock::new(BTreeMap::new());
+
+///
+/// ユーザ定義のプリセット解像度の登録
+///
+/// # 引数
+/// * `name` - プリセット名
+/// * `value` - 解像度(組み込みのプリセット名またはWxH形式)
+/// * `keep_if_smaller` - 小さい画像をそのままコピーするか否か(指定が無い
+///   場合は`None`)
+///
+/// # 戻り値
+/// 登録に成功した場合は`Ok(())`を返す。失敗した場合はエラーメッセージを
+/// `Err()`でラップして返す。
+///
+/// # 注記
+/// 組み込みのプリセット名を上書きすることはできない。また、値に他のユーザ定
+/// 義のプリセット名を指定することはできない。
+///
+fn register_user_preset(
+    name: &str,
+    value: &str,
+    keep_if_smaller: Option<bool>,
+) -> Result<(), String> {
+    if PresetResolution::from_str(name).is_ok() {
+        return Err(format!("組み込みのプリセット名と重複: {}", name));
+    }
+
+    let mut resolution = Resolution::parse_builtin(value)?;
+    resolution.keep_if_smaller = keep_if_smaller;
+
+    USER_PRESETS.write()
+        .unwrap()
+        .insert(name.to_lowercase(), resolution);
+
+    Ok(())
+}
+
+///
+/// ユーザ定義のプリセット名のリストの取得
+///
+fn user_preset_names() -> Vec<String> {
+    USER_PRESETS.read().unwrap().keys().cloned().collect()
+}
+
 ///
 /// 解像度を管理する構造体
 ///
@@ -135,6 +305,19 @@ pub struct Resolution {
 
     /// 画像の高さ(ピクセル数)
     height: u32,
+
+    /// 面積ではなく矩形に収まるように縮尺するか否か(縦横比指定の場合)
+    #[serde(default)]
+    fit: bool,
+
+    /// 拡大を行わないか否か(幅の上限のみを指定した場合)
+    #[serde(skip)]
+    shrink_only: bool,
+
+    /// 小さい画像をそのままコピーするか否か(ユーザ定義のプリセットで指定さ
+    /// れた場合のみ)
+    #[serde(skip)]
+    keep_if_smaller: Option<bool>,
 }
 
 // FromStrトレイトの実装
@@ -142,13 +325,71 @@ impl FromStr for Resolution {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        /*
+         * ユーザ定義のプリセット解像度としての評価
+         */
+        if let Some(resolution) = USER_PRESETS.read()
+            .unwrap()
+            .get(&s.to_lowercase())
+        {
+            return Ok(*resolution);
+        }
+
+        Self::parse_builtin(s)
+    }
+}
+
+// Displayトレイトの実装
+impl Display for Resolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.shrink_only {
+            write!(f, "max width {}", self.width)
+        } else if self.fit {
+            write!(f, "{}×{} (fit)", self.width, self.height)
+        } else {
+            write!(f, "{}×{}", self.width, self.height)
+        }
+    }
+}
+
+/// Resolutionに対する実装
+impl Resolution {
+    ///
+    /// 組み込みのプリセット名またはWxH形式の解像度の解析
+    ///
+    /// # 引数
+    /// * `s` - 解析する文字列
+    ///
+    /// # 戻り値
+    /// 解析に成功した場合は解像度を`Ok()`でラップして返す。失敗した場合はエ
+    /// ラーメッセージを`Err()`でラップして返す。
+    ///
+    /// # 注記
+    /// ユーザ定義のプリセット名は評価しない。
+    ///
+    fn parse_builtin(s: &str) -> Result<Self, String> {
         /*
          * プリセット解像度としての評価
          */
-        if let Ok(preset) = PresetResolution::from_str(s) {
+        let preset = PresetResolution::from_str(s);
+
+        if let Ok(preset) = preset {
             return Ok(preset.into());
         }
 
+        // 数字を含まない場合はプリセット名の誤りとして報告する
+        if !s.chars().any(|c| c.is_ascii_digit()) {
+            return preset.map(Into::into);
+        }
+
+        /*
+         * 縦横比指定としての評価
+         */
+        if let Some((size, aspect)) = s.split_once('@') {
+            return Self::parse_aspect(size, aspect)
+                .map_err(|err| format!("{}: {}", err, s));
+        }
+
         /*
          * 数値形式(WxH)としての評価
          */
@@ -164,21 +405,116 @@ impl FromStr for Resolution {
 
         let height = parts[1]
             .parse::<u32>()
-            .map_err(|_| format!("高さの指定が無効: {}", parts[0]))?;
+            .map_err(|_| format!("高さの指定が無効: {}", parts[1]))?;
 
-        Ok(Self {width, height})
+        Ok(Self::new(width, height))
     }
-}
 
-// Displayトレイトの実装
-impl Display for Resolution {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}×{}", self.width, self.height)
+    ///
+    /// 縦横比指定の解像度の解析
+    ///
+    /// # 引数
+    /// * `size` - '@'より前の部分("Wx"、"xH"または空文字列)
+    /// * `aspect` - '@'より後の部分("A:B"に",max=N"が続く場合がある)
+    ///
+    /// # 戻り値
+    /// 解析に成功した場合は縦横比と制約から導出した解像度を`Ok()`でラップし
+    /// て返す。失敗した場合はエラーメッセージを`Err()`でラップして返す。
+    ///
+    /// # 注記
+    /// 幅・高さ・長辺の最大値(max)のいずれか一つを制約として指定する必要が
+    /// ある。
+    ///
+    fn parse_aspect(size: &str, aspect: &str) -> Result<Self, String> {
+        /*
+         * 縦横比と長辺の最大値の切り出し
+         */
+        let mut items = aspect.split(',');
+        let ratio = items.next().unwrap_or_default();
+        let mut max = None;
+
+        for item in items {
+            match item.trim().split_once('=') {
+                Some(("max", value)) => {
+                    max = Some(value.trim().parse::<u32>()
+                        .ok()
+                        .filter(|value| *value > 0)
+                        .ok_or("長辺の最大値の指定が無効")?);
+                }
+
+                _ => return Err("縦横比の制約の指定が無効".to_string()),
+            }
+        }
+
+        let (a, b) = ratio.split_once(':').ok_or("縦横比の形式が不正")?;
+        let a = a.trim().parse::<f32>().ok().filter(|a| *a > 0.0);
+        let b = b.trim().parse::<f32>().ok().filter(|b| *b > 0.0);
+        let (Some(a), Some(b)) = (a, b) else {
+            return Err("縦横比の指定が無効".to_string());
+        };
+
+        /*
+         * 幅または高さの切り出し
+         */
+        let (width, height) = match size.trim().split_once('x') {
+            Some((width, "")) if !width.is_empty() => (Some(width), None),
+            Some(("", height)) if !height.is_empty() => (None, Some(height)),
+            None if size.trim().is_empty() => (None, None),
+            _ => return Err("解像度形式が不正".to_string()),
+        };
+
+        let parse = |value: &str, err: &'static str| {
+            value.trim()
+                .parse::<u32>()
+                .ok()
+                .filter(|value| *value > 0)
+                .ok_or(err)
+        };
+
+        let width = width
+            .map(|width| parse(width, "幅の指定が無効"))
+            .transpose()?;
+
+        let height = height
+            .map(|height| parse(height, "高さの指定が無効"))
+            .transpose()?;
+
+        /*
+         * 制約からの寸法の導出
+         */
+        let (width, height) = match (width, height, max) {
+            (Some(width), None, None) => {
+                (width as f32, width as f32 * b / a)
+            }
+
+            (None, Some(height), None) => {
+                (height as f32 * a / b, height as f32)
+            }
+
+            (None, None, Some(max)) if a >= b => {
+                (max as f32, max as f32 * b / a)
+            }
+
+            (None, None, Some(max)) => {
+                (max as f32 * a / b, max as f32)
+            }
+
+            _ => {
+                return Err(
+                    "幅・高さ・maxのいずれか一つを指定すること".to_string()
+                );
+            }
+        };
+
+        Ok(Self {
+            width: (width.round() as u32).max(1),
+            height: (height.round() as u32).max(1),
+            fit: true,
+            shrink_only: false,
+            keep_if_smaller: None,
+        })
     }
-}
 
-/// Resolutionに対する実装
-impl Resolution {
     ///
     /// オブジェクトの生成
     ///
@@ -190,7 +526,56 @@ impl Resolution {
     /// 生成したオブジェクトを返す
     ///
     fn new(width: u32, height: u32) -> Self {
-        Self {width, height }
+        Self {
+            width,
+            height,
+            fit: false,
+            shrink_only: false,
+            keep_if_smaller: None,
+        }
+    }
+
+    ///
+    /// 幅の上限のみを指定した解像度の生成
+    ///
+    /// # 引数
+    /// * `width` - 出力画像の幅の上限(ピクセル数)
+    ///
+    /// # 戻り値
+    /// 幅が上限を超える画像のみを縮小する(拡大は行わない)解像度を返す。
+    ///
+    pub(crate) fn max_width(width: u32) -> Self {
+        Self {
+            width,
+            height: u32::MAX,
+            fit: true,
+            shrink_only: true,
+            keep_if_smaller: None,
+        }
+    }
+
+    ///
+    /// 幅へのアクセサ
+    ///
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    ///
+    /// 高さへのアクセサ
+    ///
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    ///
+    /// プリセットで指定された小さい画像のコピーの指定へのアクセサ
+    ///
+    /// # 戻り値
+    /// ユーザ定義のプリセットで指定されていない場合は`None`を返す。
+    ///
+    pub(crate) fn keep_if_smaller(&self) -> Option<bool> {
+        self.keep_if_smaller
     }
 
     ///
@@ -205,8 +590,18 @@ impl Resolution {
     /// めの比率を返す。
     /// 引数 `width`及び`height`にこの関数の戻り値を掛けると`self`の持つ解像度
     /// と同等の面積を持つ矩形にリサイズできる。
+    /// 縦横比指定の解像度の場合は、面積ではなく`self`の持つ解像度の矩形に収
+    /// まる最大の大きさにリサイズするための比率を返す。幅の上限のみを指定し
+    /// た解像度の場合は1.0を超えない比率を返す。
     ///
     pub fn scale_ratio(&self, width: u32, height: u32) -> f32 {
+        if self.fit {
+            let scale = (self.width as f32 / width as f32)
+                .min(self.height as f32 / height as f32);
+
+            return if self.shrink_only { scale.min(1.0) } else { scale };
+        }
+
         ((self.width * self.height) as f32 / (width * height) as f32).sqrt()
     }
 
@@ -232,224 +627,3880 @@ impl Resolution {
 }
 
 ///
-/// コマンドラインオプションの情報をまとめる構造体
+/// 色情報を管理する構造体
 ///
-#[derive(Parser, Debug, Clone)]
-#[command(
-    name = "watermarker",
-    about = "画像に透かしロゴを埋め込むCLIツール",
-    version,
-    long_about = None,
-)]
-pub struct Options {
-    /// コンフィギュレーションファイルのパス
-    #[arg(short = 'c', long = "config-file", value_name = "FILE")]
-    config_file: Option<PathBuf>,
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    /// 赤成分
+    red: u8,
 
-    /// 出力先ディレクトリ
-    #[arg(short = 'o', long = "output-path", value_name= "PATH")]
-    output_path: Option<PathBuf>,
+    /// 緑成分
+    green: u8,
 
-    /// ロゴとして使用する透過PNGファイルのパス
-    #[arg(short = 'l', long = "logo-file-path", value_name = "PATH")]
-    logo_file_path: Option<PathBuf>,
+    /// 青成分
+    blue: u8,
+}
 
-    /// ロゴの配置位置
-    #[arg(short = 'p', long = "logo-position", value_enum,
-        value_name = "POSITION")]
-    logo_position: Option<Position>,
+// FromStrトレイトの実装
+impl FromStr for Color {
+    type Err = String;
 
-    /// 出力解像度(プリセット名またはWxH形式)
-    ///
-    /// 使用例:
-    ///   -r HD
-    ///   -r 1280x720
-    #[arg(short = 'r', long = "resolution", default_value = "HD")]
-    resolution: Option<Resolution>,
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        /*
+         * 色名としての評価
+         */
+        let named = match s.to_lowercase().as_str() {
+            "white" => Some((255, 255, 255)),
+            "black" => Some((0, 0, 0)),
+            "gray" | "grey" => Some((128, 128, 128)),
+            "silver" => Some((192, 192, 192)),
+            "red" => Some((255, 0, 0)),
+            "green" => Some((0, 128, 0)),
+            "blue" => Some((0, 0, 255)),
+            "yellow" => Some((255, 255, 0)),
+            "cyan" => Some((0, 255, 255)),
+            "magenta" => Some((255, 0, 255)),
+            _ => None,
+        };
 
-    /// 上書き許可
-    #[arg(short = 'f', long, default_value = "false")]
-    force: bool,
+        if let Some((red, green, blue)) = named {
+            return Ok(Self {red, green, blue});
+        }
 
-    /// 設定情報の表示
-    #[arg(short = 's', long = "show-options", default_value = "false")]
-    show_options: bool,
+        /*
+         * 16進数形式(#RRGGBB)としての評価
+         */
+        let hex = s.strip_prefix('#')
+            .filter(|hex| hex.len() == 6 && hex.is_ascii())
+            .ok_or_else(|| format!("色の指定が無効: {}", s))?;
 
-    /// 入力ファイルまたはディレクトリ
-    #[arg(required = true)]
-    inputs: Vec<PathBuf>,
+        let component = |range: std::ops::Range<usize>| {
+            u8::from_str_radix(&hex[range], 16)
+                .map_err(|_| format!("色の指定が無効: {}", s))
+        };
 
-    #[arg(skip)]
-    logo_image: Option<RgbaImage>,
+        Ok(Self {
+            red: component(0..2)?,
+            green: component(2..4)?,
+            blue: component(4..6)?,
+        })
+    }
 }
 
-impl Options {
-    ///
-    /// 出力フォルダへのアクセサ
-    ///
-    pub(crate) fn output_path(&self) -> PathBuf {
-        if let Some(path) = &self.output_path {
-            path.clone()
-        } else {
-            PathBuf::from(".")
-        }
+// Displayトレイトの実装
+impl Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.red, self.green, self.blue)
     }
+}
 
+/// Colorに対する実装
+impl Color {
     ///
-    /// ロゴファイルへのアクセサ
+    /// RGBA形式のピクセル値への変換
     ///
-    /// # 注記
-    /// バリデーション関数により、self.logo_file_path がNoneのままこの関数が呼
-    /// ばれることが無いことが保証されている。
+    /// # 戻り値
+    /// 不透明なピクセル値を返す
     ///
-    pub(crate) fn logo_file_path(&self) -> PathBuf {
-        self.logo_file_path.as_ref().unwrap().clone()
+    pub fn to_rgba(self) -> Rgba<u8> {
+        Rgba([self.red, self.green, self.blue, 255])
+    }
+}
+
+///
+/// ロゴの背後に描画する背景の指定を管理する構造体
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backdrop {
+    /// 背景の色
+    color: Color,
+
+    /// 背景の不透明度(0.0〜1.0)
+    alpha: f32,
+}
+
+// FromStrトレイトの実装
+impl FromStr for Backdrop {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (color, alpha) = match s.trim().split_once('@') {
+            Some((color, alpha)) => {
+                let alpha = alpha.trim()
+                    .parse::<f32>()
+                    .map_err(|_| format!("不透明度の指定が無効: {}", s))?;
+
+                (color, alpha)
+            }
+
+            None => (s.trim(), 1.0),
+        };
+
+        if !(0.0..=1.0).contains(&alpha) {
+            return Err(format!("不透明度は0.0〜1.0で指定すること: {}", s));
+        }
+
+        Ok(Self {color: color.trim().parse()?, alpha})
+    }
+}
+
+// Displayトレイトの実装
+impl Display for Backdrop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.color, self.alpha)
     }
+}
 
+/// Backdropに対する実装
+impl Backdrop {
     ///
-    /// ロゴイメージへのアクセサ
+    /// RGBA形式のピクセル値への変換
     ///
-    /// # 注記
-    /// バリデーション関数により、self.logo_image がNoneのままこの関数が呼ばれ
-    /// ることが無いことが保証されている。
+    /// # 戻り値
+    /// 不透明度をアルファ値に反映したピクセル値を返す
     ///
-    pub(crate) fn logo_image(&self) -> &RgbaImage {
-        self.logo_image.as_ref().unwrap()
+    pub fn to_rgba(self) -> Rgba<u8> {
+        let mut pixel = self.color.to_rgba();
+        pixel[3] = (self.alpha * 255.0).round() as u8;
+
+        pixel
     }
+}
 
+///
+/// ロゴの色付けの方法の定義
+///
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[clap(rename_all = "lower")]
+#[serde(rename_all = "lowercase")]
+pub enum TintMode {
+    /// 各チャネルに指定色を乗じる(白い部分が指定色になり濃淡は保たれる)
+    Multiply,
+
+    /// 各チャネルを指定色で置き換える(アルファ値のみを残す)
+    Replace,
+}
+
+// Displayトレイトの実装
+impl Display for TintMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Self::Multiply => "multiply",
+            Self::Replace => "replace",
+        })
+    }
+}
+
+///
+/// 後処理コマンドが失敗した場合の扱いの定義
+///
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[clap(rename_all = "lower")]
+#[serde(rename_all = "lowercase")]
+pub enum PostFailure {
+    /// 残りのファイルの処理を中断してエラーで終了する
+    Abort,
+
+    /// 警告を表示して処理を続ける
+    Warn,
+}
+
+// Displayトレイトの実装
+impl Display for PostFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Self::Abort => "abort",
+            Self::Warn => "warn",
+        })
+    }
+}
+
+///
+/// 出力形式の定義
+///
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[clap(rename_all = "lower")]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// JPEG
+    Jpeg,
+
+    /// WebP
+    Webp,
+
+    /// AVIF
+    Avif,
+
+    /// PNG
+    Png,
+
+    /// 入力ファイルと同じ形式
+    Same,
+}
+
+// Displayトレイトの実装
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Self::Jpeg => "jpeg",
+            Self::Webp => "webp",
+            Self::Avif => "avif",
+            Self::Png => "png",
+            Self::Same => "same",
+        })
+    }
+}
+
+/// OutputFormatに対する実装
+impl OutputFormat {
     ///
-    /// ロゴ展開位置へのアクセサ
+    /// 出力形式に対応する拡張子のリスト
     ///
-    pub(crate) fn logo_position(&self) -> Position {
-        if let Some(position) = self.logo_position {
-            position
-        } else {
-            Position::BottomRight
+    /// # 戻り値
+    /// 拡張子のリストを返す。先頭の要素が出力ファイルに付与する拡張子となる。
+    ///
+    /// # 注記
+    /// `Same`の場合は、入力と同じ形式で出力できない場合に用いるJPEGの拡張子
+    /// を返す。
+    ///
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Self::Jpeg | Self::Same => &["jpg", "jpeg"],
+            Self::Webp => &["webp"],
+            Self::Avif => &["avif"],
+            Self::Png => &["png"],
         }
     }
 
     ///
-    /// 出力解像度へのアクセサ
+    /// 入力画像の形式に対応する出力形式の取得
     ///
-    pub(crate) fn resolution(&self) -> Resolution {
-        if let Some(resolution) = self.resolution {
-            resolution
-        } else {
-            PresetResolution::HD.into()
+    /// # 引数
+    /// * `format` - 入力画像の形式
+    ///
+    /// # 戻り値
+    /// 同じ形式で出力できる場合はその出力形式を`Some()`でラップして返す。出
+    /// 力できない形式の場合は`None`を返す。
+    ///
+    pub fn from_image_format(format: ImageFormat) -> Option<Self> {
+        match format {
+            ImageFormat::Jpeg => Some(Self::Jpeg),
+            ImageFormat::WebP => Some(Self::Webp),
+            ImageFormat::Avif => Some(Self::Avif),
+            ImageFormat::Png => Some(Self::Png),
+            _ => None,
         }
     }
+}
+
+///
+/// JPEG出力時のクロマサブサンプリングの定義
+///
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+pub enum ChromaSubsampling {
+    /// 4:4:4(間引き無し)
+    #[value(name = "444")]
+    #[serde(rename = "444")]
+    S444,
+
+    /// 4:2:2(水平方向のみ1/2)
+    #[value(name = "422")]
+    #[serde(rename = "422")]
+    S422,
+
+    /// 4:2:0(水平・垂直方向とも1/2)
+    #[value(name = "420")]
+    #[serde(rename = "420")]
+    S420,
+}
+
+// Displayトレイトの実装
+impl Display for ChromaSubsampling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Self::S444 => "4:4:4",
+            Self::S422 => "4:2:2",
+            Self::S420 => "4:2:0",
+        })
+    }
+}
 
+/// ChromaSubsamplingに対する実装
+impl ChromaSubsampling {
     ///
-    /// 強制書き込み可否のフラグへのアクセサ
+    /// 色差成分1画素あたりの輝度画素数(水平, 垂直)
     ///
-    pub(crate) fn is_force(&self) -> bool {
-        self.force
+    /// # 戻り値
+    /// mozjpegの`set_chroma_sampling_pixel_sizes()`に渡す値を返す。
+    ///
+    pub fn pixel_sizes(&self) -> (u8, u8) {
+        match self {
+            Self::S444 => (1, 1),
+            Self::S422 => (2, 1),
+            Self::S420 => (2, 2),
+        }
+    }
+}
+
+///
+/// PNG出力時の圧縮レベルの定義
+///
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[clap(rename_all = "lower")]
+#[serde(rename_all = "lowercase")]
+pub enum PngCompression {
+    /// 高速(圧縮率は低い)
+    Fast,
+
+    /// 標準
+    Default,
+
+    /// 最高圧縮(低速)
+    Best,
+}
+
+// Displayトレイトの実装
+impl Display for PngCompression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Self::Fast => "fast",
+            Self::Default => "default",
+            Self::Best => "best",
+        })
+    }
+}
+
+// Fromトレイトの実装
+impl From<PngCompression> for CompressionType {
+    fn from(compression: PngCompression) -> Self {
+        match compression {
+            PngCompression::Fast => CompressionType::Fast,
+            PngCompression::Default => CompressionType::Default,
+            PngCompression::Best => CompressionType::Best,
+        }
+    }
+}
+
+///
+/// 作業用の色深度の定義
+///
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "u8")]
+pub enum ColorDepth {
+    /// 8ビット/チャネル
+    Eight,
+
+    /// 16ビット/チャネル
+    Sixteen,
+}
+
+// TryFromトレイトの実装
+impl TryFrom<u8> for ColorDepth {
+    type Error = String;
+
+    fn try_from(bits: u8) -> Result<Self, Self::Error> {
+        match bits {
+            8 => Ok(Self::Eight),
+            16 => Ok(Self::Sixteen),
+            _ => Err(format!("色深度は8または16で指定すること: {}", bits)),
+        }
+    }
+}
+
+// FromStrトレイトの実装
+impl FromStr for ColorDepth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<u8>()
+            .map_err(|_| format!("色深度の指定が無効: {}", s))
+            .and_then(Self::try_from)
+    }
+}
+
+// Displayトレイトの実装
+impl Display for ColorDepth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Self::Eight => "8bit",
+            Self::Sixteen => "16bit",
+        })
+    }
+}
+
+///
+/// 出力画像の寸法の揃え方の定義
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    /// 偶数に揃える
+    Even,
+
+    /// 8の倍数に揃える(JPEGのMCU)
+    Eight,
+
+    /// 16の倍数に揃える(4:2:0のJPEGのMCUや動画のマクロブロック)
+    Sixteen,
+}
+
+// FromStrトレイトの実装
+impl FromStr for Align {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "even" | "2" => Ok(Self::Even),
+            "8" => Ok(Self::Eight),
+            "16" => Ok(Self::Sixteen),
+            _ => Err(format!("寸法の揃え方はeven、8、16で指定すること: {}", s)),
+        }
+    }
+}
+
+// Displayトレイトの実装
+impl Display for Align {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Self::Even => "even",
+            Self::Eight => "8",
+            Self::Sixteen => "16",
+        })
+    }
+}
+
+/// Alignに対する実装
+impl Align {
+    ///
+    /// 揃える単位のピクセル数へのアクセサ
+    ///
+    pub fn multiple(&self) -> u32 {
+        match self {
+            Self::Even => 2,
+            Self::Eight => 8,
+            Self::Sixteen => 16,
+        }
+    }
+
+    ///
+    /// 寸法の丸め
+    ///
+    /// # 引数
+    /// * `length` - 丸める前の寸法(ピクセル数)
+    /// * `limit` - 超えてはならない寸法(ピクセル数)
+    ///
+    /// # 戻り値
+    /// 最も近い単位の倍数に丸めた寸法を返す。`limit`を超える場合は切り捨て
+    /// る。ただし単位を下回ることはない。
+    ///
+    pub fn apply(&self, length: u32, limit: Option<u32>) -> u32 {
+        let multiple = self.multiple();
+        let mut length = ((length + multiple / 2) / multiple) * multiple;
+
+        if let Some(limit) = limit
+            && length > limit
+        {
+            length = (limit / multiple) * multiple;
+        }
+
+        length.max(multiple)
+    }
+}
+
+///
+/// 出力品質の定義
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quality {
+    /// 固定の品質(1〜100)
+    Fixed(u8),
+
+    /// 入力JPEGの量子化テーブルから推定した品質
+    Auto,
+}
+
+// FromStrトレイトの実装
+impl FromStr for Quality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().eq_ignore_ascii_case("auto") {
+            return Ok(Self::Auto);
+        }
+
+        let quality = s.trim()
+            .parse::<u8>()
+            .map_err(|_| format!("品質の指定が無効: {}", s))?;
+
+        if !(1..=100).contains(&quality) {
+            return Err(format!("品質は1〜100の範囲で指定すること: {}", s));
+        }
+
+        Ok(Self::Fixed(quality))
+    }
+}
+
+// Displayトレイトの実装
+impl Display for Quality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fixed(quality) => write!(f, "{}", quality),
+            Self::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+/// Qualityに対する実装
+impl Quality {
+    ///
+    /// 品質を決定できない場合に用いる品質
+    ///
+    pub const DEFAULT: u8 = 90;
+
+    ///
+    /// 出力品質の決定
+    ///
+    /// # 引数
+    /// * `source` - 入力JPEGから推定した品質(推定できない場合は`None`)
+    ///
+    /// # 戻り値
+    /// 固定の品質の場合はその値を、自動の場合は推定した品質(推定できない場
+    /// 合は既定の品質)を返す。
+    ///
+    pub fn resolve(&self, source: Option<u8>) -> u8 {
+        match self {
+            Self::Fixed(quality) => *quality,
+            Self::Auto => source.unwrap_or(Self::DEFAULT),
+        }
+    }
+}
+
+///
+/// 用途に合わせた出力設定のプリセットの定義
+///
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QualityPreset {
+    /// 社内共有用のスクリーンショット(幅の縮小、縁、隅の注意書き)
+    Screenshot,
+}
+
+// Displayトレイトの実装
+impl Display for QualityPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Self::Screenshot => "screenshot",
+        })
+    }
+}
+
+/// QualityPresetに対する実装
+impl QualityPreset {
+    ///
+    /// スクリーンショットの幅の上限(ピクセル数)
+    ///
+    const SCREENSHOT_MAX_WIDTH: u32 = 1600;
+
+    ///
+    /// スクリーンショットの隅に描画する注意書き
+    ///
+    const SCREENSHOT_TEXT: &'static str = "Confidential — Internal Use";
+}
+
+///
+/// JPEG品質の範囲を管理する構造体
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityRange {
+    /// 品質の下限値
+    min: u8,
+
+    /// 品質の上限値
+    max: u8,
+}
+
+// FromStrトレイトの実装
+impl FromStr for QualityRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (min, max) = s.split_once('-')
+            .ok_or_else(|| format!("品質範囲の形式が不正: {}", s))?;
+
+        let min = min.trim()
+            .parse::<u8>()
+            .map_err(|_| format!("下限値の指定が無効: {}", min))?;
+
+        let max = max.trim()
+            .parse::<u8>()
+            .map_err(|_| format!("上限値の指定が無効: {}", max))?;
+
+        if !(1..=100).contains(&min) || !(1..=100).contains(&max) {
+            return Err(format!("品質は1〜100の範囲で指定すること: {}", s));
+        }
+
+        if min > max {
+            return Err(format!("下限値が上限値を超えている: {}", s));
+        }
+
+        Ok(Self {min, max})
+    }
+}
+
+// Displayトレイトの実装
+impl Display for QualityRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.min, self.max)
+    }
+}
+
+/// QualityRangeに対する実装
+impl QualityRange {
+    ///
+    /// 複雑さに応じた品質の選択
+    ///
+    /// # 引数
+    /// * `complexity` - 画像の複雑さ(0.0〜1.0)
+    ///
+    /// # 戻り値
+    /// 複雑さに応じて範囲内で線形に補間した品質値を返す。
+    ///
+    pub fn select(&self, complexity: f32) -> f32 {
+        let span = (self.max - self.min) as f32;
+        (self.min as f32 + span * complexity.clamp(0.0, 1.0)).round()
     }
+}
+
+///
+/// 出力画像の大きさに対するロゴの大きさの指定を管理する構造体
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogoScale {
+    /// 出力画像に対するロゴの大きさ(パーセント)
+    percent: f32,
+
+    /// 高さを基準にするか否か(`false`の場合は幅を基準にする)
+    by_height: bool,
+}
+
+// FromStrトレイトの実装
+impl FromStr for LogoScale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.trim().to_lowercase();
+
+        let (percent, by_height) = if let Some(value) = lower.strip_suffix("%h")
+        {
+            (value, true)
+        } else if let Some(value) = lower.strip_suffix("%w") {
+            (value, false)
+        } else if let Some(value) = lower.strip_suffix('%') {
+            (value, false)
+        } else {
+            return Err(format!("ロゴの大きさの形式が不正: {}", s));
+        };
+
+        let percent = percent.parse::<f32>()
+            .map_err(|_| format!("ロゴの大きさの指定が無効: {}", s))?;
+
+        if !(percent > 0.0 && percent <= 100.0) {
+            return Err(format!("ロゴの大きさは0〜100%で指定すること: {}", s));
+        }
+
+        Ok(Self {percent, by_height})
+    }
+}
+
+// Displayトレイトの実装
+impl Display for LogoScale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}%{}", self.percent, if self.by_height { "h" } else { "" })
+    }
+}
+
+/// LogoScaleに対する実装
+impl LogoScale {
+    ///
+    /// 縮尺後のロゴの大きさの算出
+    ///
+    /// # 引数
+    /// * `logo` - ロゴ画像の幅と高さ
+    /// * `image` - ロゴを重畳する画像の幅と高さ
+    ///
+    /// # 戻り値
+    /// 縦横比を保ったまま基準の辺を指定の割合にした幅と高さを返す。
+    ///
+    pub fn scaled_size(&self, logo: (u32, u32), image: (u32, u32))
+        -> (u32, u32)
+    {
+        let (width, height) = self.exact_size(logo, image);
+
+        (
+            (width.round() as u32).max(1),
+            (height.round() as u32).max(1),
+        )
+    }
+
+    ///
+    /// 重畳時のロゴの大きさの算出(小数点以下を丸めない)
+    ///
+    /// # 引数
+    /// * `logo` - ロゴ画像の幅と高さ
+    /// * `image` - ロゴを重畳する画像の幅と高さ
+    ///
+    /// # 戻り値
+    /// 縦横比を保ったまま基準の辺を指定の割合にした幅と高さを返す。
+    ///
+    pub fn exact_size(&self, logo: (u32, u32), image: (u32, u32))
+        -> (f32, f32)
+    {
+        let ratio = if self.by_height {
+            image.1 as f32 * self.percent / 100.0 / logo.1 as f32
+        } else {
+            image.0 as f32 * self.percent / 100.0 / logo.0 as f32
+        };
+
+        (
+            (logo.0 as f32 * ratio).max(1.0),
+            (logo.1 as f32 * ratio).max(1.0),
+        )
+    }
+}
+
+///
+/// ロゴの余白や位置の指定に用いる長さ
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Length {
+    /// ピクセル数での指定
+    Pixels(u32),
+
+    /// 出力画像の幅(または高さ)に対する割合(パーセント)での指定
+    Percent(f32),
+}
+
+// FromStrトレイトの実装
+impl FromStr for Length {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if let Some(percent) = s.strip_suffix('%') {
+            match percent.parse::<f32>() {
+                Ok(percent) if (0.0..=100.0).contains(&percent) => {
+                    Ok(Self::Percent(percent))
+                }
+                _ => Err(format!("割合は0〜100%で指定すること: {}", s)),
+            }
+        } else {
+            s.strip_suffix("px")
+                .unwrap_or(s)
+                .parse::<u32>()
+                .map(Self::Pixels)
+                .map_err(|_| format!("長さの指定が無効: {}", s))
+        }
+    }
+}
+
+// Displayトレイトの実装
+impl Display for Length {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pixels(pixels) => write!(f, "{}px", pixels),
+            Self::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+/// Lengthに対する実装
+impl Length {
+    ///
+    /// ピクセル数への変換
+    ///
+    /// # 引数
+    /// * `length` - 割合の基準となる長さ(ピクセル数)
+    ///
+    /// # 戻り値
+    /// 余白のピクセル数を返す。
+    ///
+    fn to_pixels(self, length: u32) -> u32 {
+        self.to_exact_pixels(length).round() as u32
+    }
+
+    ///
+    /// ピクセル数への変換(小数点以下を丸めない)
+    ///
+    /// # 引数
+    /// * `length` - 割合の基準となる長さ(ピクセル数)
+    ///
+    /// # 戻り値
+    /// 余白のピクセル数を返す。
+    ///
+    fn to_exact_pixels(self, length: u32) -> f32 {
+        match self {
+            Self::Pixels(pixels) => pixels as f32,
+            Self::Percent(percent) => length as f32 * percent / 100.0,
+        }
+    }
+}
+
+///
+/// 水平方向と垂直方向の間隔(余白)の指定を管理する構造体
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spacing {
+    /// 水平方向の間隔
+    x: Length,
+
+    /// 垂直方向の間隔
+    y: Length,
+}
+
+// FromStrトレイトの実装
+impl FromStr for Spacing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(',') {
+            Some((x, y)) => Ok(Self {x: x.parse()?, y: y.parse()?}),
+            None => {
+                let length = s.parse()?;
+                Ok(Self {x: length, y: length})
+            }
+        }
+    }
+}
+
+// Displayトレイトの実装
+impl Display for Spacing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.x == self.y {
+            write!(f, "{}", self.x)
+        } else {
+            write!(f, "{},{}", self.x, self.y)
+        }
+    }
+}
+
+// Defaultトレイトの実装
+impl Default for Spacing {
+    fn default() -> Self {
+        Self {x: Length::Pixels(0), y: Length::Pixels(0)}
+    }
+}
+
+/// Spacingに対する実装
+impl Spacing {
+    ///
+    /// 間隔のピクセル数の算出
+    ///
+    /// # 引数
+    /// * `image` - ロゴを重畳する画像の幅と高さ
+    ///
+    /// # 戻り値
+    /// 水平方向と垂直方向の間隔のピクセル数をタプルで返す。割合での指定は水
+    /// 平方向は画像の幅、垂直方向は画像の高さを基準にする。
+    ///
+    pub fn pixels(&self, image: (u32, u32)) -> (u32, u32) {
+        (self.x.to_pixels(image.0), self.y.to_pixels(image.1))
+    }
+
+    ///
+    /// 間隔のピクセル数の算出(小数点以下を丸めない)
+    ///
+    /// # 引数
+    /// * `image` - ロゴを重畳する画像の幅と高さ
+    ///
+    /// # 戻り値
+    /// `pixels()`と同じ(ただし小数点以下を含む)。
+    ///
+    pub fn exact_pixels(&self, image: (u32, u32)) -> (f32, f32) {
+        (
+            self.x.to_exact_pixels(image.0),
+            self.y.to_exact_pixels(image.1)
+        )
+    }
+}
+
+///
+/// ロゴの位置の座標の指定
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Coordinate {
+    /// 画像の端からの距離
+    length: Length,
+
+    /// 右端(または下端)から測るか否か
+    from_end: bool,
+}
+
+// FromStrトレイトの実装
+impl FromStr for Coordinate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        match s.strip_prefix('-') {
+            Some(length) => Ok(Self {length: length.parse()?, from_end: true}),
+            None => Ok(Self {length: s.parse()?, from_end: false}),
+        }
+    }
+}
+
+// Displayトレイトの実装
+impl Display for Coordinate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", if self.from_end { "-" } else { "" }, self.length)
+    }
+}
+
+/// Coordinateに対する実装
+impl Coordinate {
+    ///
+    /// ロゴの左端(または上端)の座標の算出
+    ///
+    /// # 引数
+    /// * `outer` - ロゴを重畳する画像の幅(または高さ)
+    /// * `inner` - ロゴの幅(または高さ)
+    ///
+    /// # 戻り値
+    /// 右端(下端)から測る場合はロゴの右端(下端)が指定の距離になる座標を返
+    /// す。
+    ///
+    fn resolve(&self, outer: u32, inner: u32) -> i64 {
+        let length = self.length.to_pixels(outer) as i64;
+
+        if self.from_end {
+            outer as i64 - inner as i64 - length
+        } else {
+            length
+        }
+    }
+
+    ///
+    /// ロゴの左端(または上端)の座標の算出(小数点以下を丸めない)
+    ///
+    /// # 引数
+    /// * `outer` - ロゴを重畳する画像の幅(または高さ)
+    /// * `inner` - ロゴの幅(または高さ)
+    ///
+    /// # 戻り値
+    /// `resolve()`と同じ(ただし小数点以下を含む)。
+    ///
+    fn resolve_exact(&self, outer: u32, inner: f32) -> f32 {
+        let length = self.length.to_exact_pixels(outer);
+
+        if self.from_end {
+            outer as f32 - inner - length
+        } else {
+            length
+        }
+    }
+}
+
+///
+/// 座標によるロゴの位置の指定を管理する構造体
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogoOffset {
+    /// 水平方向の座標
+    x: Coordinate,
+
+    /// 垂直方向の座標
+    y: Coordinate,
+}
+
+// FromStrトレイトの実装
+impl FromStr for LogoOffset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((x, y)) = s.split_once(',') else {
+            return Err(format!("座標の形式が不正: {}", s));
+        };
+
+        Ok(Self {x: x.parse()?, y: y.parse()?})
+    }
+}
+
+// Displayトレイトの実装
+impl Display for LogoOffset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{}", self.x, self.y)
+    }
+}
+
+/// LogoOffsetに対する実装
+impl LogoOffset {
+    ///
+    /// ロゴの左上の座標の算出
+    ///
+    /// # 引数
+    /// * `image` - ロゴを重畳する画像の幅と高さ
+    /// * `logo` - ロゴの幅と高さ
+    ///
+    /// # 戻り値
+    /// ロゴの左上の座標をタプルで返す。
+    ///
+    pub fn position(&self, image: (u32, u32), logo: (u32, u32)) -> (i64, i64) {
+        (self.x.resolve(image.0, logo.0), self.y.resolve(image.1, logo.1))
+    }
+
+    ///
+    /// ロゴの左上の座標の算出(小数点以下を丸めない)
+    ///
+    /// # 引数
+    /// * `image` - ロゴを重畳する画像の幅と高さ
+    /// * `logo` - ロゴの幅と高さ
+    ///
+    /// # 戻り値
+    /// ロゴの左上の座標をタプルで返す。
+    ///
+    pub fn exact_position(&self, image: (u32, u32), logo: (f32, f32))
+        -> (f32, f32)
+    {
+        (
+            self.x.resolve_exact(image.0, logo.0),
+            self.y.resolve_exact(image.1, logo.1)
+        )
+    }
+}
+
+///
+/// コマンドラインで指定できるサブコマンドの定義
+///
+/// # 注記
+/// explainは続く引数を通常の実行と同じオプションとして解析し直すので、
+/// `parse()`の中で消費し、呼び出し側には渡さない。
+///
+#[derive(Subcommand, Debug, Clone)]
+enum CliCommand {
+    #[command(flatten)]
+    Run(Command),
+
+    /// 入力ファイルごとの処理内容の判断を表示する(書き込みは行わない)
+    Explain {
+        /// 通常の実行と同じオプションと入力ファイル
+        #[arg(
+            required = true,
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            value_name = "ARGS"
+        )]
+        args: Vec<OsString>,
+    },
+}
+
+///
+/// 実行するサブコマンドの定義
+///
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// 画像のヘッダ情報を走査しJSON形式で出力する(ファイルの書き込みは行わない)
+    Scan {
+        /// 走査対象のファイルまたはディレクトリ
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+    },
+
+    /// コンフィギュレーションファイルに関する操作を行う
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+}
+
+///
+/// configサブコマンドの操作の定義
+///
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigCommand {
+    /// 2つのコンフィギュレーションファイルの実効設定の差分を表示する
+    Diff {
+        /// 変更前のコンフィギュレーションファイル
+        old: PathBuf,
+
+        /// 変更後のコンフィギュレーションファイル
+        new: PathBuf,
+    },
+}
+
+///
+/// コマンドラインオプションの情報をまとめる構造体
+///
+#[derive(Parser, Debug, Clone, Default)]
+#[command(
+    name = "watermarker",
+    about = "画像に透かしロゴを埋め込むCLIツール",
+    version,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+)]
+pub struct Options {
+    /// コンフィギュレーションファイルのパス
+    #[arg(short = 'c', long = "config-file", value_name = "FILE")]
+    config_file: Option<PathBuf>,
+
+    /// 出力先ディレクトリ
+    #[arg(short = 'o', long = "output-path", value_name= "PATH")]
+    output_path: Option<PathBuf>,
+
+    /// ロゴとして使用する透過PNGファイルのパス
+    #[arg(short = 'l', long = "logo-file-path", value_name = "PATH")]
+    logo_file_path: Option<PathBuf>,
+
+    /// 明るい背景用のロゴ(暗い色のロゴ)として使用する透過PNGファイルのパス
+    ///
+    /// 指定した場合は、画像ごとにロゴを配置する領域の平均輝度を調べ、-lのロ
+    /// ゴとこのロゴのうち背景との輝度の差が大きい方を重畳する。敷き詰める場
+    /// 合は画像全体の平均輝度で選ぶ。
+    #[arg(long = "logo-dark-file-path", value_name = "PATH",
+        conflicts_with = "text")]
+    logo_dark_file_path: Option<PathBuf>,
+
+    /// ロゴ画像の代わりに透かしとして描画する文字列
+    ///
+    /// 描画した文字列をロゴとして扱うので、大きさや配置などのロゴに対する
+    /// 指定はそのまま適用される。--fontの指定が必要。{filename}や
+    /// {exif.date}などの変数は入力ファイルごとに展開する。{seq}と
+    /// {seq_total}は処理対象全体の中での番号とファイル数、{dir_seq}と
+    /// {dir_total}は同じディレクトリの中での番号とファイル数に展開し、
+    /// {seq:3}のように桁数を指定すると0で埋める。
+    ///
+    /// 使用例:
+    ///   --text "{seq} / {seq_total}" --font font.ttf
+    #[arg(long = "text", value_name = "TEXT",
+        conflicts_with = "logo_file_path")]
+    text: Option<String>,
+
+    /// 文字列の描画に使用するフォントファイル(TrueType/OpenType)のパス
+    #[arg(long = "font", value_name = "PATH")]
+    font: Option<PathBuf>,
+
+    /// 文字列の描画サイズ(ピクセル数、省略時は48)
+    #[arg(long = "text-size", value_name = "PIXELS")]
+    text_size: Option<f32>,
+
+    /// 文字列の色(色名または#RRGGBB形式、省略時はwhite)
+    #[arg(long = "text-color", value_name = "COLOR")]
+    text_color: Option<Color>,
+
+    /// ファイル名とキャプションの対応を記述したCSVファイルのパス
+    ///
+    /// 1列目にファイル名(またはパス)、2列目にキャプションを記述する。透か
+    /// しの文字列や代替テキストの{caption}を入力ファイルごとにこの内容で展
+    /// 開する(記述の無いファイルは{exif.caption}と同じ値になる)。
+    ///
+    /// 使用例:
+    ///   --text "{caption}" --font font.ttf --captions captions.csv
+    #[arg(long = "captions", value_name = "FILE")]
+    captions_file: Option<PathBuf>,
+
+    /// テンプレートの日時に用いるタイムゾーン
+    ///
+    /// {date}の処理日と、EXIF情報に時差が記録されている場合の撮影日時をこ
+    /// のタイムゾーンの地方時で展開する(省略時は処理日をUTC、撮影日時を記
+    /// 録されたままとする)。"Asia/Tokyo"のようなIANAの名前(システムのタイ
+    /// ムゾーン情報を用いる)、"UTC"、"+09:00"のような時差で指定する。日時
+    /// の変数は{exif.datetime:%Y年%m月%d日}のように書式を指定できる。
+    ///
+    /// 使用例:
+    ///   --text "{exif.datetime:%Y年%-m月%-d日 %H:%M}" --timezone Asia/Tokyo
+    #[arg(long = "timezone", value_name = "ZONE")]
+    timezone_name: Option<String>,
+
+    /// ロゴの配置位置
+    ///
+    /// AUTOを指定すると、四隅と各辺の中央の候補ごとにリサイズ後の画像のロゴ
+    /// が重なる領域のエッジの密度と輝度のばらつきを調べ、最も細部の少ない位
+    /// 置に配置する(画像ごとに選ぶ)。
+    #[arg(short = 'p', long = "logo-position", value_enum,
+        value_name = "POSITION")]
+    logo_position: Option<Position>,
+
+    /// ロゴの不透明度(0.0〜1.0、省略時は1.0)
+    ///
+    /// ロゴのアルファ値にこの値を乗じてから重畳する。
+    #[arg(long = "logo-opacity", value_name = "OPACITY")]
+    logo_opacity: Option<f32>,
+
+    /// 出力画像の大きさに対するロゴの大きさ
+    ///
+    /// 出力画像の幅に対する割合で指定する。末尾に"h"を付けると高さに対する
+    /// 割合になる。省略時はロゴ画像を元の大きさのまま重畳する。
+    ///
+    /// 使用例:
+    ///   --logo-scale 10%
+    ///   --logo-scale 8%h
+    #[arg(long = "logo-scale", value_name = "PERCENT")]
+    logo_scale: Option<LogoScale>,
+
+    /// 出力画像の大きさに対するロゴの大きさの上限(0.0〜1.0、省略時は1.0)
+    ///
+    /// ロゴの幅(高さ)が出力画像の幅(高さ)にこの値を乗じた大きさを超える場合
+    /// は、縦横比を保ったまま収まるように縮小する。大きなロゴを小さな画像に
+    /// 重畳した際にロゴが画像からはみ出すのを防ぐ。敷き詰める場合は無視され
+    /// る。
+    #[arg(long = "logo-max-coverage", value_name = "RATIO")]
+    logo_max_coverage: Option<f32>,
+
+    /// 大きく縮小したロゴに適用するシャープ化の強度(0.0〜5.0)
+    ///
+    /// ロゴが元の大きさの半分未満に縮小される場合にのみ、ロゴだけに適用して
+    /// 細い線の潰れを抑える。写真には適用しない。省略時は行わない。
+    #[arg(long = "logo-sharpen", value_name = "AMOUNT")]
+    logo_sharpen: Option<f32>,
+
+    /// ロゴの回転角(度、反時計回り)
+    ///
+    /// リサイズ後のロゴを回転させてから配置する。回転したロゴに外接する矩形
+    /// を配置位置・余白・座標の基準にする。敷き詰める場合は敷き詰めの角度に
+    /// 加えて回転する。
+    #[arg(
+        long = "logo-rotation",
+        value_name = "DEGREES",
+        allow_hyphen_values = true
+    )]
+    logo_rotation: Option<f32>,
+
+    /// ロゴの輪郭に沿った縁取りの幅(ピクセル数)
+    ///
+    /// 明るい空に白いロゴを重ねる場合などに、ロゴが背景に埋もれないよう輪郭
+    /// を--logo-halo-colorの色で縁取る。1〜2ピクセル程度が目安。
+    #[arg(long = "logo-outline", value_name = "PIXELS")]
+    logo_outline: Option<u32>,
+
+    /// ロゴの影のぼかしの半径(ピクセル数)
+    ///
+    /// ロゴの輪郭をぼかした影を右下に少しずらして重ねる。影の色は
+    /// --logo-halo-colorで指定する。
+    #[arg(long = "logo-shadow", value_name = "PIXELS")]
+    logo_shadow: Option<u32>,
+
+    /// ロゴの縁取りと影の色(色名または#RRGGBB形式、省略時はblack)
+    #[arg(long = "logo-halo-color", value_name = "COLOR")]
+    logo_halo_color: Option<Color>,
+
+    /// ロゴの背後に描画する角丸の矩形の色と不透明度("色@不透明度"の形式)
+    ///
+    /// 背景が細かく入り組んだ画像でもロゴが読めるよう、ロゴ(縁取りと影を含
+    /// む)の周囲を少し広げた角丸の矩形を描画してから重畳する。色は色名また
+    /// は#RRGGBB形式、不透明度は0.0〜1.0で指定する(省略時は1.0)。
+    ///
+    /// 使用例:
+    ///   --logo-backdrop black@0.4
+    ///   --logo-backdrop "#FFFFFF@0.6"
+    #[arg(long = "logo-backdrop", value_name = "COLOR@ALPHA")]
+    logo_backdrop: Option<Backdrop>,
+
+    /// ロゴの背後に描画する矩形をロゴの周囲に広げる幅(ピクセル数)
+    ///
+    /// 省略時はロゴの短辺の20%とする。--logo-backdropの指定が無い場合は無
+    /// 視する。
+    #[arg(long = "logo-backdrop-padding", value_name = "PIXELS")]
+    logo_backdrop_padding: Option<u32>,
+
+    /// ロゴの背後に描画する矩形の角の丸めの半径(ピクセル数)
+    ///
+    /// 0を指定すると角を丸めない。省略時は--logo-backdrop-paddingと同じ値と
+    /// する。
+    #[arg(long = "logo-backdrop-radius", value_name = "PIXELS")]
+    logo_backdrop_radius: Option<u32>,
+
+    /// ロゴの色付けに用いる色(色名または#RRGGBB形式)
+    ///
+    /// 白いマスターロゴを顧客ごとの色で重畳する場合などに、重畳の前にロゴ
+    /// の色を--logo-tint-modeの方法で変える。アルファ値は変えない。
+    #[arg(long = "logo-tint", value_name = "COLOR")]
+    logo_tint: Option<Color>,
+
+    /// ロゴを読み込む際に彩度を落として無彩色にする
+    ///
+    /// 色のあるロゴを中立的な透かしとして使う場合に、無彩色版の画像を別に用
+    /// 意せずに済む。--logo-tintの指定がある場合は無彩色にしてから色付けす
+    /// る。
+    #[arg(long = "logo-grayscale", default_value = "false")]
+    logo_grayscale: bool,
+
+    /// ロゴの色付けの方法
+    ///
+    /// multiplyは各チャネルに指定色を乗じる(濃淡は保たれる)。replaceは各
+    /// チャネルを指定色で置き換える(ロゴの形のみを残す)。省略時はmultiply。
+    #[arg(long = "logo-tint-mode", value_enum, value_name = "MODE")]
+    logo_tint_mode: Option<TintMode>,
+
+    /// ロゴと画像の端との間の余白
+    ///
+    /// ピクセル数または出力画像の幅(高さ)に対する割合で指定する。水平方向と
+    /// 垂直方向を","で区切って個別に指定することもできる。省略時は0。
+    ///
+    /// 使用例:
+    ///   --logo-margin 16
+    ///   --logo-margin 2%
+    ///   --logo-margin 24px,2%
+    #[arg(long = "logo-margin", value_name = "MARGIN")]
+    logo_margin: Option<Spacing>,
+
+    /// ロゴの左上の座標("X,Y"の形式)
+    ///
+    /// ピクセル数または出力画像の幅(高さ)に対する割合で指定する。負の値は
+    /// 右端(下端)からロゴの右端(下端)までの距離を表す。指定した場合はロゴ
+    /// の配置位置と余白の指定は無視される。
+    ///
+    /// 使用例:
+    ///   --logo-offset 40,30
+    ///   --logo-offset=-5%,-5%
+    #[arg(long = "logo-offset", value_name = "X,Y", allow_hyphen_values = true)]
+    logo_offset: Option<LogoOffset>,
+
+    /// ロゴの配置位置をファイルごとにずらす範囲
+    ///
+    /// 配置位置から上下左右に最大でこの範囲だけ、ファイルごとに無作為にロゴ
+    /// をずらす(決まった位置のロゴを機械的に切り取られるのを防ぐ)。ピクセ
+    /// ル数または出力画像の幅(高さ)に対する割合で指定し、水平方向と垂直方向
+    /// を","で区切って個別に指定することもできる。画像に収まっているロゴは
+    /// ずらした後も画像からはみ出さない。敷き詰める場合は無視される。
+    ///
+    /// 使用例:
+    ///   --logo-jitter 5%
+    ///   --logo-jitter 40,20
+    #[arg(long = "logo-jitter", value_name = "RANGE")]
+    logo_jitter: Option<Spacing>,
+
+    /// ロゴをずらす量を決める乱数の種
+    ///
+    /// 指定した場合は同じファイル名の入力に対して常に同じだけずらす(再実行
+    /// しても結果が変わらない)。省略時は実行ごとに変わる。
+    #[arg(long = "jitter-seed", value_name = "SEED")]
+    jitter_seed: Option<u64>,
+
+    /// ロゴの大きさと位置を小数点以下の精度で扱う
+    ///
+    /// ロゴをピクセル境界に揃えず再標本化して合成する。同じ写真から大きさの
+    /// 異なる出力を作る場合に、ロゴの位置が1ピクセル単位で揺れるのを防ぐ。
+    /// 敷き詰める場合には適用されない。
+    #[arg(long = "logo-subpixel", default_value = "false")]
+    logo_subpixel: bool,
+
+    /// ロゴを斜めに繰り返し敷き詰める
+    ///
+    /// 画像全体にロゴを回転させて並べる(転載対策用)。指定した場合はロゴの
+    /// 配置位置・余白・座標の指定は無視される。不透明度は--logo-opacityで指
+    /// 定する。
+    #[arg(long = "tile", default_value = "false")]
+    tile: bool,
+
+    /// 敷き詰める際の角度(度、反時計回り)
+    ///
+    /// 省略時は30度。
+    #[arg(
+        long = "tile-angle",
+        value_name = "DEGREES",
+        allow_hyphen_values = true
+    )]
+    tile_angle: Option<f32>,
+
+    /// 敷き詰める際のロゴ同士の間隔
+    ///
+    /// ピクセル数または出力画像の幅(高さ)に対する割合で指定する。水平方向と
+    /// 垂直方向を","で区切って個別に指定することもできる。省略時はロゴの幅
+    /// (高さ)と同じ間隔を空ける。
+    ///
+    /// 使用例:
+    ///   --tile-spacing 80
+    ///   --tile-spacing 10%,5%
+    #[arg(long = "tile-spacing", value_name = "SPACING")]
+    tile_spacing: Option<Spacing>,
+
+    /// パノラマ画像でロゴを水平方向に繰り返す縦横比(幅÷高さ)
+    ///
+    /// 出力画像の縦横比がこの値以上の場合、配置位置で決まる行にロゴを一定の
+    /// 間隔で繰り返し配置する(等倍で表示した際にロゴが画面外に出てしまうの
+    /// を防ぐ)。--panorama-intervalのみを指定した場合は3.0。敷き詰める場合
+    /// は無視される。
+    #[arg(long = "panorama-ratio", value_name = "RATIO")]
+    panorama_ratio: Option<f32>,
+
+    /// パノラマ画像でロゴを繰り返す間隔
+    ///
+    /// ピクセル数または出力画像の幅に対する割合で指定する。省略時は出力画像
+    /// の高さの1.5倍。
+    ///
+    /// 使用例:
+    ///   --panorama-interval 2400
+    ///   --panorama-interval 25%
+    #[arg(long = "panorama-interval", value_name = "LENGTH")]
+    panorama_interval: Option<Length>,
+
+    /// 出力解像度(プリセット名またはWxH形式)
+    ///
+    /// プリセット名にはQVGA, VGA, SVGA, HD(720p), QuadVGA, FullHD(1080p),
+    /// QHD(1440p), UHD(4k), square-1080が使用できる。コンフィギュレーション
+    /// ファイルの[presets]で独自のプリセット名を定義することもできる。省略時
+    /// はHD。
+    /// 通常は指定解像度と同じ面積になるように縮尺するが、"Wx@A:B"、
+    /// "xH@A:B"または"@A:B,max=N"の形式で縦横比を指定した場合は、導出した
+    /// 矩形に収まるように縮尺する。
+    ///
+    /// 使用例:
+    ///   -r HD
+    ///   -r 1080p
+    ///   -r 1280x720
+    ///   -r 1920x@3:2
+    ///   -r @16:9,max=2048
+    #[arg(short = 'r', long = "resolution")]
+    resolution: Option<Resolution>,
+
+    /// キャンバスサイズ(プリセット名またはWxH形式)
+    ///
+    /// 指定した場合はリサイズ後の画像をこのサイズのキャンバス上に配置し、ロゴ
+    /// はキャンバスを基準に配置する。
+    #[arg(long = "canvas", value_name = "SIZE")]
+    canvas: Option<Resolution>,
+
+    /// キャンバスの背景色(色名または#RRGGBB形式)
+    #[arg(long = "canvas-color", value_name = "COLOR")]
+    canvas_color: Option<Color>,
+
+    /// キャンバス上での画像の配置位置
+    #[arg(long = "canvas-gravity", alias = "gravity", value_enum,
+        value_name = "POSITION")]
+    canvas_gravity: Option<Position>,
+
+    /// 出力画像の周囲に付ける縁の幅(ピクセル数)
+    ///
+    /// リサイズ(キャンバスを指定した場合はキャンバスへの配置)の後に、上下左
+    /// 右をこの幅だけ広げて縁を描画する。出力画像はその分だけ大きくなる。ロ
+    /// ゴは縁を含めた画像を基準に配置するので、縁の内側に置く場合は
+    /// --logo-marginで縁の幅以上の余白を指定する。
+    #[arg(long = "border", value_name = "PX")]
+    border: Option<u32>,
+
+    /// 縁の色(色名または#RRGGBB形式、省略時は白)
+    #[arg(long = "border-color", value_name = "COLOR")]
+    border_color: Option<Color>,
+
+    /// 出力画像の寸法の揃え方(even, 8, 16)
+    ///
+    /// 縮尺を求めた後の幅と高さを指定の倍数に丸める。動画エンコーダに渡す場
+    /// 合や、JPEGのMCU境界に揃えたい場合に使用する。キャンバスサイズは丸め
+    /// の対象としない。
+    #[arg(long = "align", value_name = "MULTIPLE")]
+    align: Option<Align>,
+
+    /// 出力品質(1〜100またはauto)
+    ///
+    /// autoを指定すると入力JPEGの量子化テーブルから元の品質を推定し、同程度
+    /// の品質で出力する(JPEG以外の入力では90を使用する)。
+    #[arg(short = 'q', long = "quality", value_name = "QUALITY")]
+    quality: Option<Quality>,
+
+    /// 用途に合わせた出力設定のプリセット
+    ///
+    /// screenshotを指定すると、幅が1600ピクセルを超える画像を縮小し(拡大は
+    /// 行わない)、薄い灰色の1ピクセルの縁を付け、右下に"Confidential —
+    /// Internal Use"の文字列を半透明の背景付きで描画してPNGで出力する。文字
+    /// 列の描画には--fontの指定が必要(ロゴ画像を指定した場合は文字列の代わ
+    /// りにロゴを重畳する)。個別に指定したオプションやコンフィギュレーショ
+    /// ンファイルの設定はプリセットより優先する。
+    ///
+    /// 使用例:
+    ///   --quality-preset screenshot --font font.ttf
+    #[arg(long = "quality-preset", value_enum, value_name = "PRESET")]
+    quality_preset: Option<QualityPreset>,
+
+    /// 画像の複雑さに応じてJPEG品質を自動選択する
+    #[arg(long = "adaptive-quality", default_value = "false")]
+    adaptive_quality: bool,
+
+    /// 品質自動選択時の品質範囲(MIN-MAX形式)
+    #[arg(long = "quality-range", value_name = "MIN-MAX")]
+    quality_range: Option<QualityRange>,
+
+    /// 処理した画像のEXIF情報を書き出すCSVファイルのパス
+    #[arg(long = "export-metadata", value_name = "FILE")]
+    export_metadata: Option<PathBuf>,
+
+    /// 出力画像の支配的な色と5色のパレットを抽出する
+    ///
+    /// --export-metadataのCSVファイルにdominant_color列とpalette列を、
+    /// --web-bundleのメタデータにpaletteを追加する(ギャラリーのプレースホル
+    /// ダの背景色などに用いる)。
+    #[arg(long = "palette", default_value = "false")]
+    palette: bool,
+
+    /// 出力画像の代替テキストのテンプレート
+    ///
+    /// 透かしの文字列と同じ変数({filename}、{stem}、{exif.caption}など)を
+    /// 入力ファイルごとに展開し、--web-bundleのメタデータにalt、
+    /// --export-metadataのCSVファイルにalt_text列として記録する。
+    /// --alt-text-csvに記述のあるファイルはそちらを優先する。
+    ///
+    /// 使用例:
+    ///   --alt-text "{exif.caption}"
+    ///   --alt-text "Photo of {stem} taken on {exif.date}"
+    #[arg(long = "alt-text", value_name = "TEMPLATE")]
+    alt_text: Option<String>,
+
+    /// ファイル名と代替テキストの対応を記述したCSVファイルのパス
+    ///
+    /// 1列目にファイル名(またはパス)、2列目に代替テキストを記述する。1行目
+    /// の1列目が"filename"の場合は見出しとして読み飛ばす。
+    #[arg(long = "alt-text-csv", value_name = "FILE")]
+    alt_text_csv: Option<PathBuf>,
+
+    /// 監査用の実行記録を追記するJSON Linesファイルのパス
+    #[arg(long = "run-log", value_name = "FILE")]
+    run_log: Option<PathBuf>,
+
+    /// 入力ファイルごとに処理するか否かを決めるコマンド
+    ///
+    /// シェル(WindowsではCMD.EXE)を介して処理の前に実行し、0で終了した場合
+    /// のみ処理する。{input}と{output}は--post-cmdと同様に置き換える。標準
+    /// 出力にJSONオブジェクトを書き出すと、そのファイルに限りposition・
+    /// scale・margin(先頭の透かし)とqualityを上書きできる。
+    ///
+    /// 使用例:
+    ///   --pre-filter "select.sh {input}"
+    ///   (出力例: {"position": "TOP-LEFT", "quality": 85})
+    #[arg(long = "pre-filter", value_name = "COMMAND")]
+    pre_filter: Option<String>,
+
+    /// 出力ファイルを書き込むたびに実行するコマンド
+    ///
+    /// シェル(WindowsではCMD.EXE)を介して実行する。{input}と{output}は入
+    /// 力ファイルと出力ファイルへのパスとして1つの引数のまま渡すので、クォ
+    /// ートで囲む必要はない。タイルに分割した場合はタイルごとに実行する。ス
+    /// キップしたファイルや処理に失敗したファイルに対しては実行しない。
+    ///
+    /// 使用例:
+    ///   --post-cmd "upload.sh {output}"
+    #[arg(long = "post-cmd", value_name = "COMMAND")]
+    post_cmd: Option<String>,
+
+    /// 同時に実行する後処理コマンドの数の上限(省略時は1)
+    ///
+    /// 上限に達した場合は最も古いコマンドの終了を待ってから次のファイルの処
+    /// 理に進む。
+    #[arg(long = "post-jobs", value_name = "N")]
+    post_jobs: Option<usize>,
+
+    /// 後処理コマンドが失敗(0以外で終了)した場合の扱い
+    ///
+    /// abortは残りのファイルの処理を中断してエラーで終了する。warnは警告を
+    /// 表示して処理を続ける。省略時はabort。
+    #[arg(long = "post-failure", value_enum, value_name = "MODE")]
+    post_failure: Option<PostFailure>,
+
+    /// 合成の中間画像を書き出すディレクトリのパス(デバッグ用)
+    ///
+    /// 入力ファイルごとにファイル名のサブディレクトリを作成し、リサイズ後の
+    /// 画像、透かしのレイヤー、そのアルファマスク、合成結果をPNG形式で書き
+    /// 出す。
+    #[arg(long = "debug-dir", value_name = "DIR")]
+    debug_dir: Option<PathBuf>,
+
+    /// 実行前に出力サイズと処理時間を見積もって表示する
+    #[arg(long = "estimate", default_value = "false")]
+    estimate: bool,
+
+    /// ロゴを重畳せずにリサイズと再圧縮のみを行う
+    #[arg(long = "no-watermark", default_value = "false")]
+    no_watermark: bool,
+
+    /// 入力画像が出力解像度以下の場合はリサイズせずにそのままコピーする
+    ///
+    /// 出力解像度にユーザ定義のプリセットを指定し、そのプリセットに
+    /// keep_resolution_if_smallerの指定がある場合はそちらを優先する。
+    #[arg(long = "keep-resolution-if-smaller", default_value = "false")]
+    keep_resolution_if_smaller: bool,
+
+    /// 入力画像がこのサイズより大きい場合のみ処理し、それ以外はそのままコピー
+    /// する(プリセット名またはWxH形式)
+    #[arg(long = "only-if-larger-than", value_name = "SIZE")]
+    only_if_larger_than: Option<Resolution>,
+
+    /// 入力ディレクトリの構造を出力先に再現する
+    #[arg(long = "mirror-tree", default_value = "false")]
+    mirror_tree: bool,
+
+    /// XMPサイドカー等のJPEG以外のファイルも出力先にコピーする
+    /// (--mirror-treeの指定が必要)
+    #[arg(long = "copy-sidecars", default_value = "false",
+        requires = "mirror_tree")]
+    copy_sidecars: bool,
+
+    /// 出力先を入力ファイルと同じ場所のサブフォルダにする(値はフォルダ名、
+    /// 省略時は"watermarked")
+    ///
+    /// 複数の無関係なフォルダを入力に指定した場合でも、それぞれの出力を元の
+    /// ファイルの隣に書き出す。
+    #[arg(long = "output-beside-input", value_name = "NAME", num_args = 0..=1,
+        require_equals = true, default_missing_value = "watermarked",
+        conflicts_with_all = ["output_path", "mirror_tree"])]
+    output_beside_input: Option<String>,
+
+    /// 出力形式
+    ///
+    /// sameを指定すると入力ファイルと同じ形式で出力する(同じ形式で出力でき
+    /// ない入力はJPEGで出力する)。
+    #[arg(long = "output-format", value_enum, value_name = "FORMAT")]
+    output_format: Option<OutputFormat>,
+
+    /// JPEGをプログレッシブ形式で出力する
+    #[arg(long = "progressive", default_value = "false")]
+    progressive: bool,
+
+    /// JPEG出力時のクロマサブサンプリング
+    #[arg(long = "subsampling", value_enum, value_name = "MODE")]
+    subsampling: Option<ChromaSubsampling>,
+
+    /// グレースケールのJPEG入力をグレースケールのまま処理・出力する
+    ///
+    /// ロゴは輝度に変換して重畳する。
+    #[arg(long = "keep-grayscale", default_value = "false")]
+    keep_grayscale: bool,
+
+    /// WebP出力時の品質(0〜100、省略時はJPEGと同じ品質を使用)
+    #[arg(long = "webp-quality", value_name = "QUALITY",
+        value_parser = clap::value_parser!(u8).range(0..=100))]
+    webp_quality: Option<u8>,
+
+    /// WebPを可逆圧縮で出力する
+    #[arg(long = "webp-lossless", default_value = "false")]
+    webp_lossless: bool,
+
+    /// AVIF出力時のエンコード速度(1〜10、小さいほど低速・高圧縮)
+    #[arg(long = "avif-speed", value_name = "SPEED",
+        value_parser = clap::value_parser!(u8).range(1..=10))]
+    avif_speed: Option<u8>,
+
+    /// PNG出力時の圧縮レベル
+    #[arg(long = "png-compression", value_enum, value_name = "LEVEL")]
+    png_compression: Option<PngCompression>,
+
+    /// 作業用の色深度(8または16)
+    ///
+    /// 16を指定するとリサイズと合成を16ビット/チャネルで行い、最後に8ビット
+    /// に変換してエンコードする。16ビットのPNG・TIFF等はその精度のままデコー
+    /// ドする。
+    #[arg(long = "depth", value_name = "BITS")]
+    depth: Option<ColorDepth>,
+
+    /// 16ビットから8ビットへの変換時のディザリング方式
+    #[arg(long = "dither", value_enum, value_name = "METHOD")]
+    dither: Option<Dither>,
+
+    /// 露出を変えて撮影したフレームをHDR合成してから処理する
+    ///
+    /// exifは同じ機種で2秒以内に続けて撮影した露出の異なるフレームを、
+    /// filenameは"_-2"・"_0"・"_+2"・"-ev1"のような露出の印を除いたファイル
+    /// 名が同じで露出の異なるフレームをまとめる(いずれもパスの順で隣り合う
+    /// 9枚までのもののみ)。合成結果はブラケットの先頭のファイルの出力先に書
+    /// き出す。
+    #[arg(long = "hdr-merge", value_enum, value_name = "GROUPING")]
+    hdr_merge: Option<HdrGrouping>,
+
+    /// 大きな出力画像をタイルに分割して出力する際のタイルの一辺(ピクセル数)
+    ///
+    /// 出力画像がこの大きさに収まらない場合、格子状のタイルに分割して
+    /// "<出力ファイル名>_行_列.<拡張子>"に書き出す(1枚の画像を受け付けない
+    /// Deep Zoomビューア向け)。透かしはタイルごとに配置する。
+    #[arg(long = "tile-output", value_name = "PIXELS")]
+    tile_output: Option<u32>,
+
+    /// タイルに分割する際に隣のタイルと重ねるピクセル数(省略時は0)
+    #[arg(long = "tile-overlap", value_name = "PIXELS")]
+    tile_overlap: Option<u32>,
+
+    /// Web向けの出力一式を書き出す
+    ///
+    /// 出力画像に加えて、--web-widthsで指定した幅の縮小版
+    /// ("<出力ファイル名>-<幅>w.<拡張子>")と、大きさ・支配的な色・BlurHash・
+    /// srcsetのエントリを記録したJSON形式のメタデータ("<出力ファイル名>.json")
+    /// を書き出す。小さい画像もそのままコピーせずに処理する。
+    #[arg(long = "web-bundle", default_value = "false",
+        conflicts_with = "tile_output")]
+    web_bundle: bool,
+
+    /// Web向けの出力一式に含める縮小版の幅(","区切り、省略時は640,1280)
+    ///
+    /// 出力画像の幅以上のものは生成しない。
+    #[arg(long = "web-widths", value_name = "WIDTHS", value_delimiter = ',')]
+    web_widths: Vec<u32>,
+
+    /// 適用する3D LUT(.cube形式)のパス
+    ///
+    /// リサイズ後、ロゴの重畳前の画像に適用する。
+    #[arg(long = "lut", value_name = "FILE")]
+    lut_file: Option<PathBuf>,
+
+    /// 適用するトーンカーブ("入力:出力"の制御点をカンマで区切って指定)
+    ///
+    /// 入出力は0〜255で指定し、制御点の間は直線で補間する。3D LUTと併用した
+    /// 場合は3D LUTの後に適用する。
+    ///
+    /// 使用例:
+    ///   --tone-curve 0:10,128:140,255:245
+    #[arg(long = "tone-curve", value_name = "CURVE")]
+    tone_curve: Option<ToneCurve>,
+
+    /// リサイズ後のシャープ化の強度(0.0〜5.0)
+    ///
+    /// 縮小で甘くなった輪郭をアンシャープマスクで補う。0.3〜0.8程度が目安。
+    /// 省略時はシャープ化を行わない。
+    #[arg(long = "sharpen", value_name = "AMOUNT")]
+    sharpen: Option<f32>,
+
+    /// シャープ化を適用するチャネル
+    ///
+    /// lumaは輝度のみに適用するので色の縁取りが出にくい。rgbは各チャネルに
+    /// 個別に適用する。省略時はluma。
+    #[arg(long = "sharpen-mode", value_enum, value_name = "MODE")]
+    sharpen_mode: Option<SharpenMode>,
+
+    /// 上書き許可
+    #[arg(short = 'f', long, default_value = "false")]
+    force: bool,
+
+    /// 完了やエラーをメッセージボックスで通知し、入力の隣にログを残す
+    ///
+    /// エクスプローラーの「送る」やドラッグ&ドロップ等、コンソール無しで起
+    /// 動する場合に使用する。
+    #[arg(long = "gui-errors", default_value = "false")]
+    gui_errors: bool,
+
+    /// 設定情報の表示
+    #[arg(short = 's', long = "show-options", default_value = "false")]
+    show_options: bool,
+
+    /// 入力画像をクリップボードから読み込む
+    #[arg(long = "from-clipboard", default_value = "false",
+        conflicts_with = "inputs")]
+    from_clipboard: bool,
+
+    /// 出力画像をファイルではなくクリップボードに書き込む
+    #[arg(long = "to-clipboard", default_value = "false")]
+    to_clipboard: bool,
+
+    /// 入力ファイルまたはディレクトリ
+    #[arg(required_unless_present = "from_clipboard")]
+    inputs: Vec<PathBuf>,
+
+    #[arg(skip)]
+    logo_orientations: OrientationRules,
+
+    #[arg(skip)]
+    extra_logos: Vec<WatermarkSpec>,
+
+    #[arg(skip)]
+    watermarks: Vec<Watermark>,
+
+    #[arg(skip)]
+    lut: Option<CubeLut>,
+
+    #[arg(skip)]
+    alt_texts: Option<TextTable>,
+
+    #[arg(skip)]
+    captions: Option<TextTable>,
+
+    #[arg(skip)]
+    timezone: Option<TimeZone>,
+
+    #[arg(skip)]
+    sequences: Arc<HashMap<PathBuf, Sequence>>,
+
+    #[arg(skip)]
+    layer_cache: Arc<LayerCache>,
+
+    #[arg(skip)]
+    jitter_key: u64,
+
+    #[arg(skip)]
+    explain: bool,
+
+    #[arg(skip)]
+    config_keys: Vec<&'static str>,
+
+    /// サブコマンド
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+}
+
+impl Options {
+    ///
+    /// 出力フォルダへのアクセサ
+    ///
+    pub(crate) fn output_path(&self) -> PathBuf {
+        if let Some(path) = &self.output_path {
+            path.clone()
+        } else {
+            PathBuf::from(".")
+        }
+    }
+
+    ///
+    /// 入力ファイルの隣に作成する出力フォルダの名前へのアクセサ
+    ///
+    /// # 戻り値
+    /// 入力ファイルの隣に出力しない場合は`None`を返す。
+    ///
+    pub(crate) fn output_beside_input(&self) -> Option<String> {
+        self.output_beside_input.clone()
+    }
+
+    ///
+    /// ロゴファイルへのアクセサ
+    ///
+    /// # 注記
+    /// バリデーション関数により、self.logo_file_path がNoneのままこの関数が呼
+    /// ばれることが無いことが保証されている。
+    ///
+    pub(crate) fn logo_file_path(&self) -> PathBuf {
+        self.logo_file_path.as_ref().unwrap().clone()
+    }
+
+    ///
+    /// 明るい背景用のロゴファイルへのアクセサ
+    ///
+    pub(crate) fn logo_dark_file_path(&self) -> Option<PathBuf> {
+        self.logo_dark_file_path.clone()
+    }
+
+    ///
+    /// 透かしとして描画する文字列へのアクセサ
+    ///
+    /// # 戻り値
+    /// 文字列が指定されていない場合は`None`を返す。
+    ///
+    pub(crate) fn text(&self) -> Option<String> {
+        self.text.clone()
+    }
+
+    ///
+    /// 文字列の描画に使用するフォントへのアクセサ
+    ///
+    pub(crate) fn font(&self) -> Option<PathBuf> {
+        self.font.clone()
+    }
+
+    ///
+    /// 文字列の描画サイズへのアクセサ
+    ///
+    pub(crate) fn text_size(&self) -> f32 {
+        self.text_size.unwrap_or(DEFAULT_TEXT_SIZE)
+    }
+
+    ///
+    /// 文字列の色へのアクセサ
+    ///
+    pub(crate) fn text_color(&self) -> Color {
+        self.text_color.unwrap_or(Color {red: 255, green: 255, blue: 255})
+    }
+
+    ///
+    /// キャプションの対応表へのパスへのアクセサ
+    ///
+    pub(crate) fn captions_file(&self) -> Option<PathBuf> {
+        self.captions_file.clone()
+    }
+
+    ///
+    /// キャプションの対応表へのアクセサ
+    ///
+    pub(crate) fn captions(&self) -> Option<&TextTable> {
+        self.captions.as_ref()
+    }
+
+    ///
+    /// テンプレートの日時に用いるタイムゾーンへのアクセサ
+    ///
+    /// # 戻り値
+    /// タイムゾーンが指定されていない場合は`None`を返す。
+    ///
+    /// # 注記
+    /// タイムゾーンはバリデーション関数で読み込まれる。
+    ///
+    pub(crate) fn timezone(&self) -> Option<&TimeZone> {
+        self.timezone.as_ref()
+    }
+
+    ///
+    /// 処理対象のファイルに番号を割り当てたオプション情報の取得
+    ///
+    /// # 引数
+    /// * `inputs` - 処理する順に並べた入力ファイルへのパスのリスト
+    ///
+    /// # 戻り値
+    /// テンプレートの`{seq}`などで参照する番号を設定したオプション情報を返
+    /// す。
+    ///
+    pub(crate) fn with_sequences(self: &Arc<Self>, inputs: &[PathBuf])
+        -> Arc<Self>
+    {
+        let mut opts = (**self).clone();
+        opts.sequences = Arc::new(Sequence::assign(inputs));
+        Arc::new(opts)
+    }
+
+    ///
+    /// 入力ファイルに割り当てた番号の取得
+    ///
+    /// # 引数
+    /// * `input_path` - 入力ファイルへのパス
+    ///
+    /// # 戻り値
+    /// 入力ファイルの番号を返す。番号が割り当てられていないファイル(クリッ
+    /// プボードからの入力など)では全て1を返す。
+    ///
+    fn sequence_of(&self, input_path: &Path) -> Sequence {
+        self.sequences.get(input_path).copied().unwrap_or_default()
+    }
+
+    ///
+    /// 入力ファイルに合わせたテンプレートの展開
+    ///
+    /// # 引数
+    /// * `text` - テンプレート文字列
+    /// * `input_path` - 入力ファイルへのパス
+    ///
+    /// # 戻り値
+    /// 展開した文字列を`Ok()`でラップして返す。失敗した場合はエラー情報を
+    /// `Err()`でラップして返す。
+    ///
+    pub(crate) fn expand_template(&self, text: &str, input_path: &Path)
+        -> Result<String>
+    {
+        template::expand(
+            text,
+            input_path,
+            self.captions(),
+            self.timezone(),
+            self.sequence_of(input_path),
+        )
+            .map_err(|err| anyhow!(err))
+    }
+
+    ///
+    /// 入力ファイルに合わせたオプション情報の取得
+    ///
+    /// # 引数
+    /// * `input_path` - 入力ファイルへのパス
+    /// * `warnings` - 警告の格納先
+    ///
+    /// # 戻り値
+    /// 透かしの文字列がテンプレート変数を含む場合は、変数を入力ファイルに合
+    /// わせて展開し描画し直した透かしを持つオプション情報を`Ok()`でラップし
+    /// て返す。それ以外の場合は自身をそのまま返す。描画に失敗した場合はエラ
+    /// ー情報を`Err()`でラップして返す。
+    ///
+    /// # 注記
+    /// 展開した結果が空白のみになる文字列の透かしは、そのファイルでは重畳せ
+    /// ずに`warnings`に警告を追加する。
+    ///
+    pub(crate) fn for_input(
+        self: &Arc<Self>,
+        input_path: &Path,
+        warnings: &mut Vec<String>,
+    ) -> Result<Arc<Self>> {
+        let mut watermarks = Vec::with_capacity(self.watermarks.len());
+        let mut changed = false;
+
+        for mark in &self.watermarks {
+            let Some(template) = mark.template() else {
+                watermarks.push(mark.clone());
+                continue;
+            };
+
+            let text = self.expand_template(template, input_path)
+                .with_context(|| format!(
+                    "{}: cannot expand the text mark",
+                    input_path.display()
+                ))?;
+
+            changed = true;
+
+            if text.trim().is_empty() {
+                let reason = if template::lacks_exif(template, input_path) {
+                    " (no EXIF data)"
+                } else {
+                    ""
+                };
+
+                warnings.push(format!(
+                    "text mark {:?} expands to an empty string{}, skipped",
+                    template,
+                    reason
+                ));
+                continue;
+            }
+
+            watermarks.push(mark.with_text(&text).with_context(|| format!(
+                "{}: cannot render the text mark {:?}",
+                input_path.display(),
+                text
+            ))?);
+        }
+
+        let jitter = watermarks.iter().any(|mark| mark.jitter().is_some());
+
+        if !changed && !jitter {
+            return Ok(self.clone());
+        }
+
+        let mut opts = (**self).clone();
+
+        if jitter {
+            opts.jitter_key =
+                placement::jitter_key(self.jitter_seed(), input_path);
+        }
+
+        if changed {
+            // 透かしの内容が変わるのでキャッシュは共有しない
+            opts.watermarks = watermarks;
+            opts.layer_cache = Arc::default();
+        }
+
+        Ok(Arc::new(opts))
+    }
+
+    ///
+    /// 出力画像の向きに合わせたオプション情報の取得
+    ///
+    /// # 引数
+    /// * `image` - 透かしを重畳する画像の幅と高さ
+    ///
+    /// # 戻り値
+    /// 画像の向きに対する配置の指定がある透かしを置き換えたオプション情報を
+    /// 返す。置き換える透かしが無い場合は自身をそのまま返す。
+    ///
+    pub(crate) fn for_output(self: &Arc<Self>, image: (u32, u32)) -> Arc<Self> {
+        let mut watermarks = self.watermarks.clone();
+        let mut changed = false;
+
+        for mark in &mut watermarks {
+            if let Some(placed) = mark.for_output(image) {
+                *mark = placed;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return self.clone();
+        }
+
+        let mut opts = (**self).clone();
+        opts.watermarks = watermarks;
+
+        // レイヤーのキャッシュはタイルの大きさで引くので、タイルに分割する場
+        // 合は向きの異なる画像と共有しない(分割しない場合は大きさで向きが決
+        // まるので共有できる)
+        if self.tile_output().is_some() {
+            opts.layer_cache = Arc::default();
+        }
+
+        Arc::new(opts)
+    }
+
+    ///
+    /// 前処理コマンドで指定された設定を適用したオプション情報の取得
+    ///
+    /// # 引数
+    /// * `overrides` - 入力ファイルに対する設定
+    ///
+    /// # 戻り値
+    /// 配置の指定を先頭の透かしに、品質の指定を出力品質に適用したオプション
+    /// 情報を返す。
+    ///
+    pub(crate) fn with_overrides(self: &Arc<Self>, overrides: &FileOverrides)
+        -> Arc<Self>
+    {
+        let rule = PlacementOverride {
+            position: overrides.position,
+            scale: overrides.scale,
+            margin: overrides.margin,
+        };
+
+        if rule == PlacementOverride::default() && overrides.quality.is_none()
+        {
+            return self.clone();
+        }
+
+        let mut opts = (**self).clone();
+
+        if let Some(quality) = overrides.quality {
+            opts.quality = Some(quality);
+        }
+
+        if rule != PlacementOverride::default()
+            && let Some(mark) = opts.watermarks.first_mut()
+        {
+            // 配置が変わるのでキャッシュは共有しない
+            *mark = mark.with_placement(rule);
+            opts.layer_cache = Arc::default();
+        }
+
+        Arc::new(opts)
+    }
+
+    ///
+    /// デバッグ用の中間画像の出力を無効にしたオプション情報の取得
+    ///
+    /// # 戻り値
+    /// 中間画像の出力先の指定が無い場合は自身をそのまま返す。
+    ///
+    /// # 注記
+    /// 見積もりのための試行のように、ファイルを書き出さずに処理する場合に用
+    /// いる。
+    ///
+    pub(crate) fn without_debug_dump(self: &Arc<Self>) -> Arc<Self> {
+        if self.debug_dir.is_none() {
+            return self.clone();
+        }
+
+        let mut opts = (**self).clone();
+        opts.debug_dir = None;
+
+        Arc::new(opts)
+    }
+
+    ///
+    /// 透かしのリストへのアクセサ
+    ///
+    /// # 戻り値
+    /// 合成する順に並べた透かしのリストを返す(先頭がコマンドラインまたは
+    /// `[logo]`で指定した透かしで、`[[logo]]`の2つ目以降が続く)。透かし無し
+    /// モードの場合は空のリストを返す。
+    ///
+    pub(crate) fn watermarks(&self) -> &[Watermark] {
+        &self.watermarks
+    }
+
+    ///
+    /// コマンドラインまたは`[logo]`で指定した透かしの指定の生成
+    ///
+    fn logo_spec(&self) -> WatermarkSpec {
+        WatermarkSpec {
+            file_path: self.logo_file_path.clone(),
+            dark_file_path: self.logo_dark_file_path.clone(),
+            text: self.text.clone(),
+            font: self.font.clone(),
+            text_size: self.text_size,
+            text_color: self.text_color,
+            position: self.logo_position,
+            opacity: self.logo_opacity,
+            scale: self.logo_scale,
+            max_coverage: self.logo_max_coverage,
+            sharpen: self.logo_sharpen,
+            rotation: self.logo_rotation,
+            outline: self.logo_outline,
+            shadow: self.logo_shadow,
+            halo_color: self.logo_halo_color,
+            backdrop: self.logo_backdrop,
+            backdrop_padding: self.logo_backdrop_padding,
+            backdrop_radius: self.logo_backdrop_radius,
+            tint: self.logo_tint,
+            tint_mode: self.logo_tint_mode,
+            grayscale: self.logo_grayscale,
+            margin: self.logo_margin,
+            offset: self.logo_offset,
+            jitter: self.logo_jitter,
+            orientations: self.logo_orientations,
+            subpixel: self.logo_subpixel,
+            tile: self.tile,
+            tile_angle: self.tile_angle,
+            tile_spacing: self.tile_spacing,
+            panorama_ratio: self.panorama_ratio,
+            panorama_interval: self.panorama_interval,
+        }
+    }
+
+    ///
+    /// ロゴ展開位置へのアクセサ
+    ///
+    pub(crate) fn logo_position(&self) -> Position {
+        if let Some(position) = self.logo_position {
+            position
+        } else {
+            Position::BottomRight
+        }
+    }
+
+    ///
+    /// ロゴの不透明度へのアクセサ
+    ///
+    pub(crate) fn logo_opacity(&self) -> f32 {
+        self.logo_opacity.unwrap_or(1.0)
+    }
+
+    ///
+    /// ロゴの大きさの指定へのアクセサ
+    ///
+    pub(crate) fn logo_scale(&self) -> Option<LogoScale> {
+        self.logo_scale
+    }
+
+    ///
+    /// ロゴの大きさの上限へのアクセサ
+    ///
+    pub(crate) fn logo_max_coverage(&self) -> f32 {
+        self.logo_max_coverage.unwrap_or(1.0)
+    }
+
+    ///
+    /// 縮小したロゴのシャープ化の強度へのアクセサ
+    ///
+    /// # 戻り値
+    /// シャープ化を行わない場合は`None`を返す。
+    ///
+    pub(crate) fn logo_sharpen(&self) -> Option<f32> {
+        self.logo_sharpen.filter(|amount| *amount > 0.0)
+    }
+
+    ///
+    /// ロゴの回転角へのアクセサ
+    ///
+    /// # 戻り値
+    /// 回転しない場合は`None`を返す。
+    ///
+    pub(crate) fn logo_rotation(&self) -> Option<f32> {
+        self.logo_rotation.filter(|degrees| *degrees % 360.0 != 0.0)
+    }
+
+    ///
+    /// ロゴの縁取りの幅へのアクセサ
+    ///
+    pub(crate) fn logo_outline(&self) -> u32 {
+        self.logo_outline.unwrap_or(0)
+    }
+
+    ///
+    /// ロゴの影のぼかしの半径へのアクセサ
+    ///
+    pub(crate) fn logo_shadow(&self) -> u32 {
+        self.logo_shadow.unwrap_or(0)
+    }
+
+    ///
+    /// ロゴの縁取りと影の色へのアクセサ
+    ///
+    pub(crate) fn logo_halo_color(&self) -> Color {
+        self.logo_halo_color.unwrap_or(DEFAULT_HALO_COLOR)
+    }
+
+    ///
+    /// ロゴの背後に描画する背景へのアクセサ
+    ///
+    pub(crate) fn logo_backdrop(&self) -> Option<Backdrop> {
+        self.logo_backdrop
+    }
+
+    ///
+    /// ロゴの背後に描画する背景を広げる幅へのアクセサ
+    ///
+    pub(crate) fn logo_backdrop_padding(&self) -> Option<u32> {
+        self.logo_backdrop_padding
+    }
+
+    ///
+    /// ロゴの背後に描画する背景の角の丸めの半径へのアクセサ
+    ///
+    pub(crate) fn logo_backdrop_radius(&self) -> Option<u32> {
+        self.logo_backdrop_radius
+    }
+
+    ///
+    /// ロゴの色付けに用いる色へのアクセサ
+    ///
+    pub(crate) fn logo_tint(&self) -> Option<Color> {
+        self.logo_tint
+    }
+
+    ///
+    /// ロゴの色付けの方法へのアクセサ
+    ///
+    pub(crate) fn logo_tint_mode(&self) -> TintMode {
+        self.logo_tint_mode.unwrap_or(TintMode::Multiply)
+    }
+
+    ///
+    /// ロゴを無彩色にするか否かのフラグへのアクセサ
+    ///
+    pub(crate) fn is_logo_grayscale(&self) -> bool {
+        self.logo_grayscale
+    }
+
+    ///
+    /// ロゴの余白へのアクセサ
+    ///
+    pub(crate) fn logo_margin(&self) -> Spacing {
+        self.logo_margin.unwrap_or_default()
+    }
+
+    ///
+    /// ロゴの座標の指定へのアクセサ
+    ///
+    pub(crate) fn logo_offset(&self) -> Option<LogoOffset> {
+        self.logo_offset
+    }
+
+    ///
+    /// ロゴをずらす範囲へのアクセサ
+    ///
+    pub(crate) fn logo_jitter(&self) -> Option<Spacing> {
+        self.logo_jitter
+    }
+
+    ///
+    /// ロゴをずらす量を決める乱数の種へのアクセサ
+    ///
+    pub(crate) fn jitter_seed(&self) -> Option<u64> {
+        self.jitter_seed
+    }
+
+    ///
+    /// 処理中の入力ファイルに対するロゴをずらす量の鍵へのアクセサ
+    ///
+    /// # 注記
+    /// `for_input()`で入力ファイルごとに設定する。
+    ///
+    pub(crate) fn jitter_key(&self) -> u64 {
+        self.jitter_key
+    }
+
+    ///
+    /// ロゴを小数点以下の精度で配置するか否かのフラグへのアクセサ
+    ///
+    pub(crate) fn is_logo_subpixel(&self) -> bool {
+        self.logo_subpixel
+    }
+
+    ///
+    /// ロゴを敷き詰めるか否かのフラグへのアクセサ
+    ///
+    pub(crate) fn is_tile(&self) -> bool {
+        self.tile
+    }
+
+    ///
+    /// ロゴを敷き詰める際の角度へのアクセサ
+    ///
+    pub(crate) fn tile_angle(&self) -> f32 {
+        self.tile_angle.unwrap_or(DEFAULT_TILE_ANGLE)
+    }
+
+    ///
+    /// ロゴを敷き詰める際の間隔へのアクセサ
+    ///
+    /// # 戻り値
+    /// 指定されていない場合は`None`を返す(ロゴの大きさと同じ間隔を用いる)。
+    ///
+    pub(crate) fn tile_spacing(&self) -> Option<Spacing> {
+        self.tile_spacing
+    }
+
+    ///
+    /// パノラマ画像でロゴを繰り返す縦横比へのアクセサ
+    ///
+    /// # 戻り値
+    /// 繰り返さない場合は`None`を返す。
+    ///
+    pub(crate) fn panorama_ratio(&self) -> Option<f32> {
+        match (self.panorama_ratio, self.panorama_interval) {
+            (None, None) => None,
+            (ratio, _) => Some(ratio.unwrap_or(DEFAULT_PANORAMA_RATIO)),
+        }
+    }
+
+    ///
+    /// 出力解像度へのアクセサ
+    ///
+    pub(crate) fn resolution(&self) -> Resolution {
+        if let Some(resolution) = self.resolution {
+            resolution
+        } else {
+            PresetResolution::HD.into()
+        }
+    }
+
+    ///
+    /// キャンバスサイズへのアクセサ
+    ///
+    /// # 戻り値
+    /// キャンバスモードが指定されていない場合は`None`を返す。
+    ///
+    pub(crate) fn canvas(&self) -> Option<Resolution> {
+        self.canvas
+    }
+
+    ///
+    /// キャンバスの背景色へのアクセサ
+    ///
+    pub(crate) fn canvas_color(&self) -> Color {
+        if let Some(color) = self.canvas_color {
+            color
+        } else {
+            Color {red: 255, green: 255, blue: 255}
+        }
+    }
+
+    ///
+    /// 縁の幅へのアクセサ
+    ///
+    /// # 戻り値
+    /// 縁を付けない場合(幅が0の場合を含む)は`None`を返す。
+    ///
+    pub(crate) fn border(&self) -> Option<u32> {
+        self.border.filter(|width| *width > 0)
+    }
+
+    ///
+    /// 縁の色へのアクセサ
+    ///
+    pub(crate) fn border_color(&self) -> Color {
+        self.border_color.unwrap_or(Color {red: 255, green: 255, blue: 255})
+    }
+
+    ///
+    /// 出力画像の寸法の揃え方へのアクセサ
+    ///
+    pub(crate) fn align(&self) -> Option<Align> {
+        self.align
+    }
+
+    ///
+    /// キャンバス上での画像の配置位置へのアクセサ
+    ///
+    pub(crate) fn canvas_gravity(&self) -> Position {
+        if let Some(gravity) = self.canvas_gravity {
+            gravity
+        } else {
+            Position::Center
+        }
+    }
+
+    ///
+    /// 品質自動選択の可否のフラグへのアクセサ
+    ///
+    pub(crate) fn is_adaptive_quality(&self) -> bool {
+        self.adaptive_quality
+    }
+
+    ///
+    /// 出力品質へのアクセサ
+    ///
+    pub(crate) fn quality(&self) -> Quality {
+        self.quality.unwrap_or(Quality::Fixed(Quality::DEFAULT))
+    }
+
+    ///
+    /// 出力設定のプリセットへのアクセサ
+    ///
+    /// # 戻り値
+    /// プリセットが指定されていない場合は`None`を返す。
+    ///
+    /// # 注記
+    /// プリセットの各項目は設定情報の構築時に個別の設定へ展開済みである。
+    ///
+    pub(crate) fn quality_preset(&self) -> Option<QualityPreset> {
+        self.quality_preset
+    }
+
+    ///
+    /// 品質自動選択時の品質範囲へのアクセサ
+    ///
+    pub(crate) fn quality_range(&self) -> QualityRange {
+        if let Some(range) = self.quality_range {
+            range
+        } else {
+            QualityRange {min: 65, max: 92}
+        }
+    }
+
+    ///
+    /// メタデータのエクスポート先へのアクセサ
+    ///
+    /// # 戻り値
+    /// エクスポートが指定されていない場合は`None`を返す。
+    ///
+    pub(crate) fn export_metadata(&self) -> Option<PathBuf> {
+        self.export_metadata.clone()
+    }
+
+    ///
+    /// パレットを抽出するか否かのフラグへのアクセサ
+    ///
+    pub(crate) fn is_palette(&self) -> bool {
+        self.palette
+    }
+
+    ///
+    /// 代替テキストのテンプレートへのアクセサ
+    ///
+    pub(crate) fn alt_text(&self) -> Option<String> {
+        self.alt_text.clone()
+    }
+
+    ///
+    /// 代替テキストの対応表へのパスへのアクセサ
+    ///
+    pub(crate) fn alt_text_csv(&self) -> Option<PathBuf> {
+        self.alt_text_csv.clone()
+    }
+
+    ///
+    /// 入力ファイルに対する代替テキストの生成
+    ///
+    /// # 引数
+    /// * `input_path` - 入力ファイルへのパス
+    ///
+    /// # 戻り値
+    /// 対応表に記述がある場合はその文字列を、そうでない場合はテンプレートを
+    /// 展開した文字列を`Ok(Some())`でラップして返す。代替テキストの指定が無
+    /// い場合は`Ok(None)`を返す。展開に失敗した場合はエラー情報を`Err()`で
+    /// ラップして返す。
+    ///
+    pub(crate) fn alt_text_for(&self, input_path: &Path)
+        -> Result<Option<String>>
+    {
+        if let Some(text) = self.alt_texts
+            .as_ref()
+            .and_then(|table| table.get(input_path))
+        {
+            return Ok(Some(text.to_string()));
+        }
+
+        self.alt_text
+            .as_ref()
+            .map(|text| self.expand_template(text, input_path))
+            .transpose()
+    }
+
+    ///
+    /// 実行記録の書き込み先へのアクセサ
+    ///
+    /// # 戻り値
+    /// 実行記録が指定されていない場合は`None`を返す。
+    ///
+    pub(crate) fn run_log(&self) -> Option<PathBuf> {
+        self.run_log.clone()
+    }
+
+    ///
+    /// 前処理コマンドへのアクセサ
+    ///
+    /// # 戻り値
+    /// 前処理コマンドが指定されていない場合は`None`を返す。
+    ///
+    pub(crate) fn pre_filter(&self) -> Option<&str> {
+        self.pre_filter.as_deref()
+    }
+
+    ///
+    /// 後処理コマンドへのアクセサ
+    ///
+    /// # 戻り値
+    /// 後処理コマンドが指定されていない場合は`None`を返す。
+    ///
+    pub(crate) fn post_cmd(&self) -> Option<&str> {
+        self.post_cmd.as_deref()
+    }
+
+    ///
+    /// 同時に実行する後処理コマンドの数の上限へのアクセサ
+    ///
+    pub(crate) fn post_jobs(&self) -> usize {
+        self.post_jobs.unwrap_or(1)
+    }
+
+    ///
+    /// 後処理コマンドが失敗した場合の扱いへのアクセサ
+    ///
+    pub(crate) fn post_failure(&self) -> PostFailure {
+        self.post_failure.unwrap_or(PostFailure::Abort)
+    }
+
+    ///
+    /// 中間画像の書き出し先へのアクセサ
+    ///
+    /// # 戻り値
+    /// デバッグ出力が指定されていない場合は`None`を返す。
+    ///
+    pub(crate) fn debug_dir(&self) -> Option<PathBuf> {
+        self.debug_dir.clone()
+    }
+
+    ///
+    /// 見積もり表示の可否のフラグへのアクセサ
+    ///
+    pub(crate) fn is_estimate(&self) -> bool {
+        self.estimate
+    }
+
+    ///
+    /// クリップボードから入力するか否かのフラグへのアクセサ
+    ///
+    pub(crate) fn is_from_clipboard(&self) -> bool {
+        self.from_clipboard
+    }
+
+    ///
+    /// クリップボードへ出力するか否かのフラグへのアクセサ
+    ///
+    pub(crate) fn is_to_clipboard(&self) -> bool {
+        self.to_clipboard
+    }
+
+    ///
+    /// 透かし無しモードか否かのフラグへのアクセサ
+    ///
+    pub(crate) fn is_no_watermark(&self) -> bool {
+        self.no_watermark
+    }
+
+    ///
+    /// 小さい画像をそのままコピーするか否かのフラグへのアクセサ
+    ///
+    /// # 注記
+    /// 出力解像度にユーザ定義のプリセットを指定し、そのプリセットで指定があ
+    /// る場合はそちらを優先する。
+    ///
+    pub(crate) fn is_keep_resolution_if_smaller(&self) -> bool {
+        self.resolution()
+            .keep_if_smaller()
+            .unwrap_or(self.keep_resolution_if_smaller)
+    }
+
+    ///
+    /// 処理対象とする画像サイズの下限へのアクセサ
+    ///
+    /// # 戻り値
+    /// 下限が指定されていない場合は`None`を返す。
+    ///
+    pub(crate) fn only_if_larger_than(&self) -> Option<Resolution> {
+        self.only_if_larger_than
+    }
+
+    ///
+    /// ディレクトリ構造を維持するか否かのフラグへのアクセサ
+    ///
+    pub(crate) fn is_mirror_tree(&self) -> bool {
+        self.mirror_tree
+    }
+
+    ///
+    /// 付随ファイルをコピーするか否かのフラグへのアクセサ
+    ///
+    pub(crate) fn is_copy_sidecars(&self) -> bool {
+        self.copy_sidecars
+    }
+
+    ///
+    /// 出力形式へのアクセサ
+    ///
+    pub(crate) fn output_format(&self) -> OutputFormat {
+        if let Some(format) = self.output_format {
+            format
+        } else {
+            OutputFormat::Jpeg
+        }
+    }
+
+    ///
+    /// WebP出力時の品質へのアクセサ
+    ///
+    /// # 戻り値
+    /// 品質が指定されていない場合は`None`を返す。
+    ///
+    pub(crate) fn webp_quality(&self) -> Option<f32> {
+        self.webp_quality.map(|quality| quality as f32)
+    }
+
+    ///
+    /// プログレッシブJPEGで出力するか否かのフラグへのアクセサ
+    ///
+    pub(crate) fn is_progressive(&self) -> bool {
+        self.progressive
+    }
+
+    ///
+    /// JPEG出力時のクロマサブサンプリングへのアクセサ
+    ///
+    pub(crate) fn subsampling(&self) -> ChromaSubsampling {
+        if let Some(subsampling) = self.subsampling {
+            subsampling
+        } else {
+            ChromaSubsampling::S420
+        }
+    }
+
+    ///
+    /// グレースケールの入力をグレースケールのまま処理するか否かのフラグへの
+    /// アクセサ
+    ///
+    pub(crate) fn is_keep_grayscale(&self) -> bool {
+        self.keep_grayscale
+    }
+
+    ///
+    /// WebPを可逆圧縮で出力するか否かのフラグへのアクセサ
+    ///
+    pub(crate) fn is_webp_lossless(&self) -> bool {
+        self.webp_lossless
+    }
+
+    ///
+    /// AVIF出力時のエンコード速度へのアクセサ
+    ///
+    pub(crate) fn avif_speed(&self) -> u8 {
+        self.avif_speed.unwrap_or(6)
+    }
+
+    ///
+    /// PNG出力時の圧縮レベルへのアクセサ
+    ///
+    pub(crate) fn png_compression(&self) -> PngCompression {
+        if let Some(compression) = self.png_compression {
+            compression
+        } else {
+            PngCompression::Default
+        }
+    }
+
+    ///
+    /// 作業用の色深度へのアクセサ
+    ///
+    pub(crate) fn depth(&self) -> ColorDepth {
+        if let Some(depth) = self.depth {
+            depth
+        } else {
+            ColorDepth::Eight
+        }
+    }
+
+    ///
+    /// ディザリング方式へのアクセサ
+    ///
+    pub(crate) fn dither(&self) -> Dither {
+        if let Some(dither) = self.dither {
+            dither
+        } else {
+            Dither::None
+        }
+    }
+
+    ///
+    /// HDR合成のためのブラケットのまとめ方へのアクセサ
+    ///
+    /// # 戻り値
+    /// HDR合成を行わない場合は`None`を返す。
+    ///
+    pub(crate) fn hdr_merge(&self) -> Option<HdrGrouping> {
+        self.hdr_merge
+    }
+
+    ///
+    /// タイルに分割する際のタイルの一辺へのアクセサ
+    ///
+    /// # 戻り値
+    /// タイルに分割しない場合は`None`を返す。
+    ///
+    pub(crate) fn tile_output(&self) -> Option<u32> {
+        self.tile_output
+    }
+
+    ///
+    /// タイルに分割する際の重なりへのアクセサ
+    ///
+    pub(crate) fn tile_overlap(&self) -> u32 {
+        self.tile_overlap.unwrap_or(0)
+    }
+
+    ///
+    /// Web向けの出力一式を書き出すか否かのフラグへのアクセサ
+    ///
+    pub(crate) fn is_web_bundle(&self) -> bool {
+        self.web_bundle
+    }
+
+    ///
+    /// Web向けの出力一式に含める縮小版の幅へのアクセサ
+    ///
+    pub(crate) fn web_widths(&self) -> Vec<u32> {
+        if self.web_widths.is_empty() {
+            web_bundle::DEFAULT_WIDTHS.to_vec()
+        } else {
+            self.web_widths.clone()
+        }
+    }
+
+    ///
+    /// 3D LUTへのアクセサ
+    ///
+    /// # 戻り値
+    /// 3D LUTが指定されていない場合は`None`を返す。
+    ///
+    /// # 注記
+    /// 3D LUTはバリデーション関数で読み込まれる。
+    ///
+    pub(crate) fn lut(&self) -> Option<&CubeLut> {
+        self.lut.as_ref()
+    }
+
+    ///
+    /// 透かしのレイヤーのキャッシュへのアクセサ
+    ///
+    pub(crate) fn layer_cache(&self) -> &LayerCache {
+        &self.layer_cache
+    }
+
+    ///
+    /// トーンカーブへのアクセサ
+    ///
+    pub(crate) fn tone_curve(&self) -> Option<&ToneCurve> {
+        self.tone_curve.as_ref()
+    }
+
+    ///
+    /// シャープ化の強度へのアクセサ
+    ///
+    /// # 戻り値
+    /// シャープ化を行わない場合は`None`を返す。
+    ///
+    pub(crate) fn sharpen(&self) -> Option<f32> {
+        self.sharpen.filter(|amount| *amount > 0.0)
+    }
+
+    ///
+    /// シャープ化を適用するチャネルへのアクセサ
+    ///
+    pub(crate) fn sharpen_mode(&self) -> SharpenMode {
+        self.sharpen_mode.unwrap_or(SharpenMode::Luma)
+    }
+
+    ///
+    /// GUI向けの結果通知を行うか否かのフラグへのアクセサ
+    ///
+    pub(crate) fn is_gui_errors(&self) -> bool {
+        self.gui_errors
+    }
+
+    ///
+    /// 強制書き込み可否のフラグへのアクセサ
+    ///
+    pub(crate) fn is_force(&self) -> bool {
+        self.force
+    }
+
+    ///
+    /// 入力ファイルリストへのアクセサ
+    ///
+    pub(crate) fn inputs(&self) -> Vec<PathBuf> {
+        self.inputs.clone()
+    }
+
+    ///
+    /// サブコマンドへのアクセサ
+    ///
+    /// # 戻り値
+    /// サブコマンドが指定されていない場合は`None`を返す。
+    ///
+    pub(crate) fn command(&self) -> Option<Command> {
+        match &self.command {
+            Some(CliCommand::Run(command)) => Some(command.clone()),
+            _ => None,
+        }
+    }
+
+    ///
+    /// オプション情報モードか否かのフラグへのアクセサ
+    ///
+    /// # 戻り値
+    /// オプション情報表示モードが指定されている場合は`true`が、通常モードのが
+    /// 指定されている場合は`false`が返される。
+    ///
+    pub(crate) fn is_show_options(&self) -> bool {
+        self.show_options
+    }
+
+    ///
+    /// 判断内容の表示モードか否かのフラグへのアクセサ
+    ///
+    /// # 戻り値
+    /// explainサブコマンドが指定されている場合は`true`を返す。
+    ///
+    pub(crate) fn is_explain(&self) -> bool {
+        self.explain
+    }
+
+    ///
+    /// 適用したコンフィギュレーションファイルへのパスへのアクセサ
+    ///
+    /// # 戻り値
+    /// 指定されたパス、または既定のパスにファイルが存在する場合はそのパスを
+    /// 返す。どちらでもない場合は`None`を返す。
+    ///
+    pub(crate) fn config_path(&self) -> Option<PathBuf> {
+        if let Some(path) = &self.config_file {
+            Some(path.clone())
+        } else {
+            let path = default_config_path();
+
+            if path.exists() {
+                Some(path)
+            } else {
+                None
+            }
+        }
+    }
+
+    ///
+    /// オプション設定内容の表示
+    ///
+    pub(crate) fn show_options(&self) {
+        println!("config path:       {:?}", self.config_path());
+        if let Some(name) = self.output_beside_input() {
+            println!("output path:       beside input ({}/)", name);
+        } else {
+            println!("output path:       {:?}", self.output_path());
+        }
+        if self.is_no_watermark() {
+            println!("logo:              disabled");
+        } else {
+            if let Some(text) = self.text() {
+                println!("logo text:         {:?}", text);
+                println!("font:              {:?}", self.font());
+                println!("text size:         {}", self.text_size());
+                println!("text color:        {}", self.text_color());
+                if let Some(path) = self.captions_file()
+                    && let Some(table) = self.captions()
+                {
+                    println!(
+                        "captions:          {:?} ({} files)",
+                        path,
+                        table.len()
+                    );
+                }
+                if let Some(timezone) = self.timezone() {
+                    println!("timezone:          {}", timezone);
+                }
+            } else {
+                println!("logo file path:    {:?}", self.logo_file_path());
+                if let Some(path) = self.logo_dark_file_path() {
+                    println!("logo dark file:    {:?}", path);
+                }
+            }
+            println!("logo position:     {}", self.logo_position());
+            println!("logo opacity:      {}", self.logo_opacity());
+            if let Some(scale) = self.logo_scale() {
+                println!("logo scale:        {}", scale);
+            }
+            println!("logo max coverage: {}", self.logo_max_coverage());
+            if let Some(amount) = self.logo_sharpen() {
+                println!("logo sharpen:      {}", amount);
+            }
+            if let Some(degrees) = self.logo_rotation() {
+                println!("logo rotation:     {}", degrees);
+            }
+            if self.logo_outline() > 0 || self.logo_shadow() > 0 {
+                println!(
+                    "logo halo:         outline {}px, shadow {}px, {}",
+                    self.logo_outline(),
+                    self.logo_shadow(),
+                    self.logo_halo_color()
+                );
+            }
+            if let Some(backdrop) = self.logo_backdrop() {
+                println!("logo backdrop:     {}", backdrop);
+
+                if let Some(padding) = self.logo_backdrop_padding() {
+                    println!("  padding:         {}px", padding);
+                }
+                if let Some(radius) = self.logo_backdrop_radius() {
+                    println!("  radius:          {}px", radius);
+                }
+            }
+            if self.is_logo_grayscale() {
+                println!("logo grayscale:    true");
+            }
+            if let Some(tint) = self.logo_tint() {
+                println!(
+                    "logo tint:         {} ({})",
+                    tint,
+                    self.logo_tint_mode()
+                );
+            }
+            println!("logo margin:       {}", self.logo_margin());
+            if let Some(offset) = self.logo_offset() {
+                println!("logo offset:       {}", offset);
+            }
+            for (orientation, rule) in self.logo_orientations.iter() {
+                println!("logo {:<13}{}", format!("{}:", orientation), rule);
+            }
+            if let Some(range) = self.logo_jitter() {
+                println!("logo jitter:       {}", range);
+                if let Some(seed) = self.jitter_seed() {
+                    println!("jitter seed:       {}", seed);
+                }
+            }
+            if self.is_logo_subpixel() {
+                println!("logo subpixel:     true");
+            }
+            if self.is_tile() {
+                println!("tile angle:        {}", self.tile_angle());
+                if let Some(spacing) = self.tile_spacing() {
+                    println!("tile spacing:      {}", spacing);
+                }
+            } else if let Some(ratio) = self.panorama_ratio() {
+                println!("panorama ratio:    {}", ratio);
+                if let Some(interval) = self.panorama_interval {
+                    println!("panorama interval: {}", interval);
+                }
+            }
+            for spec in &self.extra_logos {
+                println!("extra logo:        {}", spec);
+            }
+        }
+        if let Some(preset) = self.quality_preset() {
+            println!("quality preset:    {}", preset);
+        }
+        println!("output resolution: {}", self.resolution());
+        println!("output format:     {}", self.output_format());
+        println!("keep grayscale:    {}", self.is_keep_grayscale());
+
+        if matches!(
+            self.output_format(),
+            OutputFormat::Jpeg | OutputFormat::Same
+        ) {
+            println!("progressive:       {}", self.is_progressive());
+            println!("subsampling:       {}", self.subsampling());
+        }
+
+        if matches!(
+            self.output_format(),
+            OutputFormat::Webp | OutputFormat::Same
+        ) {
+            println!("webp lossless:     {}", self.is_webp_lossless());
+
+            if let Some(quality) = self.webp_quality() {
+                println!("webp quality:      {}", quality);
+            }
+        }
+
+        if matches!(
+            self.output_format(),
+            OutputFormat::Avif | OutputFormat::Same
+        ) {
+            println!("avif speed:        {}", self.avif_speed());
+        }
+
+        if matches!(
+            self.output_format(),
+            OutputFormat::Png | OutputFormat::Same
+        ) {
+            println!("png compression:   {}", self.png_compression());
+        }
+
+        println!("working depth:     {}", self.depth());
+
+        if self.depth() == ColorDepth::Sixteen {
+            println!("dither:            {}", self.dither());
+        }
+
+        if let Some(grouping) = self.hdr_merge() {
+            println!("hdr merge:         {}", grouping);
+        }
+
+        if let Some(tile) = self.tile_output() {
+            println!(
+                "tile output:       {}px (overlap {}px)",
+                tile,
+                self.tile_overlap()
+            );
+        }
+
+        if self.is_web_bundle() {
+            println!("web bundle:        widths {:?}", self.web_widths());
+        }
+
+        if self.is_palette() {
+            println!("palette:           true");
+        }
+
+        if let Some(text) = self.alt_text() {
+            println!("alt text:          {:?}", text);
+        }
+
+        if let Some(path) = self.alt_text_csv()
+            && let Some(table) = &self.alt_texts
+        {
+            println!("alt text csv:      {:?} ({} files)", path, table.len());
+        }
+
+        if let Some(path) = &self.lut_file {
+            println!("lut:               {:?}", path);
+        }
+
+        if let Some(curve) = self.tone_curve() {
+            println!("tone curve:        {}", curve);
+        }
+
+        if let Some(amount) = self.sharpen() {
+            println!("sharpen:           {} ({})", amount, self.sharpen_mode());
+        }
+
+        if let Some(canvas) = self.canvas() {
+            println!("canvas size:       {}", canvas);
+            println!("canvas color:      {}", self.canvas_color());
+            println!("canvas gravity:    {}", self.canvas_gravity());
+        }
+
+        if let Some(width) = self.border() {
+            println!(
+                "border:            {}px ({})",
+                width,
+                self.border_color()
+            );
+        }
+
+        if let Some(align) = self.align() {
+            println!("align:             {}", align);
+        }
+
+        if self.is_adaptive_quality() {
+            println!("quality range:     {}", self.quality_range());
+        } else {
+            println!("quality:           {}", self.quality());
+        }
+
+        if self.is_mirror_tree() {
+            println!("mirror tree:       true (copy sidecars: {})",
+                self.is_copy_sidecars());
+        }
+
+        if self.is_keep_resolution_if_smaller() {
+            println!("keep if smaller:   true");
+        }
+
+        if let Some(size) = self.only_if_larger_than() {
+            println!("only if larger:    {}", size);
+        }
+
+        if let Some(path) = self.run_log() {
+            println!("run log:           {:?}", path);
+        }
+
+        if let Some(command) = self.pre_filter() {
+            println!("pre filter:        {}", command);
+        }
+
+        if let Some(command) = self.post_cmd() {
+            println!("post command:      {}", command);
+            println!(
+                "post jobs:         {} (on failure: {})",
+                self.post_jobs(),
+                self.post_failure()
+            );
+        }
+
+        if let Some(path) = self.debug_dir() {
+            println!("debug dir:         {:?}", path);
+        }
+    }
+    ///
+    /// 実効設定の一覧の生成
+    ///
+    /// # 戻り値
+    /// コンフィギュレーションファイルのキー名と、デフォルト値を補完した設定値
+    /// の文字列のペアのリストを返す。
+    ///
+    pub(crate) fn effective_settings(&self) -> Vec<(&'static str, String)> {
+        fn optional<T: Display>(value: Option<T>) -> String {
+            value.map_or("(none)".to_string(), |value| value.to_string())
+        }
+
+        vec![
+            ("logo.file_path", optional(
+                self.logo_file_path.as_ref().map(|path| path.display())
+            )),
+            ("logo.dark_file_path", optional(
+                self.logo_dark_file_path.as_ref().map(|path| path.display())
+            )),
+            ("logo.text", optional(self.text())),
+            ("logo.font", optional(
+                self.font.as_ref().map(|path| path.display())
+            )),
+            ("logo.text_size", self.text_size().to_string()),
+            ("logo.text_color", self.text_color().to_string()),
+            ("logo.position", self.logo_position().to_string()),
+            ("logo.opacity", self.logo_opacity().to_string()),
+            ("logo.scale", optional(self.logo_scale())),
+            ("logo.max_coverage", self.logo_max_coverage().to_string()),
+            ("logo.sharpen", optional(self.logo_sharpen())),
+            ("logo.rotation", optional(self.logo_rotation())),
+            ("logo.outline", self.logo_outline().to_string()),
+            ("logo.shadow", self.logo_shadow().to_string()),
+            ("logo.halo_color", self.logo_halo_color().to_string()),
+            ("logo.backdrop", optional(self.logo_backdrop())),
+            (
+                "logo.backdrop_padding",
+                optional(self.logo_backdrop_padding())
+            ),
+            ("logo.backdrop_radius", optional(self.logo_backdrop_radius())),
+            ("logo.tint", optional(self.logo_tint())),
+            ("logo.tint_mode", self.logo_tint_mode().to_string()),
+            ("logo.grayscale", self.is_logo_grayscale().to_string()),
+            ("logo.margin", self.logo_margin().to_string()),
+            ("logo.offset", optional(self.logo_offset())),
+            ("logo.jitter", optional(self.logo_jitter())),
+            ("logo.portrait", optional(self.logo_orientations.portrait)),
+            ("logo.landscape", optional(self.logo_orientations.landscape)),
+            ("logo.square", optional(self.logo_orientations.square)),
+            ("logo.subpixel", self.is_logo_subpixel().to_string()),
+            ("logo.tile", self.is_tile().to_string()),
+            ("logo.tile_angle", self.tile_angle().to_string()),
+            ("logo.tile_spacing", optional(self.tile_spacing())),
+            ("logo.panorama_ratio", optional(self.panorama_ratio())),
+            ("logo.panorama_interval", optional(self.panorama_interval)),
+            ("logo.extra", self.extra_logos.iter()
+                .map(|spec| spec.to_string())
+                .collect::<Vec<_>>()
+                .join("; ")),
+            ("output.resolution", self.resolution().to_string()),
+            ("output.output_path", self.output_path().display().to_string()),
+            ("output.output_beside_input", optional(self.output_beside_input())),
+            ("output.canvas", optional(self.canvas())),
+            ("output.canvas_color", self.canvas_color().to_string()),
+            ("output.canvas_gravity", self.canvas_gravity().to_string()),
+            ("output.border", self.border.unwrap_or(0).to_string()),
+            ("output.border_color", self.border_color().to_string()),
+            ("output.align", optional(self.align())),
+            ("output.quality", self.quality().to_string()),
+            ("output.quality_preset", optional(self.quality_preset())),
+            ("output.adaptive_quality", self.is_adaptive_quality().to_string()),
+            ("output.quality_range", self.quality_range().to_string()),
+            ("output.format", self.output_format().to_string()),
+            ("output.progressive", self.is_progressive().to_string()),
+            ("output.subsampling", self.subsampling().to_string()),
+            ("output.keep_grayscale", self.is_keep_grayscale().to_string()),
+            ("output.webp_quality", optional(self.webp_quality)),
+            ("output.webp_lossless", self.is_webp_lossless().to_string()),
+            ("output.avif_speed", self.avif_speed().to_string()),
+            ("output.png_compression", self.png_compression().to_string()),
+            ("output.depth", self.depth().to_string()),
+            ("output.dither", self.dither().to_string()),
+            ("output.hdr_merge", optional(self.hdr_merge())),
+            ("output.tile_output", optional(self.tile_output())),
+            ("output.tile_overlap", self.tile_overlap().to_string()),
+            ("output.web_bundle", self.is_web_bundle().to_string()),
+            ("output.web_widths", format!("{:?}", self.web_widths())),
+            ("output.palette", self.is_palette().to_string()),
+            ("output.pre_filter", optional(self.pre_filter())),
+            ("output.post_cmd", optional(self.post_cmd())),
+            ("output.post_jobs", self.post_jobs().to_string()),
+            ("output.post_failure", self.post_failure().to_string()),
+            ("output.jitter_seed", optional(self.jitter_seed())),
+            ("output.captions", optional(
+                self.captions_file.as_ref().map(|path| path.display())
+            )),
+            ("output.timezone", optional(self.timezone_name.as_ref())),
+            ("output.alt_text", optional(self.alt_text())),
+            ("output.alt_text_csv", optional(
+                self.alt_text_csv.as_ref().map(|path| path.display())
+            )),
+            ("output.lut", optional(
+                self.lut_file.as_ref().map(|path| path.display())
+            )),
+            ("output.tone_curve", optional(self.tone_curve())),
+            ("output.sharpen", optional(self.sharpen())),
+            ("output.sharpen_mode", self.sharpen_mode().to_string()),
+            ("output.mirror_tree", self.is_mirror_tree().to_string()),
+            ("output.copy_sidecars", self.is_copy_sidecars().to_string()),
+            ("output.keep_resolution_if_smaller",
+                self.is_keep_resolution_if_smaller().to_string()),
+            ("output.only_if_larger_than", optional(self.only_if_larger_than())),
+        ]
+    }
+
+    ///
+    /// コンフィギュレーションファイルのみを適用したオブジェクトの生成
+    ///
+    /// # 引数
+    /// * `path` - コンフィギュレーションファイルへのパス
+    ///
+    /// # 戻り値
+    /// 生成に成功した場合はオブジェクトを`Ok()`でラップして返す。失敗した場合
+    /// はエラー情報を`Err()`でラップして返す。
+    ///
+    fn from_config_file(path: &Path) -> Result<Self> {
+        let mut opts = Self {
+            config_file: Some(path.to_path_buf()),
+            ..Default::default()
+        };
+
+        opts.apply_config()?;
+        opts.apply_quality_preset();
+
+        Ok(opts)
+    }
+
+    ///
+    /// コンフィギュレーションの適用
+    /// 
+    /// # 注記
+    /// config.tomlを読み込みオプション情報に反映する。
+    ///
+    fn apply_config(&mut self) -> Result<()> {
+        let path = if let Some(path) = &self.config_file {
+            // オプションでコンフィギュレーションファイルのパスが指定されて
+            // いる場合、そのパスに何もなければエラー
+            if !path.exists() {
+                return Err(anyhow!("{} is not exists", path.display()));
+            }
+
+            // 指定されたパスを返す
+            path.clone()
+        } else {
+            // 指定されていない場合はデフォルトのパスを返す
+            default_config_path()
+        };
+
+        // この時点でパスに何も無い場合はそのまま何もせず正常終了
+        if !path.exists() {
+            return Ok(());
+        }
+
+        // 指定されたパスにあるのがファイルでなければエラー
+        if !path.is_file() {
+            return Err(anyhow!("{} is not file", path.display()));
+        }
+
+        // そのパスからコンフィギュレーションを読み取る
+        match config::read(&path) {
+            // 読み取れた場合は内容を適用
+            Ok(config) => {
+                // 文字列とロゴ画像は一方のみを適用する(両方ある場合は文字
+                // 列を優先する)
+                if self.logo_file_path.is_none()
+                    && self.text.is_none()
+                    && let Some(text) = config.text()
+                {
+                    self.text = Some(text);
+                }
+
+                if self.logo_file_path.is_none()
+                    && self.text.is_none()
+                    && let Some(path) = config.logo_file_path()
+                {
+                    self.logo_file_path = Some(path);
+                }
+
+                if self.logo_dark_file_path.is_none()
+                    && self.text.is_none()
+                    && let Some(path) = config.logo_dark_file_path()
+                {
+                    self.logo_dark_file_path = Some(path);
+                }
+
+                if self.font.is_none()
+                    && let Some(path) = config.font()
+                {
+                    self.font = Some(path);
+                }
+
+                if self.text_size.is_none()
+                    && let Some(size) = config.text_size()
+                {
+                    self.text_size = Some(size);
+                }
+
+                if self.text_color.is_none()
+                    && let Some(color) = config.text_color()
+                {
+                    self.text_color = Some(color);
+                }
+
+                if self.logo_position.is_none()
+                    && let Some(position) = config.logo_position()
+                {
+                    self.logo_position = Some(position);
+                }
+
+                if self.logo_opacity.is_none()
+                    && let Some(opacity) = config.logo_opacity()
+                {
+                    self.logo_opacity = Some(opacity);
+                }
+
+                if self.logo_scale.is_none()
+                    && let Some(scale) = config.logo_scale()
+                {
+                    self.logo_scale = Some(scale);
+                }
+
+                if self.logo_max_coverage.is_none()
+                    && let Some(ratio) = config.logo_max_coverage()
+                {
+                    self.logo_max_coverage = Some(ratio);
+                }
+
+                if self.logo_sharpen.is_none()
+                    && let Some(amount) = config.logo_sharpen()
+                {
+                    self.logo_sharpen = Some(amount);
+                }
+
+                if self.logo_rotation.is_none()
+                    && let Some(degrees) = config.logo_rotation()
+                {
+                    self.logo_rotation = Some(degrees);
+                }
+
+                if self.logo_outline.is_none()
+                    && let Some(width) = config.logo_outline()
+                {
+                    self.logo_outline = Some(width);
+                }
+
+                if self.logo_shadow.is_none()
+                    && let Some(radius) = config.logo_shadow()
+                {
+                    self.logo_shadow = Some(radius);
+                }
+
+                if self.logo_halo_color.is_none()
+                    && let Some(color) = config.logo_halo_color()
+                {
+                    self.logo_halo_color = Some(color);
+                }
+
+                if self.logo_backdrop.is_none()
+                    && let Some(backdrop) = config.logo_backdrop()
+                {
+                    self.logo_backdrop = Some(backdrop);
+                }
+
+                if self.logo_backdrop_padding.is_none()
+                    && let Some(padding) = config.logo_backdrop_padding()
+                {
+                    self.logo_backdrop_padding = Some(padding);
+                }
+
+                if self.logo_backdrop_radius.is_none()
+                    && let Some(radius) = config.logo_backdrop_radius()
+                {
+                    self.logo_backdrop_radius = Some(radius);
+                }
+
+                if self.logo_tint.is_none()
+                    && let Some(color) = config.logo_tint()
+                {
+                    self.logo_tint = Some(color);
+                }
+
+                if self.logo_tint_mode.is_none()
+                    && let Some(mode) = config.logo_tint_mode()
+                {
+                    self.logo_tint_mode = Some(mode);
+                }
+
+                if !self.logo_grayscale {
+                    self.logo_grayscale = config.logo_grayscale();
+                }
+
+                if self.logo_margin.is_none()
+                    && let Some(margin) = config.logo_margin()
+                {
+                    self.logo_margin = Some(margin);
+                }
+
+                if self.logo_offset.is_none()
+                    && let Some(offset) = config.logo_offset()
+                {
+                    self.logo_offset = Some(offset);
+                }
+
+                if self.logo_jitter.is_none()
+                    && let Some(range) = config.logo_jitter()
+                {
+                    self.logo_jitter = Some(range);
+                }
+
+                if !self.logo_subpixel {
+                    self.logo_subpixel = config.logo_subpixel();
+                }
+
+                if !self.tile {
+                    self.tile = config.tile();
+                }
+
+                if self.tile_angle.is_none()
+                    && let Some(angle) = config.tile_angle()
+                {
+                    self.tile_angle = Some(angle);
+                }
+
+                if self.tile_spacing.is_none()
+                    && let Some(spacing) = config.tile_spacing()
+                {
+                    self.tile_spacing = Some(spacing);
+                }
+
+                if self.panorama_ratio.is_none()
+                    && let Some(ratio) = config.panorama_ratio()
+                {
+                    self.panorama_ratio = Some(ratio);
+                }
+
+                if self.panorama_interval.is_none()
+                    && let Some(interval) = config.panorama_interval()
+                {
+                    self.panorama_interval = Some(interval);
+                }
+
+                // 向きごとの配置と追加の透かしはコンフィギュレーションファ
+                // イルでのみ指定できる
+                self.logo_orientations = config.logo_orientations();
+                self.extra_logos = config.extra_logos();
+
+                if self.resolution.is_none()
+                    && let Some(resolution) = config.output_resolution()
+                {
+                    self.resolution = Some(resolution);
+                }
+
+                if self.output_path.is_none()
+                    && let Some(path) = config.output_path()
+                {
+                    self.output_path = Some(path);
+                }
+
+                if self.output_beside_input.is_none()
+                    && let Some(name) = config.output_beside_input()
+                {
+                    self.output_beside_input = Some(name);
+                }
+
+                if self.canvas.is_none()
+                    && let Some(canvas) = config.canvas()
+                {
+                    self.canvas = Some(canvas);
+                }
+
+                if self.canvas_color.is_none()
+                    && let Some(color) = config.canvas_color()
+                {
+                    self.canvas_color = Some(color);
+                }
+
+                if self.border.is_none()
+                    && let Some(width) = config.border()
+                {
+                    self.border = Some(width);
+                }
+
+                if self.border_color.is_none()
+                    && let Some(color) = config.border_color()
+                {
+                    self.border_color = Some(color);
+                }
+
+                if self.canvas_gravity.is_none()
+                    && let Some(gravity) = config.canvas_gravity()
+                {
+                    self.canvas_gravity = Some(gravity);
+                }
+
+                if self.align.is_none()
+                    && let Some(align) = config.align()
+                {
+                    self.align = Some(align);
+                }
+
+                if self.quality.is_none()
+                    && let Some(quality) = config.quality()
+                {
+                    self.quality = Some(quality);
+                }
+
+                if self.quality_preset.is_none()
+                    && let Some(preset) = config.quality_preset()
+                {
+                    self.quality_preset = Some(preset);
+                }
+
+                if !self.adaptive_quality {
+                    self.adaptive_quality = config.adaptive_quality();
+                }
+
+                if self.output_format.is_none()
+                    && let Some(format) = config.output_format()
+                {
+                    self.output_format = Some(format);
+                }
+
+                if !self.progressive {
+                    self.progressive = config.progressive();
+                }
+
+                if self.subsampling.is_none()
+                    && let Some(subsampling) = config.subsampling()
+                {
+                    self.subsampling = Some(subsampling);
+                }
+
+                if !self.keep_grayscale {
+                    self.keep_grayscale = config.keep_grayscale();
+                }
+
+                if self.webp_quality.is_none()
+                    && let Some(quality) = config.webp_quality()
+                {
+                    self.webp_quality = Some(quality);
+                }
+
+                if !self.webp_lossless {
+                    self.webp_lossless = config.webp_lossless();
+                }
+
+                if self.avif_speed.is_none()
+                    && let Some(speed) = config.avif_speed()
+                {
+                    self.avif_speed = Some(speed);
+                }
+
+                if self.png_compression.is_none()
+                    && let Some(compression) = config.png_compression()
+                {
+                    self.png_compression = Some(compression);
+                }
+
+                if self.depth.is_none()
+                    && let Some(depth) = config.depth()
+                {
+                    self.depth = Some(depth);
+                }
+
+                if self.dither.is_none()
+                    && let Some(dither) = config.dither()
+                {
+                    self.dither = Some(dither);
+                }
+
+                if self.hdr_merge.is_none()
+                    && let Some(grouping) = config.hdr_merge()
+                {
+                    self.hdr_merge = Some(grouping);
+                }
+
+                if self.tile_output.is_none()
+                    && let Some(tile) = config.tile_output()
+                {
+                    self.tile_output = Some(tile);
+                    self.config_keys.push("tile_output");
+                }
+
+                if self.tile_overlap.is_none()
+                    && let Some(overlap) = config.tile_overlap()
+                {
+                    self.tile_overlap = Some(overlap);
+                }
+
+                if !self.web_bundle && config.web_bundle() {
+                    self.web_bundle = true;
+                    self.config_keys.push("web_bundle");
+                }
+
+                if self.web_widths.is_empty()
+                    && let Some(widths) = config.web_widths()
+                {
+                    self.web_widths = widths;
+                }
+
+                if !self.palette {
+                    self.palette = config.palette();
+                }
+
+                if self.jitter_seed.is_none()
+                    && let Some(seed) = config.jitter_seed()
+                {
+                    self.jitter_seed = Some(seed);
+                }
+
+                if self.captions_file.is_none()
+                    && let Some(path) = config.captions()
+                {
+                    self.captions_file = Some(path);
+                }
+
+                if self.timezone_name.is_none()
+                    && let Some(name) = config.timezone()
+                {
+                    self.timezone_name = Some(name);
+                }
+
+                if self.alt_text.is_none()
+                    && let Some(text) = config.alt_text()
+                {
+                    self.alt_text = Some(text);
+                }
+
+                if self.alt_text_csv.is_none()
+                    && let Some(path) = config.alt_text_csv()
+                {
+                    self.alt_text_csv = Some(path);
+                }
+
+                if self.lut_file.is_none()
+                    && let Some(path) = config.lut_file()
+                {
+                    self.lut_file = Some(path);
+                }
+
+                if self.tone_curve.is_none()
+                    && let Some(curve) = config.tone_curve()
+                {
+                    self.tone_curve = Some(curve);
+                }
+
+                if self.sharpen.is_none()
+                    && let Some(amount) = config.sharpen()
+                {
+                    self.sharpen = Some(amount);
+                }
+
+                if self.sharpen_mode.is_none()
+                    && let Some(mode) = config.sharpen_mode()
+                {
+                    self.sharpen_mode = Some(mode);
+                }
+
+                if !self.mirror_tree {
+                    self.mirror_tree = config.mirror_tree();
+                }
+
+                if !self.copy_sidecars {
+                    self.copy_sidecars = config.copy_sidecars();
+                }
+
+                if !self.keep_resolution_if_smaller {
+                    self.keep_resolution_if_smaller =
+                        config.keep_resolution_if_smaller();
+                }
+
+                if self.only_if_larger_than.is_none()
+                    && let Some(size) = config.only_if_larger_than()
+                {
+                    self.only_if_larger_than = Some(size);
+                }
+
+                if self.run_log.is_none()
+                    && let Some(path) = config.run_log()
+                {
+                    self.run_log = Some(path);
+                }
+
+                if self.pre_filter.is_none()
+                    && let Some(command) = config.pre_filter()
+                {
+                    self.pre_filter = Some(command);
+                }
 
-    ///
-    /// 入力ファイルリストへのアクセサ
-    ///
-    pub(crate) fn inputs(&self) -> Vec<PathBuf> {
-        self.inputs.clone()
-    }
+                if self.post_cmd.is_none()
+                    && let Some(command) = config.post_cmd()
+                {
+                    self.post_cmd = Some(command);
+                }
 
-    ///
-    /// オプション情報モードか否かのフラグへのアクセサ
-    ///
-    /// # 戻り値
-    /// オプション情報表示モードが指定されている場合は`true`が、通常モードのが
-    /// 指定されている場合は`false`が返される。
-    ///
-    pub(crate) fn is_show_options(&self) -> bool {
-        self.show_options
-    }
+                if self.post_jobs.is_none()
+                    && let Some(jobs) = config.post_jobs()
+                {
+                    self.post_jobs = Some(jobs);
+                }
 
-    ///
-    /// オプション設定内容の表示
-    ///
-    pub(crate) fn show_options(&self) {
-        let config_path = if let Some(path) = &self.config_file {
-            Some(path.clone())
-        } else {
-            let path = default_config_path();
+                if self.post_failure.is_none()
+                    && let Some(mode) = config.post_failure()
+                {
+                    self.post_failure = Some(mode);
+                }
 
-            if path.exists() {
-                Some(path)
-            } else {
-                None
+                if self.quality_range.is_none()
+                    && let Some(range) = config.quality_range()
+                {
+                    self.quality_range = Some(range);
+                }
+
+                Ok(())
             }
-        };
 
-        println!("config path:       {:?}", config_path);
-        println!("output path:       {:?}", self.output_path());
-        println!("logo file path:    {:?}", self.logo_file_path());
-        println!("logo position:     {}", self.logo_position());
-        println!("output resolution: {}", self.resolution());
+            // エラーが出たらエラー
+            Err(err) => Err(anyhow!("{}", err))
+        }
     }
+
+    ///
+    /// 出力設定のプリセットの適用
     ///
-    /// コンフィギュレーションの適用
-    /// 
     /// # 注記
-    /// config.tomlを読み込みオプション情報に反映する。
+    /// オプションやコンフィギュレーションファイルで指定されていない項目のみ
+    /// をプリセットの値で埋める。文字列はロゴ画像が指定されていない場合のみ
+    /// 設定する。
     ///
-    fn apply_config(&mut self) -> Result<()> {
-        let path = if let Some(path) = &self.config_file {
-            // オプションでコンフィギュレーションファイルのパスが指定されて
-            // いる場合、そのパスに何もなければエラー
-            if !path.exists() {
-                return Err(anyhow!("{} is not exists", path.display()));
-            }
-
-            // 指定されたパスを返す
-            path.clone()
-        } else {
-            // 指定されていない場合はデフォルトのパスを返す
-            default_config_path()
-        };
+    fn apply_quality_preset(&mut self) {
+        match self.quality_preset {
+            Some(QualityPreset::Screenshot) => {
+                if self.resolution.is_none() {
+                    self.resolution = Some(Resolution::max_width(
+                        QualityPreset::SCREENSHOT_MAX_WIDTH
+                    ));
+                }
 
-        // この時点でパスに何も無い場合はそのまま何もせず正常終了
-        if !path.exists() {
-            return Ok(());
-        }
+                if self.border.is_none() {
+                    self.border = Some(1);
+                }
 
-        // 指定されたパスにあるのがファイルでなければエラー
-        if !path.is_file() {
-            return Err(anyhow!("{} is not file", path.display()));
-        }
+                if self.border_color.is_none() {
+                    self.border_color = Some(
+                        Color {red: 200, green: 200, blue: 200}
+                    );
+                }
 
-        // そのパスからコンフィギュレーションを読み取る
-        match config::read(&path) {
-            // 読み取れた場合は内容を適用
-            Ok(config) => {
-                if self.logo_file_path.is_none() {
-                    if let Some(path) = &config.logo_file_path() {
-                        self.logo_file_path = Some(path.clone());
-                    }
+                if self.output_format.is_none() {
+                    self.output_format = Some(OutputFormat::Png);
                 }
 
-                if self.logo_position.is_none() {
-                    if let Some(position) = config.logo_position() {
-                        self.logo_position = Some(position);
+                if self.logo_file_path.is_none() && self.text.is_none() {
+                    self.text = Some(QualityPreset::SCREENSHOT_TEXT.into());
+
+                    if self.text_size.is_none() {
+                        self.text_size = Some(20.0);
                     }
-                }
 
-                if self.resolution.is_none() {
-                    if let Some(resolution) = &config.output_resolution() {
-                        self.resolution = Some(resolution.clone());
+                    if self.logo_backdrop.is_none() {
+                        self.logo_backdrop = Some(Backdrop {
+                            color: Color {red: 0, green: 0, blue: 0},
+                            alpha: 0.5,
+                        });
                     }
                 }
 
-                if self.output_path.is_none() {
-                    if let Some(path) = &config.output_path() {
-                        self.output_path = Some(path.clone());
-                    }
+                if self.logo_position.is_none() {
+                    self.logo_position = Some(Position::BottomRight);
                 }
 
-                Ok(())
+                if self.logo_margin.is_none() {
+                    self.logo_margin = Some(Spacing {
+                        x: Length::Pixels(12),
+                        y: Length::Pixels(12),
+                    });
+                }
             }
 
-            // エラーが出たらエラー
-            Err(err) => Err(anyhow!("{}", err))
+            None => {}
         }
     }
 
@@ -460,31 +4511,309 @@ impl Options {
     /// 設定情報に問題が無い場合は`Ok(())`を返す。問題があった場合はエラー情報
     /// を`Err()`でラップして返す。
     ///
+    /// # 注記
+    /// 最初の問題で打ち切らず、全ての問題を対処方法の提案と共にまとめて報告
+    /// する。
+    ///
     fn validate(&mut self) -> Result<()> {
+        let mut problems = Vec::new();
+
         /*
          * 出力先パスの確認
          */
         if let Some(path) = &self.output_path {
             if !path.is_dir() {
-                return Err(anyhow!(
-                    "output path \"{}\" is not directory",
-                    path.display()
+                problems.push(Problem::new(
+                    format!(
+                        "output path \"{}\" is not directory",
+                        path.display()
+                    ),
+                    "create the directory first or pass an existing one",
+                ));
+            } else if !is_writable_dir(path) {
+                problems.push(Problem::new(
+                    format!(
+                        "output path \"{}\" is not writable",
+                        path.display()
+                    ),
+                    "check the permissions or choose another directory with -o",
+                ));
+            }
+        }
+
+        /*
+         * 入力ファイルの隣に作成する出力フォルダの名前の確認
+         */
+        if let Some(name) = &self.output_beside_input
+            && (name.is_empty() || name.contains(['/', '\\']) || name == "..")
+        {
+            problems.push(Problem::new(
+                format!("output folder name \"{}\" is invalid", name),
+                "use a plain folder name without path separators",
+            ));
+        }
+
+        /*
+         * キャンバス上での配置位置の確認(自動配置はロゴのみ)
+         */
+        if self.canvas_gravity == Some(Position::Auto) {
+            problems.push(Problem::new(
+                "canvas gravity cannot be AUTO".to_string(),
+                "choose a fixed position such as CENTER for --canvas-gravity",
+            ));
+        }
+
+        /*
+         * タイル分割の指定の確認
+         */
+        if let Some(tile) = self.tile_output {
+            if tile < 256 {
+                problems.push(Problem::new(
+                    format!("tile size {} is too small (256 or more)", tile),
+                    "specify the tile edge in pixels, e.g. --tile-output 4096",
+                ));
+            } else if self.tile_overlap() * 2 >= tile {
+                problems.push(Problem::new(
+                    format!(
+                        "tile overlap {} is too large for tile size {}",
+                        self.tile_overlap(),
+                        tile
+                    ),
+                    "keep the overlap below half of the tile size; 1-8 is \
+                     typical for deep zoom viewers",
+                ));
+            }
+
+            if self.web_bundle {
+                // 両方をコマンドラインで指定した場合はclapが弾くので、少なく
+                // とも一方はコンフィギュレーションファイルの指定
+                let hint = match (
+                    self.config_keys.contains(&"web_bundle"),
+                    self.config_keys.contains(&"tile_output"),
+                ) {
+                    (true, false) => {
+                        "drop --tile-output or remove web_bundle from the \
+                         config"
+                    }
+
+                    (false, true) => {
+                        "drop --web-bundle or remove tile_output from the \
+                         config"
+                    }
+
+                    _ => {
+                        "remove either web_bundle or tile_output from the \
+                         config"
+                    }
+                };
+
+                problems.push(Problem::new(
+                    "web bundle cannot be combined with tile output"
+                        .to_string(),
+                    hint,
                 ));
             }
         }
 
         /*
-         * ロゴファイルのパスの確認
+         * 組み込みのコーデックで扱えない出力の指定の確認
          */
-        if let Some(path) = &self.logo_file_path {
-            if !path.is_file() {
-                return Err(anyhow!(
-                    "logo file path \"{}\" is not file",
-                    path.display()
+        #[cfg(not(feature = "native-codecs"))]
+        {
+            if self.output_format() == OutputFormat::Webp
+                && !self.is_webp_lossless()
+            {
+                problems.push(Problem::new(
+                    "lossy WebP output requires the native-codecs feature"
+                        .to_string(),
+                    "add --webp-lossless, or rebuild with the default \
+                     features",
                 ));
             }
-        } else {
-            return Err(anyhow!("logo file path is not specified"));
+
+            if self.progressive {
+                problems.push(Problem::new(
+                    "progressive JPEG requires the native-codecs feature"
+                        .to_string(),
+                    "drop --progressive, or rebuild with the default features",
+                ));
+            }
+
+            if self.subsampling.is_some() {
+                problems.push(Problem::new(
+                    "chroma subsampling requires the native-codecs feature"
+                        .to_string(),
+                    "drop --subsampling, or rebuild with the default features",
+                ));
+            }
+        }
+
+        /*
+         * 3D LUTの読み込み
+         */
+        if let Some(path) = &self.lut_file {
+            match CubeLut::load(path) {
+                Ok(lut) => self.lut = Some(lut),
+                Err(err) => problems.push(Problem::new(
+                    format!("lut file cannot be used: {}", err),
+                    "pass a 3D LUT in .cube format with --lut or [output] lut",
+                )),
+            }
+        }
+
+        /*
+         * タイムゾーンの読み込み
+         */
+        if let Some(name) = &self.timezone_name {
+            match TimeZone::load(name) {
+                Ok(timezone) => self.timezone = Some(timezone),
+                Err(err) => problems.push(Problem::new(
+                    format!("time zone cannot be used: {}", err),
+                    "use an IANA name such as Asia/Tokyo, UTC, or an offset \
+                     such as +09:00",
+                )),
+            }
+        }
+
+        /*
+         * キャプションの対応表の読み込み
+         */
+        if let Some(path) = &self.captions_file {
+            match TextTable::load(path) {
+                Ok(table) => self.captions = Some(table),
+                Err(err) => problems.push(Problem::new(
+                    format!("captions csv cannot be used: {:#}", err),
+                    "pass a CSV file of \"filename,caption\" rows with \
+                     --captions",
+                )),
+            }
+        }
+
+        /*
+         * 代替テキストのテンプレートの確認と対応表の読み込み
+         */
+        if let Some(text) = &self.alt_text
+            && let Err(err) = template::check(text)
+        {
+            problems.push(Problem::new(
+                format!("alt text template is invalid: {}", err),
+                "use variables such as {stem} or {exif.caption}, and {{ }} \
+                 for literal braces",
+            ));
+        }
+
+        if let Some(path) = &self.alt_text_csv {
+            match TextTable::load(path) {
+                Ok(table) => self.alt_texts = Some(table),
+                Err(err) => problems.push(Problem::new(
+                    format!("alt text csv cannot be used: {:#}", err),
+                    "pass a CSV file of \"filename,alt text\" rows with \
+                     --alt-text-csv",
+                )),
+            }
+        }
+
+        /*
+         * シャープ化の強度の確認
+         */
+        if let Some(amount) = self.sharpen
+            && !(0.0..=5.0).contains(&amount)
+        {
+            problems.push(Problem::new(
+                format!("sharpen amount {} is out of range (0.0-5.0)", amount),
+                "specify a value from 0.0 (off) to 5.0; 0.3-0.8 is typical",
+            ));
+        }
+
+        /*
+         * 後処理コマンドの同時実行数の確認
+         */
+        if self.post_jobs == Some(0) {
+            problems.push(Problem::new(
+                "post jobs must be at least 1".to_string(),
+                "specify 1 to run post commands one at a time",
+            ));
+        }
+
+        /*
+         * ロゴの大きさの上限の確認
+         */
+        if let Some(ratio) = self.logo_max_coverage
+            && !(ratio > 0.0 && ratio <= 1.0)
+        {
+            problems.push(Problem::new(
+                format!(
+                    "logo max coverage {} is out of range (0.0-1.0)",
+                    ratio
+                ),
+                "specify a ratio above 0.0 and up to 1.0, such as 0.5",
+            ));
+        }
+
+        /*
+         * 透かしの読み込み(透かし無しモードでは不要)
+         */
+        if !self.no_watermark {
+            let mut watermarks = Vec::new();
+
+            if let Some(mark) = Watermark::load(
+                self.logo_spec(),
+                "logo",
+                &mut problems
+            ) {
+                watermarks.push(mark);
+            }
+
+            for (index, spec) in self.extra_logos.iter().enumerate() {
+                let label = format!("logo #{}", index + 2);
+
+                if let Some(mark) = Watermark::load(
+                    spec.clone(),
+                    &label,
+                    &mut problems
+                ) {
+                    watermarks.push(mark);
+                }
+            }
+
+            self.watermarks = watermarks;
+        }
+
+        /*
+         * 出力品質の確認
+         */
+        if let Some(Quality::Fixed(quality)) = self.quality
+            && !(1..=100).contains(&quality)
+        {
+            problems.push(Problem::new(
+                format!("quality {} is out of range (1-100)", quality),
+                "specify a value from 1 to 100, or \"auto\"",
+            ));
+        }
+
+        /*
+         * WebP出力時の品質の確認(コンフィギュレーションファイル由来の値)
+         */
+        if let Some(quality) = self.webp_quality
+            && quality > 100
+        {
+            problems.push(Problem::new(
+                format!("webp quality {} is out of range (0-100)", quality),
+                "specify a value from 0 to 100",
+            ));
+        }
+
+        /*
+         * AVIF出力時のエンコード速度の確認(コンフィギュレーションファイル由来
+         * の値)
+         */
+        if let Some(speed) = self.avif_speed
+            && !(1..=10).contains(&speed)
+        {
+            problems.push(Problem::new(
+                format!("avif speed {} is out of range (1-10)", speed),
+                "specify a value from 1 (slowest) to 10 (fastest)",
+            ));
         }
 
         /*
@@ -492,21 +4821,77 @@ impl Options {
          */
         for path in self.inputs.iter() {
             if !(path.is_file() || path.is_dir()) {
-                return Err(anyhow!(
-                    "input path \"{}\" is not file or directory",
-                    path.display()
+                problems.push(Problem::new(
+                    format!(
+                        "input path \"{}\" is not file or directory",
+                        path.display()
+                    ),
+                    "check the spelling, or quote paths containing spaces",
                 ));
             }
         }
 
         /*
-         * ロゴファイルの読み込み
+         * クリップボードへの出力時の入力の確認(書き込めるのは1枚のみ)
          */
-        self.logo_image = Some(
-            image::open(self.logo_file_path())?.to_rgba8()
-        );
+        if self.to_clipboard
+            && !self.from_clipboard
+            && (self.inputs.len() != 1 || !self.inputs[0].is_file())
+        {
+            problems.push(Problem::new(
+                "--to-clipboard requires exactly one input file".to_string(),
+                "pass a single image file, or add --from-clipboard",
+            ));
+        }
+
+        match problems.len() {
+            0 => Ok(()),
+            1 => Err(anyhow!(
+                "{}\n  hint: {}",
+                problems[0].message,
+                problems[0].hint
+            )),
+            n => Err(anyhow!(
+                "{} problems found\n{}",
+                n,
+                problems.iter()
+                    .map(|problem| format!(
+                        "  - {}\n    hint: {}",
+                        problem.message,
+                        problem.hint
+                    ))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            )),
+        }
+    }
+}
+
+///
+/// バリデーションで検出した問題を格納する構造体
+///
+struct Problem {
+    /// 問題の内容
+    message: String,
 
-        Ok(())
+    /// 対処方法の提案
+    hint: &'static str,
+}
+
+/// Problemに対する実装
+impl Problem {
+    ///
+    /// オブジェクトの生成
+    ///
+    /// # 引数
+    /// * `message` - 問題の内容
+    /// * `hint` - 対処方法の提案
+    ///
+    /// # 戻り値
+    /// 生成したオブジェクトを返す。
+    ///
+    fn new(message: String, hint: &'static str) -> Self {
+        Self {message, hint}
     }
 }
 
@@ -518,13 +4903,50 @@ impl Options {
 /// して返す。失敗した場合はエラー情報を`Err()`でラップして返す。
 ///
 pub(crate) fn parse() -> Result<Arc<Options>> {
+    /*
+     * ユーザ定義のプリセット解像度の登録(解像度の指定の解析より前に行う)
+     */
+    config::load_presets(
+        &config_path_from_args().unwrap_or_else(default_config_path)
+    )?;
+
+    /*
+     * コマンドラインオプションの解析
+     */
     let mut opts = Options::parse();
 
+    /*
+     * explainサブコマンドの場合は続く引数を通常の実行と同じく解析する
+     */
+    if let Some(CliCommand::Explain {args}) = opts.command.clone() {
+        let name = std::env::args_os().next().unwrap_or_default();
+        let args = std::iter::once(name).chain(args);
+
+        opts = Options::parse_from(args);
+        opts.explain = true;
+
+        if opts.command.is_some() {
+            return Err(anyhow!("explain cannot take another subcommand"));
+        }
+    }
+
+    /*
+     * サブコマンドが指定されている場合は設定情報の適用を行わない
+     */
+    if opts.command.is_some() {
+        return Ok(Arc::new(opts));
+    }
+
     /*
      * コンフィギュレーションファイルの適用
      */
     opts.apply_config()?;
 
+    /*
+     * 出力設定のプリセットの適用(個別の指定が無い項目のみ)
+     */
+    opts.apply_quality_preset();
+
     /*
      * 設定情報のバリデーションとキャッシュの構築
      */
@@ -535,3 +4957,112 @@ pub(crate) fn parse() -> Result<Arc<Options>> {
      */
     Ok(Arc::new(opts))
 }
+
+///
+/// フォルダに書き込めるか否かの判定
+///
+/// # 引数
+/// * `path` - 判定対象のフォルダへのパス
+///
+/// # 戻り値
+/// 試験用のファイルを作成できた場合は`true`を返す。
+///
+/// # 注記
+/// 読み取り専用属性だけではACLや所有者による制限を判断できないので、実際に
+/// ファイルを作成して確かめる。作成したファイルはすぐに削除する。
+///
+fn is_writable_dir(path: &Path) -> bool {
+    let probe = path.join(format!(
+        ".watermarker-write-test-{}",
+        std::process::id()
+    ));
+
+    let created = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .is_ok();
+
+    if created {
+        let _ = std::fs::remove_file(&probe);
+    }
+
+    created
+}
+
+///
+/// コマンドライン引数からのコンフィギュレーションファイルのパスの取得
+///
+/// # 戻り値
+/// `-c`または`--config-file`が指定されている場合はそのパスを返す。指定され
+/// ていない場合は`None`を返す。
+///
+/// # 注記
+/// ユーザ定義のプリセット解像度をオプションの解析前に登録するために、clapを
+/// 介さずに引数を走査する。
+///
+fn config_path_from_args() -> Option<PathBuf> {
+    let mut args = std::env::args_os().skip(1);
+
+    while let Some(arg) = args.next() {
+        let arg = arg.to_string_lossy().into_owned();
+
+        if arg == "--" {
+            break;
+        }
+
+        if arg == "-c" || arg == "--config-file" {
+            return args.next().map(PathBuf::from);
+        }
+
+        if let Some(path) = arg.strip_prefix("--config-file=") {
+            return Some(PathBuf::from(path));
+        }
+
+        if let Some(path) = arg.strip_prefix("-c")
+            && !arg.starts_with("--")
+            && !path.is_empty()
+        {
+            return Some(PathBuf::from(path.trim_start_matches('=')));
+        }
+    }
+
+    None
+}
+
+///
+/// コンフィギュレーションファイルの実効設定の差分の表示
+///
+/// # 引数
+/// * `old` - 変更前のコンフィギュレーションファイルへのパス
+/// * `new` - 変更後のコンフィギュレーションファイルへのパス
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を`Err()`で
+/// ラップして返す。
+///
+/// # 注記
+/// それぞれのファイルを(インクルードを展開した上で)デフォルト値とマージし、
+/// 値が異なる設定項目のみを表示する。コマンドラインオプションは考慮しない。
+///
+pub(crate) fn diff_configs(old: &Path, new: &Path) -> Result<()> {
+    let old_settings = Options::from_config_file(old)?.effective_settings();
+    let new_settings = Options::from_config_file(new)?.effective_settings();
+    let mut changed = 0;
+
+    for ((key, old_value), (_, new_value)) in
+        old_settings.iter().zip(new_settings.iter())
+    {
+        if old_value != new_value {
+            println!("- {} = {}", key, old_value);
+            println!("+ {} = {}", key, new_value);
+            changed += 1;
+        }
+    }
+
+    if changed == 0 {
+        println!("no differences in effective settings");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 誤った指定値に対する候補の提案を行うモジュール
+//!
+
+///
+/// 比較用の正規化
+///
+/// # 注記
+/// 大文字小文字と区切り文字('-'と'_')の違いは無視する。
+///
+fn normalize(s: &str) -> Vec<char> {
+    s.chars()
+        .map(|c| if c == '_' { '-' } else { c.to_ascii_lowercase() })
+        .collect()
+}
+
+///
+/// 編集距離(レーベンシュタイン距離)の算出
+///
+/// # 引数
+/// * `a` - 比較する文字列
+/// * `b` - 比較する文字列
+///
+/// # 戻り値
+/// 一方を他方に変換するのに必要な挿入・削除・置換の最小回数を返す。
+///
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    let mut curr = vec![0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j] + cost)
+                .min(prev[j + 1] + 1)
+                .min(curr[j] + 1);
+        }
+
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+///
+/// 最も近い候補の提案文の生成
+///
+/// # 引数
+/// * `input` - 指定された値
+/// * `candidates` - 有効な値のリスト
+///
+/// # 戻り値
+/// 十分に近い候補がある場合はエラーメッセージに続ける提案文を返す。無い場合
+/// は空文字列を返す。
+///
+/// # 注記
+/// 編集距離が入力長の1/3(最低2)以下の候補のみを提案する。
+///
+pub(super) fn did_you_mean(input: &str, candidates: &[&str]) -> String {
+    let input = normalize(input);
+    let limit = (input.len() / 3).max(2);
+
+    candidates.iter()
+        .map(|candidate| {
+            (edit_distance(&input, &normalize(candidate)), candidate)
+        })
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| format!(" (もしかして: {})", candidate))
+        .unwrap_or_default()
+}
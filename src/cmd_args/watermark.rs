@@ -0,0 +1,1095 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 透かし(ロゴ画像または文字列)1つ分の設定をまとめたモジュール
+//!
+
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use image::RgbaImage;
+
+use super::{
+    Backdrop, Color, Length, LogoOffset, LogoScale, Position, Problem, Spacing,
+    TintMode,
+};
+use crate::analysis;
+use crate::template::{self, Sequence};
+use crate::text;
+
+///
+/// 敷き詰める際の角度の既定値(度)
+///
+pub(super) const DEFAULT_TILE_ANGLE: f32 = 30.0;
+
+///
+/// 文字列の描画サイズの既定値(ピクセル数)
+///
+pub(super) const DEFAULT_TEXT_SIZE: f32 = 48.0;
+
+///
+/// パノラマ画像でロゴを繰り返す縦横比の既定値(幅÷高さ)
+///
+pub(super) const DEFAULT_PANORAMA_RATIO: f32 = 3.0;
+
+///
+/// パノラマ画像でロゴを繰り返す間隔の既定値(出力画像の高さに対する倍率)
+///
+const DEFAULT_PANORAMA_INTERVAL: f32 = 1.5;
+
+///
+/// 縁取りと影の色の既定値
+///
+pub(super) const DEFAULT_HALO_COLOR: Color = Color {red: 0, green: 0, blue: 0};
+
+///
+/// 正方形とみなす縦横比の1からのずれの上限
+///
+const SQUARE_TOLERANCE: f32 = 0.02;
+
+///
+/// 出力画像の向きの定義
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Orientation {
+    /// 縦長
+    Portrait,
+
+    /// 横長
+    Landscape,
+
+    /// 正方形(縦横比のずれが`SQUARE_TOLERANCE`以内)
+    Square,
+}
+
+// Displayトレイトの実装
+impl Display for Orientation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Self::Portrait => "portrait",
+            Self::Landscape => "landscape",
+            Self::Square => "square",
+        })
+    }
+}
+
+/// Orientationに対する実装
+impl Orientation {
+    ///
+    /// 画像の大きさからの向きの判定
+    ///
+    /// # 引数
+    /// * `(width, height)` - 画像の幅と高さ
+    ///
+    pub(crate) fn of((width, height): (u32, u32)) -> Self {
+        let ratio = width as f32 / height.max(1) as f32;
+
+        if (ratio - 1.0).abs() <= SQUARE_TOLERANCE {
+            Self::Square
+        } else if width < height {
+            Self::Portrait
+        } else {
+            Self::Landscape
+        }
+    }
+}
+
+///
+/// 出力画像の向きに応じて置き換える配置の指定を格納する構造体
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct PlacementOverride {
+    /// 配置する場所
+    pub(super) position: Option<Position>,
+
+    /// 出力画像の大きさに対する大きさ
+    pub(super) scale: Option<LogoScale>,
+
+    /// 画像の端との間の余白
+    pub(super) margin: Option<Spacing>,
+}
+
+// Displayトレイトの実装
+impl Display for PlacementOverride {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut items = Vec::new();
+
+        if let Some(position) = self.position {
+            items.push(format!("position {}", position));
+        }
+
+        if let Some(scale) = self.scale {
+            items.push(format!("scale {}", scale));
+        }
+
+        if let Some(margin) = self.margin {
+            items.push(format!("margin {}", margin));
+        }
+
+        if items.is_empty() {
+            write!(f, "(no change)")
+        } else {
+            write!(f, "{}", items.join(", "))
+        }
+    }
+}
+
+///
+/// 出力画像の向きごとの配置の指定を格納する構造体
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct OrientationRules {
+    /// 縦長の場合の指定
+    pub(super) portrait: Option<PlacementOverride>,
+
+    /// 横長の場合の指定
+    pub(super) landscape: Option<PlacementOverride>,
+
+    /// 正方形の場合の指定
+    pub(super) square: Option<PlacementOverride>,
+}
+
+/// OrientationRulesに対する実装
+impl OrientationRules {
+    ///
+    /// 向きに対する指定の取得
+    ///
+    pub(crate) fn get(&self, orientation: Orientation)
+        -> Option<PlacementOverride>
+    {
+        match orientation {
+            Orientation::Portrait => self.portrait,
+            Orientation::Landscape => self.landscape,
+            Orientation::Square => self.square,
+        }
+    }
+
+    ///
+    /// 指定のある向きとその指定の列挙
+    ///
+    pub(crate) fn iter(&self)
+        -> impl Iterator<Item = (Orientation, PlacementOverride)> + '_
+    {
+        [Orientation::Portrait, Orientation::Landscape, Orientation::Square]
+            .into_iter()
+            .filter_map(|orientation| {
+                self.get(orientation).map(|rule| (orientation, rule))
+            })
+    }
+}
+
+///
+/// 透かし1つ分の指定を格納する構造体
+///
+/// # 注記
+/// 未指定の項目は`Watermark`のアクセサで既定値に置き換える。
+///
+#[derive(Debug, Clone, Default)]
+pub(crate) struct WatermarkSpec {
+    /// ロゴに使用する画像ファイルへのパス
+    pub(super) file_path: Option<PathBuf>,
+
+    /// 明るい背景用のロゴに使用する画像ファイルへのパス
+    pub(super) dark_file_path: Option<PathBuf>,
+
+    /// ロゴ画像の代わりに描画する文字列
+    pub(super) text: Option<String>,
+
+    /// 文字列の描画に使用するフォントファイルへのパス
+    pub(super) font: Option<PathBuf>,
+
+    /// 文字列の描画サイズ(ピクセル数)
+    pub(super) text_size: Option<f32>,
+
+    /// 文字列の色
+    pub(super) text_color: Option<Color>,
+
+    /// 配置位置
+    pub(super) position: Option<Position>,
+
+    /// 不透明度(0.0〜1.0)
+    pub(super) opacity: Option<f32>,
+
+    /// 出力画像の大きさに対する大きさ
+    pub(super) scale: Option<LogoScale>,
+
+    /// 出力画像の大きさに対する大きさの上限
+    pub(super) max_coverage: Option<f32>,
+
+    /// 大きく縮小した場合に適用するシャープ化の強度
+    pub(super) sharpen: Option<f32>,
+
+    /// 回転角(度、反時計回り)
+    pub(super) rotation: Option<f32>,
+
+    /// 輪郭に沿った縁取りの幅(ピクセル数)
+    pub(super) outline: Option<u32>,
+
+    /// 影のぼかしの半径(ピクセル数)
+    pub(super) shadow: Option<u32>,
+
+    /// 縁取りと影の色
+    pub(super) halo_color: Option<Color>,
+
+    /// 背後に描画する背景
+    pub(super) backdrop: Option<Backdrop>,
+
+    /// 背景を周囲に広げる幅(ピクセル数)
+    pub(super) backdrop_padding: Option<u32>,
+
+    /// 背景の角の丸めの半径(ピクセル数)
+    pub(super) backdrop_radius: Option<u32>,
+
+    /// 色付けに用いる色
+    pub(super) tint: Option<Color>,
+
+    /// 色付けの方法
+    pub(super) tint_mode: Option<TintMode>,
+
+    /// 無彩色にするか否か
+    pub(super) grayscale: bool,
+
+    /// 画像の端との間の余白
+    pub(super) margin: Option<Spacing>,
+
+    /// 左上の座標
+    pub(super) offset: Option<LogoOffset>,
+
+    /// ファイルごとにずらす範囲
+    pub(super) jitter: Option<Spacing>,
+
+    /// 出力画像の向きごとの配置の指定
+    pub(super) orientations: OrientationRules,
+
+    /// 大きさと位置を小数点以下の精度で扱うか否か
+    pub(super) subpixel: bool,
+
+    /// 斜めに繰り返し敷き詰めるか否か
+    pub(super) tile: bool,
+
+    /// 敷き詰める際の角度(度)
+    pub(super) tile_angle: Option<f32>,
+
+    /// 敷き詰める際の間隔
+    pub(super) tile_spacing: Option<Spacing>,
+
+    /// 水平方向に繰り返す縦横比(幅÷高さ)
+    pub(super) panorama_ratio: Option<f32>,
+
+    /// 水平方向に繰り返す間隔
+    pub(super) panorama_interval: Option<Length>,
+}
+
+// Displayトレイトの実装
+impl Display for WatermarkSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(text) = &self.text {
+            write!(f, "text {:?}", text)?;
+        } else if let Some(path) = &self.file_path {
+            write!(f, "{:?}", path)?;
+
+            if let Some(dark) = &self.dark_file_path {
+                write!(f, " (dark {:?})", dark)?;
+            }
+        } else {
+            write!(f, "(none)")?;
+        }
+
+        write!(f, " at {}", self.position.unwrap_or(Position::BottomRight))?;
+
+        if let Some(scale) = self.scale {
+            write!(f, ", scale {}", scale)?;
+        }
+
+        if let Some(opacity) = self.opacity {
+            write!(f, ", opacity {}", opacity)?;
+        }
+
+        if let Some(degrees) = self.rotation {
+            write!(f, ", rotated {}", degrees)?;
+        }
+
+        if let Some(width) = self.outline.filter(|width| *width > 0) {
+            write!(f, ", outline {}px", width)?;
+        }
+
+        if let Some(radius) = self.shadow.filter(|radius| *radius > 0) {
+            write!(f, ", shadow {}px", radius)?;
+        }
+
+        if let Some(backdrop) = self.backdrop {
+            write!(f, ", backdrop {}", backdrop)?;
+
+            if let Some(padding) = self.backdrop_padding {
+                write!(f, " padding {}px", padding)?;
+            }
+
+            if let Some(radius) = self.backdrop_radius {
+                write!(f, " radius {}px", radius)?;
+            }
+        }
+
+        if self.grayscale {
+            write!(f, ", grayscale")?;
+        }
+
+        if let Some(tint) = self.tint {
+            write!(
+                f,
+                ", tint {} ({})",
+                tint,
+                self.tint_mode.unwrap_or(TintMode::Multiply)
+            )?;
+        }
+
+        if let Some(range) = self.jitter {
+            write!(f, ", jitter {}", range)?;
+        }
+
+        for (orientation, rule) in self.orientations.iter() {
+            write!(f, ", {} {{{}}}", orientation, rule)?;
+        }
+
+        if self.subpixel {
+            write!(f, ", subpixel")?;
+        }
+
+        if self.tile {
+            write!(f, ", tiled")?;
+        } else if self.panorama_ratio.is_some()
+            || self.panorama_interval.is_some()
+        {
+            write!(f, ", panorama")?;
+        }
+
+        Ok(())
+    }
+}
+
+///
+/// 読み込み済みの透かし1つ分の情報を格納する構造体
+///
+#[derive(Debug, Clone)]
+pub(crate) struct Watermark {
+    /// 指定内容
+    spec: WatermarkSpec,
+
+    /// 不透明度を適用済みのロゴ画像(文字列の場合は描画結果)
+    image: RgbaImage,
+
+    /// 不透明度を適用済みの明るい背景用のロゴ画像
+    dark: Option<RgbaImage>,
+}
+
+/// Watermarkに対する実装
+impl Watermark {
+    ///
+    /// 指定に基づく透かしの読み込み
+    ///
+    /// # 引数
+    /// * `spec` - 透かしの指定
+    /// * `label` - 問題を報告する際の透かしの呼び名
+    /// * `problems` - 検出した問題の格納先
+    ///
+    /// # 戻り値
+    /// 読み込みに成功した場合は透かしの情報を`Some()`でラップして返す。問題
+    /// があった場合は`problems`に追加して`None`を返す。
+    ///
+    /// # 注記
+    /// 文字列がテンプレート変数を含む場合は入力ファイルごとに描画し直すので、
+    /// ここではテンプレートのまま描画してフォントが使えることを確認する。
+    ///
+    pub(super) fn load(
+        spec: WatermarkSpec,
+        label: &str,
+        problems: &mut Vec<Problem>,
+    ) -> Option<Self> {
+        let count = problems.len();
+
+        /*
+         * 数値の範囲の確認
+         */
+        if let Some(opacity) = spec.opacity
+            && !(0.0..=1.0).contains(&opacity)
+        {
+            problems.push(Problem::new(
+                format!(
+                    "{} opacity {} is out of range (0.0-1.0)",
+                    label,
+                    opacity
+                ),
+                "specify a value from 0.0 (invisible) to 1.0 (opaque)",
+            ));
+        }
+
+        if let Some(amount) = spec.sharpen
+            && !(0.0..=5.0).contains(&amount)
+        {
+            problems.push(Problem::new(
+                format!(
+                    "{} sharpen amount {} is out of range (0.0-5.0)",
+                    label,
+                    amount
+                ),
+                "specify a value from 0.0 (off) to 5.0; 0.5-1.0 is typical",
+            ));
+        }
+
+        if let Some(width) = spec.outline
+            && width > 16
+        {
+            problems.push(Problem::new(
+                format!(
+                    "{} outline width {} is out of range (0-16)",
+                    label,
+                    width
+                ),
+                "specify the width in pixels; 1-2 is typical",
+            ));
+        }
+
+        if let Some(radius) = spec.shadow
+            && radius > 64
+        {
+            problems.push(Problem::new(
+                format!(
+                    "{} shadow radius {} is out of range (0-64)",
+                    label,
+                    radius
+                ),
+                "specify the blur radius in pixels",
+            ));
+        }
+
+        if let Some(ratio) = spec.panorama_ratio
+            && ratio <= 1.0
+        {
+            problems.push(Problem::new(
+                format!(
+                    "{} panorama ratio {} must be greater than 1.0",
+                    label,
+                    ratio
+                ),
+                "specify the width / height ratio, e.g. 3.0 for 3:1 panoramas",
+            ));
+        }
+
+        if let Some(padding) = spec.backdrop_padding
+            && padding > 256
+        {
+            problems.push(Problem::new(
+                format!(
+                    "{} backdrop padding {} is out of range (0-256)",
+                    label,
+                    padding
+                ),
+                "specify the padding in pixels around the logo",
+            ));
+        }
+
+        if let Some(radius) = spec.backdrop_radius
+            && radius > 256
+        {
+            problems.push(Problem::new(
+                format!(
+                    "{} backdrop radius {} is out of range (0-256)",
+                    label,
+                    radius
+                ),
+                "specify the corner radius in pixels; 0 for square corners",
+            ));
+        }
+
+        if spec.panorama_interval
+            .is_some_and(|interval| interval.to_exact_pixels(100) <= 0.0)
+        {
+            problems.push(Problem::new(
+                format!("{} panorama interval must not be zero", label),
+                "specify the distance between repeated logos, e.g. 25%",
+            ));
+        }
+
+        /*
+         * 文字列の描画またはロゴ画像の読み込み
+         */
+        let mut image = None;
+
+        if let Some(text) = &spec.text {
+            let size = spec.text_size.unwrap_or(DEFAULT_TEXT_SIZE);
+
+            if !(1.0..=2000.0).contains(&size) {
+                problems.push(Problem::new(
+                    format!(
+                        "{} text size {} is out of range (1-2000)",
+                        label,
+                        size
+                    ),
+                    "specify the glyph height in pixels with --text-size",
+                ));
+            } else if let Err(err) = template::check(text) {
+                problems.push(Problem::new(
+                    format!("{} text template is invalid: {}", label, err),
+                    "use variables such as {filename} or {exif.date}, and \
+                     {{ }} for literal braces",
+                ));
+            } else if let Some(font) = &spec.font {
+                let text = if template::has_variables(text) {
+                    text.clone()
+                } else {
+                    template::expand(
+                        text,
+                        Path::new(""),
+                        None,
+                        None,
+                        Sequence::default(),
+                    )
+                        .unwrap_or_default()
+                };
+
+                match render(&spec, font, &text) {
+                    Ok(rendered) => image = Some(rendered),
+                    Err(err) => problems.push(Problem::new(
+                        format!("{} text cannot be rendered: {}", label, err),
+                        "check the font given by --font or [logo] font",
+                    )),
+                }
+            } else {
+                problems.push(Problem::new(
+                    format!("font for the {} text is not specified", label),
+                    "pass a TrueType/OpenType font with --font",
+                ));
+            }
+        } else {
+            match &spec.file_path {
+                Some(path) if !path.is_file() => {
+                    problems.push(Problem::new(
+                        format!(
+                            "{} file path \"{}\" is not file",
+                            label,
+                            path.display()
+                        ),
+                        "check the path given by -l or [logo] in the config",
+                    ));
+                }
+
+                Some(path) => match image::open(path) {
+                    Ok(logo) => {
+                        let mut logo = logo.to_rgba8();
+                        apply_grayscale(&mut logo, &spec);
+                        apply_tint(&mut logo, &spec);
+                        apply_opacity(&mut logo, spec.opacity);
+                        image = Some(logo);
+                    }
+
+                    Err(err) => problems.push(Problem::new(
+                        format!(
+                            "{} file \"{}\" cannot be read: {}",
+                            label,
+                            path.display(),
+                            err
+                        ),
+                        "use a transparent PNG file as the logo",
+                    )),
+                },
+
+                None => problems.push(Problem::new(
+                    format!("{} file path is not specified", label),
+                    "pass a logo with -l, or use --no-watermark",
+                )),
+            }
+        }
+
+        /*
+         * 明るい背景用のロゴ画像の読み込み(文字列の場合は使用しない)
+         */
+        let mut dark = None;
+
+        if spec.text.is_none()
+            && let Some(path) = &spec.dark_file_path
+        {
+            match image::open(path) {
+                Ok(logo) => {
+                    let mut logo = logo.to_rgba8();
+                    apply_grayscale(&mut logo, &spec);
+                    apply_tint(&mut logo, &spec);
+                    apply_opacity(&mut logo, spec.opacity);
+                    dark = Some(logo);
+                }
+
+                Err(err) => problems.push(Problem::new(
+                    format!(
+                        "{} dark file \"{}\" cannot be read: {}",
+                        label,
+                        path.display(),
+                        err
+                    ),
+                    "check the path given by --logo-dark-file-path or \
+                     [logo] dark_file_path",
+                )),
+            }
+        }
+
+        if problems.len() > count {
+            return None;
+        }
+
+        image.map(|image| Self {spec, image, dark})
+    }
+
+    ///
+    /// 入力ファイルごとに展開する文字列へのアクセサ
+    ///
+    /// # 戻り値
+    /// 文字列がテンプレート変数を含む場合は、そのテンプレートを`Some()`でラ
+    /// ップして返す。描画し直す必要が無い場合は`None`を返す。
+    ///
+    pub(super) fn template(&self) -> Option<&str> {
+        let (Some(text), Some(_)) = (&self.spec.text, &self.spec.font) else {
+            return None;
+        };
+
+        template::has_variables(text).then_some(text.as_str())
+    }
+
+    ///
+    /// 文字列を置き換えた透かしの生成
+    ///
+    /// # 引数
+    /// * `text` - 描画する文字列(テンプレート変数は展開済みのもの)
+    ///
+    /// # 戻り値
+    /// 描画し直した透かしを`Ok()`でラップして返す。失敗した場合はエラー情報
+    /// を`Err()`でラップして返す。
+    ///
+    pub(super) fn with_text(&self, text: &str) -> Result<Self> {
+        let Some(font) = &self.spec.font else {
+            return Ok(self.clone());
+        };
+
+        Ok(Self {
+            spec: self.spec.clone(),
+            image: render(&self.spec, font, text)?,
+            dark: None,
+        })
+    }
+
+    ///
+    /// 出力画像の向きに合わせた透かしの生成
+    ///
+    /// # 引数
+    /// * `image` - 透かしを重畳する画像の幅と高さ
+    ///
+    /// # 戻り値
+    /// 画像の向きに対する配置の指定がある場合は、配置する場所・大きさ・余白
+    /// をその指定で置き換えた透かしを`Some()`でラップして返す。指定が無い場
+    /// 合は`None`を返す。
+    ///
+    pub(super) fn for_output(&self, image: (u32, u32)) -> Option<Self> {
+        self.spec.orientations
+            .get(Orientation::of(image))
+            .map(|rule| self.with_placement(rule))
+    }
+
+    ///
+    /// 配置の指定を置き換えた透かしの生成
+    ///
+    /// # 引数
+    /// * `rule` - 置き換える配置の指定(指定の無い項目は元のまま)
+    ///
+    pub(super) fn with_placement(&self, rule: PlacementOverride) -> Self {
+        let mut spec = self.spec.clone();
+
+        spec.position = rule.position.or(spec.position);
+        spec.scale = rule.scale.or(spec.scale);
+        spec.margin = rule.margin.or(spec.margin);
+
+        Self {spec, image: self.image.clone(), dark: self.dark.clone()}
+    }
+
+    ///
+    /// ロゴ画像へのアクセサ
+    ///
+    pub(crate) fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    ///
+    /// 明るい背景用のロゴ画像へのアクセサ
+    ///
+    pub(crate) fn dark_image(&self) -> Option<&RgbaImage> {
+        self.dark.as_ref()
+    }
+
+    ///
+    /// 重畳するロゴ画像の取得
+    ///
+    /// # 引数
+    /// * `dark` - 明るい背景用のロゴを用いるか否か
+    ///
+    /// # 戻り値
+    /// `dark`が`true`かつ明るい背景用のロゴがある場合はそのロゴを、それ以外
+    /// の場合は通常のロゴを返す。
+    ///
+    pub(crate) fn variant(&self, dark: bool) -> &RgbaImage {
+        match &self.dark {
+            Some(image) if dark => image,
+            _ => &self.image,
+        }
+    }
+
+    ///
+    /// 背景の明るさに応じたロゴの選択
+    ///
+    /// # 引数
+    /// * `background` - ロゴを配置する領域の平均輝度(0.0〜255.0)
+    ///
+    /// # 戻り値
+    /// 明るい背景用のロゴの方が通常のロゴより背景との輝度の差が大きい場合は
+    /// `true`を返す。明るい背景用のロゴが無い場合は常に`false`を返す。
+    ///
+    pub(crate) fn prefers_dark(&self, background: f32) -> bool {
+        let Some(dark) = &self.dark else {
+            return false;
+        };
+
+        let contrast = |image: &RgbaImage| {
+            analysis::mean_luma(image)
+                .map_or(0.0, |luma| (luma - background).abs())
+        };
+
+        contrast(dark) > contrast(&self.image)
+    }
+
+    ///
+    /// 透かしとして描画する文字列(テンプレート)へのアクセサ
+    ///
+    pub(crate) fn text(&self) -> Option<&str> {
+        self.spec.text.as_deref()
+    }
+
+    ///
+    /// 配置位置へのアクセサ
+    ///
+    pub(crate) fn position(&self) -> Position {
+        self.spec.position.unwrap_or(Position::BottomRight)
+    }
+
+    ///
+    /// 大きさの指定へのアクセサ
+    ///
+    pub(crate) fn scale(&self) -> Option<LogoScale> {
+        self.spec.scale
+    }
+
+    ///
+    /// 出力画像の大きさに対する大きさの上限へのアクセサ
+    ///
+    /// # 注記
+    /// 敷き詰める場合は`None`を返す(画像より大きなロゴも敷き詰められる)。
+    ///
+    pub(crate) fn max_coverage(&self) -> Option<f32> {
+        if self.spec.tile {
+            None
+        } else {
+            Some(self.spec.max_coverage.unwrap_or(1.0))
+        }
+    }
+
+    ///
+    /// 縮小した場合のシャープ化の強度へのアクセサ
+    ///
+    /// # 戻り値
+    /// シャープ化を行わない場合は`None`を返す。
+    ///
+    pub(crate) fn sharpen(&self) -> Option<f32> {
+        self.spec.sharpen.filter(|amount| *amount > 0.0)
+    }
+
+    ///
+    /// 回転角へのアクセサ
+    ///
+    /// # 戻り値
+    /// 回転しない場合は`None`を返す。
+    ///
+    pub(crate) fn rotation(&self) -> Option<f32> {
+        self.spec.rotation.filter(|degrees| *degrees % 360.0 != 0.0)
+    }
+
+    ///
+    /// 縁取りの幅へのアクセサ
+    ///
+    /// # 戻り値
+    /// 縁取りしない場合は`0`を返す。
+    ///
+    pub(crate) fn outline(&self) -> u32 {
+        self.spec.outline.unwrap_or(0)
+    }
+
+    ///
+    /// 影のぼかしの半径へのアクセサ
+    ///
+    /// # 戻り値
+    /// 影を付けない場合は`0`を返す。
+    ///
+    pub(crate) fn shadow(&self) -> u32 {
+        self.spec.shadow.unwrap_or(0)
+    }
+
+    ///
+    /// 縁取りと影の色へのアクセサ
+    ///
+    pub(crate) fn halo_color(&self) -> Color {
+        self.spec.halo_color.unwrap_or(DEFAULT_HALO_COLOR)
+    }
+
+    ///
+    /// 背後に描画する背景へのアクセサ
+    ///
+    pub(crate) fn backdrop(&self) -> Option<Backdrop> {
+        self.spec.backdrop
+    }
+
+    ///
+    /// 背景を周囲に広げる幅へのアクセサ
+    ///
+    /// # 戻り値
+    /// 指定が無い場合は`None`を返す(ロゴの大きさから決める)。
+    ///
+    pub(crate) fn backdrop_padding(&self) -> Option<u32> {
+        self.spec.backdrop_padding
+    }
+
+    ///
+    /// 背景の角の丸めの半径へのアクセサ
+    ///
+    /// # 戻り値
+    /// 指定が無い場合は`None`を返す(広げる幅と同じ値にする)。
+    ///
+    pub(crate) fn backdrop_radius(&self) -> Option<u32> {
+        self.spec.backdrop_radius
+    }
+
+    ///
+    /// 色付けに用いる色へのアクセサ
+    ///
+    pub(crate) fn tint(&self) -> Option<Color> {
+        self.spec.tint
+    }
+
+    ///
+    /// 色付けの方法へのアクセサ
+    ///
+    pub(crate) fn tint_mode(&self) -> TintMode {
+        self.spec.tint_mode.unwrap_or(TintMode::Multiply)
+    }
+
+    ///
+    /// 無彩色にするか否かのフラグへのアクセサ
+    ///
+    pub(crate) fn is_grayscale(&self) -> bool {
+        self.spec.grayscale
+    }
+
+    ///
+    /// 余白へのアクセサ
+    ///
+    pub(crate) fn margin(&self) -> Spacing {
+        self.spec.margin.unwrap_or_default()
+    }
+
+    ///
+    /// 座標の指定へのアクセサ
+    ///
+    pub(crate) fn offset(&self) -> Option<LogoOffset> {
+        self.spec.offset
+    }
+
+    ///
+    /// 出力画像の向きごとの配置の指定へのアクセサ
+    ///
+    pub(crate) fn orientations(&self) -> &OrientationRules {
+        &self.spec.orientations
+    }
+
+    ///
+    /// ファイルごとにずらす範囲へのアクセサ
+    ///
+    /// # 注記
+    /// 敷き詰める場合は`None`を返す。
+    ///
+    pub(crate) fn jitter(&self) -> Option<Spacing> {
+        self.spec.jitter.filter(|_| !self.spec.tile)
+    }
+
+    ///
+    /// 小数点以下の精度で配置するか否かのフラグへのアクセサ
+    ///
+    pub(crate) fn is_subpixel(&self) -> bool {
+        self.spec.subpixel
+    }
+
+    ///
+    /// 敷き詰めるか否かのフラグへのアクセサ
+    ///
+    pub(crate) fn is_tile(&self) -> bool {
+        self.spec.tile
+    }
+
+    ///
+    /// 敷き詰める際の角度へのアクセサ
+    ///
+    pub(crate) fn tile_angle(&self) -> f32 {
+        self.spec.tile_angle.unwrap_or(DEFAULT_TILE_ANGLE)
+    }
+
+    ///
+    /// 敷き詰める際の間隔へのアクセサ
+    ///
+    /// # 戻り値
+    /// 指定されていない場合は`None`を返す(ロゴの大きさと同じ間隔を用いる)。
+    ///
+    pub(crate) fn tile_spacing(&self) -> Option<Spacing> {
+        self.spec.tile_spacing
+    }
+
+    ///
+    /// パノラマ画像でロゴを繰り返す間隔の算出
+    ///
+    /// # 引数
+    /// * `(width, height)` - ロゴを重畳する画像の幅と高さ
+    ///
+    /// # 戻り値
+    /// 繰り返しの指定があり、かつ画像の縦横比が指定値以上の場合は間隔のピク
+    /// セル数を返す。それ以外の場合(敷き詰める場合を含む)は`None`を返す。
+    ///
+    pub(crate) fn panorama_interval(&self, (width, height): (u32, u32))
+        -> Option<u32>
+    {
+        let spec = &self.spec;
+        let ratio = match (spec.panorama_ratio, spec.panorama_interval) {
+            (None, None) => return None,
+            (ratio, _) => ratio.unwrap_or(DEFAULT_PANORAMA_RATIO),
+        };
+
+        if self.spec.tile || (width as f32) < height as f32 * ratio {
+            return None;
+        }
+
+        let interval = match self.spec.panorama_interval {
+            Some(interval) => interval.to_pixels(width),
+            None => (height as f32 * DEFAULT_PANORAMA_INTERVAL).round() as u32,
+        };
+
+        Some(interval.max(1))
+    }
+}
+
+///
+/// 文字列の描画
+///
+/// # 引数
+/// * `spec` - 透かしの指定
+/// * `font` - フォントファイルへのパス
+/// * `text` - 描画する文字列(変数は展開済みのもの)
+///
+/// # 戻り値
+/// 描画して不透明度を適用した画像を`Ok()`でラップして返す。失敗した場合は
+/// エラー情報を`Err()`でラップして返す。
+///
+fn render(spec: &WatermarkSpec, font: &Path, text: &str) -> Result<RgbaImage> {
+    let size = spec.text_size.unwrap_or(DEFAULT_TEXT_SIZE);
+    let color = spec.text_color
+        .unwrap_or(Color {red: 255, green: 255, blue: 255})
+        .to_rgba();
+
+    let mut image = text::render_text(font, text, size, color)?;
+    apply_grayscale(&mut image, spec);
+    apply_tint(&mut image, spec);
+    apply_opacity(&mut image, spec.opacity);
+
+    Ok(image)
+}
+
+///
+/// ロゴ画像の無彩色化
+///
+/// # 引数
+/// * `logo` - 適用対象のロゴ画像
+/// * `spec` - 透かしの指定(無彩色にするか否かを参照する)
+///
+/// # 注記
+/// 各チャネルをITU-R BT.601の係数で求めた輝度値に置き換える。アルファ値は
+/// 変えない。
+///
+fn apply_grayscale(logo: &mut RgbaImage, spec: &WatermarkSpec) {
+    if !spec.grayscale {
+        return;
+    }
+
+    for pixel in logo.pixels_mut() {
+        let luma = analysis::luma(&pixel.0).round().clamp(0.0, 255.0) as u8;
+
+        pixel[0] = luma;
+        pixel[1] = luma;
+        pixel[2] = luma;
+    }
+}
+
+///
+/// ロゴ画像への色付けの適用
+///
+/// # 引数
+/// * `logo` - 適用対象のロゴ画像
+/// * `spec` - 透かしの指定(色付けの色と方法を参照する)
+///
+/// # 注記
+/// 乗算の場合は各チャネルに指定色の値を255で割ったものを乗じ、置き換えの場
+/// 合は各チャネルを指定色の値にする。いずれもアルファ値は変えない。
+///
+fn apply_tint(logo: &mut RgbaImage, spec: &WatermarkSpec) {
+    let Some(tint) = spec.tint else {
+        return;
+    };
+
+    let tint = tint.to_rgba();
+    let mode = spec.tint_mode.unwrap_or(TintMode::Multiply);
+
+    for pixel in logo.pixels_mut() {
+        for c in 0..3 {
+            pixel[c] = match mode {
+                TintMode::Multiply => {
+                    (pixel[c] as u32 * tint[c] as u32 + 127) / 255
+                }
+
+                TintMode::Replace => tint[c] as u32,
+            } as u8;
+        }
+    }
+}
+
+///
+/// ロゴ画像への不透明度の適用
+///
+/// # 引数
+/// * `logo` - 適用対象のロゴ画像
+/// * `opacity` - 不透明度(0.0〜1.0、省略時は1.0)
+///
+/// # 注記
+/// 各画素のアルファ値に不透明度を乗じる。
+///
+fn apply_opacity(logo: &mut RgbaImage, opacity: Option<f32>) {
+    let opacity = opacity.unwrap_or(1.0).clamp(0.0, 1.0);
+
+    if opacity >= 1.0 {
+        return;
+    }
+
+    for pixel in logo.pixels_mut() {
+        pixel[3] = (pixel[3] as f32 * opacity).round() as u8;
+    }
+}
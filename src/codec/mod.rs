@@ -0,0 +1,170 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! JPEG・WebPのエンコード・デコードを行うバックエンドを抽象化するモジュール
+//!
+//! `native-codecs`フィーチャが有効な場合はmozjpeg・libwebpを、無効な場合は
+//! `image`クレートの実装(Rustのみで完結しCコンパイラを必要としない)を使用す
+//! る。
+//!
+
+#[cfg(feature = "native-codecs")]
+mod native;
+
+#[cfg(not(feature = "native-codecs"))]
+mod pure;
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Result;
+use image::{GrayImage, RgbaImage};
+
+///
+/// JPEGのバックエンドを抽象化するトレイト
+///
+pub(crate) trait JpegCodec {
+    ///
+    /// JPEGファイルのデコード
+    ///
+    /// # 引数
+    /// * `path` - デコード対象のJPEGファイルへのパス
+    /// * `min_size` - 縮小デコード時に下回ってはならない画像サイズ(`None`の
+    ///   場合は縮小しない)
+    ///
+    /// # 戻り値
+    /// 処理に成功した場合はデコードした画像を`RgbaImage`オブジェクトとして
+    /// `Ok()`でラップして返す。失敗した場合はエラー情報を`Err()`でラップし
+    /// て返す。
+    ///
+    /// # 注記
+    /// DCT領域での縮小(1/2、1/4、1/8)に対応するバックエンドは、`min_size`
+    /// を下回らない範囲で最も小さく縮小してデコードする。
+    ///
+    fn decode(&self, path: &Path, min_size: Option<(u32, u32)>)
+        -> Result<RgbaImage>;
+
+    ///
+    /// グレースケールJPEGファイルの輝度成分のデコード
+    ///
+    /// # 引数
+    /// * `path` - デコード対象のJPEGファイルへのパス
+    /// * `min_size` - 縮小デコード時に下回ってはならない画像サイズ(`None`の
+    ///   場合は縮小しない)
+    ///
+    /// # 戻り値
+    /// 処理に成功した場合は、グレースケールのファイルであればデコードした画
+    /// 像を`Some()`で、そうでなければ`None`を`Ok()`でラップして返す。失敗し
+    /// た場合はエラー情報を`Err()`でラップして返す。
+    ///
+    fn decode_gray(&self, path: &Path, min_size: Option<(u32, u32)>)
+        -> Result<Option<GrayImage>>;
+
+    ///
+    /// JPEGファイルの画像サイズの読み出し
+    ///
+    /// # 引数
+    /// * `path` - 対象のJPEGファイルへのパス
+    ///
+    /// # 戻り値
+    /// 処理に成功した場合は幅と高さをタプルにパックし`Ok()`でラップして返す。
+    /// 失敗した場合はエラー情報を`Err()`でラップして返す。
+    ///
+    fn read_size(&self, path: &Path) -> Result<(u32, u32)>;
+
+    ///
+    /// JPEGへのエンコード
+    ///
+    /// # 引数
+    /// * `writer` - エンコード結果の書き込み先
+    /// * `image` - エンコード対象のイメージデータ
+    /// * `quality` - JPEGの品質(1〜100)
+    /// * `progressive` - プログレッシブJPEGで出力する場合は`true`
+    /// * `subsampling` - 色差成分1画素あたりの輝度画素数(水平, 垂直)
+    ///
+    /// # 戻り値
+    /// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を
+    /// `Err()`でラップして返す。
+    ///
+    fn encode(
+        &self,
+        writer: &mut dyn Write,
+        image: RgbaImage,
+        quality: f32,
+        progressive: bool,
+        subsampling: (u8, u8),
+    ) -> Result<()>;
+
+    ///
+    /// グレースケールJPEGへのエンコード
+    ///
+    /// # 引数
+    /// * `writer` - エンコード結果の書き込み先
+    /// * `image` - エンコード対象のイメージデータ
+    /// * `quality` - JPEGの品質(1〜100)
+    /// * `progressive` - プログレッシブJPEGで出力する場合は`true`
+    ///
+    /// # 戻り値
+    /// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を
+    /// `Err()`でラップして返す。
+    ///
+    fn encode_gray(
+        &self,
+        writer: &mut dyn Write,
+        image: GrayImage,
+        quality: f32,
+        progressive: bool,
+    ) -> Result<()>;
+}
+
+///
+/// WebPのバックエンドを抽象化するトレイト
+///
+pub(crate) trait WebpCodec {
+    ///
+    /// WebPへのエンコード
+    ///
+    /// # 引数
+    /// * `writer` - エンコード結果の書き込み先
+    /// * `image` - エンコード対象のイメージデータ
+    /// * `quality` - 非可逆圧縮時の品質(0〜100)
+    /// * `lossless` - 可逆圧縮で出力する場合は`true`
+    ///
+    /// # 戻り値
+    /// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を
+    /// `Err()`でラップして返す。
+    ///
+    fn encode(
+        &self,
+        writer: &mut dyn Write,
+        image: RgbaImage,
+        quality: f32,
+        lossless: bool,
+    ) -> Result<()>;
+}
+
+///
+/// 有効なJPEGバックエンドの取得
+///
+pub(crate) fn jpeg() -> &'static dyn JpegCodec {
+    #[cfg(feature = "native-codecs")]
+    return &native::MozJpegCodec;
+
+    #[cfg(not(feature = "native-codecs"))]
+    return &pure::ImageJpegCodec;
+}
+
+///
+/// 有効なWebPバックエンドの取得
+///
+pub(crate) fn webp() -> &'static dyn WebpCodec {
+    #[cfg(feature = "native-codecs")]
+    return &native::LibWebpCodec;
+
+    #[cfg(not(feature = "native-codecs"))]
+    return &pure::ImageWebpCodec;
+}
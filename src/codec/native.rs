@@ -0,0 +1,511 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! mozjpeg・libwebpを使用するバックエンドの実装
+//!
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::panic::{self, UnwindSafe};
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use image::{DynamicImage, GrayImage, ImageBuffer, RgbaImage};
+use mozjpeg::{ColorSpace, Compress, Decompress, Marker};
+use moxcms::{ColorProfile, Layout, TransformOptions};
+
+use super::{JpegCodec, WebpCodec};
+
+///
+/// mozjpegによるJPEGバックエンド
+///
+pub(crate) struct MozJpegCodec;
+
+// JpegCodecトレイトの実装
+impl JpegCodec for MozJpegCodec {
+    fn decode(&self, path: &Path, min_size: Option<(u32, u32)>)
+        -> Result<RgbaImage>
+    {
+        try_mozjpeg(|| decode_jpeg(path, min_size))
+    }
+
+    fn decode_gray(&self, path: &Path, min_size: Option<(u32, u32)>)
+        -> Result<Option<GrayImage>>
+    {
+        try_mozjpeg(|| decode_jpeg_gray(path, min_size))
+    }
+
+    fn read_size(&self, path: &Path) -> Result<(u32, u32)> {
+        try_mozjpeg(|| {
+            let reader = BufReader::new(File::open(path)?);
+            let (width, height) = Decompress::new_reader(reader)?.size();
+            Ok((width as u32, height as u32))
+        })
+    }
+
+    fn encode(
+        &self,
+        writer: &mut dyn Write,
+        image: RgbaImage,
+        quality: f32,
+        progressive: bool,
+        subsampling: (u8, u8),
+    ) -> Result<()> {
+        encode_jpeg(writer, image, quality, progressive, subsampling)
+    }
+
+    fn encode_gray(
+        &self,
+        writer: &mut dyn Write,
+        image: GrayImage,
+        quality: f32,
+        progressive: bool,
+    ) -> Result<()> {
+        encode_jpeg_gray(writer, image, quality, progressive)
+    }
+}
+
+///
+/// libwebpによるWebPバックエンド
+///
+pub(crate) struct LibWebpCodec;
+
+// WebpCodecトレイトの実装
+impl WebpCodec for LibWebpCodec {
+    fn encode(
+        &self,
+        writer: &mut dyn Write,
+        image: RgbaImage,
+        quality: f32,
+        lossless: bool,
+    ) -> Result<()> {
+        encode_webp(writer, image, quality, lossless)
+    }
+}
+
+///
+/// JPEGファイルのデコード
+///
+/// # 引数
+/// * `path` - デコード対象のJPEGファイルへのパス
+/// * `min_size` - 縮小デコード時に下回ってはならない画像サイズ
+///
+/// # 戻り値
+/// 処理に成功した場合はデコードした画像を`RgbaImage`オブエクトとして`Ok()`で
+/// ラップして返す。失敗した場合はエラー情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// CMYK(YCCK)のファイルはCMYKとしてデコードした上でRGBに変換する。
+///
+fn decode_jpeg<P>(path: P, min_size: Option<(u32, u32)>) -> Result<RgbaImage>
+where 
+    P: AsRef<Path>
+{
+    let reader= BufReader::new(File::open(&path)?);
+
+    let mut decomp = Decompress::builder()
+        .with_markers(&[Marker::APP(2), Marker::APP(14)])
+        .from_reader(reader)?;
+
+    if let Some(min_size) = min_size {
+        decomp.scale(scale_numerator(decomp.size(), min_size));
+    }
+
+    if matches!(
+        decomp.color_space(),
+        ColorSpace::JCS_CMYK | ColorSpace::JCS_YCCK
+    ) {
+        return decode_jpeg_cmyk(decomp);
+    }
+
+    let mut decomp = decomp.rgba()?;
+
+    let width = decomp.width() as u32;
+    let height = decomp.height() as u32;
+    let pixels = decomp.read_scanlines::<[u8; 4]>()?.concat();
+
+    ImageBuffer::from_raw(width, height, pixels)
+        .ok_or_else(|| anyhow!("invalid dimensions"))
+}
+
+///
+/// CMYK JPEGファイルのデコード
+///
+/// # 引数
+/// * `decomp` - ヘッダの読み込みを終えたデコーダ
+///
+/// # 戻り値
+/// 処理に成功した場合はRGBに変換した画像を`RgbaImage`オブジェクトとして
+/// `Ok()`でラップして返す。失敗した場合はエラー情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// Adobeマーカーを持つファイル(Photoshop等が出力したもの)は値が反転して格納
+/// されているので、反転を戻してから変換する。ICCプロファイルが埋め込まれて
+/// いる場合はそれを使ってsRGBに変換し、無い場合(またはプロファイルを扱えな
+/// い場合)は単純な計算式で変換する。
+///
+fn decode_jpeg_cmyk<R>(decomp: Decompress<R>) -> Result<RgbaImage>
+where
+    R: BufRead
+{
+    let inverted = decomp.markers().any(|marker| {
+        marker.marker == Marker::APP(14) && marker.data.starts_with(b"Adobe")
+    });
+    let profile = read_icc_profile(&decomp);
+
+    let mut decomp = decomp.to_colorspace(ColorSpace::JCS_CMYK)?;
+
+    let width = decomp.width() as u32;
+    let height = decomp.height() as u32;
+    let mut cmyk = decomp.read_scanlines::<u8>()?;
+
+    if inverted {
+        cmyk.iter_mut().for_each(|value| *value = 255 - *value);
+    }
+
+    let pixels = profile
+        .and_then(|profile| cmyk_to_rgba_with_profile(&profile, &cmyk))
+        .unwrap_or_else(|| cmyk_to_rgba(&cmyk));
+
+    ImageBuffer::from_raw(width, height, pixels)
+        .ok_or_else(|| anyhow!("invalid dimensions"))
+}
+
+///
+/// JPEGファイルに埋め込まれたICCプロファイルの読み出し
+///
+/// # 引数
+/// * `decomp` - ヘッダの読み込みを終えたデコーダ
+///
+/// # 戻り値
+/// ICCプロファイルが埋め込まれている場合はそのデータを`Some()`でラップして返
+/// す。埋め込まれていない場合は`None`を返す。
+///
+/// # 注記
+/// ICCプロファイルは複数のAPP2セグメントに分割して格納されることがあるので、
+/// 各セグメントの通し番号順に連結する。
+///
+fn read_icc_profile<R>(decomp: &Decompress<R>) -> Option<Vec<u8>> {
+    const SIGNATURE: &[u8] = b"ICC_PROFILE\0";
+
+    let mut chunks = decomp.markers()
+        .filter(|marker| marker.marker == Marker::APP(2))
+        .filter(|marker| marker.data.len() > SIGNATURE.len() + 2)
+        .filter(|marker| marker.data.starts_with(SIGNATURE))
+        .map(|marker| {
+            let seq = marker.data[SIGNATURE.len()];
+            (seq, &marker.data[SIGNATURE.len() + 2..])
+        })
+        .collect::<Vec<_>>();
+
+    if chunks.is_empty() {
+        return None;
+    }
+
+    chunks.sort_by_key(|(seq, _)| *seq);
+
+    Some(chunks.into_iter().flat_map(|(_, data)| data.to_vec()).collect())
+}
+
+///
+/// ICCプロファイルを用いたCMYKからRGBAへの変換
+///
+/// # 引数
+/// * `profile` - 入力画像に埋め込まれたICCプロファイルのデータ
+/// * `cmyk` - インク量として格納したCMYKの画素データ
+///
+/// # 戻り値
+/// 変換に成功した場合はRGBAの画素データを`Some()`でラップして返す。プロファ
+/// イルがCMYKのものでない場合や変換に失敗した場合は`None`を返す。
+///
+fn cmyk_to_rgba_with_profile(profile: &[u8], cmyk: &[u8]) -> Option<Vec<u8>> {
+    let profile = ColorProfile::new_from_slice(profile).ok()?;
+    let transform = profile.create_transform_8bit(
+        Layout::Rgba,
+        &ColorProfile::new_srgb(),
+        Layout::Rgb,
+        TransformOptions::default()
+    ).ok()?;
+
+    let mut rgb = vec![0u8; cmyk.len() / 4 * 3];
+    transform.transform(cmyk, &mut rgb).ok()?;
+
+    Some(rgb.chunks_exact(3).flat_map(|px| [px[0], px[1], px[2], 255]).collect())
+}
+
+///
+/// 計算式によるCMYKからRGBAへの変換
+///
+/// # 引数
+/// * `cmyk` - インク量として格納したCMYKの画素データ
+///
+/// # 戻り値
+/// RGBAの画素データを返す。
+///
+fn cmyk_to_rgba(cmyk: &[u8]) -> Vec<u8> {
+    cmyk.chunks_exact(4)
+        .flat_map(|px| {
+            let k = 255 - px[3] as u32;
+            let conv = |value: u8| ((255 - value as u32) * k / 255) as u8;
+
+            [conv(px[0]), conv(px[1]), conv(px[2]), 255]
+        })
+        .collect()
+}
+
+///
+/// グレースケールJPEGファイルの輝度成分のデコード
+///
+/// # 引数
+/// * `path` - デコード対象のJPEGファイルへのパス
+/// * `min_size` - 縮小デコード時に下回ってはならない画像サイズ
+///
+/// # 戻り値
+/// 処理に成功した場合は、グレースケールのファイルであればデコードした画像を
+/// `Some()`で、そうでなければ`None`を`Ok()`でラップして返す。失敗した場合は
+/// エラー情報を`Err()`でラップして返す。
+///
+fn decode_jpeg_gray(path: &Path, min_size: Option<(u32, u32)>)
+    -> Result<Option<GrayImage>>
+{
+    let reader= BufReader::new(File::open(path)?);
+    let mut decomp = Decompress::new_reader(reader)?;
+
+    if decomp.color_space() != ColorSpace::JCS_GRAYSCALE {
+        return Ok(None);
+    }
+
+    if let Some(min_size) = min_size {
+        decomp.scale(scale_numerator(decomp.size(), min_size));
+    }
+
+    let mut decomp = decomp.grayscale()?;
+
+    let width = decomp.width() as u32;
+    let height = decomp.height() as u32;
+    let pixels = decomp.read_scanlines::<u8>()?;
+
+    Ok(Some(
+        ImageBuffer::from_raw(width, height, pixels)
+            .ok_or_else(|| anyhow!("invalid dimensions"))?
+    ))
+}
+
+///
+/// DCT領域での縮小率の決定
+///
+/// # 引数
+/// * `size` - 元の画像サイズ
+/// * `min_size` - 縮小後に下回ってはならない画像サイズ
+///
+/// # 戻り値
+/// 縮小率の分子(分母は8)を返す。
+///
+/// # 注記
+/// 1/8、1/4、1/2の順に試し、縮小後の幅と高さが共に`min_size`以上となる最も
+/// 小さい縮小率を選ぶ。いずれも満たさない場合は縮小しない(8/8)。
+///
+fn scale_numerator(size: (usize, usize), min_size: (u32, u32)) -> u8 {
+    let scaled = |len: usize, num: usize| len.div_ceil(8 / num);
+
+    [1, 2, 4]
+        .into_iter()
+        .find(|&num| {
+            scaled(size.0, num) >= min_size.0 as usize
+                && scaled(size.1, num) >= min_size.1 as usize
+        })
+        .unwrap_or(8) as u8
+}
+
+///
+/// mozjpegを使用する処理の実行
+///
+/// # 引数
+/// * `func` - 実行する処理
+///
+/// # 戻り値
+/// 処理の結果を返す。処理中にパニックが発生した場合はエラー情報を`Err()`でラ
+/// ップして返す。
+///
+/// # 注記
+/// mozjpegはデコードエラーをパニックとして通知するので、捕捉してエラーに変換
+/// する。
+///
+fn try_mozjpeg<T, F>(func: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + UnwindSafe
+{
+    panic::catch_unwind(func)
+        .unwrap_or_else(|_| Err(anyhow!("mozjpeg could not decode the file")))
+}
+
+
+///
+/// JPEGファイルへのエンコード
+///
+/// # 引数
+/// * `writer` - エンコード結果の書き込み先
+/// * `image` - エンコード対象のイメージデータ
+/// * `quality` - JPEGの品質(1〜100)
+/// * `progressive` - プログレッシブJPEGで出力する場合は`true`
+/// * `subsampling` - 色差成分1画素あたりの輝度画素数(水平, 垂直)
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を `Err()`でラ
+/// ップして返す。
+///
+/// # 注記
+/// mozjpegはデフォルトでプログレッシブJPEGを出力するので、プログレッシブで
+/// ない場合はスキャンスクリプトを外してベースラインJPEGとして出力する。
+///
+fn encode_jpeg<W>(
+    writer: W,
+    image: RgbaImage,
+    quality: f32,
+    progressive: bool,
+    subsampling: (u8, u8),
+) -> Result<()>
+where 
+    W: Write
+{
+    let mut comp = Compress::new(ColorSpace::JCS_EXT_RGBA);
+    comp.set_size(image.width() as usize, image.height() as usize);
+    comp.set_quality(quality);
+    comp.set_optimize_coding(true);
+    comp.set_chroma_sampling_pixel_sizes(subsampling, subsampling);
+
+    if progressive {
+        comp.set_progressive_mode();
+        comp.set_optimize_scans(true);
+    } else {
+        comp.set_optimize_scans(false);
+    }
+
+    let mut comp = comp.start_compress(writer)?;
+    comp.write_scanlines(image.as_raw().as_slice())?;
+    comp.finish()?;
+
+    Ok(())
+}
+
+///
+/// グレースケールJPEGファイルへのエンコード
+///
+/// # 引数
+/// * `writer` - エンコード結果の書き込み先
+/// * `image` - エンコード対象のイメージデータ
+/// * `quality` - JPEGの品質(1〜100)
+/// * `progressive` - プログレッシブJPEGで出力する場合は`true`
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を `Err()`でラ
+/// ップして返す。
+///
+/// # 注記
+/// 輝度成分のみの1コンポーネントのJPEGとして出力するので、色差のサブサンプ
+/// リングの指定は持たない。
+///
+fn encode_jpeg_gray<W>(
+    writer: W,
+    image: GrayImage,
+    quality: f32,
+    progressive: bool,
+) -> Result<()>
+where 
+    W: Write
+{
+    let mut comp = Compress::new(ColorSpace::JCS_GRAYSCALE);
+    comp.set_size(image.width() as usize, image.height() as usize);
+    comp.set_quality(quality);
+    comp.set_optimize_coding(true);
+
+    if progressive {
+        comp.set_progressive_mode();
+        comp.set_optimize_scans(true);
+    } else {
+        comp.set_optimize_scans(false);
+    }
+
+    let mut comp = comp.start_compress(writer)?;
+    comp.write_scanlines(image.as_raw().as_slice())?;
+    comp.finish()?;
+
+    Ok(())
+}
+
+///
+/// WebPファイルへのエンコード
+///
+/// # 引数
+/// * `writer` - エンコード結果の書き込み先
+/// * `image` - エンコード対象のイメージデータ
+/// * `quality` - 非可逆圧縮時の品質(0〜100)
+/// * `lossless` - 可逆圧縮で出力する場合は`true`
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を `Err()`でラ
+/// ップして返す。
+///
+/// # 注記
+/// 出力画像は不透明なので、アルファチャネルを落としたRGBデータとしてエンコー
+/// ドする。
+///
+fn encode_webp<W>(
+    mut writer: W,
+    image: RgbaImage,
+    quality: f32,
+    lossless: bool,
+) -> Result<()>
+where 
+    W: Write
+{
+    let rgb = DynamicImage::ImageRgba8(image).to_rgb8();
+    let encoder = webp::Encoder::from_rgb(
+        rgb.as_raw(),
+        rgb.width(),
+        rgb.height()
+    );
+
+    let data = encoder.encode_simple(lossless, quality)
+        .map_err(|err| anyhow!("webp encode failed: {:?}", err))?;
+
+    writer.write_all(&data)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_numerator_picks_smallest_scale() {
+        assert_eq!(scale_numerator((4000, 3000), (500, 375)), 1);
+        assert_eq!(scale_numerator((4000, 3000), (501, 375)), 2);
+        assert_eq!(scale_numerator((4000, 3000), (1001, 750)), 4);
+        assert_eq!(scale_numerator((4000, 3000), (0, 0)), 1);
+    }
+
+    #[test]
+    fn scale_numerator_rounds_scaled_size_up() {
+        assert_eq!(scale_numerator((4001, 3001), (501, 376)), 1);
+        assert_eq!(scale_numerator((4001, 3001), (502, 376)), 2);
+    }
+
+    #[test]
+    fn scale_numerator_checks_both_sides() {
+        assert_eq!(scale_numerator((3000, 4000), (500, 375)), 2);
+        assert_eq!(scale_numerator((4000, 3000), (100, 1501)), 8);
+    }
+
+    #[test]
+    fn scale_numerator_keeps_small_images() {
+        assert_eq!(scale_numerator((1280, 720), (1280, 720)), 8);
+        assert_eq!(scale_numerator((640, 480), (1280, 720)), 8);
+    }
+}
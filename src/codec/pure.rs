@@ -0,0 +1,163 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! `image`クレートのRust実装を使用するバックエンドの実装
+//!
+//! Cコンパイラを必要としないが、mozjpeg・libwebpと比べて以下の制約がある。
+//!
+//! * JPEGは常にベースライン・4:2:0で出力する(プログレッシブとサブサンプリン
+//!   グの指定は無視する)
+//! * CMYK(YCCK)のJPEGは埋め込みICCプロファイルを使わずに変換する
+//! * JPEGのDCT領域での縮小デコードは行わない
+//! * WebPは可逆圧縮のみ出力できる
+//!
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{
+    DynamicImage, ExtendedColorType, GrayImage, ImageEncoder, ImageFormat,
+    ImageReader, RgbaImage
+};
+
+use super::{JpegCodec, WebpCodec};
+
+///
+/// `image`クレートによるJPEGバックエンド
+///
+pub(crate) struct ImageJpegCodec;
+
+// JpegCodecトレイトの実装
+impl JpegCodec for ImageJpegCodec {
+    fn decode(&self, path: &Path, _min_size: Option<(u32, u32)>)
+        -> Result<RgbaImage>
+    {
+        Ok(read_jpeg(path)?.to_rgba8())
+    }
+
+    fn decode_gray(&self, path: &Path, _min_size: Option<(u32, u32)>)
+        -> Result<Option<GrayImage>>
+    {
+        match read_jpeg(path)? {
+            DynamicImage::ImageLuma8(image) => Ok(Some(image)),
+            _ => Ok(None),
+        }
+    }
+
+    fn read_size(&self, path: &Path) -> Result<(u32, u32)> {
+        let mut reader = ImageReader::open(path)?;
+        reader.set_format(ImageFormat::Jpeg);
+
+        Ok(reader.into_dimensions()?)
+    }
+
+    fn encode(
+        &self,
+        writer: &mut dyn Write,
+        image: RgbaImage,
+        quality: f32,
+        _progressive: bool,
+        _subsampling: (u8, u8),
+    ) -> Result<()> {
+        let rgb = DynamicImage::ImageRgba8(image).to_rgb8();
+
+        JpegEncoder::new_with_quality(writer, jpeg_quality(quality))
+            .write_image(
+                rgb.as_raw(),
+                rgb.width(),
+                rgb.height(),
+                ExtendedColorType::Rgb8
+            )?;
+
+        Ok(())
+    }
+
+    fn encode_gray(
+        &self,
+        writer: &mut dyn Write,
+        image: GrayImage,
+        quality: f32,
+        _progressive: bool,
+    ) -> Result<()> {
+        JpegEncoder::new_with_quality(writer, jpeg_quality(quality))
+            .write_image(
+                image.as_raw(),
+                image.width(),
+                image.height(),
+                ExtendedColorType::L8
+            )?;
+
+        Ok(())
+    }
+}
+
+///
+/// `image`クレートによるWebPバックエンド
+///
+pub(crate) struct ImageWebpCodec;
+
+// WebpCodecトレイトの実装
+impl WebpCodec for ImageWebpCodec {
+    fn encode(
+        &self,
+        writer: &mut dyn Write,
+        image: RgbaImage,
+        _quality: f32,
+        lossless: bool,
+    ) -> Result<()> {
+        if !lossless {
+            return Err(anyhow!(
+                "lossy WebP output requires the native-codecs feature \
+                 (use --webp-lossless)"
+            ));
+        }
+
+        let rgb = DynamicImage::ImageRgba8(image).to_rgb8();
+
+        WebPEncoder::new_lossless(writer).write_image(
+            rgb.as_raw(),
+            rgb.width(),
+            rgb.height(),
+            ExtendedColorType::Rgb8
+        )?;
+
+        Ok(())
+    }
+}
+
+///
+/// JPEGファイルとしての読み込み
+///
+/// # 引数
+/// * `path` - 読み込むJPEGファイルへのパス
+///
+/// # 戻り値
+/// 処理に成功した場合はデコードした画像を`Ok()`でラップして返す。失敗した場
+/// 合はエラー情報を`Err()`でラップして返す。
+///
+fn read_jpeg(path: &Path) -> Result<DynamicImage> {
+    let mut reader = ImageReader::open(path)?;
+    reader.set_format(ImageFormat::Jpeg);
+
+    Ok(reader.decode()?)
+}
+
+///
+/// 品質指定の`image`クレートのJPEGエンコーダ向けの変換
+///
+/// # 引数
+/// * `quality` - JPEGの品質(1〜100)
+///
+/// # 戻り値
+/// 整数に丸めた品質を返す
+///
+fn jpeg_quality(quality: f32) -> u8 {
+    quality.round().clamp(1.0, 100.0) as u8
+}
@@ -0,0 +1,161 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 1ファイル分の処理の状態をまとめたコンテキストを定義するモジュール
+//!
+//! 画像1枚の処理は、このコンテキストを各段(デコード、合成、エンコード、書
+//! き込み)に順に渡すパイプラインとして行う。各段は前段の結果をコンテキスト
+//! から受け取り、自身の結果をコンテキストに格納する。
+//!
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use image::ImageFormat;
+
+use crate::cmd_args::OutputFormat;
+use crate::pixel::{OutputImage, SourceImage};
+
+///
+/// 1ファイル分の処理の状態を保持する構造体
+///
+pub(crate) struct ProcessingContext {
+    /// 入力ファイルへのパス
+    pub(crate) input_path: PathBuf,
+
+    /// 出力ファイルへのパス
+    pub(crate) output_path: PathBuf,
+
+    /// 入力ファイルとHDR合成する他のフレームへのパスのリスト
+    pub(crate) brackets: Vec<PathBuf>,
+
+    /// 入力ファイルの内容から判別した画像形式
+    pub(crate) source_format: Option<ImageFormat>,
+
+    /// 入力画像のサイズ(向きの補正後の幅と高さのタプル)
+    pub(crate) source_size: Option<(u32, u32)>,
+
+    /// 出力形式
+    pub(crate) output_format: OutputFormat,
+
+    /// デコード済みの入力画像(合成の段で消費する)
+    pub(crate) source: Option<SourceImage>,
+
+    /// 合成後の出力画像(エンコードの段で消費する)
+    pub(crate) output: Option<OutputImage>,
+
+    /// 出力品質
+    pub(crate) quality: f32,
+
+    /// 出力画像から抽出したパレット(画素数の多い順、抽出しない場合は空)
+    pub(crate) palette: Vec<[u8; 3]>,
+
+    /// 出力画像の代替テキスト(指定が無い場合は`None`)
+    pub(crate) alt_text: Option<String>,
+
+    /// エンコード結果
+    pub(crate) encoded: Vec<u8>,
+
+    /// タイルに分割した場合のタイルごとの出力先・幅と高さ・エンコード結果の
+    /// リスト(分割した場合は`encoded`は空になる)
+    pub(crate) tiles: Vec<(PathBuf, (u32, u32), Vec<u8>)>,
+
+    /// Web向けの出力一式として出力画像に加えて書き出すファイルの出力先とデー
+    /// タのリスト
+    pub(crate) bundle: Vec<(PathBuf, Vec<u8>)>,
+
+    /// 処理中に発生した警告のリスト
+    pub(crate) warnings: Vec<String>,
+
+    /// 各段の所要時間のリスト(段の名前と所要時間のタプル)
+    pub(crate) timings: Vec<(&'static str, Duration)>,
+}
+
+/// ProcessingContextに対する実装
+impl ProcessingContext {
+    ///
+    /// オブジェクトの生成
+    ///
+    /// # 引数
+    /// * `input_path` - 入力ファイルへのパス
+    /// * `output_path` - 出力ファイルへのパス
+    /// * `output_format` - 出力形式
+    ///
+    /// # 戻り値
+    /// 生成したオブジェクトを返す。
+    ///
+    pub(crate) fn new(
+        input_path: &Path,
+        output_path: &Path,
+        output_format: OutputFormat,
+    ) -> Self {
+        Self {
+            input_path: input_path.to_path_buf(),
+            output_path: output_path.to_path_buf(),
+            brackets: Vec::new(),
+            source_format: crate::decoder::sniff_format(input_path),
+            source_size: None,
+            output_format,
+            source: None,
+            output: None,
+            quality: 0.0,
+            palette: Vec::new(),
+            alt_text: None,
+            encoded: Vec::new(),
+            tiles: Vec::new(),
+            bundle: Vec::new(),
+            warnings: Vec::new(),
+            timings: Vec::new(),
+        }
+    }
+
+    ///
+    /// 処理の段の実行
+    ///
+    /// # 引数
+    /// * `name` - 段の名前
+    /// * `stage` - 段の処理
+    ///
+    /// # 戻り値
+    /// 段の処理の結果をそのまま返す。
+    ///
+    /// # 注記
+    /// 処理の成否によらず所要時間を記録する。
+    ///
+    pub(crate) fn run_stage<T, F>(&mut self, name: &'static str, stage: F)
+        -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        let start = Instant::now();
+        let result = stage(self);
+        self.timings.push((name, start.elapsed()));
+
+        result
+    }
+
+    ///
+    /// 警告の追加
+    ///
+    /// # 引数
+    /// * `message` - 警告の内容
+    ///
+    pub(crate) fn warn<S>(&mut self, message: S)
+    where
+        S: Into<String>,
+    {
+        self.warnings.push(message.into());
+    }
+
+    ///
+    /// 処理全体の所要時間へのアクセサ
+    ///
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.timings.iter().map(|(_, time)| *time).sum()
+    }
+}
@@ -0,0 +1,860 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! テンプレートで用いる日時の書式化とタイムゾーンの変換をまとめたモジュール
+//!
+
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+
+///
+/// タイムゾーン情報(TZif形式)を探すディレクトリのリスト
+///
+const ZONEINFO_DIRS: &[&str] = &[
+    "/usr/share/zoneinfo",
+    "/usr/lib/zoneinfo",
+    "/usr/share/lib/zoneinfo",
+    "/etc/zoneinfo",
+];
+
+///
+/// 夏時間の規則で切り替え時刻の指定が無い場合の時刻(地方時の秒数)
+///
+const DEFAULT_RULE_TIME: i64 = 2 * 3600;
+
+///
+/// 暦の上の日時を格納する構造体
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct DateTime {
+    /// 年
+    year: i64,
+
+    /// 月(1〜12)
+    month: u32,
+
+    /// 日(1〜31)
+    day: u32,
+
+    /// 時(0〜23)
+    hour: u32,
+
+    /// 分(0〜59)
+    minute: u32,
+
+    /// 秒(0〜60)
+    second: u32,
+}
+
+/// DateTimeに対する実装
+impl DateTime {
+    ///
+    /// オブジェクトの生成
+    ///
+    pub(crate) fn new(
+        year: i64,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+    ) -> Self {
+        Self {year, month, day, hour, minute, second}
+    }
+
+    ///
+    /// 1970-01-01 00:00:00からの秒数からの変換
+    ///
+    pub(crate) fn from_unix(secs: i64) -> Self {
+        let (year, month, day) = civil_from_days(secs.div_euclid(86400));
+        let time = secs.rem_euclid(86400) as u32;
+
+        Self {
+            year,
+            month,
+            day,
+            hour: time / 3600,
+            minute: time / 60 % 60,
+            second: time % 60,
+        }
+    }
+
+    ///
+    /// 1970-01-01 00:00:00からの秒数への変換
+    ///
+    pub(crate) fn to_unix(self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * 86400
+            + self.hour as i64 * 3600
+            + self.minute as i64 * 60
+            + self.second as i64
+    }
+
+    ///
+    /// 書式に従った文字列への変換
+    ///
+    /// # 引数
+    /// * `format` - 書式(`check_format()`で確認済みのもの)
+    ///
+    /// # 注記
+    /// 扱える指定子は`check_format()`を参照。
+    ///
+    pub(crate) fn format(&self, format: &str) -> String {
+        let mut result = String::new();
+        let mut chars = format.chars();
+
+        while let Some(ch) = chars.next() {
+            if ch != '%' {
+                result.push(ch);
+                continue;
+            }
+
+            let mut spec = chars.next();
+            let pad = spec != Some('-');
+
+            if !pad {
+                spec = chars.next();
+            }
+
+            let number = |value: u32| {
+                if pad {
+                    format!("{:02}", value)
+                } else {
+                    value.to_string()
+                }
+            };
+
+            match spec {
+                Some('Y') => result.push_str(&format!("{:04}", self.year)),
+                Some('y') => {
+                    result.push_str(&number(self.year.rem_euclid(100) as u32))
+                }
+                Some('m') => result.push_str(&number(self.month)),
+                Some('d') => result.push_str(&number(self.day)),
+                Some('H') => result.push_str(&number(self.hour)),
+                Some('M') => result.push_str(&number(self.minute)),
+                Some('S') => result.push_str(&number(self.second)),
+                Some('F') => result.push_str(&self.format("%Y-%m-%d")),
+                Some('T') => result.push_str(&self.format("%H:%M:%S")),
+                Some('%') => result.push('%'),
+                _ => {}
+            }
+        }
+
+        result
+    }
+}
+
+///
+/// 日時の書式の確認
+///
+/// # 引数
+/// * `format` - 書式
+///
+/// # 戻り値
+/// 書式に問題が無い場合は`Ok(())`を返す。問題がある場合はその内容を
+/// `Err()`でラップして返す。
+///
+/// # 注記
+/// `%Y`(4桁の年)、`%y`(2桁の年)、`%m`、`%d`、`%H`、`%M`、`%S`、`%F`
+/// (`%Y-%m-%d`)、`%T`(`%H:%M:%S`)と`%%`を扱う。`%-m`のように`-`を挟むと
+/// 0で桁を揃えない。
+///
+pub(crate) fn check_format(format: &str) -> Result<(), String> {
+    let mut chars = format.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            continue;
+        }
+
+        let spec = match chars.next() {
+            Some('-') => chars.next().filter(|ch| "ymdHMS".contains(*ch)),
+            spec => spec.filter(|ch| "YymdHMSFT%".contains(*ch)),
+        };
+
+        if spec.is_none() {
+            return Err(format!("invalid date format \"{}\"", format));
+        }
+    }
+
+    Ok(())
+}
+
+///
+/// タイムゾーンを管理する構造体
+///
+#[derive(Debug, Clone)]
+pub(crate) struct TimeZone {
+    /// 指定された名前
+    name: String,
+
+    /// UTCとの時差の決め方
+    zone: Zone,
+}
+
+///
+/// UTCとの時差の決め方の定義
+///
+#[derive(Debug, Clone)]
+enum Zone {
+    /// 固定の時差(秒数)
+    Fixed(i64),
+
+    /// タイムゾーン情報ファイルの内容
+    Tzif {
+        /// 切り替わる時刻(UTCの秒数)と切り替わった後の時差の番号のリスト
+        transitions: Vec<(i64, usize)>,
+
+        /// 時差(秒数)のリスト
+        offsets: Vec<i64>,
+
+        /// 最後の切り替わり以降に用いる規則
+        rule: Option<PosixRule>,
+    },
+}
+
+///
+/// POSIX形式のTZ文字列による時差の規則を格納する構造体
+///
+#[derive(Debug, Clone, PartialEq)]
+struct PosixRule {
+    /// 標準時の時差(秒数)
+    std_offset: i64,
+
+    /// 夏時間の時差と開始・終了の規則
+    dst: Option<DstRule>,
+}
+
+///
+/// 夏時間の規則を格納する構造体
+///
+#[derive(Debug, Clone, PartialEq)]
+struct DstRule {
+    /// 夏時間の時差(秒数)
+    offset: i64,
+
+    /// 開始日と時刻(地方時の秒数)
+    start: (RuleDate, i64),
+
+    /// 終了日と時刻(地方時の秒数)
+    end: (RuleDate, i64),
+}
+
+///
+/// 夏時間の開始日・終了日の指定の定義
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RuleDate {
+    /// 2月29日を数えない1始まりの通算日("Jn")
+    Julian(u32),
+
+    /// 2月29日を数える0始まりの通算日("n")
+    Ordinal(u32),
+
+    /// 月・第何週・曜日("Mm.w.d"、週の5は最終週、曜日の0は日曜日)
+    MonthWeekDay(u32, u32, u32),
+}
+
+// Displayトレイトの実装
+impl Display for TimeZone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// TimeZoneに対する実装
+impl TimeZone {
+    ///
+    /// タイムゾーンの読み込み
+    ///
+    /// # 引数
+    /// * `name` - タイムゾーンの名前("Asia/Tokyo"などのIANAの名前、"UTC"、
+    ///   または"+09:00"のような時差)
+    ///
+    /// # 戻り値
+    /// 読み込みに成功した場合はタイムゾーンを`Ok()`でラップして返す。失敗し
+    /// た場合はエラー情報を`Err()`でラップして返す。
+    ///
+    /// # 注記
+    /// IANAの名前はシステムのタイムゾーン情報(環境変数TZDIRのディレクトリ、
+    /// 無ければ/usr/share/zoneinfoなど)から読み込む。
+    ///
+    pub(crate) fn load(name: &str) -> Result<Self> {
+        let trimmed = name.trim();
+
+        if ["UTC", "GMT", "Z"].iter().any(|s| s.eq_ignore_ascii_case(trimmed))
+        {
+            return Ok(Self {name: trimmed.to_string(), zone: Zone::Fixed(0)});
+        }
+
+        if trimmed.starts_with(['+', '-']) {
+            let offset = parse_offset(trimmed)
+                .filter(|offset| offset.abs() <= 18 * 3600)
+                .ok_or_else(|| anyhow!("invalid UTC offset \"{}\"", trimmed))?;
+
+            return Ok(Self {
+                name: trimmed.to_string(),
+                zone: Zone::Fixed(offset),
+            });
+        }
+
+        if trimmed.is_empty()
+            || trimmed.starts_with('/')
+            || trimmed.split('/').any(|part| part == "..")
+        {
+            return Err(anyhow!("invalid time zone name \"{}\"", trimmed));
+        }
+
+        let dirs = env::var_os("TZDIR")
+            .map(PathBuf::from)
+            .into_iter()
+            .chain(ZONEINFO_DIRS.iter().map(PathBuf::from));
+
+        for dir in dirs {
+            if let Ok(data) = fs::read(dir.join(trimmed)) {
+                let zone = parse_tzif(&data).ok_or_else(|| {
+                    anyhow!("time zone file for \"{}\" is broken", trimmed)
+                })?;
+
+                return Ok(Self {name: trimmed.to_string(), zone});
+            }
+        }
+
+        Err(anyhow!("unknown time zone \"{}\"", trimmed))
+    }
+
+    ///
+    /// UTCとの時差の取得
+    ///
+    /// # 引数
+    /// * `utc` - 対象の時刻(1970-01-01 00:00:00 UTCからの秒数)
+    ///
+    /// # 戻り値
+    /// 地方時からUTCを引いた秒数を返す。
+    ///
+    pub(crate) fn offset_at(&self, utc: i64) -> i64 {
+        match &self.zone {
+            Zone::Fixed(offset) => *offset,
+
+            Zone::Tzif {transitions, offsets, rule} => {
+                let index = transitions
+                    .partition_point(|(time, _)| *time <= utc);
+
+                match rule {
+                    Some(rule) if index == transitions.len() => {
+                        rule.offset_at(utc)
+                    }
+
+                    _ if index == 0 => offsets[0],
+                    _ => offsets[transitions[index - 1].1],
+                }
+            }
+        }
+    }
+
+    ///
+    /// UTCの時刻から地方時の日時への変換
+    ///
+    /// # 引数
+    /// * `utc` - 対象の時刻(1970-01-01 00:00:00 UTCからの秒数)
+    ///
+    pub(crate) fn to_local(&self, utc: i64) -> DateTime {
+        DateTime::from_unix(utc + self.offset_at(utc))
+    }
+}
+
+/// PosixRuleに対する実装
+impl PosixRule {
+    ///
+    /// TZ文字列の解析
+    ///
+    /// # 注記
+    /// "JST-9"や"CET-1CEST,M3.5.0,M10.5.0/3"の形式を扱う。時差の符号は
+    /// POSIXの規約に従いUTCとの差と逆になる。
+    ///
+    fn parse(s: &str) -> Option<Self> {
+        let mut rest = s;
+
+        let std_name = take_name(&mut rest)?;
+        let std_offset = -take_offset(&mut rest)?;
+
+        if std_name.is_empty() {
+            return None;
+        }
+
+        if rest.is_empty() {
+            return Some(Self {std_offset, dst: None});
+        }
+
+        take_name(&mut rest).filter(|name| !name.is_empty())?;
+
+        let offset = if rest.starts_with(|ch: char| {
+            ch.is_ascii_digit() || ch == '+' || ch == '-'
+        }) {
+            -take_offset(&mut rest)?
+        } else {
+            std_offset + 3600
+        };
+
+        // 規則の記述が無い場合は米国の規則とする
+        let rules = match rest.strip_prefix(',') {
+            Some(rules) => rules,
+            None if rest.is_empty() => "M3.2.0,M11.1.0",
+            None => return None,
+        };
+
+        let (start, end) = rules.split_once(',')?;
+
+        Some(Self {
+            std_offset,
+            dst: Some(DstRule {
+                offset,
+                start: parse_rule_date(start)?,
+                end: parse_rule_date(end)?,
+            }),
+        })
+    }
+
+    ///
+    /// UTCとの時差の取得
+    ///
+    fn offset_at(&self, utc: i64) -> i64 {
+        let Some(dst) = &self.dst else {
+            return self.std_offset;
+        };
+
+        let year = DateTime::from_unix(utc + self.std_offset).year;
+        let start = rule_time(year, dst.start) - self.std_offset;
+        let end = rule_time(year, dst.end) - dst.offset;
+
+        let in_dst = if start < end {
+            start <= utc && utc < end
+        } else {
+            // 南半球では年をまたいで夏時間になる
+            !(end <= utc && utc < start)
+        };
+
+        if in_dst {
+            dst.offset
+        } else {
+            self.std_offset
+        }
+    }
+}
+
+///
+/// TZif形式のタイムゾーン情報の解析
+///
+/// # 戻り値
+/// 解析に成功した場合は時差の決め方を`Some()`でラップして返す。形式が正し
+/// くない場合は`None`を返す。
+///
+/// # 注記
+/// バージョン2以降のファイルは64ビットの時刻の部分と末尾のTZ文字列を用い
+/// る。
+///
+fn parse_tzif(data: &[u8]) -> Option<Zone> {
+    let header = |data: &[u8]| -> Option<[usize; 6]> {
+        if data.get(..4)? != b"TZif" {
+            return None;
+        }
+
+        let mut counts = [0; 6];
+
+        for (index, count) in counts.iter_mut().enumerate() {
+            let at = 20 + index * 4;
+            let bytes = data.get(at..at + 4)?.try_into().ok()?;
+            *count = u32::from_be_bytes(bytes) as usize;
+        }
+
+        Some(counts)
+    };
+
+    /*
+     * ヘッダの読み込み(バージョン2以降は後半のデータを用いる)
+     */
+    let counts = header(data)?;
+    let version = *data.get(4)?;
+
+    let (data, time_size, counts) = if version >= b'2' {
+        let [isut, isstd, leap, time, types, chars] = counts;
+        let skip = 44 + time * 5 + types * 6 + chars + leap * 8 + isstd + isut;
+        let data = data.get(skip..)?;
+
+        (data, 8, header(data)?)
+    } else {
+        (data, 4, counts)
+    };
+
+    let [isut, isstd, leap, time_count, type_count, chars] = counts;
+
+    /*
+     * 切り替わりの時刻と時差の読み込み
+     */
+    let mut pos = 44;
+    let mut times = Vec::with_capacity(time_count);
+
+    for _ in 0..time_count {
+        let bytes = data.get(pos..pos + time_size)?;
+        let time = if time_size == 8 {
+            i64::from_be_bytes(bytes.try_into().ok()?)
+        } else {
+            i32::from_be_bytes(bytes.try_into().ok()?) as i64
+        };
+
+        times.push(time);
+        pos += time_size;
+    }
+
+    let indices = data.get(pos..pos + time_count)?;
+    pos += time_count;
+
+    let mut offsets = Vec::with_capacity(type_count);
+
+    for _ in 0..type_count {
+        let bytes = data.get(pos..pos + 4)?.try_into().ok()?;
+        offsets.push(i32::from_be_bytes(bytes) as i64);
+        pos += 6;
+    }
+
+    if offsets.is_empty()
+        || indices.iter().any(|index| *index as usize >= offsets.len())
+    {
+        return None;
+    }
+
+    pos += chars + leap * (time_size + 4) + isstd + isut;
+
+    /*
+     * 末尾のTZ文字列の読み込み
+     */
+    let rule = if version >= b'2' {
+        data.get(pos..)
+            .and_then(|footer| footer.strip_prefix(b"\n"))
+            .and_then(|footer| footer.split(|ch| *ch == b'\n').next())
+            .and_then(|footer| std::str::from_utf8(footer).ok())
+            .and_then(PosixRule::parse)
+    } else {
+        None
+    };
+
+    Some(Zone::Tzif {
+        transitions: times.into_iter()
+            .zip(indices.iter().map(|index| *index as usize))
+            .collect(),
+        offsets,
+        rule,
+    })
+}
+
+///
+/// "+09:00"や"-0530"の形式の時差の解析
+///
+/// # 戻り値
+/// UTCとの時差(秒数)を`Some()`でラップして返す。形式が正しくない場合は
+/// `None`を返す。
+///
+pub(crate) fn parse_offset(s: &str) -> Option<i64> {
+    let (sign, digits) = match s.trim().split_at_checked(1)? {
+        ("+", digits) => (1, digits),
+        ("-", digits) => (-1, digits),
+        _ => return None,
+    };
+
+    let (hours, minutes) = match digits.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        None if digits.len() == 4 => digits.split_at(2),
+        None => (digits, "0"),
+    };
+
+    if hours.is_empty()
+        || !hours.chars().chain(minutes.chars()).all(|ch| ch.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let hours: i64 = hours.parse().ok()?;
+    let minutes: i64 = minutes.parse().ok()?;
+
+    (minutes < 60).then_some(sign * (hours * 3600 + minutes * 60))
+}
+
+///
+/// TZ文字列からのタイムゾーン名の切り出し
+///
+fn take_name<'a>(rest: &mut &'a str) -> Option<&'a str> {
+    if let Some(quoted) = rest.strip_prefix('<') {
+        let (name, tail) = quoted.split_once('>')?;
+        *rest = tail;
+
+        return Some(name);
+    }
+
+    let end = rest.find(|ch: char| !ch.is_ascii_alphabetic())
+        .unwrap_or(rest.len());
+    let (name, tail) = rest.split_at(end);
+    *rest = tail;
+
+    Some(name)
+}
+
+///
+/// TZ文字列からの時刻("[+-]hh[:mm[:ss]]")の切り出し
+///
+/// # 戻り値
+/// 秒数を`Some()`でラップして返す(符号はそのまま)。
+///
+fn take_offset(rest: &mut &str) -> Option<i64> {
+    let (sign, tail) = match rest.as_bytes().first()? {
+        b'+' => (1, &rest[1..]),
+        b'-' => (-1, &rest[1..]),
+        _ => (1, *rest),
+    };
+
+    let end = tail.find(|ch: char| !ch.is_ascii_digit() && ch != ':')
+        .unwrap_or(tail.len());
+    let (time, tail) = tail.split_at(end);
+    let mut seconds = 0;
+
+    for (index, part) in time.split(':').enumerate() {
+        if index > 2 || part.is_empty() {
+            return None;
+        }
+
+        seconds += part.parse::<i64>().ok()? * [3600, 60, 1][index];
+    }
+
+    *rest = tail;
+
+    Some(sign * seconds)
+}
+
+///
+/// 夏時間の開始日・終了日の指定("M3.2.0/2"など)の解析
+///
+fn parse_rule_date(s: &str) -> Option<(RuleDate, i64)> {
+    let (date, time) = match s.split_once('/') {
+        Some((date, time)) => {
+            let mut rest = time;
+            let time = take_offset(&mut rest)?;
+
+            if !rest.is_empty() {
+                return None;
+            }
+
+            (date, time)
+        }
+
+        None => (s, DEFAULT_RULE_TIME),
+    };
+
+    let date = if let Some(day) = date.strip_prefix('J') {
+        let day = day.parse().ok().filter(|day| (1..=365).contains(day))?;
+        RuleDate::Julian(day)
+    } else if let Some(rule) = date.strip_prefix('M') {
+        let mut items = rule.split('.').map(|item| item.parse::<u32>().ok());
+        let month = items.next()??;
+        let week = items.next()??;
+        let weekday = items.next()??;
+
+        if items.next().is_some()
+            || !(1..=12).contains(&month)
+            || !(1..=5).contains(&week)
+            || weekday > 6
+        {
+            return None;
+        }
+
+        RuleDate::MonthWeekDay(month, week, weekday)
+    } else {
+        RuleDate::Ordinal(date.parse().ok().filter(|day| *day <= 365)?)
+    };
+
+    Some((date, time))
+}
+
+///
+/// 夏時間の開始・終了の地方時の算出
+///
+/// # 戻り値
+/// 1970-01-01 00:00:00からの秒数を地方時で返す。
+///
+fn rule_time(year: i64, (date, time): (RuleDate, i64)) -> i64 {
+    let jan1 = days_from_civil(year, 1, 1);
+    let leap = days_from_civil(year, 3, 1) - days_from_civil(year, 2, 28) == 2;
+
+    let days = match date {
+        RuleDate::Julian(day) => {
+            jan1 + day as i64 - 1 + i64::from(leap && day >= 60)
+        }
+
+        RuleDate::Ordinal(day) => jan1 + day as i64,
+
+        RuleDate::MonthWeekDay(month, week, weekday) => {
+            let first = days_from_civil(year, month, 1);
+            let next = if month == 12 {
+                days_from_civil(year + 1, 1, 1)
+            } else {
+                days_from_civil(year, month + 1, 1)
+            };
+
+            // 1970-01-01は木曜日
+            let first_weekday = (first + 4).rem_euclid(7);
+            let mut day = first
+                + (weekday as i64 - first_weekday).rem_euclid(7)
+                + (week as i64 - 1) * 7;
+
+            while day >= next {
+                day -= 7;
+            }
+
+            day
+        }
+    };
+
+    days * 86400 + time
+}
+
+///
+/// 年月日から1970-01-01からの日数への変換
+///
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let month = month as i64;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146097 + doe - 719468
+}
+
+///
+/// 1970-01-01からの日数から年月日への変換
+///
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn civil_round_trip() {
+        for days in [-719468, -1, 0, 59, 365, 11016, 19844, 2932896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19782), (2024, 2, 29));
+    }
+
+    #[test]
+    fn format_datetime() {
+        let dt = DateTime::new(2024, 5, 1, 9, 5, 3);
+
+        assert_eq!(dt.format("%Y年%m月%d日"), "2024年05月01日");
+        assert_eq!(dt.format("%Y年%-m月%-d日"), "2024年5月1日");
+        assert_eq!(dt.format("%F %T"), "2024-05-01 09:05:03");
+        assert_eq!(dt.format("%y/%m/%d %H:%M 100%%"), "24/05/01 09:05 100%");
+        assert_eq!(DateTime::from_unix(dt.to_unix()), dt);
+    }
+
+    #[test]
+    fn check_date_format() {
+        assert!(check_format("%Y年%-m月%-d日").is_ok());
+        assert!(check_format("%F %T").is_ok());
+        assert!(check_format("%Q").is_err());
+        assert!(check_format("%-Y").is_err());
+        assert!(check_format("100%").is_err());
+    }
+
+    #[test]
+    fn fixed_offsets() {
+        assert_eq!(parse_offset("+09:00"), Some(9 * 3600));
+        assert_eq!(parse_offset("-0530"), Some(-(5 * 3600 + 30 * 60)));
+        assert_eq!(parse_offset("+9"), Some(9 * 3600));
+        assert_eq!(parse_offset("09:00"), None);
+        assert_eq!(parse_offset("+09:75"), None);
+
+        let tz = TimeZone::load("+09:00").unwrap();
+        assert_eq!(tz.to_local(0), DateTime::new(1970, 1, 1, 9, 0, 0));
+        assert!(TimeZone::load("+25:00").is_err());
+        assert!(TimeZone::load("../etc/passwd").is_err());
+    }
+
+    #[test]
+    fn posix_rule() {
+        let rule = PosixRule::parse("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+
+        // 2024年の夏時間は3月31日01:00 UTCから10月27日01:00 UTCまで
+        let at = |m, d, h| {
+            rule.offset_at(DateTime::new(2024, m, d, h, 0, 0).to_unix())
+        };
+
+        assert_eq!(at(1, 15, 12), 3600);
+        assert_eq!(at(3, 31, 0), 3600);
+        assert_eq!(at(3, 31, 1), 7200);
+        assert_eq!(at(10, 27, 0), 7200);
+        assert_eq!(at(10, 27, 1), 3600);
+
+        let rule = PosixRule::parse("<+10>-10").unwrap();
+        assert_eq!(rule, PosixRule {std_offset: 36000, dst: None});
+
+        assert_eq!(
+            PosixRule::parse("JST-9"),
+            Some(PosixRule {std_offset: 9 * 3600, dst: None})
+        );
+        assert!(PosixRule::parse("-9").is_none());
+    }
+
+    #[test]
+    fn system_time_zone() {
+        // タイムゾーン情報がインストールされていない環境では確認しない
+        let Ok(tz) = TimeZone::load("America/New_York") else {
+            return;
+        };
+
+        let utc = |y, m, d| DateTime::new(y, m, d, 12, 0, 0).to_unix();
+
+        assert_eq!(tz.offset_at(utc(2024, 1, 15)), -5 * 3600);
+        assert_eq!(tz.offset_at(utc(2024, 7, 15)), -4 * 3600);
+        assert_eq!(tz.offset_at(utc(2060, 7, 15)), -4 * 3600);
+        assert_eq!(
+            tz.to_local(utc(2024, 7, 15)),
+            DateTime::new(2024, 7, 15, 8, 0, 0)
+        );
+    }
+
+    #[test]
+    fn southern_hemisphere_rule() {
+        let rule = PosixRule::parse("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        let at = |m, d| {
+            rule.offset_at(DateTime::new(2024, m, d, 0, 0, 0).to_unix())
+        };
+
+        assert_eq!(at(1, 15), 11 * 3600);
+        assert_eq!(at(7, 15), 10 * 3600);
+        assert_eq!(at(12, 15), 11 * 3600);
+    }
+}
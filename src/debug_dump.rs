@@ -0,0 +1,133 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 合成処理の中間画像の書き出し(デバッグ用)をまとめたモジュール
+//!
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use image::{GrayImage, Luma, Primitive};
+
+use crate::pixel::{WorkImage, WorkPixel};
+
+///
+/// 中間画像の書き出し先を管理する構造体
+///
+pub(crate) struct DebugDump {
+    /// 書き出し先のディレクトリ
+    dir: PathBuf,
+}
+
+/// DebugDumpに対する実装
+impl DebugDump {
+    ///
+    /// オブジェクトの生成
+    ///
+    /// # 引数
+    /// * `root` - デバッグ出力のルートディレクトリ
+    /// * `name` - 対象の名前(入力ファイルのファイル名から拡張子を除いたもの)
+    ///
+    /// # 戻り値
+    /// `root`の下に`name`のディレクトリを作成し、生成したオブジェクトを
+    /// `Ok()`でラップして返す。失敗した場合はエラー情報を`Err()`でラップし
+    /// て返す。
+    ///
+    pub(crate) fn new(root: &Path, name: &str) -> Result<Self> {
+        let dir = root.join(name);
+
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("cannot create {}", dir.display()))?;
+
+        Ok(Self {dir})
+    }
+
+    ///
+    /// 入力ファイルに対応するオブジェクトの生成
+    ///
+    /// # 引数
+    /// * `root` - デバッグ出力のルートディレクトリ
+    /// * `input_path` - 入力ファイルへのパス
+    ///
+    /// # 戻り値
+    /// `new()`と同じ。
+    ///
+    pub(crate) fn for_input(root: &Path, input_path: &Path) -> Result<Self> {
+        let name = input_path.file_stem()
+            .map(|stem| stem.to_string_lossy())
+            .unwrap_or_default();
+
+        Self::new(root, &name)
+    }
+
+    ///
+    /// 画像の書き出し
+    ///
+    /// # 引数
+    /// * `name` - ファイル名(拡張子を除く)
+    /// * `image` - 書き出す画像データ
+    ///
+    /// # 戻り値
+    /// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を
+    /// `Err()`でラップして返す。
+    ///
+    /// # 注記
+    /// 作業用の色深度のままPNG形式で書き出す。
+    ///
+    pub(crate) fn save<T>(&self, name: &str, image: &WorkImage<T>)
+        -> Result<()>
+    where
+        T: WorkPixel,
+    {
+        let path = self.path(name);
+
+        T::into_dynamic(image.clone())
+            .save(&path)
+            .with_context(|| format!("cannot write {}", path.display()))
+    }
+
+    ///
+    /// アルファチャネルの書き出し
+    ///
+    /// # 引数
+    /// * `name` - ファイル名(拡張子を除く)
+    /// * `image` - アルファチャネルを取り出す画像データ
+    ///
+    /// # 戻り値
+    /// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を
+    /// `Err()`でラップして返す。
+    ///
+    /// # 注記
+    /// アルファチャネル(最後のチャネル)を8ビットのグレースケール画像として
+    /// 書き出す。
+    ///
+    pub(crate) fn save_alpha<T>(&self, name: &str, image: &WorkImage<T>)
+        -> Result<()>
+    where
+        T: WorkPixel,
+    {
+        let path = self.path(name);
+        let max: f32 = T::Subpixel::DEFAULT_MAX_VALUE.into();
+        let alpha = T::CHANNEL_COUNT as usize - 1;
+
+        let mask = GrayImage::from_fn(image.width(), image.height(), |x, y| {
+            let value: f32 = image.get_pixel(x, y).channels()[alpha].into();
+            Luma([(value / max * 255.0).round() as u8])
+        });
+
+        mask.save(&path)
+            .with_context(|| format!("cannot write {}", path.display()))
+    }
+
+    ///
+    /// 書き出し先のファイルへのパスの生成
+    ///
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.png", name))
+    }
+}
@@ -0,0 +1,779 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 入力画像のデコード関連の処理をまとめたモジュール
+//!
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use image::codecs::gif::GifDecoder;
+use image::{
+    AnimationDecoder, DynamicImage, GrayImage, ImageBuffer, ImageFormat,
+    ImageReader, Pixel, Rgba, RgbaImage
+};
+use image::imageops::{
+    flip_horizontal, flip_vertical, rotate180, rotate270, rotate90
+};
+use crate::codec;
+use crate::pixel::{Rgba16Image, WorkPixel};
+use crate::registry;
+
+#[cfg(feature = "heif")]
+use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+#[cfg(feature = "raw")]
+use image::RgbImage;
+
+///
+/// JPEG規格(Annex K)の輝度成分の標準量子化テーブル(ジグザグ順)
+///
+const STD_LUMINANCE_QUANT: [u16; 64] = [
+    16,  11,  12,  14,  12,  10,  16,  14,
+    13,  14,  18,  17,  16,  19,  24,  40,
+    26,  24,  22,  22,  24,  49,  35,  37,
+    29,  40,  58,  51,  61,  60,  57,  51,
+    56,  55,  64,  72,  92,  78,  64,  68,
+    87,  69,  55,  56,  80, 109,  81,  87,
+    95,  98, 103, 104, 103,  62,  77, 113,
+   121, 112, 100, 120,  92, 101, 103,  99,
+];
+
+///
+/// 汎用のデコーダで扱う画像ファイルの拡張子のリスト
+///
+const INPUT_EXTENSIONS: &[&str] = &["png", "tif", "tiff"];
+
+///
+/// デコード可能な画像ファイルか否かの判定
+///
+/// # 引数
+/// * `path` - 判定対象のファイルへのパス
+///
+/// # 戻り値
+/// 専用のデコーダが登録されている形式か、拡張子が汎用のデコーダで扱う形式の
+/// ものであれば`true`を返す。いずれでもない場合は、ファイルの先頭を読んで
+/// `image`クレートでデコード可能な形式(アニメーションGIFを除く)であれば
+/// `true`を返す。
+///
+pub(crate) fn is_decodable(path: &Path) -> bool {
+    if registry::find_decoder(path).is_some() {
+        return true;
+    }
+
+    if crate::has_extension(path, INPUT_EXTENSIONS) {
+        return true;
+    }
+
+    match sniff_format(path) {
+        Some(ImageFormat::Gif) => !is_animated_gif(path).unwrap_or(true),
+        Some(_) => true,
+        None => false,
+    }
+}
+
+///
+/// ファイル内容からの画像形式の判別
+///
+/// # 引数
+/// * `path` - 判別対象のファイルへのパス
+///
+/// # 戻り値
+/// `image`クレートでデコード可能な形式であればその形式を`Some()`でラップして
+/// 返す。判別できない場合やデコードできない形式の場合は`None`を返す。
+///
+/// # 注記
+/// ファイル先頭のシグネチャで判別できない場合は拡張子による判別結果を返す。
+///
+pub(crate) fn sniff_format(path: &Path) -> Option<ImageFormat> {
+    ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .format()
+        .filter(|format| format.reading_enabled())
+}
+
+///
+/// 縮小しながらのデコードに対応した形式か否かの判定
+///
+/// # 引数
+/// * `path` - 判定対象のファイルへのパス
+///
+/// # 戻り値
+/// 専用のデコーダが縮小デコードに対応している場合は`true`を返す。
+///
+/// # 注記
+/// 縮小デコードに対応しない形式では、デコード前に画像サイズを読み出しても
+/// 得るものが無い。RAWのようにサイズの読み出しにデコードと同等のコストが掛
+/// かる形式もあるので、呼び出し側はこの判定で読み出しを省略できる。
+///
+pub(crate) fn is_scalable(path: &Path) -> bool {
+    registry::find_decoder(path).is_some_and(|entry| entry.scalable)
+}
+
+///
+/// 画像ファイルのデコード
+///
+/// # 引数
+/// * `path` - デコード対象の画像ファイルへのパス
+/// * `min_size` - 後段で必要となる最小の画像サイズ(向きの補正後の幅と高さ
+///   のタプル、縮小を許さない場合は`None`)
+///
+/// # 戻り値
+/// 処理に成功した場合はEXIF Orientationに従って向きを補正した画像を
+/// `RgbaImage`オブジェクトとして`Ok()`でラップして返す。失敗した場合はエラー
+/// 情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// 専用のデコーダが登録されている形式(JPEG、および有効なフィーチャで扱える
+/// 形式)はそのデコーダで、それ以外は`image`クレートでデコードする。デコーダ
+/// が対応している場合は`min_size`を下回らない範囲で縮小してデコードするので、
+/// 返される画像は元の画像サイズより小さいことがある。
+///
+pub(crate) fn decode_image<P>(path: P, min_size: Option<(u32, u32)>)
+    -> Result<RgbaImage>
+where 
+    P: AsRef<Path>
+{
+    let path = path.as_ref();
+
+    let image = match registry::find_decoder(path) {
+        Some(entry) => match (entry.decode)(
+            path,
+            unoriented_size(path, min_size, entry.oriented)?
+        ) {
+            // 向きの補正を行うデコーダの場合はEXIF Orientationは適用しない
+            Ok(image) if entry.oriented => return Ok(image),
+            Ok(image) => image,
+
+            // 拡張子が一致しても専用のデコーダで扱えないファイル(中身が別
+            // 形式のもの等)は汎用のデコーダにフォールバックする
+            Err(err) => decode_any(path).map_err(|_| anyhow!(
+                "{}: {} decode failed: {}",
+                path.display(),
+                entry.name,
+                err
+            ))?,
+        },
+
+        None => decode_any(path)?,
+    };
+
+    Ok(apply_orientation(image, read_exif_orientation(path)?))
+}
+
+///
+/// 16ビット/チャネルでの画像ファイルのデコード
+///
+/// # 引数
+/// * `path` - デコード対象の画像ファイルへのパス
+/// * `min_size` - 後段で必要となる最小の画像サイズ(向きの補正後の幅と高さ
+///   のタプル、縮小を許さない場合は`None`)
+///
+/// # 戻り値
+/// 処理に成功した場合はEXIF Orientationに従って向きを補正した画像を
+/// `Rgba16Image`オブジェクトとして`Ok()`でラップして返す。失敗した場合はエ
+/// ラー情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// `image`クレートでデコードする形式(16ビットのPNG・TIFF等)は元の精度のま
+/// まデコードする。専用のデコーダが登録されている形式は8ビットでデコードし
+/// たものを16ビットに拡張する。
+///
+pub(crate) fn decode_image16<P>(path: P, min_size: Option<(u32, u32)>)
+    -> Result<Rgba16Image>
+where 
+    P: AsRef<Path>
+{
+    let path = path.as_ref();
+
+    if registry::find_decoder(path).is_some() {
+        let image = decode_image(path, min_size)?;
+        return Ok(Rgba::<u16>::from_rgba8_image(&image));
+    }
+
+    let image = read_any(path)?.to_rgba16();
+
+    Ok(apply_orientation(image, read_exif_orientation(path)?))
+}
+
+///
+/// グレースケールJPEGファイルのデコード
+///
+/// # 引数
+/// * `path` - デコード対象の画像ファイルへのパス
+/// * `min_size` - 後段で必要となる最小の画像サイズ(向きの補正後の幅と高さ
+///   のタプル、縮小を許さない場合は`None`)
+///
+/// # 戻り値
+/// 対象がグレースケールのJPEGファイルだった場合は、EXIF Orientationに従って
+/// 向きを補正した画像を`GrayImage`オブジェクトとして`Some()`でラップし、更に
+/// `Ok()`でラップして返す。それ以外の場合は`Ok(None)`を返す。EXIF情報の読み
+/// 込みに失敗した場合はエラー情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// 輝度成分のみを持つJPEGファイルはRGBAへの変換を行わずにそのままデコード
+/// する。JPEGバックエンドで扱えないファイルは`Ok(None)`として通常のデコード
+/// に委ねる。
+///
+pub(crate) fn decode_grayscale<P>(path: P, min_size: Option<(u32, u32)>)
+    -> Result<Option<GrayImage>>
+where 
+    P: AsRef<Path>
+{
+    let path = path.as_ref();
+
+    let is_jpeg = registry::find_decoder(path)
+        .is_some_and(|entry| entry.name == "jpeg");
+
+    if !is_jpeg {
+        return Ok(None);
+    }
+
+    let min_size = unoriented_size(path, min_size, false)?;
+
+    match codec::jpeg().decode_gray(path, min_size) {
+        Ok(Some(image)) => {
+            let orientation = read_exif_orientation(path)?;
+            Ok(Some(apply_orientation(image, orientation)))
+        }
+
+        _ => Ok(None),
+    }
+}
+
+///
+/// 向きの補正前の座標系での画像サイズへの変換
+///
+/// # 引数
+/// * `path` - 対象の画像ファイルへのパス
+/// * `size` - 向きの補正後の座標系での画像サイズ
+/// * `oriented` - デコーダ自身が向きの補正を行うか否か
+///
+/// # 戻り値
+/// デコーダに渡す画像サイズを`Ok()`でラップして返す。EXIF情報の読み込みに失
+/// 敗した場合はエラー情報を`Err()`でラップして返す。
+///
+fn unoriented_size(path: &Path, size: Option<(u32, u32)>, oriented: bool)
+    -> Result<Option<(u32, u32)>>
+{
+    let Some((width, height)) = size else {
+        return Ok(None);
+    };
+
+    if oriented {
+        return Ok(size);
+    }
+
+    Ok(Some(match read_exif_orientation(path)? {
+        5..=8 => (height, width),
+        _ => (width, height),
+    }))
+}
+
+///
+/// 形式を判別した上での汎用のデコード
+///
+/// # 引数
+/// * `path` - デコード対象の画像ファイルへのパス
+///
+/// # 戻り値
+/// 処理に成功した場合はデコードした画像を`RgbaImage`オブジェクトとして`Ok()`
+/// でラップして返す。失敗した場合はエラー情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// 形式はファイル先頭のシグネチャ(判別できない場合は拡張子)で判別し、
+/// `image`クレートでデコードする。アニメーションGIFは扱わない。
+///
+pub(crate) fn decode_any(path: &Path) -> Result<RgbaImage> {
+    Ok(read_any(path)?.to_rgba8())
+}
+
+///
+/// 形式を判別した上での汎用の読み込み
+///
+/// # 引数
+/// * `path` - 読み込む画像ファイルへのパス
+///
+/// # 戻り値
+/// 処理に成功した場合はデコードした画像を元のカラータイプのまま`Ok()`でラッ
+/// プして返す。失敗した場合はエラー情報を`Err()`でラップして返す。
+///
+fn read_any(path: &Path) -> Result<DynamicImage> {
+    let reader = ImageReader::open(path)?.with_guessed_format()?;
+
+    match reader.format() {
+        None => {
+            return Err(anyhow!(
+                "{}: unsupported image format",
+                path.display()
+            ));
+        }
+
+        Some(ImageFormat::Gif) if is_animated_gif(path)? => {
+            return Err(anyhow!(
+                "{}: animated GIF is not supported",
+                path.display()
+            ));
+        }
+
+        _ => {}
+    }
+
+    Ok(reader.decode()?)
+}
+
+///
+/// アニメーションGIFか否かの判定
+///
+/// # 引数
+/// * `path` - 判定対象のGIFファイルへのパス
+///
+/// # 戻り値
+/// 複数のフレームを持つ場合は`true`を`Ok()`でラップして返す。読み込みに失敗
+/// した場合はエラー情報を`Err()`でラップして返す。
+///
+fn is_animated_gif(path: &Path) -> Result<bool> {
+    let decoder = GifDecoder::new(BufReader::new(File::open(path)?))?;
+    Ok(decoder.into_frames().take(2).count() > 1)
+}
+
+///
+/// HEIFファイルのデコード
+///
+/// # 引数
+/// * `path` - デコード対象のHEIFファイルへのパス
+///
+/// # 戻り値
+/// 処理に成功した場合はデコードした画像を`RgbaImage`オブジェクトとして`Ok()`
+/// でラップして返す。失敗した場合はエラー情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// プライマリイメージのみをデコードする。HEIFファイル内に記録された回転・反
+/// 転はデコード時に適用される。
+///
+#[cfg(feature = "heif")]
+pub(crate) fn decode_heif(path: &Path) -> Result<RgbaImage> {
+    let name = path.to_str()
+        .ok_or_else(|| anyhow!("invalid path {}", path.display()))?;
+
+    let context = HeifContext::read_from_file(name)?;
+    let handle = context.primary_image_handle()?;
+    let image = LibHeif::new().decode(
+        &handle,
+        ColorSpace::Rgb(RgbChroma::Rgba),
+        None
+    )?;
+
+    let plane = image.planes()
+        .interleaved
+        .ok_or_else(|| anyhow!("no interleaved plane in {}", path.display()))?;
+
+    // 行末にパディングを持つ場合があるので1行ずつ詰めてコピーする
+    let row_bytes = plane.width as usize * 4;
+    let pixels = plane.data
+        .chunks(plane.stride)
+        .take(plane.height as usize)
+        .flat_map(|row| &row[..row_bytes])
+        .copied()
+        .collect();
+
+    ImageBuffer::from_raw(plane.width, plane.height, pixels)
+        .ok_or_else(|| anyhow!("invalid dimensions"))
+}
+
+///
+/// HEIFファイルの画像サイズの読み出し
+///
+/// # 引数
+/// * `path` - 対象のHEIFファイルへのパス
+///
+/// # 戻り値
+/// 処理に成功した場合は回転・反転を適用した後の幅と高さをタプルにパックし
+/// `Ok()`でラップして返す。失敗した場合はエラー情報を`Err()`でラップして返
+/// す。
+///
+#[cfg(feature = "heif")]
+pub(crate) fn read_heif_size(path: &Path) -> Result<(u32, u32)> {
+    let name = path.to_str()
+        .ok_or_else(|| anyhow!("invalid path {}", path.display()))?;
+
+    let handle = HeifContext::read_from_file(name)?.primary_image_handle()?;
+
+    Ok((handle.width(), handle.height()))
+}
+
+///
+/// カメラRAWファイルのデコード
+///
+/// # 引数
+/// * `path` - デコード対象のカメラRAWファイルへのパス
+///
+/// # 戻り値
+/// 処理に成功した場合はデモザイク処理を行ったsRGB画像を`RgbaImage`オブジェク
+/// トとして`Ok()`でラップして返す。失敗した場合はエラー情報を`Err()`でラップ
+/// して返す。
+///
+/// # 注記
+/// 現像パラメータはimagepipeのデフォルト(カメラのホワイトバランス等)を使用
+/// する。
+///
+#[cfg(feature = "raw")]
+pub(crate) fn decode_raw(path: &Path) -> Result<RgbaImage> {
+    let image = imagepipe::simple_decode_8bit(path, 0, 0)
+        .map_err(|err| anyhow!("{}: {}", path.display(), err))?;
+
+    let rgb = RgbImage::from_raw(
+        image.width as u32,
+        image.height as u32,
+        image.data
+    ).ok_or_else(|| anyhow!("invalid dimensions"))?;
+
+    Ok(DynamicImage::ImageRgb8(rgb).to_rgba8())
+}
+
+///
+/// カメラRAWファイルの画像サイズの読み出し
+///
+/// # 引数
+/// * `path` - 対象のカメラRAWファイルへのパス
+///
+/// # 戻り値
+/// 処理に成功した場合は現像後の幅と高さをタプルにパックし`Ok()`でラップして
+/// 返す。失敗した場合はエラー情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// ヘッダのみから現像後のサイズを得られないので現像して求める。
+///
+#[cfg(feature = "raw")]
+pub(crate) fn read_raw_size(path: &Path) -> Result<(u32, u32)> {
+    Ok(decode_raw(path)?.dimensions())
+}
+
+///
+/// EXIF Orientationに従った画像の向きの補正
+///
+/// # 引数
+/// * `image` - 補正対象の画像データ
+/// * `orientation` - EXIF Orientationの値
+///
+/// # 戻り値
+/// 向きを補正した画像データを返す。
+///
+fn apply_orientation<P>(
+    image: ImageBuffer<P, Vec<P::Subpixel>>,
+    orientation: u32
+) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel + 'static
+{
+    match orientation {
+        1 => image,
+        2 => flip_horizontal(&image),
+        3 => rotate180(&image),
+        4 => flip_vertical(&image),
+        5 => flip_horizontal(&rotate90(&image)),
+        6 => rotate90(&image),
+        7 => flip_vertical(&rotate90(&image)),
+        8 => rotate270(&image),
+        _ => image,
+    }
+}
+
+///
+/// JPEGファイルの品質の推定
+///
+/// # 引数
+/// * `path` - 対象の画像ファイルへのパス
+///
+/// # 戻り値
+/// 推定に成功した場合は品質(1〜100)を`Some()`でラップして返す。JPEGファイル
+/// でない場合や輝度成分の量子化テーブルが見つからない場合は`None`を返す。
+///
+/// # 注記
+/// 輝度成分の量子化テーブルを、libjpegの品質スケーリングを各品質で適用した
+/// 標準テーブルと比較し、最も近いものの品質を推定値とする。
+///
+pub(crate) fn estimate_jpeg_quality<P>(path: P) -> Option<u8>
+where 
+    P: AsRef<Path>
+{
+    let file = File::open(path.as_ref()).ok()?;
+    let table = read_luminance_quant(BufReader::new(file)).ok()??;
+
+    quality_of_quant(&table)
+}
+
+///
+/// 輝度成分の量子化テーブルに最も近い品質の探索
+///
+/// # 引数
+/// * `table` - 輝度成分の量子化テーブル(ジグザグ順)
+///
+/// # 戻り値
+/// 標準テーブルとの差の合計が最も小さくなる品質(1〜100)を`Some()`でラップ
+/// して返す。
+///
+fn quality_of_quant(table: &[u16; 64]) -> Option<u8> {
+    (1..=100u8).min_by_key(|&quality| {
+        let scale = if quality < 50 {
+            5000 / quality as u32
+        } else {
+            200 - quality as u32 * 2
+        };
+
+        STD_LUMINANCE_QUANT.iter()
+            .zip(table.iter())
+            .map(|(&std, &actual)| {
+                let expected = ((std as u32 * scale + 50) / 100).clamp(1, 255);
+                expected.abs_diff(actual as u32)
+            })
+            .sum::<u32>()
+    })
+}
+
+///
+/// JPEGファイルからの輝度成分の量子化テーブルの読み出し
+///
+/// # 引数
+/// * `reader` - JPEGファイルの先頭から読み出すリーダ
+///
+/// # 戻り値
+/// 処理に成功した場合は、テーブル番号0の量子化テーブル(見つからない場合は
+/// `None`)を`Ok()`でラップして返す。読み込みに失敗した場合はエラー情報を
+/// `Err()`でラップして返す。
+///
+/// # 注記
+/// SOSマーカー(画像データの開始)までのマーカーセグメントのみを走査する。
+///
+fn read_luminance_quant<R>(mut reader: R) -> Result<Option<[u16; 64]>>
+where
+    R: Read
+{
+    let mut header = [0u8; 2];
+
+    reader.read_exact(&mut header)?;
+    if header != [0xff, 0xd8] {
+        return Ok(None);
+    }
+
+    loop {
+        reader.read_exact(&mut header)?;
+        if header[0] != 0xff {
+            return Ok(None);
+        }
+
+        let marker = header[1];
+
+        // フィルバイトは読み飛ばす
+        if marker == 0xff {
+            continue;
+        }
+
+        // SOS以降は画像データなので走査を打ち切る
+        if marker == 0xda || marker == 0xd9 {
+            return Ok(None);
+        }
+
+        reader.read_exact(&mut header)?;
+        let length = u16::from_be_bytes(header) as usize;
+        if length < 2 {
+            return Ok(None);
+        }
+
+        let mut segment = vec![0u8; length - 2];
+        reader.read_exact(&mut segment)?;
+
+        if marker != 0xdb {
+            continue;
+        }
+
+        // 1つのDQTセグメントに複数のテーブルが格納されている場合がある
+        let mut data = segment.as_slice();
+        while let Some((&info, rest)) = data.split_first() {
+            let wide = (info >> 4) != 0;
+            let size = if wide { 128 } else { 64 };
+
+            if rest.len() < size {
+                break;
+            }
+
+            if info & 0x0f == 0 {
+                let mut table = [0u16; 64];
+
+                for (i, value) in table.iter_mut().enumerate() {
+                    *value = if wide {
+                        u16::from_be_bytes([rest[i * 2], rest[i * 2 + 1]])
+                    } else {
+                        rest[i] as u16
+                    };
+                }
+
+                return Ok(Some(table));
+            }
+
+            data = &rest[size..];
+        }
+    }
+}
+
+///
+/// 画像ファイルの画像サイズの読み出し
+///
+/// # 引数
+/// * `path` - 対象の画像ファイルへのパス
+///
+/// # 戻り値
+/// 処理に成功した場合はEXIF Orientationによる回転を考慮した幅と高さをタプル
+/// にパックし`Ok()`でラップして返す。失敗した場合はエラー情報を`Err()`でラッ
+/// プして返す。
+///
+/// # 注記
+/// 画素データのデコードは行わず、ヘッダ情報のみを読み取る。
+///
+pub(crate) fn read_image_size<P>(path: P) -> Result<(u32, u32)>
+where 
+    P: AsRef<Path>
+{
+    let path = path.as_ref();
+
+    let size = registry::find_decoder(path)
+        .map(|entry| ((entry.read_size)(path), entry.oriented));
+
+    let (width, height) = match size {
+        // 向きの補正を行うデコーダの場合は補正後のサイズが得られる
+        Some((Ok(size), true)) => return Ok(size),
+        Some((Ok(size), false)) => size,
+        _ => ImageReader::open(path)?
+            .with_guessed_format()?
+            .into_dimensions()?,
+    };
+
+    Ok(match read_exif_orientation(path)? {
+        5..=8 => (height, width),
+        _ => (width, height),
+    })
+}
+
+///
+/// EXIF Orientationの読み出し
+///
+/// # 引数
+/// * `path` - 対象ファイルのパス
+///
+/// # 戻り値
+/// Exif Orientationの値。EXIF情報が存在しない場合やEXIF情報を格納できない形
+/// 式の場合は1(補正無し)を返す。
+///
+/// # 参考
+/// https://qiita.com/yoya/items/4e14f696e1afd5a54403
+///
+pub(crate) fn read_exif_orientation<P>(path: P) -> Result<u32>
+where 
+    P: AsRef<Path>
+{
+    let mut buf = BufReader::new(File::open(path)?);
+    let exif = match exif::Reader::new().read_from_container(&mut buf) {
+        Ok(exif) => exif,
+        Err(exif::Error::NotFound(_)) => return Ok(1),
+
+        // EXIFを格納できない形式(BMP, GIF等)の場合も補正無しとする
+        Err(exif::Error::InvalidFormat("Unknown image format")) => return Ok(1),
+
+        Err(err) => return Err(err.into()),
+    };
+
+    Ok(exif
+        .get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .unwrap_or(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    ///
+    /// 指定品質のlibjpeg互換の輝度成分の量子化テーブルの生成
+    ///
+    fn scaled_quant(quality: u32) -> Vec<u8> {
+        let scale = if quality < 50 {
+            5000 / quality
+        } else {
+            200 - quality * 2
+        };
+
+        STD_LUMINANCE_QUANT.iter()
+            .map(|&std| ((std as u32 * scale + 50) / 100).clamp(1, 255) as u8)
+            .collect()
+    }
+
+    ///
+    /// DQTセグメントのみを持つJPEGヘッダの生成
+    ///
+    fn jpeg_header(dqt: &[u8]) -> Vec<u8> {
+        let length = (dqt.len() + 2) as u16;
+        let mut bytes = vec![0xff, 0xd8, 0xff, 0xdb];
+
+        bytes.extend(length.to_be_bytes());
+        bytes.extend(dqt);
+        bytes.extend([0xff, 0xda]);
+        bytes
+    }
+
+    fn estimate(bytes: &[u8]) -> Option<u8> {
+        read_luminance_quant(Cursor::new(bytes))
+            .unwrap()
+            .and_then(|table| quality_of_quant(&table))
+    }
+
+    #[test]
+    fn estimates_scaled_tables() {
+        for quality in [10, 50, 75, 85, 95, 100] {
+            let mut dqt = vec![0x00];
+            dqt.extend(scaled_quant(quality));
+
+            assert_eq!(estimate(&jpeg_header(&dqt)), Some(quality as u8));
+        }
+    }
+
+    #[test]
+    fn finds_luminance_table_after_chroma_and_in_16_bits() {
+        let mut dqt = vec![0x01];
+        dqt.extend([1u8; 64]);
+        dqt.push(0x10);
+        dqt.extend(scaled_quant(60).iter().flat_map(|&v| [0, v]));
+
+        assert_eq!(estimate(&jpeg_header(&dqt)), Some(60));
+    }
+
+    #[test]
+    fn estimates_encoded_jpeg() {
+        let image = RgbImage::from_pixel(16, 16, Rgb([128, 64, 32]));
+        let mut bytes = Vec::new();
+
+        JpegEncoder::new_with_quality(&mut bytes, 80)
+            .encode_image(&image)
+            .unwrap();
+
+        assert_eq!(estimate(&bytes), Some(80));
+    }
+
+    #[test]
+    fn ignores_non_jpeg_and_missing_tables() {
+        assert_eq!(estimate(b"\x89PNG\r\n\x1a\n"), None);
+        assert_eq!(estimate(&[0xff, 0xd8, 0xff, 0xda]), None);
+    }
+}
@@ -0,0 +1,228 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 出力画像のエンコード関連の処理をまとめたモジュール
+//!
+
+use std::io::Write;
+
+use anyhow::Result;
+use image::codecs::avif::AvifEncoder;
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::{
+    DynamicImage, ExtendedColorType, GrayImage, ImageEncoder, RgbaImage
+};
+
+use crate::codec;
+
+///
+/// 出力画像のエンコーダを抽象化するトレイト
+///
+pub(crate) trait Encoder {
+    ///
+    /// 画像データのエンコード
+    ///
+    /// # 引数
+    /// * `writer` - エンコード結果の書き込み先
+    /// * `image` - エンコード対象のイメージデータ
+    /// * `quality` - 出力品質(1〜100)
+    ///
+    /// # 戻り値
+    /// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を
+    /// `Err()`でラップして返す。
+    ///
+    fn encode(&self, writer: &mut dyn Write, image: RgbaImage, quality: f32)
+        -> Result<()>;
+
+    ///
+    /// グレースケール画像のエンコード
+    ///
+    /// # 引数
+    /// * `writer` - エンコード結果の書き込み先
+    /// * `image` - エンコード対象のイメージデータ
+    /// * `quality` - 出力品質(1〜100)
+    ///
+    /// # 戻り値
+    /// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を
+    /// `Err()`でラップして返す。
+    ///
+    /// # 注記
+    /// デフォルトの実装ではRGBAに変換した上で`encode()`に委譲する。
+    ///
+    fn encode_gray(&self, writer: &mut dyn Write, image: GrayImage, quality: f32)
+        -> Result<()>
+    {
+        self.encode(writer, DynamicImage::ImageLuma8(image).to_rgba8(), quality)
+    }
+}
+
+///
+/// JPEGエンコーダ
+///
+pub(crate) struct JpegEncoder {
+    /// プログレッシブJPEGで出力するか否か
+    pub(crate) progressive: bool,
+
+    /// 色差成分1画素あたりの輝度画素数(水平, 垂直)
+    pub(crate) subsampling: (u8, u8),
+}
+
+// Encoderトレイトの実装
+impl Encoder for JpegEncoder {
+    fn encode(&self, writer: &mut dyn Write, image: RgbaImage, quality: f32)
+        -> Result<()>
+    {
+        codec::jpeg().encode(
+            writer,
+            image,
+            quality,
+            self.progressive,
+            self.subsampling
+        )
+    }
+
+    fn encode_gray(&self, writer: &mut dyn Write, image: GrayImage, quality: f32)
+        -> Result<()>
+    {
+        codec::jpeg()
+            .encode_gray(writer, image, quality, self.progressive)
+    }
+}
+
+///
+/// WebPエンコーダ
+///
+pub(crate) struct WebpEncoder {
+    /// WebP固有の品質指定(`None`の場合は共通の品質を使用する)
+    pub(crate) quality: Option<f32>,
+
+    /// 可逆圧縮で出力するか否か
+    pub(crate) lossless: bool,
+}
+
+// Encoderトレイトの実装
+impl Encoder for WebpEncoder {
+    fn encode(&self, writer: &mut dyn Write, image: RgbaImage, quality: f32)
+        -> Result<()>
+    {
+        let quality = self.quality.unwrap_or(quality);
+        codec::webp().encode(writer, image, quality, self.lossless)
+    }
+}
+
+///
+/// AVIFエンコーダ
+///
+pub(crate) struct AvifImageEncoder {
+    /// エンコード速度(1〜10、小さいほど低速・高圧縮)
+    pub(crate) speed: u8,
+}
+
+// Encoderトレイトの実装
+impl Encoder for AvifImageEncoder {
+    fn encode(&self, writer: &mut dyn Write, image: RgbaImage, quality: f32)
+        -> Result<()>
+    {
+        encode_avif(writer, image, quality, self.speed)
+    }
+}
+
+///
+/// PNGエンコーダ
+///
+pub(crate) struct PngImageEncoder {
+    /// 圧縮レベル
+    pub(crate) compression: CompressionType,
+}
+
+// Encoderトレイトの実装
+impl Encoder for PngImageEncoder {
+    fn encode(&self, writer: &mut dyn Write, image: RgbaImage, _quality: f32)
+        -> Result<()>
+    {
+        encode_png(writer, image, self.compression)
+    }
+}
+
+///
+/// AVIFファイルへのエンコード
+///
+/// # 引数
+/// * `writer` - エンコード結果の書き込み先
+/// * `image` - エンコード対象のイメージデータ
+/// * `quality` - 品質(1〜100)
+/// * `speed` - エンコード速度(1〜10)
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を `Err()`でラ
+/// ップして返す。
+///
+pub(crate) fn encode_avif<W>(
+    writer: W,
+    image: RgbaImage,
+    quality: f32,
+    speed: u8,
+) -> Result<()>
+where 
+    W: Write
+{
+    let encoder = AvifEncoder::new_with_speed_quality(
+        writer,
+        speed,
+        quality.round().clamp(1.0, 100.0) as u8
+    );
+
+    encoder.write_image(
+        image.as_raw(),
+        image.width(),
+        image.height(),
+        ExtendedColorType::Rgba8
+    )?;
+
+    Ok(())
+}
+
+///
+/// PNGファイルへのエンコード
+///
+/// # 引数
+/// * `writer` - エンコード結果の書き込み先
+/// * `image` - エンコード対象のイメージデータ
+/// * `compression` - 圧縮レベル
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を `Err()`でラ
+/// ップして返す。
+///
+/// # 注記
+/// 出力画像は不透明なので、アルファチャネルを落としたRGBデータとしてエンコー
+/// ドする。可逆圧縮なので品質の指定は持たない。
+///
+pub(crate) fn encode_png<W>(
+    writer: W,
+    image: RgbaImage,
+    compression: CompressionType,
+) -> Result<()>
+where 
+    W: Write
+{
+    let rgb = DynamicImage::ImageRgba8(image).to_rgb8();
+    let encoder = PngEncoder::new_with_quality(
+        writer,
+        compression,
+        FilterType::Adaptive
+    );
+
+    encoder.write_image(
+        rgb.as_raw(),
+        rgb.width(),
+        rgb.height(),
+        ExtendedColorType::Rgb8
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,270 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 実行前の出力サイズ・処理時間の見積もり処理をまとめたモジュール
+//!
+
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+
+use crate::cmd_args::Options;
+use crate::context::ProcessingContext;
+
+///
+/// 見積もりのためにサンプリングするファイル数
+///
+const SAMPLE_COUNT: usize = 5;
+
+///
+/// 実行前に確認を求める対象とするファイル数
+///
+const LARGE_RUN_FILES: usize = 1000;
+
+///
+/// 実行前に確認を求める対象とする推定処理時間
+///
+const LARGE_RUN_DURATION: Duration = Duration::from_secs(10 * 60);
+
+///
+/// 見積もり結果を格納する構造体
+///
+struct Estimate {
+    /// 処理対象のファイル数
+    files: usize,
+
+    /// サンプリングしたファイル数
+    samples: usize,
+
+    /// 推定出力サイズの合計(バイト数)
+    output_bytes: u64,
+
+    /// 推定処理時間
+    duration: Duration,
+}
+
+///
+/// バイト数の表示用文字列への変換
+///
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+///
+/// 処理時間の表示用文字列への変換
+///
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}h{:02}m{:02}s", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+///
+/// 入力ファイルのサイズの合計の算出
+///
+/// # 引数
+/// * `input_path` - 入力ファイルへのパス
+/// * `brackets` - HDR合成する残りのフレームのパスのリスト
+///
+/// # 戻り値
+/// 入力ファイルとブラケットのファイルサイズの合計(バイト数)を返す。サイズ
+/// を取得できないファイルは数えない。
+///
+fn input_bytes(input_path: &Path, brackets: &[PathBuf]) -> u64 {
+    std::iter::once(input_path)
+        .chain(brackets.iter().map(PathBuf::as_path))
+        .filter_map(|path| path.metadata().ok())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+///
+/// 1ファイル分の試行
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `input_path` - 入力ファイルへのパス
+/// * `brackets` - HDR合成する残りのフレームのパスのリスト
+///
+/// # 戻り値
+/// 処理に成功した場合は出力サイズ(バイト数)と処理時間をパックしたタプルを
+/// `Ok()`でラップして返す。失敗した場合はエラー情報を`Err()`でラップして返
+/// す。
+///
+/// # 注記
+/// そのままコピーする画像は入力ファイルのサイズを出力サイズとし、処理時間は
+/// 判定に要した時間のみとする。
+///
+fn sample(opts: &Arc<Options>, input_path: &Path, brackets: &[PathBuf])
+    -> Result<(u64, Duration)>
+{
+    let mut skipped = Vec::new();
+    let opts = &opts.for_input(input_path, &mut skipped)?;
+    let mut ctx = ProcessingContext::new(
+        input_path,
+        input_path,
+        crate::output_format_for(opts, Some(input_path))
+    );
+
+    ctx.brackets = brackets.to_vec();
+
+    let passthrough = ctx.run_stage("probe", |ctx| {
+        if ctx.brackets.is_empty() && !opts.is_web_bundle() {
+            crate::passthrough_size(opts, &ctx.input_path)
+        } else {
+            Ok(None)
+        }
+    })?;
+
+    if passthrough.is_some() {
+        return Ok((input_bytes(input_path, &[]), ctx.elapsed()));
+    }
+
+    crate::encode_file(opts, &mut ctx)?;
+
+    let output = ctx.encoded.len() as u64
+        + ctx.tiles.iter()
+            .map(|(_, _, data)| data.len() as u64)
+            .sum::<u64>();
+
+    Ok((output, ctx.elapsed()))
+}
+
+///
+/// 見積もりの算出
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `files` - 処理対象のファイル(入力ファイルへのパスとHDR合成する残りの
+///   フレームのパスのリスト)のリスト
+///
+/// # 戻り値
+/// 見積もり結果を返す。
+///
+/// # 注記
+/// 処理対象から等間隔にサンプリングしたファイルを実際に処理(出力はメモリ上
+/// のみで、デバッグ用の中間画像も書き出さない)し、入力ファイルサイズあたり
+/// の出力サイズと処理時間を全体に外挿する。処理に失敗したファイルは警告を表
+/// 示してサンプルから除く。
+///
+fn calculate(opts: &Arc<Options>, files: &[(PathBuf, Vec<PathBuf>)])
+    -> Estimate
+{
+    let opts = &opts.without_debug_dump();
+    let step = files.len().div_ceil(SAMPLE_COUNT).max(1);
+
+    let mut sample_input = 0u64;
+    let mut sample_output = 0u64;
+    let mut sample_time = Duration::ZERO;
+    let mut samples = 0;
+
+    for (path, brackets) in files.iter().step_by(step) {
+        match sample(opts, path, brackets) {
+            Ok((output, elapsed)) => {
+                sample_time += elapsed;
+                sample_input += input_bytes(path, brackets);
+                sample_output += output;
+                samples += 1;
+            }
+
+            Err(err) => {
+                eprintln!(
+                    "warning: {}: not sampled for the estimate: {}",
+                    path.display(),
+                    err
+                );
+            }
+        }
+    }
+
+    let total_input = files.iter()
+        .map(|(path, brackets)| input_bytes(path, brackets))
+        .sum::<u64>();
+
+    let ratio = if sample_input > 0 {
+        total_input as f64 / sample_input as f64
+    } else {
+        0.0
+    };
+
+    Estimate {
+        files: files.len(),
+        samples,
+        output_bytes: (sample_output as f64 * ratio) as u64,
+        duration: sample_time.mul_f64(ratio),
+    }
+}
+
+///
+/// 見積もりの表示と実行可否の確認
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `files` - 処理対象のファイル(入力ファイルへのパスとHDR合成する残りの
+///   フレームのパスのリスト)のリスト
+///
+/// # 戻り値
+/// 処理を続行する場合は`Ok(true)`を、中止する場合は`Ok(false)`を返す。確認
+/// の入出力に失敗した場合はエラー情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// 大規模な処理(ファイル数または推定処理時間が閾値を超える場合)では標準入力
+/// から続行の確認を求める。それ以外の場合は見積もりを表示してそのまま続行す
+/// る。標準入力が端末でない場合は確認できないので、大規模な処理はエラーとす
+/// る。
+///
+pub(crate) fn confirm(
+    opts: &Arc<Options>,
+    files: &[(PathBuf, Vec<PathBuf>)],
+) -> Result<bool> {
+    if files.is_empty() {
+        return Ok(true);
+    }
+
+    let estimate = calculate(opts, files);
+
+    eprintln!(
+        "estimate: {} files (sampled {}), output {}, time {}",
+        estimate.files,
+        estimate.samples,
+        format_bytes(estimate.output_bytes),
+        format_duration(estimate.duration)
+    );
+
+    if estimate.files < LARGE_RUN_FILES
+        && estimate.duration < LARGE_RUN_DURATION
+    {
+        return Ok(true);
+    }
+
+    if !io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "this is a large run and stdin is not a terminal to confirm it; \
+             run without --estimate to proceed unattended"
+        ));
+    }
+
+    eprint!("this is a large run. continue? [y/N] ");
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
@@ -0,0 +1,462 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 入力ファイルごとの処理内容の判断の表示(explainサブコマンド)をまとめたモ
+//! ジュール
+//!
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+
+use crate::cmd_args::{
+    Orientation, OutputFormat, Options, Position, Quality, Watermark,
+};
+use crate::context::ProcessingContext;
+use crate::decoder;
+use crate::placement;
+use crate::tile_output;
+use crate::tiling;
+
+///
+/// explainサブコマンドの実行
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を`Err()`で
+/// ラップして返す。
+///
+/// # 注記
+/// 通常の実行と同じ判断をデコードと合成まで行って表示する。エンコードとファ
+/// イルの書き込みは行わない。
+///
+pub(crate) fn run(opts: &Arc<Options>) -> Result<()> {
+    match opts.config_path() {
+        Some(path) => println!("config file:       {}", path.display()),
+        None => println!("config file:       (none)"),
+    }
+
+    for path in opts.inputs() {
+        println!();
+        explain_file(opts, &path)?;
+    }
+
+    Ok(())
+}
+
+///
+/// 1ファイル分の判断内容の表示
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `input_path` - 入力ファイルへのパス
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を`Err()`で
+/// ラップして返す。
+///
+fn explain_file(opts: &Arc<Options>, input_path: &Path) -> Result<()> {
+    if !input_path.is_file() {
+        return Err(anyhow!("{} is not file", input_path.display()));
+    }
+
+    /*
+     * 入出力の確認
+     */
+    let (width, height) = decoder::read_image_size(input_path)?;
+    let passthrough = crate::passthrough_size(opts, input_path)?.is_some();
+    let mut output_path = crate::output_path_for(opts, None, input_path);
+
+    // そのままコピーする場合は元の拡張子のまま出力する
+    if passthrough && let Some(ext) = input_path.extension() {
+        output_path = output_path.with_extension(ext);
+    }
+
+    let mut ctx = ProcessingContext::new(
+        input_path,
+        &output_path,
+        crate::output_format_for(opts, Some(input_path))
+    );
+
+    let format = ctx.source_format
+        .map(|format| format!("{:?}", format).to_lowercase())
+        .unwrap_or("unknown".to_string());
+
+    println!("input:             {}", input_path.display());
+    println!("source:            {} {}×{}", format, width, height);
+    println!("output path:       {}", output_path.display());
+
+    /*
+     * 処理方法の判断
+     */
+    if output_path.exists() && !opts.is_force() {
+        println!("action:            skip (output exists, -f to overwrite)");
+        return Ok(());
+    }
+
+    if passthrough {
+        let keep = opts.is_keep_resolution_if_smaller()
+            && opts.resolution().scale_ratio(width, height) >= 1.0;
+
+        let reason = match opts.only_if_larger_than() {
+            Some(limit) if !keep => format!("not larger than {}", limit),
+            _ => "not larger than the output resolution".to_string(),
+        };
+
+        println!("action:            copy as is ({})", reason);
+        return Ok(());
+    }
+
+    println!("action:            process");
+    println!("output format:     {}", ctx.output_format);
+
+    /*
+     * 出力サイズの決定
+     */
+    let resolution = opts.resolution();
+    let size = crate::output_size(opts, width, height);
+
+    println!(
+        "resolution:        {} (scale {:.3})",
+        resolution,
+        resolution.scale_ratio(width, height)
+    );
+    println!("output size:       {}×{}", size.0, size.1);
+
+    let image = if let Some(canvas) = opts.canvas() {
+        println!(
+            "canvas:            {} ({}, gravity {})",
+            canvas,
+            opts.canvas_color(),
+            opts.canvas_gravity()
+        );
+
+        (canvas.width(), canvas.height())
+    } else {
+        size
+    };
+
+    let image = match opts.border() {
+        Some(border) => {
+            let framed = (image.0 + border * 2, image.1 + border * 2);
+
+            println!(
+                "border:            {}px ({}), framed {}×{}",
+                border,
+                opts.border_color(),
+                framed.0,
+                framed.1
+            );
+
+            framed
+        }
+
+        None => image,
+    };
+
+    if let Some(tile) = opts.tile_output() {
+        let tiles = tile_output::grid(image, tile, opts.tile_overlap());
+        let last = tiles.last().map(|rect| (rect.row + 1, rect.col + 1));
+
+        match last {
+            Some((rows, cols)) if tiles.len() > 1 => println!(
+                "tile output:       {}×{} tiles (overlap {}px)",
+                cols,
+                rows,
+                opts.tile_overlap()
+            ),
+            _ => println!("tile output:       not split (fits in one tile)"),
+        }
+    }
+
+    /*
+     * ロゴの配置の決定
+     */
+    let mut skipped = Vec::new();
+    let opts = &opts.for_input(input_path, &mut skipped)?.for_output(image);
+
+    for warning in &skipped {
+        println!("logo:              {}", warning);
+    }
+
+    if opts.watermarks().is_empty() {
+        println!("logo:              disabled");
+    }
+
+    for (index, mark) in opts.watermarks().iter().enumerate() {
+        let label = if index == 0 {
+            "logo".to_string()
+        } else {
+            format!("logo #{}", index + 1)
+        };
+
+        let jitter = match mark.jitter() {
+            Some(range) => placement::jitter(
+                opts.jitter_key(),
+                index,
+                range.pixels(image)
+            ),
+            None => (0, 0),
+        };
+
+        explain_watermark(
+            &label,
+            mark,
+            input_path,
+            opts,
+            image,
+            jitter
+        )?;
+    }
+
+    /*
+     * 出力品質の決定(品質の自動選択のために合成までは行う)
+     */
+    ctx.run_stage("decode", |ctx| crate::decode_stage(opts, ctx))?;
+    ctx.run_stage("render", |ctx| crate::render_stage(opts, ctx))?;
+
+    println!("quality:           {}", describe_quality(opts, &ctx));
+
+    for warning in &ctx.warnings {
+        println!("warning:           {}", warning);
+    }
+
+    Ok(())
+}
+
+///
+/// 透かし1つ分の配置の表示
+///
+/// # 引数
+/// * `label` - 表示に用いる透かしの呼び名
+/// * `mark` - 透かしの情報
+/// * `input_path` - 入力ファイルへのパス
+/// * `opts` - テンプレートの展開に用いるオプション情報
+/// * `image` - 透かしを重畳する画像の幅と高さ
+/// * `jitter` - 入力ファイルに対するロゴをずらす量
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を`Err()`で
+/// ラップして返す。
+///
+fn explain_watermark(
+    label: &str,
+    mark: &Watermark,
+    input_path: &Path,
+    opts: &Options,
+    image: (u32, u32),
+    jitter: (i64, i64),
+) -> Result<()> {
+    let head = |name: &str| format!("{:<19}", format!("{} {}:", label, name));
+
+    if let Some(text) = mark.text() {
+        let text = opts.expand_template(text, input_path)?;
+
+        println!("{}{:?}", head("text"), text);
+    }
+
+    let logo = mark.image();
+    let (w, h) = crate::logo_size(mark, logo.dimensions(), image);
+    let wanted = match mark.scale() {
+        Some(scale) => scale.scaled_size(logo.dimensions(), image),
+        None => logo.dimensions(),
+    };
+
+    if let Some(ratio) = mark.max_coverage()
+        && (w, h) != wanted
+    {
+        println!(
+            "{}{}×{} shrunk to fit (max coverage {})",
+            head("fit"),
+            wanted.0,
+            wanted.1,
+            ratio
+        );
+    }
+
+    if let Some(amount) = crate::logo_sharpen(mark, logo.dimensions(), (w, h)) {
+        println!("{}{}", head("sharpen"), amount);
+    }
+
+    if mark.outline() > 0 || mark.shadow() > 0 {
+        println!(
+            "{}outline {}px, shadow {}px, {}",
+            head("halo"),
+            mark.outline(),
+            mark.shadow(),
+            mark.halo_color()
+        );
+    }
+
+    if let Some(backdrop) = mark.backdrop() {
+        let padding = mark.backdrop_padding()
+            .unwrap_or_else(|| crate::halo::backdrop_padding((w, h)));
+
+        println!(
+            "{}{}, padding {}px, radius {}px",
+            head("backdrop"),
+            backdrop,
+            padding,
+            mark.backdrop_radius().unwrap_or(padding)
+        );
+    }
+
+    if mark.is_grayscale() {
+        println!("{}desaturated at load", head("grayscale"));
+    }
+
+    if let Some(tint) = mark.tint() {
+        println!("{}{} ({})", head("tint"), tint, mark.tint_mode());
+    }
+
+    let orientation = Orientation::of(image);
+
+    if let Some(rule) = mark.orientations().get(orientation) {
+        println!("{}{} ({})", head("orientation"), orientation, rule);
+    }
+
+    if mark.dark_image().is_some() {
+        println!("{}chosen by background luminance", head("dark variant"));
+    }
+
+    let (w, h) = match mark.rotation() {
+        Some(degrees) => {
+            println!("{}{} degrees", head("rotation"), degrees);
+            tiling::rotated_size((w, h), degrees)
+        }
+
+        None => (w, h),
+    };
+
+    if mark.position() == Position::Auto && !mark.is_tile() {
+        println!(
+            "{}AUTO (least detailed region per image, rect below is the \
+             BOTTOM-RIGHT fallback)",
+            head("position")
+        );
+    }
+
+    if let Some(range) = mark.jitter() {
+        let (range_x, range_y) = range.pixels(image);
+
+        println!(
+            "{}±{}px, ±{}px (shifted by {}, {} for this file)",
+            head("jitter"),
+            range_x,
+            range_y,
+            jitter.0,
+            jitter.1
+        );
+    }
+
+    if mark.is_tile() {
+        let (gap_x, gap_y) = match mark.tile_spacing() {
+            Some(spacing) => spacing.pixels(image),
+            None => (w, h),
+        };
+
+        println!(
+            "{:<19}{}×{} tiled at {} degrees, gap {}×{}",
+            format!("{}:", label),
+            w,
+            h,
+            mark.tile_angle(),
+            gap_x,
+            gap_y
+        );
+    } else if mark.is_subpixel() {
+        let (w, h) = crate::logo_exact_size(mark, logo.dimensions(), image);
+        let (x, y) = placement::jittered(
+            crate::logo_exact_origin(mark, mark.position(), image, (w, h)),
+            jitter,
+            (w, h),
+            image
+        );
+
+        println!(
+            "{}{:.2}×{:.2} at ({:.2}, {:.2}) (subpixel)",
+            head("rect"),
+            w,
+            h,
+            x,
+            y
+        );
+    } else {
+        let (x, y) = crate::logo_origin(mark, mark.position(), image, (w, h));
+        let (x, y) = placement::jittered(
+            (x as f32, y as f32),
+            jitter,
+            (w as f32, h as f32),
+            image
+        );
+        let (x, y) = (x as i64, y as i64);
+
+        if let Some(interval) = mark.panorama_interval(image) {
+            println!(
+                "{}every {}px, {} copies",
+                head("repeat"),
+                interval,
+                crate::logo_repeats(mark, image, x, w as f32).len()
+            );
+        }
+
+        let outside = x < 0
+            || y < 0
+            || x + w as i64 > image.0 as i64
+            || y + h as i64 > image.1 as i64;
+
+        println!(
+            "{}{}×{} at ({}, {}){}",
+            head("rect"),
+            w,
+            h,
+            x,
+            y,
+            if outside { " (partly outside the image)" } else { "" }
+        );
+    }
+
+    Ok(())
+}
+
+///
+/// 出力品質の説明の生成
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `ctx` - 合成を終えたコンテキスト
+///
+/// # 戻り値
+/// 出力品質とその決め方を表す文字列を返す。
+///
+fn describe_quality(opts: &Arc<Options>, ctx: &ProcessingContext) -> String {
+    let quality = ctx.quality.round();
+
+    match ctx.output_format {
+        OutputFormat::Png => "not used for png".to_string(),
+
+        OutputFormat::Webp if opts.is_webp_lossless() => {
+            "not used for lossless webp".to_string()
+        }
+
+        OutputFormat::Webp if opts.webp_quality().is_some() => {
+            format!("{} (webp quality)", opts.webp_quality().unwrap())
+        }
+
+        _ if opts.is_adaptive_quality() => {
+            format!("{} (adaptive, range {})", quality, opts.quality_range())
+        }
+
+        _ => match opts.quality() {
+            Quality::Auto => format!("{} (estimated from the source)", quality),
+            Quality::Fixed(_) => format!("{}", quality),
+        },
+    }
+}
@@ -0,0 +1,143 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! コンソール無しで起動された場合(エクスプローラーの「送る」やドラッグ&ド
+//! ロップ等)の結果通知処理をまとめたモジュール
+//!
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::Result;
+
+///
+/// ログファイルの名前
+///
+const LOG_FILE_NAME: &str = "watermarker.log";
+
+///
+/// ログファイルのパスの決定
+///
+/// # 引数
+/// * `inputs` - 入力ファイルまたはディレクトリのリスト
+///
+/// # 戻り値
+/// 先頭の入力と同じ場所(入力がディレクトリの場合はその親ディレクトリ)に置く
+/// ログファイルのパスを返す。
+///
+fn log_path(inputs: &[PathBuf]) -> PathBuf {
+    let dir = inputs.first()
+        .and_then(|path| path.parent())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+
+    dir.join(LOG_FILE_NAME)
+}
+
+///
+/// ログファイルへの結果の追記
+///
+/// # 引数
+/// * `path` - ログファイルへのパス
+/// * `inputs` - 入力ファイルまたはディレクトリのリスト
+/// * `message` - 書き込む結果のメッセージ
+///
+/// # 戻り値
+/// 書き込みに成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を
+/// `Err()`でラップして返す。
+///
+fn write_log(path: &Path, inputs: &[PathBuf], message: &str) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+
+    let inputs = inputs.iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    writeln!(
+        file,
+        "{} {} (inputs: {})",
+        humantime::format_rfc3339_seconds(SystemTime::now()),
+        message,
+        inputs
+    )?;
+
+    Ok(())
+}
+
+///
+/// メッセージボックスの表示
+///
+/// # 引数
+/// * `text` - 表示するメッセージ
+/// * `is_error` - エラーとして表示する場合は`true`
+///
+#[cfg(windows)]
+fn show_message_box(text: &str, is_error: bool) {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        MessageBoxW, MB_ICONERROR, MB_ICONINFORMATION, MB_OK
+    };
+
+    let wide = |s: &str| s.encode_utf16().chain([0]).collect::<Vec<u16>>();
+    let text = wide(text);
+    let caption = wide(env!("CARGO_PKG_NAME"));
+    let icon = if is_error { MB_ICONERROR } else { MB_ICONINFORMATION };
+
+    // SAFETY: 渡す文字列はいずれもNUL終端したUTF-16で、呼び出しの間は有効
+    unsafe {
+        MessageBoxW(
+            std::ptr::null_mut(),
+            text.as_ptr(),
+            caption.as_ptr(),
+            MB_OK | icon
+        );
+    }
+}
+
+///
+/// メッセージボックスの表示(Windows以外)
+///
+/// # 注記
+/// ネイティブのメッセージボックスを持たないので標準エラー出力に出力する。
+///
+#[cfg(not(windows))]
+fn show_message_box(text: &str, _is_error: bool) {
+    eprintln!("{}", text);
+}
+
+///
+/// 実行結果の通知
+///
+/// # 引数
+/// * `inputs` - 入力ファイルまたはディレクトリのリスト
+/// * `result` - 実行結果
+///
+/// # 注記
+/// 入力の隣のログファイルに結果を追記した上で、メッセージボックスで完了また
+/// はエラーを通知する。ログファイルの書き込みに失敗した場合もメッセージボッ
+/// クスの表示は行う。
+///
+pub(crate) fn report(inputs: &[PathBuf], result: &Result<()>) {
+    let path = log_path(inputs);
+
+    let message = match result {
+        Ok(()) => "completed".to_string(),
+        Err(err) => format!("error: {}", err),
+    };
+
+    let text = match write_log(&path, inputs, &message) {
+        Ok(()) => format!("{}\n\nlog: {}", message, path.display()),
+        Err(err) => format!("{}\n\n(failed to write log: {})", message, err),
+    };
+
+    show_message_box(&text, result.is_err());
+}
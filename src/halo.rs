@@ -0,0 +1,226 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! ロゴの背後に描画する影・縁取り・背景の矩形をまとめたモジュール
+//!
+
+use image::imageops::overlay;
+use image::{GrayImage, Luma, Primitive, Rgba};
+use imageproc::filter::gaussian_blur_f32;
+use imageproc::morphology::{grayscale_dilate, Mask};
+
+use crate::pixel::{WorkImage, WorkPixel};
+
+///
+/// 影の不透明度
+///
+const SHADOW_OPACITY: f32 = 0.6;
+
+///
+/// ロゴの短辺に対する背景の矩形の余白の割合
+///
+const BACKDROP_PADDING_RATIO: f32 = 0.2;
+
+///
+/// 影と縁取りのために広げる幅の算出
+///
+/// # 引数
+/// * `outline` - 縁取りの幅(ピクセル数、0の場合は縁取りしない)
+/// * `shadow` - 影のぼかしの半径(ピクセル数、0の場合は影を付けない)
+///
+/// # 戻り値
+/// ロゴの上下左右それぞれに広げるピクセル数を返す。
+///
+/// # 注記
+/// 影はぼかしの広がり(半径の1.5倍)に加えて右下に半径の半分ずらすので、半
+/// 径の2倍を確保する。
+///
+pub(crate) fn padding(outline: u32, shadow: u32) -> u32 {
+    outline + shadow * 2
+}
+
+///
+/// 影と縁取りの描画
+///
+/// # 引数
+/// * `logo` - 対象のロゴ画像
+/// * `outline` - 縁取りの幅(ピクセル数、0の場合は縁取りしない)
+/// * `shadow` - 影のぼかしの半径(ピクセル数、0の場合は影を付けない)
+/// * `color` - 影と縁取りの色
+///
+/// # 戻り値
+/// 上下左右を`padding()`の分だけ広げ、ロゴの輪郭(アルファ値)を膨張させた縁
+/// 取りと、ぼかした影の上にロゴを重ねた画像を返す。
+///
+/// # 注記
+/// 縁取りと影を併用した場合は、縁取りを含めた輪郭から影を作る。輪郭の算出は
+/// 8ビットのアルファ値で行う(ロゴ自体の色深度は保たれる)。
+///
+pub(crate) fn add_halo<T>(
+    logo: &WorkImage<T>,
+    outline: u32,
+    shadow: u32,
+    color: Rgba<u8>,
+) -> WorkImage<T>
+where
+    T: WorkPixel,
+{
+    let pad = padding(outline, shadow);
+    let width = logo.width() + pad * 2;
+    let height = logo.height() + pad * 2;
+
+    /*
+     * ロゴのアルファ値の抽出
+     */
+    let max: f32 = T::Subpixel::DEFAULT_MAX_VALUE.into();
+    let alpha = T::CHANNEL_COUNT as usize - 1;
+    let mut silhouette = GrayImage::new(width, height);
+
+    for (x, y, pixel) in logo.enumerate_pixels() {
+        let opacity: f32 = pixel.channels()[alpha].into();
+        let value = (opacity / max * 255.0).round() as u8;
+
+        silhouette.put_pixel(x + pad, y + pad, Luma([value]));
+    }
+
+    /*
+     * 縁取り(輪郭の膨張)
+     */
+    if outline > 0 {
+        let mask = Mask::disk(outline.min(u8::MAX as u32) as u8);
+        silhouette = grayscale_dilate(&silhouette, &mask);
+    }
+
+    let mut halo = if outline > 0 {
+        silhouette.clone()
+    } else {
+        GrayImage::new(width, height)
+    };
+
+    /*
+     * 影(輪郭のぼかしを右下にずらして重ねる)
+     */
+    if shadow > 0 {
+        let blurred = gaussian_blur_f32(&silhouette, shadow as f32 / 2.0);
+        let shift = shadow / 2;
+
+        for y in shift..height {
+            for x in shift..width {
+                let value = blurred.get_pixel(x - shift, y - shift)[0] as f32
+                    * SHADOW_OPACITY;
+                let pixel = halo.get_pixel_mut(x, y);
+
+                pixel[0] = pixel[0].max(value.round() as u8);
+            }
+        }
+    }
+
+    /*
+     * 影と縁取りの上にロゴを重畳
+     */
+    let mut image = WorkImage::<T>::from_fn(width, height, |x, y| {
+        let [red, green, blue, _] = color.0;
+        T::from_rgba8(Rgba([red, green, blue, halo.get_pixel(x, y)[0]]))
+    });
+
+    overlay(&mut image, logo, pad as i64, pad as i64);
+
+    image
+}
+
+///
+/// 背景の矩形のために広げる幅の算出
+///
+/// # 引数
+/// * `(width, height)` - ロゴ(影と縁取りを除く)の幅と高さ
+///
+/// # 戻り値
+/// ロゴの上下左右それぞれに広げるピクセル数を返す(角の丸めの半径は指定が
+/// 無い場合にこの値を用いる)。
+///
+pub(crate) fn backdrop_padding((width, height): (u32, u32)) -> u32 {
+    ((width.min(height) as f32 * BACKDROP_PADDING_RATIO).round() as u32).max(1)
+}
+
+///
+/// 背景の矩形の描画
+///
+/// # 引数
+/// * `logo` - 対象のロゴ画像(影と縁取りを描画済みのもの)
+/// * `padding` - 上下左右に広げるピクセル数
+/// * `radius` - 角の丸めの半径(ピクセル数、0の場合は丸めない)
+/// * `color` - 背景の色(アルファ値を含む)
+///
+/// # 戻り値
+/// 上下左右を`padding`だけ広げた角丸の矩形の上にロゴを重ねた画像を返す。矩
+/// 形の縁は画素を覆う割合に応じて半透明にする。
+///
+/// # 注記
+/// 角の丸めの半径は矩形の短辺の半分を上限とする。
+///
+pub(crate) fn add_backdrop<T>(
+    logo: &WorkImage<T>,
+    padding: u32,
+    radius: u32,
+    color: Rgba<u8>,
+) -> WorkImage<T>
+where
+    T: WorkPixel,
+{
+    let width = logo.width() + padding * 2;
+    let height = logo.height() + padding * 2;
+    // 半径が0.5未満だと内側の画素まで半透明になるので、角を丸めない場合も
+    // 0.5とする
+    let radius = (radius.min(width.min(height) / 2) as f32).max(0.5);
+    let [red, green, blue, alpha] = color.0;
+
+    /*
+     * 角丸の矩形の描画(角の円弧からの距離で覆う割合を求める)
+     */
+    let mut image = WorkImage::<T>::from_fn(width, height, |x, y| {
+        let px = x as f32 + 0.5;
+        let py = y as f32 + 0.5;
+        let dx = (radius - px).max(px - (width as f32 - radius)).max(0.0);
+        let dy = (radius - py).max(py - (height as f32 - radius)).max(0.0);
+        let coverage = (radius + 0.5 - dx.hypot(dy)).clamp(0.0, 1.0);
+        let opacity = (alpha as f32 * coverage).round() as u8;
+
+        T::from_rgba8(Rgba([red, green, blue, opacity]))
+    });
+
+    overlay(&mut image, logo, padding as i64, padding as i64);
+
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn backdrop_rounds_corners() {
+        let logo = RgbaImage::new(20, 10);
+        let color = Rgba([0, 0, 0, 200]);
+        let image = add_backdrop(&logo, 4, 4, color);
+
+        assert_eq!(image.dimensions(), (28, 18));
+        assert_eq!(image.get_pixel(0, 0)[3], 0);
+        assert_eq!(image.get_pixel(14, 0)[3], 200);
+        assert_eq!(image.get_pixel(14, 9)[3], 200);
+    }
+
+    #[test]
+    fn backdrop_without_radius_is_square() {
+        let logo = RgbaImage::new(20, 10);
+        let image = add_backdrop(&logo, 4, 0, Rgba([0, 0, 0, 200]));
+
+        assert_eq!(image.get_pixel(0, 0)[3], 200);
+        assert_eq!(image.get_pixel(27, 17)[3], 200);
+        assert_eq!(image.get_pixel(14, 9)[3], 200);
+    }
+}
@@ -0,0 +1,638 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 露出を変えて撮影した複数のフレーム(ブラケット撮影)のHDR合成をまとめた
+//! モジュール
+//!
+
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use exif::{In, Tag, Value};
+use image::{Rgba, RgbaImage};
+use serde::Deserialize;
+
+use crate::metadata;
+use crate::pixel::{linear_to_srgb, srgb_to_linear, Rgba16Image};
+
+///
+/// EXIFでまとめる場合の、連続するフレームの撮影時刻の間隔の上限(秒)
+///
+const MAX_FRAME_GAP: i64 = 2;
+
+///
+/// 露出が異なるとみなす露出量の比の下限
+///
+const MIN_EXPOSURE_RATIO: f32 = 1.05;
+
+///
+/// 1つのブラケットにまとめるフレーム数の上限
+///
+const MAX_BRACKET_FRAMES: usize = 9;
+
+///
+/// ファイル名の露出の印として扱う露出補正値の絶対値の上限
+///
+const MAX_BRACKET_EV: f32 = 10.0;
+
+///
+/// トーンマッピングで平均的な明るさを割り当てる値(Reinhardのkey値)
+///
+const KEY_VALUE: f32 = 0.18;
+
+///
+/// ブラケットのまとめ方の定義
+///
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[clap(rename_all = "lower")]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum HdrGrouping {
+    /// 撮影時刻が連続し露出のみが異なるフレームをまとめる
+    Exif,
+
+    /// 露出の印("_-2"や"-ev1"など)を除いたファイル名が同じフレームをまと
+    /// める
+    Filename,
+}
+
+// Displayトレイトの実装
+impl Display for HdrGrouping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Self::Exif => "exif",
+            Self::Filename => "filename",
+        })
+    }
+}
+
+///
+/// フレームごとの撮影情報を格納する構造体
+///
+struct FrameInfo {
+    /// 撮影時刻(比較用の通算秒)
+    time: Option<i64>,
+
+    /// カメラの機種名
+    model: Option<String>,
+
+    /// 露出量(相対値)
+    exposure: Option<f32>,
+
+    /// ファイル名から求めたブラケットのキーと露出補正値
+    bracket: Option<(String, f32)>,
+}
+
+///
+/// 処理対象ファイルのブラケットへのまとめ
+///
+/// # 引数
+/// * `files` - 入力ファイルと出力ファイルへのパスのリスト
+/// * `grouping` - まとめ方
+///
+/// # 戻り値
+/// 入力ファイル、出力ファイル、合成する他のフレームのタプルのリストを返す。
+/// ブラケットの先頭のフレームを入力ファイルとし、その出力先を用いる。まと
+/// める相手の無いファイルは他のフレームを空にしてそのまま返す。
+///
+/// # 注記
+/// 入力ファイルのパスの順に並べ替え、隣り合うファイルのみをまとめる。1つの
+/// ブラケットは`MAX_BRACKET_FRAMES`枚までとし、超えた分は次のブラケットと
+/// する。
+///
+pub(crate) fn group(mut files: Vec<(PathBuf, PathBuf)>, grouping: HdrGrouping)
+    -> Vec<(PathBuf, PathBuf, Vec<PathBuf>)>
+{
+    files.sort();
+
+    let mut result: Vec<(PathBuf, PathBuf, Vec<PathBuf>)> = Vec::new();
+    let mut last: Option<FrameInfo> = None;
+
+    for (input_path, output_path) in files {
+        let info = frame_info(&input_path, grouping);
+
+        let joined = result.last().is_some_and(|(first, _, frames)| {
+            let prev = frames.last().unwrap_or(first);
+
+            if prev.parent() != input_path.parent()
+                || frames.len() + 1 >= MAX_BRACKET_FRAMES
+            {
+                return false;
+            }
+
+            match (grouping, &last) {
+                (HdrGrouping::Filename, Some(prev)) => {
+                    is_same_bracket(prev, &info)
+                }
+
+                (HdrGrouping::Exif, Some(prev)) => is_next_frame(prev, &info),
+
+                _ => false,
+            }
+        });
+
+        if joined && let Some((_, _, frames)) = result.last_mut() {
+            frames.push(input_path);
+        } else {
+            result.push((input_path, output_path, Vec::new()));
+        }
+
+        last = Some(info);
+    }
+
+    result
+}
+
+///
+/// ファイル名からのブラケットのキーの取得
+///
+/// # 戻り値
+/// ファイル名(拡張子を除く)が"_"または"-"に続く露出の印("-2"、"0"、
+/// "+1.5"、"ev-1"など)で終わる場合は、印より前の部分と露出補正値をタプル
+/// にして返す。露出の印で終わらない場合は`None`を返す。
+///
+/// # 注記
+/// 連番と区別するため、0以外で0から始まる数や絶対値が`MAX_BRACKET_EV`を超
+/// える数は露出の印とみなさない("IMG_0001"や"DSC-1234"はまとめない)。
+///
+fn bracket_key(path: &Path) -> Option<(String, f32)> {
+    let stem = path.file_stem()?.to_string_lossy();
+
+    /*
+     * 末尾の数値の切り出し
+     */
+    let start = stem
+        .rfind(|ch: char| !ch.is_ascii_digit() && ch != '.')
+        .map_or(0, |pos| pos + 1);
+    let number = &stem[start..];
+    let integer = number.split('.').next().unwrap_or_default();
+
+    if integer.is_empty()
+        || (integer.len() > 1 && integer.starts_with('0'))
+        || number.ends_with('.')
+    {
+        return None;
+    }
+
+    let ev: f32 = number.parse().ok()?;
+
+    if ev > MAX_BRACKET_EV {
+        return None;
+    }
+
+    /*
+     * 符号と"ev"の取り除き
+     */
+    let mut rest = &stem[..start];
+    let sign = rest.chars().last().filter(|ch| matches!(ch, '+' | '-'));
+
+    if sign.is_some() {
+        rest = &rest[..rest.len() - 1];
+    }
+
+    let marked = rest.len() >= 2
+        && rest.is_char_boundary(rest.len() - 2)
+        && rest[rest.len() - 2..].eq_ignore_ascii_case("ev");
+
+    if marked {
+        rest = &rest[..rest.len() - 2];
+    }
+
+    /*
+     * 区切りの確認
+     */
+    let (key, ev) = match rest.strip_suffix(['_', '-']) {
+        Some(key) if sign == Some('-') => (key, -ev),
+        Some(key) => (key, ev),
+
+        // "name-2"の"-"は符号ではなく区切りとみなす
+        None if sign == Some('-') && !marked => (rest, ev),
+
+        None => return None,
+    };
+
+    (!key.is_empty()).then(|| (key.to_string(), ev))
+}
+
+///
+/// ファイル名が同じブラケットを示すか否かの判定
+///
+/// # 注記
+/// 露出の印を除いたファイル名が同じで、露出が異なる場合に同じブラケットと
+/// みなす。露出はEXIF情報から求められる場合はその値で、求められない場合は
+/// ファイル名の露出補正値で比較する。
+///
+fn is_same_bracket(prev: &FrameInfo, info: &FrameInfo) -> bool {
+    let (Some((prev_key, prev_ev)), Some((key, ev))) =
+        (&prev.bracket, &info.bracket)
+    else {
+        return false;
+    };
+
+    if prev_key != key {
+        return false;
+    }
+
+    match (prev.exposure, info.exposure) {
+        (Some(prev_exposure), Some(exposure)) => {
+            prev_exposure.max(exposure) / prev_exposure.min(exposure)
+                >= MIN_EXPOSURE_RATIO
+        }
+
+        _ => prev_ev != ev,
+    }
+}
+
+///
+/// 直前のフレームに続くフレームか否かの判定
+///
+/// # 注記
+/// 同じ機種で撮影時刻の間隔が`MAX_FRAME_GAP`以内、かつ露出が異なる場合に
+/// 続くフレームとみなす。
+///
+fn is_next_frame(prev: &FrameInfo, info: &FrameInfo) -> bool {
+    let (Some(prev_time), Some(time)) = (prev.time, info.time) else {
+        return false;
+    };
+
+    let (Some(prev_exposure), Some(exposure)) = (prev.exposure, info.exposure)
+    else {
+        return false;
+    };
+
+    let ratio = prev_exposure.max(exposure) / prev_exposure.min(exposure);
+
+    prev.model == info.model
+        && (time - prev_time).abs() <= MAX_FRAME_GAP
+        && ratio >= MIN_EXPOSURE_RATIO
+}
+
+///
+/// フレームの撮影情報の読み出し
+///
+fn frame_info(path: &Path, grouping: HdrGrouping) -> FrameInfo {
+    let bracket = match grouping {
+        HdrGrouping::Exif => None,
+        HdrGrouping::Filename => bracket_key(path),
+    };
+
+    let Some(exif) = metadata::read_exif(path) else {
+        return FrameInfo {time: None, model: None, exposure: None, bracket};
+    };
+
+    let time = exif.get_field(Tag::DateTimeOriginal, In::PRIMARY)
+        .and_then(|field| match &field.value {
+            Value::Ascii(ascii) => ascii.first().cloned(),
+            _ => None,
+        })
+        .and_then(|ascii| exif::DateTime::from_ascii(&ascii).ok())
+        .map(|dt| {
+            // 間隔の比較にのみ用いるので月の日数の違いは考慮しない
+            let days = (dt.year as i64 * 12 + dt.month as i64) * 31
+                + dt.day as i64;
+            ((days * 24 + dt.hour as i64) * 60 + dt.minute as i64) * 60
+                + dt.second as i64
+        });
+
+    FrameInfo {
+        time,
+        model: metadata::field_value(&exif, Tag::Model),
+        exposure: exposure_of(&exif),
+        bracket,
+    }
+}
+
+///
+/// EXIF情報からの露出量の算出
+///
+/// # 戻り値
+/// 露出時間×ISO感度÷F値の2乗を返す(ISO感度とF値が無い場合は露出時間の
+/// み)。露出時間が無い場合は露出補正値から求める。いずれも無い場合は
+/// `None`を返す。
+///
+fn exposure_of(exif: &exif::Exif) -> Option<f32> {
+    let number = |tag| {
+        exif.get_field(tag, In::PRIMARY).and_then(|field| {
+            match &field.value {
+                Value::Rational(v) => v.first().map(|v| v.to_f64() as f32),
+                Value::SRational(v) => v.first().map(|v| v.to_f64() as f32),
+                value => value.get_uint(0).map(|v| v as f32),
+            }
+        })
+    };
+
+    if let Some(time) = number(Tag::ExposureTime).filter(|v| *v > 0.0) {
+        let iso = number(Tag::PhotographicSensitivity)
+            .filter(|v| *v > 0.0)
+            .unwrap_or(1.0);
+        let aperture = number(Tag::FNumber)
+            .filter(|v| *v > 0.0)
+            .unwrap_or(1.0);
+
+        return Some(time * iso / (aperture * aperture));
+    }
+
+    number(Tag::ExposureBiasValue).map(|bias| 2.0f32.powf(bias))
+}
+
+///
+/// フレームの露出量の読み出し
+///
+/// # 引数
+/// * `path` - フレームのファイルへのパス
+///
+/// # 戻り値
+/// EXIF情報から露出量を求められた場合は`Some()`でラップして返す。
+///
+pub(crate) fn read_exposure(path: &Path) -> Option<f32> {
+    metadata::read_exif(path).and_then(|exif| exposure_of(&exif))
+}
+
+///
+/// ブラケットのHDR合成
+///
+/// # 引数
+/// * `frames` - 合成するフレームの画像データ
+/// * `exposures` - フレームごとの露出量(EXIF情報から求めたもの)
+///
+/// # 戻り値
+/// 合成してトーンマッピングした画像を`Ok()`でラップして返す。フレームの大
+/// きさが揃っていない場合はエラー情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// 各フレームをリニアな値に戻して露出量で割り、白飛びや黒つぶれに近い画素
+/// ほど軽く重み付けして平均したものを輝度とする。露出量が1つでも不明な場合
+/// は、全フレームの露出量を画素値の比から推定する。トーンマッピングには
+/// Reinhardのグローバルオペレータを用いる。
+///
+pub(crate) fn merge(frames: &[RgbaImage], exposures: &[Option<f32>])
+    -> Result<Rgba16Image>
+{
+    let Some(first) = frames.first() else {
+        return Err(anyhow!("no frames to merge"));
+    };
+
+    let (width, height) = first.dimensions();
+
+    if frames.iter().any(|frame| frame.dimensions() != (width, height)) {
+        return Err(anyhow!("bracketed frames differ in size"));
+    }
+
+    let table = (0..256)
+        .map(|v| srgb_to_linear(v as f32 / 255.0))
+        .collect::<Vec<f32>>();
+
+    /*
+     * 露出量の決定
+     */
+    let exposures = if exposures.iter().all(Option::is_some) {
+        exposures.iter().map(|e| e.unwrap()).collect::<Vec<f32>>()
+    } else {
+        frames.iter().map(|frame| estimate_ratio(first, frame, &table))
+            .collect()
+    };
+
+    /*
+     * 輝度(シーンの明るさに比例する値)の算出
+     */
+    let count = (width * height) as usize;
+    let mut radiance = vec![[0.0f32; 3]; count];
+
+    for (i, value) in radiance.iter_mut().enumerate() {
+        let mut sum = [0.0f32; 3];
+        let mut total = 0.0;
+        let mut level = 0.0;
+
+        for (frame, exposure) in frames.iter().zip(&exposures) {
+            let pixel = &frame.as_raw()[i * 4..i * 4 + 3];
+            let peak = *pixel.iter().max().unwrap() as f32 / 255.0;
+            let weight = 1.0 - (2.0 * peak - 1.0).powi(2);
+
+            for c in 0..3 {
+                sum[c] += weight * table[pixel[c] as usize] / exposure;
+            }
+
+            total += weight;
+            level += peak;
+        }
+
+        if total > 1e-6 {
+            for c in 0..3 {
+                value[c] = sum[c] / total;
+            }
+            continue;
+        }
+
+        // 全フレームで白飛び(黒つぶれ)している場合は最も暗い(明るい)フ
+        // レームの値を用いる
+        let pick = if level / frames.len() as f32 >= 0.5 {
+            min_index(&exposures)
+        } else {
+            max_index(&exposures)
+        };
+
+        let pixel = &frames[pick].as_raw()[i * 4..i * 4 + 3];
+
+        for c in 0..3 {
+            value[c] = table[pixel[c] as usize] / exposures[pick];
+        }
+    }
+
+    /*
+     * トーンマッピング
+     */
+    let luminance = |rgb: &[f32; 3]| {
+        0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2]
+    };
+
+    let log_average = (radiance.iter()
+        .map(|rgb| (1e-6 + luminance(rgb)).ln())
+        .sum::<f32>() / count as f32)
+        .exp();
+
+    let scale = KEY_VALUE / log_average;
+    let white = radiance.iter()
+        .map(|rgb| luminance(rgb) * scale)
+        .fold(0.0f32, f32::max)
+        .max(1e-6);
+
+    let mut result = Rgba16Image::new(width, height);
+
+    for (pixel, rgb) in result.pixels_mut().zip(&radiance) {
+        let lum = luminance(rgb) * scale;
+        let mapped = lum * (1.0 + lum / (white * white)) / (1.0 + lum);
+        let ratio = if lum > 0.0 { mapped / lum * scale } else { 0.0 };
+        let mut out = [u16::MAX; 4];
+
+        for c in 0..3 {
+            let value = linear_to_srgb((rgb[c] * ratio).clamp(0.0, 1.0));
+            out[c] = (value * 65535.0).round() as u16;
+        }
+
+        *pixel = Rgba(out);
+    }
+
+    Ok(result)
+}
+
+///
+/// 基準のフレームに対する露出量の比の推定
+///
+/// # 注記
+/// 両方のフレームで白飛びも黒つぶれもしていない画素のリニアな値の合計の比
+/// を用いる。該当する画素が無い場合は1.0を返す。
+///
+fn estimate_ratio(base: &RgbaImage, frame: &RgbaImage, table: &[f32]) -> f32 {
+    let mut base_sum = 0.0f64;
+    let mut frame_sum = 0.0f64;
+
+    for (a, b) in base.pixels().zip(frame.pixels()) {
+        let usable = |pixel: &Rgba<u8>| {
+            pixel.0[..3].iter().all(|v| (13..=242).contains(v))
+        };
+
+        if usable(a) && usable(b) {
+            for c in 0..3 {
+                base_sum += table[a[c] as usize] as f64;
+                frame_sum += table[b[c] as usize] as f64;
+            }
+        }
+    }
+
+    if base_sum > 0.0 && frame_sum > 0.0 {
+        (frame_sum / base_sum) as f32
+    } else {
+        1.0
+    }
+}
+
+///
+/// 最小値の位置の取得
+///
+fn min_index(values: &[f32]) -> usize {
+    (0..values.len())
+        .min_by(|&a, &b| values[a].total_cmp(&values[b]))
+        .unwrap_or(0)
+}
+
+///
+/// 最大値の位置の取得
+///
+fn max_index(values: &[f32]) -> usize {
+    (0..values.len())
+        .max_by(|&a, &b| values[a].total_cmp(&values[b]))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(names: &[&str]) -> Vec<(PathBuf, PathBuf)> {
+        names.iter()
+            .map(|name| {
+                (
+                    Path::new("no-such-dir").join(name),
+                    Path::new("out").join(name),
+                )
+            })
+            .collect()
+    }
+
+    fn sizes(groups: &[(PathBuf, PathBuf, Vec<PathBuf>)]) -> Vec<usize> {
+        groups.iter().map(|(_, _, frames)| frames.len() + 1).collect()
+    }
+
+    #[test]
+    fn bracket_key_accepts_exposure_tokens() {
+        let key = |name: &str| bracket_key(Path::new(name));
+
+        assert_eq!(key("sunset_-2.jpg"), Some(("sunset".to_string(), -2.0)));
+        assert_eq!(key("sunset_0.jpg"), Some(("sunset".to_string(), 0.0)));
+        assert_eq!(key("sunset_+2.jpg"), Some(("sunset".to_string(), 2.0)));
+        assert_eq!(key("sunset-1.5.jpg"), Some(("sunset".to_string(), 1.5)));
+        assert_eq!(key("sunset_ev-1.jpg"), Some(("sunset".to_string(), -1.0)));
+        assert_eq!(key("sunset-EV2.jpg"), Some(("sunset".to_string(), 2.0)));
+    }
+
+    #[test]
+    fn bracket_key_rejects_sequence_numbers() {
+        let key = |name: &str| bracket_key(Path::new(name));
+
+        assert_eq!(key("IMG_0001.jpg"), None);
+        assert_eq!(key("DSC-1234.jpg"), None);
+        assert_eq!(key("sunset.jpg"), None);
+        assert_eq!(key("sunset_ev.jpg"), None);
+        assert_eq!(key("_2.jpg"), None);
+    }
+
+    #[test]
+    fn group_by_filename_merges_brackets() {
+        let groups = group(
+            files(&["sunset_0.jpg", "sunset_+2.jpg", "sunset_-2.jpg"]),
+            HdrGrouping::Filename,
+        );
+
+        assert_eq!(sizes(&groups), vec![3]);
+        assert_eq!(groups[0].1, Path::new("out/sunset_+2.jpg"));
+    }
+
+    #[test]
+    fn group_by_filename_keeps_sequence_numbers_apart() {
+        let groups = group(
+            files(&["IMG_0001.jpg", "IMG_0002.jpg", "DSC-1234.jpg"]),
+            HdrGrouping::Filename,
+        );
+
+        assert_eq!(sizes(&groups), vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn group_by_filename_requires_different_exposures() {
+        let groups = group(
+            files(&["night_1.jpg", "night-1.jpg", "day_1.jpg"]),
+            HdrGrouping::Filename,
+        );
+
+        assert_eq!(sizes(&groups), vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn group_by_filename_keeps_directories_apart() {
+        let groups = group(
+            vec![
+                (PathBuf::from("a/sunset_-2.jpg"), PathBuf::from("1.jpg")),
+                (PathBuf::from("b/sunset_0.jpg"), PathBuf::from("2.jpg")),
+            ],
+            HdrGrouping::Filename,
+        );
+
+        assert_eq!(sizes(&groups), vec![1, 1]);
+    }
+
+    #[test]
+    fn group_caps_bracket_size() {
+        let names = (-5..=5)
+            .map(|ev| format!("pano_{:+}.jpg", ev))
+            .collect::<Vec<_>>();
+        let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+        let groups = group(files(&names), HdrGrouping::Filename);
+
+        assert_eq!(sizes(&groups), vec![MAX_BRACKET_FRAMES, 2]);
+    }
+
+    #[test]
+    fn group_by_exif_needs_exif() {
+        let groups = group(
+            files(&["sunset_-2.jpg", "sunset_0.jpg"]),
+            HdrGrouping::Exif,
+        );
+
+        assert_eq!(sizes(&groups), vec![1, 1]);
+    }
+}
@@ -0,0 +1,112 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 透かしのレイヤーのキャッシュをまとめたモジュール
+//!
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+
+use crate::pixel::{WorkImage, WorkPixel};
+use crate::placement::LogoChoice;
+
+///
+/// 保持するレイヤーの最大数
+///
+/// # 注記
+/// 出力サイズがファイルごとに異なる場合にメモリを使い続けないよう、これを
+/// 超えた時点でキャッシュを空にする。
+///
+const MAX_LAYERS: usize = 8;
+
+///
+/// キャッシュのキー(ピクセル形式と、幅と高さ、背景に基づく選択結果)
+///
+type Key = (TypeId, u32, u32, Vec<LogoChoice>);
+
+///
+/// 透かしのレイヤーのキャッシュを管理する構造体
+///
+/// # 注記
+/// 透かしのレイヤーは重畳先の大きさとピクセル形式、背景に基づくロゴの選択
+/// 結果(明るい背景用のロゴと自動配置)のみで決まるので、同じ大きさの出力が
+/// 続くバッチではリサイズ・シャープ化・敷き詰めを済ませたレイヤーを使い回
+/// す。透かしの内容が変わる場合(テンプレート変数を含む文字列など)は別の
+/// キャッシュを用いること。
+///
+#[derive(Default)]
+pub(crate) struct LayerCache {
+    /// 生成済みのレイヤー
+    layers: Mutex<HashMap<Key, Arc<dyn Any + Send + Sync>>>,
+}
+
+// Debugトレイトの実装
+impl Debug for LayerCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let count = self.layers.lock().map(|layers| layers.len()).unwrap_or(0);
+
+        f.debug_struct("LayerCache").field("layers", &count).finish()
+    }
+}
+
+/// LayerCacheに対する実装
+impl LayerCache {
+    ///
+    /// レイヤーの取得
+    ///
+    /// # 引数
+    /// * `size` - 重畳先の画像の幅と高さ
+    /// * `choices` - 透かしごとの背景に基づく選択結果のリスト
+    /// * `render` - キャッシュに無い場合にレイヤーを生成するクロージャ
+    ///
+    /// # 戻り値
+    /// キャッシュ済みのレイヤー、または生成してキャッシュに加えたレイヤーを
+    /// `Ok()`でラップして返す。生成に失敗した場合はエラー情報を`Err()`でラ
+    /// ップして返す。
+    ///
+    /// # 注記
+    /// 生成中はロックを保持しないので、複数のスレッドから同時に呼び出された
+    /// 場合は同じレイヤーを重複して生成することがある(結果は同じになる)。
+    ///
+    pub(crate) fn get_or_render<T, F>(
+        &self,
+        size: (u32, u32),
+        choices: &[LogoChoice],
+        render: F,
+    ) -> Result<Arc<WorkImage<T>>>
+    where
+        T: WorkPixel,
+        F: FnOnce() -> Result<WorkImage<T>>,
+    {
+        let key = (TypeId::of::<T>(), size.0, size.1, choices.to_vec());
+
+        let cached = self.layers.lock()
+            .ok()
+            .and_then(|layers| layers.get(&key).cloned())
+            .and_then(|layer| layer.downcast::<WorkImage<T>>().ok());
+
+        if let Some(layer) = cached {
+            return Ok(layer);
+        }
+
+        let layer = Arc::new(render()?);
+
+        if let Ok(mut layers) = self.layers.lock() {
+            if layers.len() >= MAX_LAYERS {
+                layers.clear();
+            }
+
+            layers.insert(key, layer.clone());
+        }
+
+        Ok(layer)
+    }
+}
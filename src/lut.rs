@@ -0,0 +1,346 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 3D LUTとトーンカーブによる色調の変換をまとめたモジュール
+//!
+
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use image::Primitive;
+use imageproc::definitions::Clamp;
+
+use crate::analysis::luma;
+use crate::pixel::{WorkImage, WorkPixel};
+
+///
+/// .cube形式の3D LUTを管理する構造体
+///
+#[derive(Debug, Clone)]
+pub(crate) struct CubeLut {
+    /// 格子の一辺の点数
+    size: usize,
+
+    /// 入力値の下限(RGB)
+    domain_min: [f32; 3],
+
+    /// 入力値の上限(RGB)
+    domain_max: [f32; 3],
+
+    /// 格子点の出力値(Rが最も速く変化する順)
+    table: Vec<[f32; 3]>,
+}
+
+/// CubeLutに対する実装
+impl CubeLut {
+    ///
+    /// .cubeファイルの読み込み
+    ///
+    /// # 引数
+    /// * `path` - 読み込むファイルへのパス
+    ///
+    /// # 戻り値
+    /// 読み込みに成功した場合はLUTを`Ok()`でラップして返す。失敗した場合は
+    /// エラー情報を`Err()`でラップして返す。
+    ///
+    /// # 注記
+    /// 3D LUTのみに対応する(LUT_1D_SIZEを持つファイルはエラーとする)。
+    ///
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("cannot read {}", path.display()))?;
+
+        let mut size = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut table = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let error = |message: &str| {
+                anyhow!(
+                    "{} (line {} of {})",
+                    message,
+                    index + 1,
+                    path.display()
+                )
+            };
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut items = line.split_whitespace();
+            let keyword = items.next().unwrap_or_default();
+
+            match keyword {
+                "TITLE" => {}
+
+                "LUT_1D_SIZE" => {
+                    return Err(error("1D LUTs are not supported"));
+                }
+
+                "LUT_3D_SIZE" => {
+                    size = Some(items.next()
+                        .and_then(|value| value.parse::<usize>().ok())
+                        .filter(|value| (2..=256).contains(value))
+                        .ok_or_else(|| error("invalid LUT_3D_SIZE"))?);
+                }
+
+                "DOMAIN_MIN" | "DOMAIN_MAX" => {
+                    let values = parse_triplet(items)
+                        .ok_or_else(|| error("invalid domain"))?;
+
+                    if keyword == "DOMAIN_MIN" {
+                        domain_min = values;
+                    } else {
+                        domain_max = values;
+                    }
+                }
+
+                _ => {
+                    let values = parse_triplet(line.split_whitespace())
+                        .ok_or_else(|| error("invalid table entry"))?;
+
+                    table.push(values);
+                }
+            }
+        }
+
+        let size = size.ok_or_else(|| {
+            anyhow!("LUT_3D_SIZE is missing in {}", path.display())
+        })?;
+
+        if table.len() != size * size * size {
+            return Err(anyhow!(
+                "{} has {} entries, but LUT_3D_SIZE {} needs {}",
+                path.display(),
+                table.len(),
+                size,
+                size * size * size
+            ));
+        }
+
+        if (0..3).any(|i| domain_max[i] <= domain_min[i]) {
+            return Err(anyhow!("invalid domain in {}", path.display()));
+        }
+
+        Ok(Self {size, domain_min, domain_max, table})
+    }
+
+    ///
+    /// 格子点の出力値の取得
+    ///
+    fn entry(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        self.table[(b * self.size + g) * self.size + r]
+    }
+
+    ///
+    /// 色の変換
+    ///
+    /// # 引数
+    /// * `rgb` - 変換する色(各チャネル0.0〜1.0)
+    ///
+    /// # 戻り値
+    /// 格子点の間を三線形補間した変換後の色を返す。
+    ///
+    fn apply(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let last = (self.size - 1) as f32;
+        let mut base = [0; 3];
+        let mut frac = [0.0; 3];
+
+        for i in 0..3 {
+            let range = self.domain_max[i] - self.domain_min[i];
+            let pos = ((rgb[i] - self.domain_min[i]) / range * last)
+                .clamp(0.0, last);
+
+            base[i] = (pos.floor() as usize).min(self.size - 2);
+            frac[i] = pos - base[i] as f32;
+        }
+
+        let mut result = [0.0; 3];
+
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let weight = (0..3)
+                .map(|i| if offset[i] == 1 { frac[i] } else { 1.0 - frac[i] })
+                .product::<f32>();
+
+            let value = self.entry(
+                base[0] + offset[0],
+                base[1] + offset[1],
+                base[2] + offset[2]
+            );
+
+            for i in 0..3 {
+                result[i] += value[i] * weight;
+            }
+        }
+
+        result
+    }
+}
+
+///
+/// 3つの数値の組の解析
+///
+fn parse_triplet<'a, I>(items: I) -> Option<[f32; 3]>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let values = items
+        .map(|item| item.parse::<f32>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    values.try_into().ok()
+}
+
+///
+/// トーンカーブを管理する構造体
+///
+/// # 注記
+/// 入出力を0〜255で表した制御点を持ち、制御点の間は直線で補間する。RGBの各
+/// チャネルに同じカーブを適用する。
+///
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ToneCurve {
+    /// 制御点のリスト(入力値の昇順)
+    points: Vec<(f32, f32)>,
+}
+
+// FromStrトレイトの実装
+impl FromStr for ToneCurve {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |value: &str| {
+            value.trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|value| (0.0..=255.0).contains(value))
+        };
+
+        let mut points = Vec::new();
+
+        for item in s.split(',') {
+            let point = item.split_once(':')
+                .and_then(|(x, y)| Some((parse(x)?, parse(y)?)))
+                .ok_or(format!("トーンカーブの制御点の指定が無効: {}", item))?;
+
+            points.push(point);
+        }
+
+        if points.len() < 2 {
+            return Err(format!("トーンカーブには制御点が2つ以上必要: {}", s));
+        }
+
+        if points.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err(format!(
+                "トーンカーブの制御点は入力値の昇順で指定すること: {}", s
+            ));
+        }
+
+        Ok(Self {points})
+    }
+}
+
+// Displayトレイトの実装
+impl Display for ToneCurve {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let points = self.points.iter()
+            .map(|(x, y)| format!("{}:{}", x, y))
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", points.join(","))
+    }
+}
+
+/// ToneCurveに対する実装
+impl ToneCurve {
+    ///
+    /// 値の変換
+    ///
+    /// # 引数
+    /// * `value` - 変換する値(0.0〜1.0)
+    ///
+    /// # 戻り値
+    /// 変換後の値(0.0〜1.0)を返す。両端の制御点の外側は端の値になる。
+    ///
+    fn apply(&self, value: f32) -> f32 {
+        let x = value * 255.0;
+        let first = self.points[0];
+        let last = self.points[self.points.len() - 1];
+
+        let y = if x <= first.0 {
+            first.1
+        } else if x >= last.0 {
+            last.1
+        } else {
+            let pair = self.points.windows(2)
+                .find(|pair| x <= pair[1].0)
+                .unwrap();
+            let t = (x - pair[0].0) / (pair[1].0 - pair[0].0);
+
+            pair[0].1 + (pair[1].1 - pair[0].1) * t
+        };
+
+        y / 255.0
+    }
+}
+
+///
+/// 3D LUTとトーンカーブの適用
+///
+/// # 引数
+/// * `image` - 対象の画像データ
+/// * `lut` - 3D LUT
+/// * `curve` - トーンカーブ
+///
+/// # 注記
+/// 両方が指定されている場合は3D LUTを先に適用する。グレースケールの画像に
+/// 3D LUTを適用した場合は変換後の色の輝度を用いる。アルファチャネル(最後の
+/// チャネル)は変更しない。
+///
+pub(crate) fn apply_look<T>(
+    image: &mut WorkImage<T>,
+    lut: Option<&CubeLut>,
+    curve: Option<&ToneCurve>,
+)
+where
+    T: WorkPixel,
+{
+    let max: f32 = T::Subpixel::DEFAULT_MAX_VALUE.into();
+    let colors = T::CHANNEL_COUNT as usize - 1;
+
+    for pixel in image.pixels_mut() {
+        let channels = pixel.channels_mut();
+        let mut rgb = [0.0; 3];
+
+        for (i, value) in rgb.iter_mut().enumerate() {
+            *value = channels[i.min(colors - 1)].into() / max;
+        }
+
+        if let Some(lut) = lut {
+            rgb = lut.apply(rgb);
+        }
+
+        if let Some(curve) = curve {
+            rgb = rgb.map(|value| curve.apply(value));
+        }
+
+        if colors == 1 {
+            rgb[0] = luma(&rgb);
+        }
+
+        for i in 0..colors {
+            channels[i] = Clamp::clamp((rgb[i] * max).round());
+        }
+    }
+}
@@ -8,26 +8,68 @@
 //! プログラムのエントリポイント
 //!
 
+mod analysis;
+mod clipboard;
+mod codec;
 mod cmd_args;
+mod context;
+mod datetime;
+mod debug_dump;
+mod decoder;
+mod encoder;
+mod estimate;
+mod explain;
+mod gui;
+mod halo;
+mod hdr;
+mod layer_cache;
+mod lut;
+mod manifest;
+mod metadata;
+mod palette;
+mod pixel;
+mod placement;
+mod post_cmd;
+mod pre_filter;
+mod registry;
+mod scan;
+mod sharpen;
+mod storage;
+mod subpixel;
+mod template;
+mod text;
+mod text_table;
+mod tile_output;
+mod tiling;
+mod web_bundle;
 
-use std::fs::File;
-use std::io::{BufWriter, BufReader};
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::{anyhow, Result};
+use anyhow::{Context, Result};
 use fast_image_resize::{
-    FilterType, PixelType, Resizer, ResizeOptions, ResizeAlg
+    FilterType, Resizer, ResizeOptions, ResizeAlg
 };
 use fast_image_resize::images::Image;
-use image::{ImageBuffer, RgbaImage};
-use image::imageops::{
-    flip_horizontal, flip_vertical, rotate180, rotate270, rotate90, overlay
-};
-use mozjpeg::{ColorSpace, Compress, Decompress};
-use walkdir::{DirEntry, WalkDir};
+use image::{DynamicImage, GrayImage, LumaA, Rgba, RgbaImage};
+use image::imageops::{crop_imm, overlay};
 
-use cmd_args::Options;
+use cmd_args::{
+    ColorDepth, Command, ConfigCommand, Options, OutputFormat, Position,
+    Quality, Watermark,
+};
+use context::ProcessingContext;
+use debug_dump::DebugDump;
+use manifest::RunManifest;
+use metadata::MetadataExporter;
+use pixel::{OutputImage, Rgba16Image, SourceImage, WorkImage, WorkPixel};
+use placement::LogoChoice;
+use post_cmd::PostCommand;
+use pre_filter::PreFilter;
+use storage::{LocalStorage, Storage};
+use tile_output::TileRect;
 
 ///
 /// プログラムのエントリポイント
@@ -44,15 +86,52 @@ fn main() {
         },
     };
 
+    /*
+     * サブコマンドの実行
+     */
+    if let Some(command) = opts.command() {
+        let result = match command {
+            Command::Scan {inputs} => scan::run(&inputs),
+            Command::Config {action: ConfigCommand::Diff {old, new}} => {
+                cmd_args::diff_configs(&old, &new)
+            }
+        };
+
+        if let Err(err) = result {
+            eprintln!("error: {}", err);
+            std::process::exit(1);
+        }
+
+        std::process::exit(0);
+    }
+
     if opts.is_show_options() {
         opts.show_options();
         std::process::exit(0);
     }
 
+    /*
+     * 判断内容の表示(ファイルの書き込みは行わない)
+     */
+    if opts.is_explain() {
+        if let Err(err) = explain::run(&opts) {
+            eprintln!("error: {}", err);
+            std::process::exit(1);
+        }
+
+        std::process::exit(0);
+    }
+
     /*
      * 実行関数の呼び出し
      */
-    if let Err(err) = run(opts) {
+    let result = run(opts.clone());
+
+    if opts.is_gui_errors() {
+        gui::report(&opts.inputs(), &result);
+    }
+
+    if let Err(err) = result {
         eprintln!("error: {}", err);
         std::process::exit(1);
     }
@@ -69,258 +148,2053 @@ fn main() {
 /// `Err()`でラップして返す。
 ///
 fn run(opts: Arc<Options>) -> Result<()> {
+    let storage = LocalStorage;
+
+    /*
+     * クリップボードを介する場合は単一の画像のみを処理する
+     */
+    if opts.is_from_clipboard() || opts.is_to_clipboard() {
+        return run_clipboard(&opts, &storage);
+    }
+
+    /*
+     * 処理対象ファイルの列挙
+     */
+    let mut files = Vec::new();
+    let mut sidecars = Vec::new();
+
     for path in opts.inputs().iter() {
         if path.is_file() {
-            /*
-             * ファイルの場合はそのまま処理
-             */
-            proc_file(&opts, &path)?;
+            // ファイルの場合はそのまま処理対象とする
+            files.push((path.clone(), output_path_for(&opts, None, path)));
 
         } else if path.is_dir() {
-            /*
-             * ディレクトリの場合は、再帰的にJPEGファイルを探査しそれぞれで
-             * 処理する
-             */
-            for entry in jpeg_files(path) {
-                proc_file(&opts, entry.path())?;
+            // ディレクトリの場合は、再帰的に画像ファイルを探査しそれぞれを
+            // 処理対象とする
+            files.extend(image_files(&storage, path)?.into_iter().filter(|entry| {
+                // 入力ファイルの隣に出力する場合は、以前の実行で出力したファ
+                // イルを入力として拾わないようにする
+                !is_beside_output(&opts, entry)
+            }).map(|entry| {
+                let output_path = output_path_for(&opts, Some(path), &entry);
+                (entry, output_path)
+            }));
+
+            // ディレクトリ構造を維持する場合は付随ファイルもコピー対象とす
+            // る
+            if opts.is_mirror_tree() && opts.is_copy_sidecars() {
+                sidecars.extend(sidecar_files(&storage, path)?.into_iter().map(
+                    |entry| {
+                        let output_path = output_path_for(
+                            &opts,
+                            Some(path),
+                            &entry
+                        );
+
+                        (entry, output_path)
+                    }
+                ));
             }
         }
     }
 
-    Ok(())
-}
+    /*
+     * HDR合成するブラケットのまとめ(指定が無い場合は1ファイルずつ処理する)
+     */
+    let files = match opts.hdr_merge() {
+        Some(grouping) => hdr::group(files, grouping),
+        None => files.into_iter()
+            .map(|(input_path, output_path)| {
+                (input_path, output_path, Vec::new())
+            })
+            .collect(),
+    };
 
-///
-/// JPEGファイルのリストアップ
-///
-/// # 引数
-/// * `path` - 探査の起点となるフォルダへのパス
-///
-/// # 戻り値
-/// JPEGファイルをリストアップしたイテレーター
-///
-/// # 注記
-/// 引数で指定されたフォルダを起点に再帰的に降下探査しJPEGファイルをリストアッ
-/// プするイテレータを返す。
-///
-fn jpeg_files<P>(path: P) -> impl Iterator<Item = DirEntry>
-where 
-    P: AsRef<Path>,
-{
-    WalkDir::new(path)
-        .into_iter()
-        .filter_map(Result::ok)
-        .filter(|e| e.file_type().is_file())
-        .filter(|e| {
-            e.path()
-                .extension()
-                .and_then(|s| s.to_str())
-                .map(|ext| {
-                    match ext.to_lowercase().as_str() {
-                        "jpg" | "jpeg" => true,
-                        _ => false
-                    }
-                })
-                .unwrap_or(false)
-        })
+    /*
+     * 処理規模の見積もり
+     */
+    let inputs = files.iter()
+        .map(|(input_path, _, _)| input_path.clone())
+        .collect::<Vec<_>>();
+
+    // テンプレートの{seq}などで参照する番号は処理する順に割り当てる
+    let opts = opts.with_sequences(&inputs);
+
+    if opts.is_estimate() {
+        let targets = files.iter()
+            .map(|(input_path, _, brackets)| {
+                (input_path.clone(), brackets.clone())
+            })
+            .collect::<Vec<_>>();
+
+        if !estimate::confirm(&opts, &targets)? {
+            eprintln!("aborted");
+            return Ok(());
+        }
+    }
+
+    /*
+     * メタデータのエクスポート先の準備
+     */
+    let mut exporter = match opts.export_metadata() {
+        Some(path) => Some(MetadataExporter::create(
+            path,
+            opts.is_palette(),
+            opts.alt_text().is_some() || opts.alt_text_csv().is_some()
+        )?),
+        None => None,
+    };
+
+    /*
+     * 実行記録の準備
+     */
+    let mut manifest = match opts.run_log() {
+        Some(path) => Some(RunManifest::new(path, &opts, &inputs)?),
+        None => None,
+    };
+
+    /*
+     * 前処理コマンドと後処理コマンドの準備
+     */
+    let pre_filter = opts.pre_filter().map(PreFilter::new);
+
+    let mut post_cmd = opts.post_cmd().map(|template| {
+        PostCommand::new(template, opts.post_jobs(), opts.post_failure())
+    });
+
+    /*
+     * 各ファイルの処理
+     */
+    let result = files.iter().try_for_each(|(input_path, output, brackets)| {
+        // 前処理コマンドの指定がある場合は、その結果に従って処理するか否か
+        // とファイルごとの設定を決める
+        let opts = match &pre_filter {
+            Some(filter) => filter.check(input_path, output)
+                .map(|verdict| verdict.map(|overrides| {
+                    opts.with_overrides(&overrides)
+                })),
+            None => Ok(Some(opts.clone())),
+        };
+
+        let result = match opts {
+            Ok(Some(opts)) => proc_file(
+                &opts,
+                &storage,
+                input_path,
+                brackets,
+                output
+            ),
+
+            Ok(None) => {
+                eprintln!("{} skip (filtered out)", input_path.display());
+                Ok(None)
+            }
+
+            Err(err) => Err(err),
+        };
+
+        // 失敗したファイルも記録に残すため、エラーの伝播は記録の後に行う
+        if let Some(manifest) = &mut manifest {
+            manifest.record(input_path, &result);
+        }
+
+        let Some(processed) = result? else {
+            return Ok(());
+        };
+
+        // タイルに分割した場合はタイルごとに1行ずつ書き出す
+        if let Some(exporter) = &mut exporter {
+            for (output_path, size) in processed.outputs() {
+                exporter.write(
+                    input_path,
+                    output_path,
+                    size,
+                    &processed.palette,
+                    processed.alt_text.as_deref()
+                )?;
+            }
+        }
+
+        if let Some(post_cmd) = &mut post_cmd {
+            for (output_path, _) in processed.outputs() {
+                post_cmd.spawn(input_path, output_path)?;
+            }
+        }
+
+        Ok::<(), anyhow::Error>(())
+    });
+
+    // 処理を中断した場合も実行中の後処理コマンドの終了は待つ
+    let result = match post_cmd {
+        Some(post_cmd) => result.and(post_cmd.finish()),
+        None => result,
+    };
+
+    let recorded = match manifest {
+        Some(manifest) => manifest.finish(result.is_ok()),
+        None => Ok(()),
+    };
+
+    // 実行記録の書き込みにも失敗した場合は処理自体のエラーを優先して返す
+    if let Err(err) = result {
+        if let Err(log_err) = recorded {
+            eprintln!("warning: cannot write the run log: {:#}", log_err);
+        }
+
+        return Err(err);
+    }
+
+    recorded?;
+
+    if let Some(exporter) = exporter {
+        exporter.finish()?;
+    }
+
+    /*
+     * 付随ファイルのコピー
+     */
+    for (input_path, output_path) in sidecars.iter() {
+        copy_sidecar(&opts, &storage, input_path, output_path)?;
+    }
+
+    Ok(())
 }
 
 ///
-/// JPEGファイルに対する画像操作
+/// クリップボードを介した処理の実行
 ///
 /// # 引数
 /// * `opts` - オプション情報をパックしたオブジェクト
-/// * `input_path` - 処理対象のJPEGファイルへのパス
+/// * `storage` - 出力先のストレージ
 ///
 /// # 戻り値
-/// 処理に成功した場合は`Ok(())`を返す。処理に失敗した場合はエラー情報を`Err()`
-/// でラップして返す。
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を`Err()`で
+/// ラップして返す。
 ///
 /// # 注記
-/// オプション情報で強制書き込みが指定されていない場合かつ、出力ファイルが既に
-/// 存在する場合は処理をスキップした上で`Ok(())`を返すので注意すること。
+/// クリップボードから入力した場合の出力ファイル名は"clipboard"に出力形式の
+/// 拡張子を付けたものとする。
 ///
-fn proc_file<P>(opts: &Arc<Options>, input_path: P) -> Result<()>
-where 
-    P: AsRef<Path>
-{
-    let input_path = input_path.as_ref();
-    let output_path = opts.output_path()
-        .join(input_path.file_name().unwrap());
-
+fn run_clipboard(opts: &Arc<Options>, storage: &dyn Storage) -> Result<()> {
     /*
-     * 出力ファイルが既に存在する場合はスキップ
+     * 入力画像の取得
      */
-    if output_path.exists() && !opts.is_force() {
-        eprintln!(
-            "{} => {} skip (already exist)",
-            input_path.display(),
-            output_path.display()
-        );
-        return Ok(());
+    let (source, image) = if opts.is_from_clipboard() {
+        ("clipboard".to_string(), clipboard::read_image()?)
+    } else {
+        let path = &opts.inputs()[0];
+        (path.display().to_string(), decoder::decode_image(path, None)?)
+    };
+
+    let mut skipped = Vec::new();
+    let opts = &opts.for_input(Path::new(&source), &mut skipped)?;
+
+    for warning in &skipped {
+        eprintln!("warning: {}: {}", source, warning);
     }
 
     /*
-     * JPEGのデコード
+     * 画像の生成
      */
-    let image = decode_jpeg(input_path)?;
+    let source_quality = if opts.is_from_clipboard() {
+        base_quality(opts, None)
+    } else {
+        base_quality(opts, Some(&opts.inputs()[0]))
+    };
 
-    /*
-     * 画像のリサイズ
-     */
-    let (width, height) = opts.resolution()
-        .scaled_size(image.width(), image.height());
+    let dump = match opts.debug_dir() {
+        Some(dir) if opts.is_from_clipboard() => {
+            Some(DebugDump::new(&dir, "clipboard")?)
+        }
+        Some(dir) => Some(DebugDump::for_input(&dir, &opts.inputs()[0])?),
+        None => None,
+    };
 
-    let mut bg = resize_image(width, height, image)?;
+    let size = output_size(opts, image.width(), image.height());
+    let (image, quality) = render_decoded(
+        opts,
+        image,
+        size,
+        source_quality,
+        dump.as_ref()
+    )?;
+    let image = OutputImage::Rgba(image);
 
     /*
-     * ロゴの重畳
+     * 出力
      */
-    let logo = opts.logo_image();
-    let right = width as i64 - logo.width() as i64;
-    let bottom = height as i64 - logo.height() as i64;
+    if opts.is_to_clipboard() {
+        clipboard::write_image(image.into_rgba8())?;
+        println!("{} => clipboard", source);
+        return Ok(());
+    }
 
-    let (x, y) = match opts.logo_position() {
-        cmd_args::Position::TopLeft => (0, 0),
-        cmd_args::Position::TopRight => (right, 0),
-        cmd_args::Position::BottomLeft => (0, bottom),
-        cmd_args::Position::BottomRight => (right, bottom),
-        cmd_args::Position::Center => (right / 2, bottom / 2),
+    let format = if opts.is_from_clipboard() {
+        output_format_for(opts, None)
+    } else {
+        output_format_for(opts, Some(&opts.inputs()[0]))
     };
 
-    overlay(&mut bg, logo, x, y);
+    let output_path = opts.output_path()
+        .join("clipboard")
+        .with_extension(format.extensions()[0]);
 
-    /*
-     * ファイルの書き込み
-     */
-    encode_jpeg(&output_path, bg)?;
+    if storage.exists(&output_path) && !opts.is_force() {
+        eprintln!(
+            "{} => {} skip (already exist)",
+            source,
+            output_path.display()
+        );
+        return Ok(());
+    }
 
-    println!("{} => {}", input_path.display(), output_path.display());
+    let mut buf = Vec::new();
+    encode_image(opts, format, &mut buf, image, quality)?;
+    storage.write(&output_path, &buf)?;
+
+    println!("{} => {}", source, output_path.display());
 
     Ok(())
 }
 
 ///
-/// JPEGファイルのデコード
+/// JPEGファイルか否かの判定
 ///
 /// # 引数
-/// * `path` - デコード対象のJPEGファイルへのパス
+/// * `path` - 判定対象のファイルへのパス
 ///
 /// # 戻り値
-/// 処理に成功した場合はデコードした画像を`RgbaImage`オブエクトとして`Ok()`で
-/// ラップして返す。失敗した場合はエラー情報を`Err()`でラップして返す。
+/// 拡張子がJPEGファイルのものであれば`true`を返す。
 ///
-fn decode_jpeg<P>(path: P) -> Result<RgbaImage>
+#[cfg(feature = "native-codecs")]
+fn is_jpeg<P>(path: P) -> bool
 where 
-    P: AsRef<Path>
+    P: AsRef<Path>,
 {
-    let reader= BufReader::new(File::open(&path)?);
-
-    let mut decomp = Decompress::new_reader(reader)?.rgba()?;
-
-    let width = decomp.width() as u32;
-    let height = decomp.height() as u32;
-    let pixels = decomp.read_scanlines::<[u8; 4]>()?.concat();
-
-    let image: RgbaImage = ImageBuffer::from_raw(width, height, pixels)
-        .ok_or_else(|| anyhow!("invalid dimensions"))?;
-
-    Ok(match read_exif_orientation(&path)? {
-        1 => image,
-        2 => flip_horizontal(&image),
-        3 => rotate180(&image),
-        4 => flip_vertical(&image),
-        5 => flip_horizontal(&rotate90(&image)),
-        6 => rotate90(&image),
-        7 => flip_vertical(&rotate90(&image)),
-        8 => rotate270(&image),
-        _ => image,
-    })
+    has_extension(path, &["jpg", "jpeg"])
 }
 
 ///
-/// EXIF Orientationの読み出し
+/// 入力として扱える画像ファイルか否かの判定
 ///
 /// # 引数
-/// * `path` - 対象ファイルのパス
+/// * `path` - 判定対象のファイルへのパス
 ///
 /// # 戻り値
-/// Exif Orientationの値
+/// 拡張子がデコード可能な形式のものであれば`true`を返す。
+///
+fn is_input_image<P>(path: P) -> bool
+where 
+    P: AsRef<Path>,
+{
+    decoder::is_decodable(path.as_ref())
+}
+
+///
+/// 拡張子の判定
+///
+/// # 引数
+/// * `path` - 判定対象のファイルへのパス
+/// * `extensions` - 許容する拡張子のリスト(小文字で指定)
 ///
-/// # 参考
-/// https://qiita.com/yoya/items/4e14f696e1afd5a54403
+/// # 戻り値
+/// ファイルの拡張子(大文字小文字は区別しない)がリストに含まれていれば`true`
+/// を返す。
 ///
-fn read_exif_orientation<P>(path: P) -> Result<u32>
+fn has_extension<P>(path: P, extensions: &[&str]) -> bool
 where 
-    P: AsRef<Path>
+    P: AsRef<Path>,
 {
-    let mut buf = BufReader::new(File::open(path)?);
-    let exif = exif::Reader::new().read_from_container(&mut buf)?;
+    path.as_ref()
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|ext| extensions.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
 
-    Ok(exif
-        .get_field(exif::Tag::Orientation, exif::In::PRIMARY)
-        .and_then(|field| field.value.get_uint(0))
-        .unwrap_or(1))
+///
+/// 画像ファイルのリストアップ
+///
+/// # 引数
+/// * `storage` - 探査対象のストレージ
+/// * `path` - 探査の起点となるフォルダへのパス
+///
+/// # 戻り値
+/// 画像ファイルのリストを`Ok()`でラップして返す。探査に失敗した場合はエラー
+/// 情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// 引数で指定されたフォルダを起点に再帰的に降下探査し、入力として扱える画像
+/// ファイルをリストアップする。
+///
+fn image_files(storage: &dyn Storage, path: &Path) -> Result<Vec<PathBuf>> {
+    Ok(storage.list(path)?
+        .into_iter()
+        .filter(|path| is_input_image(path))
+        .collect())
 }
 
 ///
-/// JPEGファイルへのエンコード(ファイルへの出力)
+/// 付随ファイル(画像ファイル以外のファイル)のリストアップ
 ///
 /// # 引数
-/// * `path` - エンコード結果の書き込み対象ファイルへのパス
-/// * `image` - エンコード対象のイメージデータ
+/// * `storage` - 探査対象のストレージ
+/// * `path` - 探査の起点となるフォルダへのパス
 ///
 /// # 戻り値
-/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を `Err()`でラ
-/// ップして返す。
+/// XMPサイドカーやキャプションファイルなど、入力として扱う画像ファイル以外の
+/// ファイルのリストを`Ok()`でラップして返す。探査に失敗した場合はエラー情報
+/// を`Err()`でラップして返す。
 ///
-fn encode_jpeg<P>(path: P, image: RgbaImage) -> Result<()>
-where 
-    P: AsRef<Path>
+fn sidecar_files(storage: &dyn Storage, path: &Path) -> Result<Vec<PathBuf>> {
+    Ok(storage.list(path)?
+        .into_iter()
+        .filter(|path| !is_input_image(path))
+        .collect())
+}
+
+///
+/// 出力ファイルのパスの決定
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `root` - 入力ファイルを探査した起点のフォルダ(ファイルが直接指定された
+///   場合は`None`)
+/// * `input_path` - 入力ファイルへのパス
+///
+/// # 戻り値
+/// 出力ファイルのパスを返す。
+///
+/// # 注記
+/// 入力ファイルの隣への出力が指定されている場合は、入力ファイルと同じ場所の
+/// サブフォルダに出力する。ディレクトリ構造の維持が指定されている場合は、探
+/// 査の起点からの相対パスを出力先に再現する。それ以外の場合は出力先の直下に
+/// ファイル名のみで出力する。画像ファイルの拡張子が出力形式と一致しない場合
+/// は出力形式の拡張子に置き換える。
+///
+fn output_path_for(opts: &Arc<Options>, root: Option<&Path>, input_path: &Path)
+    -> PathBuf
 {
-    let writer = BufWriter::new(File::create(path)?);
+    let output_path = if let Some(name) = opts.output_beside_input() {
+        input_path.parent()
+            .unwrap_or(Path::new("."))
+            .join(name)
+            .join(input_path.file_name().unwrap())
+    } else if opts.is_mirror_tree()
+        && let Some(relative) = root
+            .and_then(|root| input_path.strip_prefix(root).ok())
+    {
+        opts.output_path().join(relative)
+    } else {
+        opts.output_path().join(input_path.file_name().unwrap())
+    };
 
-    let mut comp = Compress::new(ColorSpace::JCS_EXT_RGBA);
-    comp.set_size(image.width() as usize, image.height() as usize);
-    comp.set_quality(90 as f32);
-    comp.set_optimize_coding(true);
+    // 付随ファイルは元の拡張子のままコピーする
+    if !is_input_image(input_path) {
+        return output_path;
+    }
 
-    let mut comp = comp.start_compress(writer)?;
-    comp.write_scanlines(image.as_raw().as_slice())?;
-    comp.finish()?;
+    let extensions = output_format_for(opts, Some(input_path)).extensions();
 
-    Ok(())
+    if has_extension(&output_path, extensions) {
+        output_path
+    } else {
+        output_path.with_extension(extensions[0])
+    }
 }
 
 ///
-/// 画像データのリサイズ　
+/// 入力ファイルに対する出力形式の決定
 ///
 /// # 引数
-/// * `width` - ターゲットサイズの幅(ピクセル数)
-/// * `height` - ターゲットサイズの高さ(ピクセル数) 
-/// * `image` - リサイズ元の画像データ
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `input_path` - 入力ファイルへのパス(クリップボードからの入力の場合は
+///   `None`)
 ///
 /// # 戻り値
-/// リサイズに成功した場合は、リサイズされた画像データを`Ok()`でラップして返す。
-/// 処理に失敗した場合はエラー情報を`Err()`でラップして返す。
+/// 出力形式を返す。
+///
+/// # 注記
+/// 入力と同じ形式での出力が指定されている場合は、入力ファイルの内容から判別
+/// した形式を返す。判別できない場合や同じ形式で出力できない場合はJPEGを返す。
+///
+fn output_format_for(opts: &Arc<Options>, input_path: Option<&Path>)
+    -> OutputFormat
+{
+    match opts.output_format() {
+        OutputFormat::Same => input_path
+            .and_then(decoder::sniff_format)
+            .and_then(OutputFormat::from_image_format)
+            .unwrap_or(OutputFormat::Jpeg),
+
+        format => format,
+    }
+}
+
+///
+/// 入力ファイルの隣に出力されたファイルか否かの判定
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `path` - 判定対象のファイルへのパス
+///
+/// # 戻り値
+/// 入力ファイルの隣への出力が指定されており、かつ判定対象のファイルがその出
+/// 力フォルダ内にある場合は`true`を返す。
+///
+fn is_beside_output(opts: &Arc<Options>, path: &Path) -> bool {
+    let Some(name) = opts.output_beside_input() else {
+        return false;
+    };
+
+    path.parent()
+        .and_then(|dir| dir.file_name())
+        .is_some_and(|dir| dir == name.as_str())
+}
+
+///
+/// 付随ファイルのコピー
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `storage` - 入出力先のストレージ
+/// * `input_path` - コピー元のファイルへのパス
+/// * `output_path` - コピー先のファイルへのパス
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を`Err()`で
+/// ラップして返す。
+///
+fn copy_sidecar(
+    opts: &Arc<Options>,
+    storage: &dyn Storage,
+    input_path: &Path,
+    output_path: &Path,
+) -> Result<()> {
+    if storage.exists(output_path) && !opts.is_force() {
+        eprintln!(
+            "{} => {} skip (already exist)",
+            input_path.display(),
+            output_path.display()
+        );
+        return Ok(());
+    }
+
+    storage.write(output_path, &storage.read(input_path)?)?;
+
+    println!("{} => {}", input_path.display(), output_path.display());
+
+    Ok(())
+}
+
+///
+/// 1ファイル分の処理結果を格納する構造体
 ///
-fn resize_image(width: u32, height: u32, image: RgbaImage)
-    -> Result<RgbaImage>
+struct Processed {
+    /// 出力ファイルへのパス
+    output_path: PathBuf,
+
+    /// タイルに分割した場合のタイルごとの出力先と幅と高さのリスト(分割しな
+    /// い場合は空)
+    tiles: Vec<(PathBuf, (u32, u32))>,
+
+    /// 出力画像の幅(ピクセル数)
+    width: u32,
+
+    /// 出力画像の高さ(ピクセル数)
+    height: u32,
+
+    /// 入力ファイルをそのままコピーしたか否か
+    copied: bool,
+
+    /// 出力画像から抽出したパレット(抽出しない場合は空)
+    palette: Vec<[u8; 3]>,
+
+    /// 出力画像の代替テキスト
+    alt_text: Option<String>,
+
+    /// 処理中に発生した警告のリスト
+    warnings: Vec<String>,
+
+    /// 処理に要した時間
+    elapsed: Duration,
+}
+
+/// Processedに対する実装
+impl Processed {
+    ///
+    /// 処理を終えたコンテキストからの生成
+    ///
+    /// # 引数
+    /// * `ctx` - 処理を終えたコンテキスト
+    /// * `(width, height)` - 出力画像のサイズ
+    /// * `copied` - 入力ファイルをそのままコピーしたか否か
+    ///
+    /// # 戻り値
+    /// 生成したオブジェクトを返す。
+    ///
+    fn from_context(
+        ctx: ProcessingContext,
+        (width, height): (u32, u32),
+        copied: bool,
+    ) -> Self {
+        Self {
+            elapsed: ctx.elapsed(),
+            output_path: ctx.output_path,
+            tiles: ctx.tiles.into_iter()
+                .map(|(path, size, _)| (path, size))
+                .collect(),
+            width,
+            height,
+            copied,
+            palette: ctx.palette,
+            alt_text: ctx.alt_text,
+            warnings: ctx.warnings,
+        }
+    }
+
+    ///
+    /// 書き込んだ出力ファイルのリストの取得
+    ///
+    /// # 戻り値
+    /// 出力ファイルへのパスと出力画像の幅と高さのタプルのリストを返す。タイ
+    /// ルに分割した場合はタイルごとのものを返す。
+    ///
+    fn outputs(&self) -> Vec<(&Path, (u32, u32))> {
+        if self.tiles.is_empty() {
+            return vec![(&self.output_path, (self.width, self.height))];
+        }
+
+        self.tiles.iter()
+            .map(|(path, size)| (path.as_path(), *size))
+            .collect()
+    }
+}
+
+///
+/// 画像ファイルに対する画像操作
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `storage` - 入出力先のストレージ
+/// * `input_path` - 処理対象の画像ファイルへのパス
+/// * `brackets` - 入力ファイルとHDR合成する他のフレームへのパスのリスト
+/// * `output_path` - 出力ファイルへのパス
+///
+/// # 戻り値
+/// 処理に成功した場合は処理結果を`Ok(Some())`でラップして返す。処理に失敗し
+/// た場合はエラー情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// オプション情報で強制書き込みが指定されていない場合かつ、出力ファイルが既に
+/// 存在する場合は処理をスキップした上で`Ok(None)`を返すので注意すること。
+///
+/// HDR合成する場合は、入力ファイルが小さくてもそのままコピーはしない。
+///
+/// 処理は`ProcessingContext`を判定・デコード・合成・エンコード・書き込みの
+/// 各段に順に渡して行う。
+///
+fn proc_file<P, Q>(
+    opts: &Arc<Options>,
+    storage: &dyn Storage,
+    input_path: P,
+    brackets: &[PathBuf],
+    output_path: Q,
+) -> Result<Option<Processed>>
+where 
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let input_path = input_path.as_ref();
+
+    /*
+     * 透かしの文字列のテンプレート変数の展開
+     */
+    let mut skipped = Vec::new();
+    let opts = &opts.for_input(input_path, &mut skipped)?;
+
+    let mut ctx = ProcessingContext::new(
+        input_path,
+        output_path.as_ref(),
+        output_format_for(opts, Some(input_path))
+    );
+
+    for warning in skipped {
+        ctx.warn(warning);
+    }
+
+    ctx.brackets = brackets.to_vec();
+    ctx.alt_text = opts.alt_text_for(input_path)?;
+
+    if opts.output_format() == OutputFormat::Same
+        && ctx.source_format
+            .and_then(OutputFormat::from_image_format)
+            .is_none()
+    {
+        ctx.warn("the source format cannot be written, output as JPEG");
+    }
+
+    /*
+     * 処理不要な小さい画像の判定(元のエンコードのままコピーするので、出力
+     * ファイルの拡張子も元のファイルに合わせる)
+     */
+    let passthrough = ctx.run_stage("probe", |ctx| {
+        if ctx.brackets.is_empty() && !opts.is_web_bundle() {
+            passthrough_size(opts, &ctx.input_path)
+        } else {
+            Ok(None)
+        }
+    })?;
+
+    if passthrough.is_some()
+        && let Some(ext) = input_path.extension()
+    {
+        ctx.output_path = ctx.output_path.with_extension(ext);
+    }
+
+    let output_path = ctx.output_path.clone();
+
+    /*
+     * 出力ファイルが既に存在する場合はスキップ(タイルに分割する場合は先頭の
+     * タイルで判断する)
+     */
+    let exists = storage.exists(&output_path)
+        || (passthrough.is_none()
+            && opts.tile_output().is_some()
+            && storage.exists(&tile_output::tile_path(&output_path, 0, 0)));
+
+    if exists && !opts.is_force() {
+        eprintln!(
+            "{} => {} skip (already exist)",
+            input_path.display(),
+            output_path.display()
+        );
+        return Ok(None);
+    }
+
+    /*
+     * 処理不要な小さい画像はそのままコピー
+     */
+    if let Some(size) = passthrough {
+        ctx.source_size = Some(size);
+        ctx.run_stage("copy", |ctx| copy_source(storage, ctx))?;
+        report_warnings(&ctx);
+
+        println!(
+            "{} => {} (copied)",
+            input_path.display(),
+            output_path.display()
+        );
+
+        return Ok(Some(Processed::from_context(ctx, size, true)));
+    }
+
+    /*
+     * 画像の生成
+     */
+    let size = encode_file(opts, &mut ctx)?;
+
+    /*
+     * ファイルの書き込み
+     */
+    ctx.run_stage("write", |ctx| {
+        if ctx.tiles.is_empty() {
+            return storage.write(&ctx.output_path, &ctx.encoded);
+        }
+
+        ctx.tiles.iter()
+            .try_for_each(|(path, _, data)| storage.write(path, data))
+    })?;
+
+    ctx.run_stage("bundle", |ctx| {
+        ctx.bundle.iter().try_for_each(|(path, data)| storage.write(path, data))
+    })?;
+
+    report_warnings(&ctx);
+
+    if !ctx.tiles.is_empty() {
+        println!(
+            "{} => {} ({} tiles)",
+            input_path.display(),
+            output_path.display(),
+            ctx.tiles.len()
+        );
+    } else if brackets.is_empty() {
+        println!("{} => {}", input_path.display(), output_path.display());
+    } else {
+        println!(
+            "{} (+{} frames, hdr merged) => {}",
+            input_path.display(),
+            brackets.len(),
+            output_path.display()
+        );
+    }
+
+    Ok(Some(Processed::from_context(ctx, size, false)))
+}
+
+///
+/// 入力ファイルからの出力データの生成
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `ctx` - 処理中のコンテキスト
+///
+/// # 戻り値
+/// 処理に成功した場合は出力画像のサイズを`Ok()`でラップして返す。失敗した場
+/// 合はエラー情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// デコード・合成・エンコードの各段を順に実行し、エンコード結果をコンテキス
+/// トに格納する。書き込みは行わない。
+///
+fn encode_file(opts: &Arc<Options>, ctx: &mut ProcessingContext)
+    -> Result<(u32, u32)>
+{
+    ctx.run_stage("decode", |ctx| decode_stage(opts, ctx))?;
+    ctx.run_stage("render", |ctx| render_stage(opts, ctx))?;
+    ctx.run_stage("encode", |ctx| encode_stage(opts, ctx))
+}
+
+///
+/// 入力ファイルのコピー(処理の段)
+///
+/// # 引数
+/// * `storage` - 入出力先のストレージ
+/// * `ctx` - 処理中のコンテキスト
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を`Err()`で
+/// ラップして返す。
+///
+/// # 注記
+/// 元のエンコードのまま出力する。出力ファイルの拡張子は`proc_file()`で元の
+/// ファイルに合わせてある。
+///
+fn copy_source(storage: &dyn Storage, ctx: &mut ProcessingContext)
+    -> Result<()>
+{
+    storage.write(&ctx.output_path, &storage.read(&ctx.input_path)?)
+}
+
+///
+/// 入力画像のデコード(処理の段)
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `ctx` - 処理中のコンテキスト
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を`Err()`で
+/// ラップして返す。
+///
+/// # 注記
+/// グレースケールの維持が指定されている場合、グレースケールのJPEGファイルは
+/// RGBAに変換せずにグレースケールのまま扱う。作業用の色深度が16ビットの場合
+/// は入力画像も可能な限り16ビットでデコードする。JPEGファイルは出力サイズを
+/// 下回らない範囲でDCT領域で縮小しながらデコードする。
+///
+fn decode_stage(opts: &Arc<Options>, ctx: &mut ProcessingContext)
+    -> Result<()>
+{
+    let path = ctx.input_path.as_path();
+
+    // 縮小デコードで下回ってはならないサイズ(リサイズ後のサイズ)。縮小デ
+    // コードできない形式ではサイズを先読みせず、デコード結果から得る
+    let min_size = if decoder::is_scalable(path) {
+        let (width, height) = decoder::read_image_size(path)?;
+        ctx.source_size = Some((width, height));
+        Some(output_size(opts, width, height))
+    } else {
+        None
+    };
+
+    // HDR合成する場合は全フレームをデコードして合成したものを入力とする
+    if !ctx.brackets.is_empty() {
+        let source = decode_brackets(opts, ctx, min_size)?;
+        ctx.source_size.get_or_insert(source.dimensions());
+        ctx.source = Some(source);
+        return Ok(());
+    }
+
+    let source = if opts.is_keep_grayscale()
+        && let Some(image) = decoder::decode_grayscale(path, min_size)?
+    {
+        SourceImage::Gray(image)
+    } else {
+        match opts.depth() {
+            ColorDepth::Eight => {
+                SourceImage::Rgba(decoder::decode_image(path, min_size)?)
+            }
+
+            ColorDepth::Sixteen => {
+                SourceImage::Rgba16(decoder::decode_image16(path, min_size)?)
+            }
+        }
+    };
+
+    ctx.source_size.get_or_insert(source.dimensions());
+    ctx.source = Some(source);
+
+    Ok(())
+}
+
+///
+/// ブラケットのデコードとHDR合成
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `ctx` - 処理中のコンテキスト
+/// * `min_size` - 縮小デコードで下回ってはならないサイズ
+///
+/// # 戻り値
+/// 処理に成功した場合は合成した画像を`Ok()`でラップして返す。失敗した場合
+/// はエラー情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// 合成結果は16ビットで得られるので、作業用の色深度が8ビットの場合はディザ
+/// リングの指定に従って変換する。グレースケールの維持の指定は適用しない。
+///
+fn decode_brackets(
+    opts: &Arc<Options>,
+    ctx: &ProcessingContext,
+    min_size: Option<(u32, u32)>,
+) -> Result<SourceImage> {
+    let paths = std::iter::once(&ctx.input_path)
+        .chain(ctx.brackets.iter())
+        .collect::<Vec<_>>();
+
+    let frames = paths.iter()
+        .map(|path| decoder::decode_image(path, min_size))
+        .collect::<Result<Vec<_>>>()?;
+
+    let exposures = paths.iter()
+        .map(|path| hdr::read_exposure(path))
+        .collect::<Vec<_>>();
+
+    let merged = hdr::merge(&frames, &exposures).with_context(|| {
+        format!("cannot merge brackets of {}", ctx.input_path.display())
+    })?;
+
+    Ok(match opts.depth() {
+        ColorDepth::Eight => {
+            SourceImage::Rgba(pixel::reduce_to_rgba8(&merged, opts.dither()))
+        }
+
+        ColorDepth::Sixteen => SourceImage::Rgba16(merged),
+    })
+}
+
+///
+/// 出力画像の合成(処理の段)
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `ctx` - 処理中のコンテキスト
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を`Err()`で
+/// ラップして返す。
+///
+fn render_stage(opts: &Arc<Options>, ctx: &mut ProcessingContext)
+    -> Result<()>
+{
+    let base_quality = base_quality(opts, Some(&ctx.input_path));
+
+    let Some(source) = ctx.source.take() else {
+        unreachable!("render stage without decoded image");
+    };
+
+    // 縮小デコードした場合も元の画像サイズを基準に出力サイズを決める
+    let (width, height) = ctx.source_size.unwrap_or(source.dimensions());
+    let size = output_size(opts, width, height);
+
+    // デバッグ出力の指定がある場合は中間画像の書き出し先を用意する
+    let dump = opts.debug_dir()
+        .map(|dir| DebugDump::for_input(&dir, &ctx.input_path))
+        .transpose()?;
+    let dump = dump.as_ref();
+
+    let (output, quality) = match source {
+        SourceImage::Rgba(image) => {
+            let (image, quality) = render_decoded(
+                opts,
+                image,
+                size,
+                base_quality,
+                dump
+            )?;
+
+            (OutputImage::Rgba(image), quality)
+        }
+
+        SourceImage::Rgba16(image) => {
+            let (image, quality) = render_decoded16(
+                opts,
+                image,
+                size,
+                base_quality,
+                dump
+            )?;
+
+            (OutputImage::Rgba(image), quality)
+        }
+
+        SourceImage::Gray(image) => {
+            let (image, quality) = render_grayscale(
+                opts,
+                image,
+                size,
+                base_quality,
+                dump
+            )?;
+
+            (OutputImage::Gray(image), quality)
+        }
+    };
+
+    ctx.output = Some(output);
+    ctx.quality = quality;
+
+    Ok(())
+}
+
+///
+/// 出力画像のエンコード(処理の段)
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `ctx` - 処理中のコンテキスト
+///
+/// # 戻り値
+/// 処理に成功した場合は出力画像のサイズを`Ok()`でラップして返す。失敗した場
+/// 合はエラー情報を`Err()`でラップして返す。
+///
+fn encode_stage(opts: &Arc<Options>, ctx: &mut ProcessingContext)
+    -> Result<(u32, u32)>
+{
+    let Some(image) = ctx.output.take() else {
+        unreachable!("encode stage without rendered image");
+    };
+
+    let size = image.dimensions();
+
+    // パレットの抽出の指定がある場合は出力画像の色を解析する
+    if opts.is_palette() {
+        ctx.palette = palette::extract(&palette::thumbnail(&image));
+    }
+
+    // Web向けの出力一式の指定がある場合は縮小版とメタデータも生成する
+    if opts.is_web_bundle() {
+        ctx.bundle = web_bundle::build(opts, ctx, &image)?;
+    }
+
+    // タイルに分割する場合はタイルごとに切り出してエンコードする
+    let tiles = match opts.tile_output() {
+        Some(tile) => tile_output::grid(size, tile, opts.tile_overlap()),
+        None => Vec::new(),
+    };
+
+    if tiles.len() > 1 {
+        for rect in &tiles {
+            let tile = match &image {
+                OutputImage::Rgba(image) => OutputImage::Rgba(
+                    crop_imm(image, rect.x, rect.y, rect.width, rect.height)
+                        .to_image()
+                ),
+
+                OutputImage::Gray(image) => OutputImage::Gray(
+                    crop_imm(image, rect.x, rect.y, rect.width, rect.height)
+                        .to_image()
+                ),
+            };
+
+            let mut buf = Vec::new();
+            encode_image(opts, ctx.output_format, &mut buf, tile, ctx.quality)?;
+            ctx.tiles.push((
+                rect.path(&ctx.output_path),
+                (rect.width, rect.height),
+                buf
+            ));
+        }
+
+        return Ok(size);
+    }
+
+    let mut buf = Vec::new();
+    encode_image(opts, ctx.output_format, &mut buf, image, ctx.quality)?;
+    ctx.encoded = buf;
+
+    Ok(size)
+}
+
+///
+/// 処理中に発生した警告の表示
+///
+/// # 引数
+/// * `ctx` - 処理を終えたコンテキスト
+///
+fn report_warnings(ctx: &ProcessingContext) {
+    for warning in &ctx.warnings {
+        eprintln!("warning: {}: {}", ctx.input_path.display(), warning);
+    }
+}
+
+///
+/// そのままコピーする画像か否かの判定
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `input_path` - 処理対象の画像ファイルへのパス
+///
+/// # 戻り値
+/// そのままコピーする場合は画像のサイズを`Ok(Some())`でラップして返す。処理
+/// が必要な場合は`Ok(None)`を返す。判定に失敗した場合はエラー情報を`Err()`で
+/// ラップして返す。
+///
+/// # 注記
+/// 判定にはヘッダ情報のみを用い、画素データのデコードは行わない。
+///
+fn passthrough_size<P>(opts: &Arc<Options>, input_path: P)
+    -> Result<Option<(u32, u32)>>
+where 
+    P: AsRef<Path>
+{
+    let keep = opts.is_keep_resolution_if_smaller();
+    let limit = opts.only_if_larger_than();
+
+    if !keep && limit.is_none() {
+        return Ok(None);
+    }
+
+    let (width, height) = decoder::read_image_size(input_path)?;
+
+    // 出力解像度以下(リサイズすると拡大になる)の場合
+    if keep && opts.resolution().scale_ratio(width, height) >= 1.0 {
+        return Ok(Some((width, height)));
+    }
+
+    // 指定サイズより大きくない場合
+    if let Some(limit) = limit
+        && width <= limit.width()
+        && height <= limit.height()
+    {
+        return Ok(Some((width, height)));
+    }
+
+    Ok(None)
+}
+
+///
+/// 出力品質の決定
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `input_path` - 入力ファイルへのパス(クリップボードからの入力の場合は
+///   `None`)
+///
+/// # 戻り値
+/// 品質の自動選択を行わない場合の出力品質を返す。
+///
+/// # 注記
+/// 品質にautoが指定されている場合は入力JPEGの量子化テーブルから推定した品質
+/// を返す。推定できない場合は既定の品質を返す。
+///
+fn base_quality(opts: &Arc<Options>, input_path: Option<&Path>) -> f32 {
+    let source = match opts.quality() {
+        Quality::Auto => input_path.and_then(decoder::estimate_jpeg_quality),
+        Quality::Fixed(_) => None,
+    };
+
+    opts.quality().resolve(source) as f32
+}
+
+///
+/// 出力画像サイズの決定
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `width` - 入力画像の幅(ピクセル数)
+/// * `height` - 入力画像の高さ(ピクセル数)
+///
+/// # 戻り値
+/// リサイズ後の幅と高さをタプルにパックして返す。
+///
+fn output_size(opts: &Arc<Options>, width: u32, height: u32) -> (u32, u32) {
+    let (mut width, mut height) = opts.resolution().scaled_size(width, height);
+
+    // キャンバスモードの場合はキャンバスに収まるサイズに制限する
+    if let Some(canvas) = opts.canvas() {
+        let scale = (canvas.width() as f32 / width as f32)
+            .min(canvas.height() as f32 / height as f32);
+
+        if scale < 1.0 {
+            width = ((width as f32 * scale).round() as u32).max(1);
+            height = ((height as f32 * scale).round() as u32).max(1);
+        }
+    }
+
+    // 寸法の揃え方の指定がある場合は倍数に丸める(キャンバスは超えない)
+    if let Some(align) = opts.align() {
+        let canvas = opts.canvas();
+
+        width = align.apply(width, canvas.map(|canvas| canvas.width()));
+        height = align.apply(height, canvas.map(|canvas| canvas.height()));
+    }
+
+    (width, height)
+}
+
+///
+/// デコード済みの画像からの出力画像の生成
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `image` - デコード済みの入力画像
+/// * `(width, height)` - リサイズ後の画像サイズ
+/// * `base_quality` - 品質の自動選択を行わない場合の出力品質
+/// * `dump` - 中間画像の書き出し先(デバッグ用)
+///
+/// # 戻り値
+/// 処理に成功した場合は、ロゴを重畳した出力画像と出力時のJPEG品質をタプルに
+/// パックし`Ok()`でラップして返す。処理に失敗した場合はエラー情報を`Err()`で
+/// ラップして返す。
+///
+fn render_decoded(
+    opts: &Arc<Options>,
+    image: RgbaImage,
+    (width, height): (u32, u32),
+    base_quality: f32,
+    dump: Option<&DebugDump>,
+) -> Result<(RgbaImage, f32)> {
+    if opts.depth() == ColorDepth::Sixteen {
+        let image = Rgba::<u16>::from_rgba8_image(&image);
+        return render_decoded16(
+            opts,
+            image,
+            (width, height),
+            base_quality,
+            dump
+        );
+    }
+
+    /*
+     * 画像のリサイズと合成
+     */
+    let bg = compose_image::<Rgba<u8>>(opts, image, width, height, dump)?;
+
+    /*
+     * 出力品質の決定
+     */
+    let quality = select_quality(opts, &bg, base_quality);
+
+    Ok((bg, quality))
+}
+
+///
+/// 16ビット/チャネルの画像からの出力画像の生成
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `image` - デコード済みの入力画像
+/// * `(width, height)` - リサイズ後の画像サイズ
+/// * `base_quality` - 品質の自動選択を行わない場合の出力品質
+/// * `dump` - 中間画像の書き出し先(デバッグ用)
+///
+/// # 戻り値
+/// 処理に成功した場合は、ロゴを重畳し8ビットに変換した出力画像と出力時の
+/// JPEG品質をタプルにパックし`Ok()`でラップして返す。処理に失敗した場合はエ
+/// ラー情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// リサイズと合成を16ビットで行い、最後に指定のディザリング方式で8ビットに
+/// 変換する。
+///
+fn render_decoded16(
+    opts: &Arc<Options>,
+    image: Rgba16Image,
+    (width, height): (u32, u32),
+    base_quality: f32,
+    dump: Option<&DebugDump>,
+) -> Result<(RgbaImage, f32)> {
+    /*
+     * 画像のリサイズと合成
+     */
+    let bg = compose_image::<Rgba<u16>>(opts, image, width, height, dump)?;
+
+    /*
+     * 8ビットへの変換
+     */
+    let bg = pixel::reduce_to_rgba8(&bg, opts.dither());
+
+    /*
+     * 出力品質の決定
+     */
+    let quality = select_quality(opts, &bg, base_quality);
+
+    Ok((bg, quality))
+}
+
+///
+/// 出力画像に応じた出力品質の決定
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `image` - 合成後の出力画像
+/// * `base_quality` - 品質の自動選択を行わない場合の出力品質
+///
+/// # 戻り値
+/// 品質の自動選択が指定されている場合は画像の複雑さに応じた品質を、そうで
+/// ない場合は`base_quality`を返す。
+///
+fn select_quality(opts: &Arc<Options>, image: &RgbaImage, base_quality: f32)
+    -> f32
+{
+    if opts.is_adaptive_quality() {
+        opts.quality_range().select(analysis::estimate_complexity(image))
+    } else {
+        base_quality
+    }
+}
+
+///
+/// グレースケール画像からの出力画像の生成
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `image` - デコード済みのグレースケールの入力画像
+/// * `(width, height)` - リサイズ後の画像サイズ
+/// * `base_quality` - 品質の自動選択を行わない場合の出力品質
+/// * `dump` - 中間画像の書き出し先(デバッグ用)
+///
+/// # 戻り値
+/// 処理に成功した場合は、ロゴを輝度に変換して重畳した出力画像と出力時のJPEG
+/// 品質をタプルにパックし`Ok()`でラップして返す。処理に失敗した場合はエラー
+/// 情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// 作業用の色深度の指定によらず8ビットの輝度のみで合成する。
+///
+fn render_grayscale(
+    opts: &Arc<Options>,
+    image: GrayImage,
+    (width, height): (u32, u32),
+    base_quality: f32,
+    dump: Option<&DebugDump>,
+) -> Result<(GrayImage, f32)> {
+    /*
+     * 輝度のみでの合成
+     */
+    let image = DynamicImage::ImageLuma8(image).to_luma_alpha8();
+    let bg = compose_image::<LumaA<u8>>(opts, image, width, height, dump)?;
+    let bg = DynamicImage::ImageLumaA8(bg).to_luma8();
+
+    /*
+     * 出力品質の決定
+     */
+    let quality = if opts.is_adaptive_quality() {
+        opts.quality_range().select(analysis::estimate_gray_complexity(&bg))
+    } else {
+        base_quality
+    };
+
+    Ok((bg, quality))
+}
+
+///
+/// 出力形式に応じた画像のエンコード
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `format` - 出力形式
+/// * `writer` - エンコード結果の書き込み先
+/// * `image` - エンコード対象のイメージデータ
+/// * `quality` - 出力品質(1〜100)
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を `Err()`でラ
+/// ップして返す。
+///
+fn encode_image<W>(
+    opts: &Arc<Options>,
+    format: OutputFormat,
+    mut writer: W,
+    image: OutputImage,
+    quality: f32,
+) -> Result<()>
+where 
+    W: Write
+{
+    let encoder = registry::create_encoder(opts, format);
+
+    match image {
+        OutputImage::Rgba(image) => encoder.encode(&mut writer, image, quality),
+        OutputImage::Gray(image) => {
+            encoder.encode_gray(&mut writer, image, quality)
+        }
+    }
+}
+
+///
+/// 作業用の色深度での画像の合成
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `image` - デコード済みの入力画像
+/// * `width` - リサイズ後の幅(ピクセル数)
+/// * `height` - リサイズ後の高さ(ピクセル数)
+/// * `dump` - 中間画像の書き出し先(デバッグ用)
+///
+/// # 戻り値
+/// 処理に成功した場合は、リサイズ・キャンバスへの配置・ロゴの重畳を行った画
+/// 像を`Ok()`でラップして返す。失敗した場合はエラー情報を`Err()`でラップして
+/// 返す。
+///
+fn compose_image<T>(
+    opts: &Arc<Options>,
+    image: WorkImage<T>,
+    width: u32,
+    height: u32,
+    dump: Option<&DebugDump>,
+) -> Result<WorkImage<T>>
+where 
+    T: WorkPixel,
+{
+    let mut bg = resize_image(width, height, image)?;
+
+    /*
+     * 3D LUTとトーンカーブの適用(ロゴとキャンバスの背景には適用しない)
+     */
+    if opts.lut().is_some() || opts.tone_curve().is_some() {
+        lut::apply_look(&mut bg, opts.lut(), opts.tone_curve());
+    }
+
+    /*
+     * リサイズ後のシャープ化(ロゴとキャンバスの背景には適用しない)
+     */
+    if let Some(amount) = opts.sharpen() {
+        bg = sharpen::unsharp_mask(&bg, amount, opts.sharpen_mode());
+    }
+
+    if let Some(dump) = dump {
+        dump.save("1-resized", &bg)?;
+    }
+
+    /*
+     * キャンバスへの配置
+     */
+    if let Some(canvas) = opts.canvas() {
+        let mut base = WorkImage::<T>::from_pixel(
+            canvas.width(),
+            canvas.height(),
+            T::from_rgba8(opts.canvas_color().to_rgba())
+        );
+
+        let (x, y) = anchor_position(
+            opts.canvas_gravity(),
+            (base.width(), base.height()),
+            (bg.width(), bg.height())
+        );
+
+        overlay(&mut base, &bg, x, y);
+        bg = base;
+    }
+
+    /*
+     * 縁の付加(縁を含めた画像を基準にロゴを配置する)
+     */
+    if let Some(border) = opts.border() {
+        let mut base = WorkImage::<T>::from_pixel(
+            bg.width() + border * 2,
+            bg.height() + border * 2,
+            T::from_rgba8(opts.border_color().to_rgba())
+        );
+
+        overlay(&mut base, &bg, border as i64, border as i64);
+        bg = base;
+    }
+
+    /*
+     * 透かしの重畳(透かし無しモードの場合は行わない)
+     */
+    if !opts.watermarks().is_empty() {
+        // 出力画像の向きに対する配置の指定がある場合はそれに従う
+        let opts = &opts.for_output(bg.dimensions());
+
+        // タイルに分割する場合はタイルごとに配置する(分割しない場合は画像全
+        // 体を1枚のタイルとして扱う)
+        let tile = opts.tile_output().unwrap_or(bg.width().max(bg.height()));
+        let regions =
+            tile_output::grid(bg.dimensions(), tile, opts.tile_overlap());
+
+        for (index, rect) in regions.iter().enumerate() {
+            // 自動配置や明るい背景用のロゴの指定がある場合は配置先の背景を
+            // 調べて選ぶ(ファイルごとにずらす量もここで決める)
+            let choices = logo_choices(opts, &bg, rect);
+
+            // 同じ大きさの出力では生成済みのレイヤーを使い回す
+            let size = (rect.width, rect.height);
+            let layer = opts.layer_cache().get_or_render(size, &choices, || {
+                render_watermark::<T>(opts, size, &choices)
+            })?;
+
+            if let Some(dump) = dump
+                && index == 0
+            {
+                dump.save("2-watermark", &layer)?;
+                dump.save_alpha("3-alpha", &layer)?;
+            }
+
+            overlay(&mut bg, layer.as_ref(), rect.x as i64, rect.y as i64);
+        }
+    }
+
+    if let Some(dump) = dump {
+        dump.save("4-composite", &bg)?;
+    }
+
+    Ok(bg)
+}
+
+///
+/// 透かしごとの背景に基づくロゴの選択
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `image` - 透かしを重畳する画像
+/// * `rect` - 透かしのレイヤーを重畳する範囲
+///
+/// # 戻り値
+/// 透かしごとに自動配置で選んだ配置位置と、明るい背景用のロゴを用いるか否
+/// かと、ロゴをずらす量を並べたリストを返す。背景を調べたりロゴをずらした
+/// りする必要のある透かしが無い場合は空のリストを返す。
+///
+/// # 注記
+/// 自動配置の場合は、選んだ配置位置の背景で明るい背景用のロゴを使い分ける。
+///
+fn logo_choices<T>(opts: &Arc<Options>, image: &WorkImage<T>, rect: &TileRect)
+    -> Vec<LogoChoice>
+where
+    T: WorkPixel,
+{
+    let marks = opts.watermarks();
+    let is_auto = |mark: &Watermark| {
+        mark.position() == Position::Auto
+            && mark.offset().is_none()
+            && !mark.is_tile()
+    };
+
+    if marks.iter().all(|mark| {
+        mark.dark_image().is_none() && !is_auto(mark) && mark.jitter().is_none()
+    }) {
+        return Vec::new();
+    }
+
+    let size = (rect.width, rect.height);
+    let shift = |(x, y, w, h): (i64, i64, u32, u32)| {
+        (x + rect.x as i64, y + rect.y as i64, w, h)
+    };
+
+    marks.iter()
+        .enumerate()
+        .map(|(index, mark)| {
+            let position = is_auto(mark).then(|| {
+                placement::least_busy(image, |position| {
+                    shift(logo_region(mark, position, size))
+                })
+            });
+
+            let dark = mark.dark_image().is_some() && {
+                let position = position.unwrap_or(mark.position());
+                let region = shift(logo_region(mark, position, size));
+
+                analysis::region_luma(image, region)
+                    .is_some_and(|luma| mark.prefers_dark(luma))
+            };
+
+            let jitter = match mark.jitter() {
+                Some(range) => placement::jitter(
+                    opts.jitter_key(),
+                    index,
+                    range.pixels(size)
+                ),
+                None => (0, 0),
+            };
+
+            LogoChoice {dark, position, jitter}
+        })
+        .collect()
+}
+
+///
+/// 透かしのレイヤーの生成
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `(width, height)` - 透かしを重畳する画像の大きさ
+/// * `choices` - 透かしごとの背景に基づく選択結果のリスト(空の場合は全て
+///   通常のロゴを指定の配置位置に配置する)
+///
+/// # 戻り値
+/// 処理に成功した場合は、透明な背景に透かしを配置した重畳する画像と同じ大き
+/// さのレイヤーを`Ok()`でラップして返す。失敗した場合はエラー情報を`Err()`
+/// でラップして返す。
+///
+/// # 注記
+/// 複数の透かしが指定されている場合は、指定された順に重ねて配置する。
+///
+fn render_watermark<T>(
+    opts: &Arc<Options>,
+    (width, height): (u32, u32),
+    choices: &[LogoChoice],
+) -> Result<WorkImage<T>>
+where 
+    T: WorkPixel,
+{
+    let mut layer = WorkImage::<T>::from_pixel(
+        width,
+        height,
+        T::from_rgba8(Rgba([0, 0, 0, 0]))
+    );
+
+    for (index, mark) in opts.watermarks().iter().enumerate() {
+        let choice = choices.get(index).copied().unwrap_or_default();
+        let position = choice.position.unwrap_or(mark.position());
+        let source = mark.variant(choice.dark);
+        let mut logo = T::from_rgba8_image(source);
+
+        // 大きさの指定がある場合は出力画像に合わせてリサイズする
+        let size = logo_size(mark, logo.dimensions(), (width, height));
+
+        if size != logo.dimensions() {
+            let original = logo.dimensions();
+            logo = resize_image(size.0, size.1, logo)?;
+
+            // 大きく縮小した場合は細い線が潰れないようにシャープ化する
+            if let Some(amount) = logo_sharpen(mark, original, size) {
+                logo = sharpen::sharpen_logo(&logo, amount);
+            }
+        }
+
+        // 回転の指定がある場合は配置の前に回転させる
+        if let Some(degrees) = mark.rotation() {
+            logo = tiling::rotate_expanded(&logo, degrees);
+        }
+
+        // 影や縁取りの指定がある場合はその分だけロゴを広げて描画する(配置は
+        // 広げる前の大きさで決める)
+        let base = logo.dimensions();
+        let mut pad = halo::padding(mark.outline(), mark.shadow());
+
+        if pad > 0 {
+            logo = halo::add_halo(
+                &logo,
+                mark.outline(),
+                mark.shadow(),
+                mark.halo_color().to_rgba()
+            );
+        }
+
+        // 背景の指定がある場合は影や縁取りも含めて角丸の矩形の上に載せる
+        if let Some(backdrop) = mark.backdrop() {
+            let padding = mark.backdrop_padding()
+                .unwrap_or_else(|| halo::backdrop_padding(base));
+            let radius = mark.backdrop_radius().unwrap_or(padding);
+
+            logo = halo::add_backdrop(
+                &logo,
+                padding,
+                radius,
+                backdrop.to_rgba()
+            );
+            pad += padding;
+        }
+
+        // 敷き詰めの指定がある場合は画像全体に並べる
+        if mark.is_tile() {
+            let gap = match mark.tile_spacing() {
+                Some(spacing) => spacing.pixels(layer.dimensions()),
+                None => logo.dimensions(),
+            };
+
+            tiling::overlay_tiled(&mut layer, &logo, mark.tile_angle(), gap);
+            continue;
+        }
+
+        // 小数点以下の精度の指定がある場合は再標本化して配置する
+        if mark.is_subpixel() {
+            let size = logo_exact_size(
+                mark,
+                source.dimensions(),
+                (width, height)
+            );
+            let (x, y) = placement::jittered(
+                logo_exact_origin(mark, position, (width, height), size),
+                choice.jitter,
+                size,
+                (width, height)
+            );
+
+            let left = x.floor() as i64;
+            let pad = pad as f32;
+            let padded = (size.0 + pad * 2.0, size.1 + pad * 2.0);
+
+            for shift in logo_repeats(mark, (width, height), left, size.0) {
+                let origin = (x + shift as f32 - pad, y - pad);
+                subpixel::overlay_resampled(&mut layer, &logo, origin, padded);
+            }
+
+            continue;
+        }
+
+        let (x, y) = logo_origin(mark, position, (width, height), base);
+        let (x, y) = placement::jittered(
+            (x as f32, y as f32),
+            choice.jitter,
+            (base.0 as f32, base.1 as f32),
+            (width, height)
+        );
+        let (x, y) = (x as i64, y as i64);
+        let pad = pad as i64;
+
+        for shift in logo_repeats(mark, (width, height), x, base.0 as f32) {
+            overlay(&mut layer, &logo, x + shift - pad, y - pad);
+        }
+    }
+
+    Ok(layer)
+}
+
+///
+/// 重畳時のロゴの大きさの決定
+///
+/// # 引数
+/// * `mark` - 透かしの情報
+/// * `logo` - ロゴ画像の幅と高さ
+/// * `image` - ロゴを重畳する画像の幅と高さ
+///
+/// # 戻り値
+/// 大きさの指定がある場合は出力画像に合わせた大きさを、そうでない場合はロゴ
+/// 画像の大きさをそのまま返す。いずれの場合も大きさの上限を超える場合は収ま
+/// るように縮小した大きさを返す。
+///
+fn logo_size(mark: &Watermark, logo: (u32, u32), image: (u32, u32))
+    -> (u32, u32)
+{
+    let size = match mark.scale() {
+        Some(scale) => scale.scaled_size(logo, image),
+        None => logo,
+    };
+
+    let fitted = logo_fit(mark, (size.0 as f32, size.1 as f32), image);
+
+    if fitted.0 < size.0 as f32 || fitted.1 < size.1 as f32 {
+        (
+            (fitted.0.round() as u32).max(1),
+            (fitted.1.round() as u32).max(1),
+        )
+    } else {
+        size
+    }
+}
+
+///
+/// 大きさの上限に収まるロゴの大きさの算出
+///
+/// # 引数
+/// * `mark` - 透かしの情報
+/// * `(width, height)` - ロゴの幅と高さ
+/// * `image` - ロゴを重畳する画像の幅と高さ
+///
+/// # 戻り値
+/// ロゴが大きさの上限(画像の幅と高さに上限の割合を乗じたもの)を超える場合
+/// は、縦横比を保ったまま上限に収まるように縮小した幅と高さを返す。超えない
+/// 場合はそのまま返す。
+///
+fn logo_fit(mark: &Watermark, (width, height): (f32, f32), image: (u32, u32))
+    -> (f32, f32)
+{
+    let Some(ratio) = mark.max_coverage() else {
+        return (width, height);
+    };
+
+    let limit = (image.0 as f32 * ratio, image.1 as f32 * ratio);
+    let factor = (limit.0 / width).min(limit.1 / height);
+
+    if factor < 1.0 {
+        (width * factor, height * factor)
+    } else {
+        (width, height)
+    }
+}
+
+///
+/// 縮小したロゴに適用するシャープ化の強度の決定
+///
+/// # 引数
+/// * `mark` - 透かしの情報
+/// * `original` - ロゴ画像の幅と高さ
+/// * `scaled` - 重畳時のロゴの幅と高さ
+///
+/// # 戻り値
+/// シャープ化の指定があり、かつロゴを元の大きさの半分未満に縮小する場合は
+/// 強度を返す。それ以外の場合は`None`を返す。
+///
+fn logo_sharpen(mark: &Watermark, original: (u32, u32), scaled: (u32, u32))
+    -> Option<f32>
+{
+    mark.sharpen().filter(|_| {
+        (scaled.0 as f32 / original.0 as f32) < sharpen::LOGO_SHARPEN_RATIO
+    })
+}
+
+///
+/// ロゴの左上の座標の決定
+///
+/// # 引数
+/// * `mark` - 透かしの情報
+/// * `position` - 配置位置(自動配置で選んだものを含む)
+/// * `(width, height)` - ロゴを重畳する画像の幅と高さ
+/// * `logo` - 重畳時のロゴの幅と高さ
+///
+/// # 戻り値
+/// ロゴの左上の座標をタプルで返す。
+///
+fn logo_origin(
+    mark: &Watermark,
+    position: Position,
+    (width, height): (u32, u32),
+    logo: (u32, u32),
+) -> (i64, i64) {
+    if let Some(offset) = mark.offset() {
+        // 座標の指定がある場合はそれに従う
+        return offset.position((width, height), logo);
+    }
+
+    // 余白を除いた領域の中で配置位置を決める
+    let (margin_x, margin_y) = mark.margin().pixels((width, height));
+
+    let (x, y) = anchor_position(
+        position,
+        (
+            width.saturating_sub(margin_x * 2),
+            height.saturating_sub(margin_y * 2)
+        ),
+        logo
+    );
+
+    (x + margin_x as i64, y + margin_y as i64)
+}
+
+///
+/// ロゴを配置する領域の決定
+///
+/// # 引数
+/// * `mark` - 透かしの情報
+/// * `position` - 配置位置(自動配置で選んだものを含む)
+/// * `image` - ロゴを重畳する画像の幅と高さ
+///
+/// # 戻り値
+/// 通常のロゴ(回転後)を配置する領域の左上の座標と大きさをタプルで返す。敷
+/// き詰める場合は画像全体を返す。
+///
+/// # 注記
+/// 背景の明るさを調べるためのものなので、パノラマ画像で繰り返す場合も配置
+/// 位置で決まる1つ目の領域のみを返す。
+///
+fn logo_region(mark: &Watermark, position: Position, image: (u32, u32))
+    -> (i64, i64, u32, u32)
+{
+    if mark.is_tile() {
+        return (0, 0, image.0, image.1);
+    }
+
+    let size = logo_size(mark, mark.image().dimensions(), image);
+    let size = match mark.rotation() {
+        Some(degrees) => tiling::rotated_size(size, degrees),
+        None => size,
+    };
+
+    let (x, y) = logo_origin(mark, position, image, size);
+
+    (x, y, size.0, size.1)
+}
+
+///
+/// パノラマ画像でロゴを繰り返す際のずらし量の列挙
+///
+/// # 引数
+/// * `mark` - 透かしの情報
+/// * `image` - ロゴを重畳する画像の幅と高さ
+/// * `left` - 配置位置で決まるロゴの左端のX座標
+/// * `logo_width` - 重畳時のロゴの幅
+///
+/// # 戻り値
+/// 配置位置からの水平方向のずらし量を、画像に掛かるものに限って返す。繰り
+/// 返さない場合は`0`のみを返す。
+///
+/// # 注記
+/// 配置位置で決めたロゴを基準に左右へ同じ間隔で並べるので、配置位置の指定
+/// (左寄せ・中央・右寄せ)はロゴの並びの位相として働く。
+///
+fn logo_repeats(
+    mark: &Watermark,
+    image: (u32, u32),
+    left: i64,
+    logo_width: f32,
+) -> Vec<i64> {
+    let Some(interval) = mark.panorama_interval(image) else {
+        return vec![0];
+    };
+
+    let interval = interval as i64;
+    let logo_width = logo_width.ceil() as i64;
+    let first = -(left + logo_width).div_euclid(interval) - 1;
+    let last = (image.0 as i64 - left).div_euclid(interval);
+
+    (first..=last)
+        .map(|step| step * interval)
+        .filter(|shift| {
+            left + shift + logo_width > 0 && left + shift < image.0 as i64
+        })
+        .collect()
+}
+
+///
+/// 重畳時のロゴの大きさの決定(小数点以下を丸めない)
+///
+/// # 引数
+/// * `mark` - 透かしの情報
+/// * `logo` - ロゴ画像の幅と高さ
+/// * `image` - ロゴを重畳する画像の幅と高さ
+///
+/// # 戻り値
+/// `logo_size()`と同じ(ただし小数点以下を含み、回転させる場合は回転後の外
+/// 接矩形の大きさ)。
+///
+fn logo_exact_size(mark: &Watermark, logo: (u32, u32), image: (u32, u32))
+    -> (f32, f32)
+{
+    let size = match mark.scale() {
+        Some(scale) => scale.exact_size(logo, image),
+        None => (logo.0 as f32, logo.1 as f32),
+    };
+    let size = logo_fit(mark, size, image);
+
+    // 回転させる場合は回転後の外接矩形の大きさにする
+    match mark.rotation() {
+        Some(degrees) => tiling::rotated_exact_size(size, degrees),
+        None => size,
+    }
+}
+
+///
+/// ロゴの左上の座標の決定(小数点以下を丸めない)
+///
+/// # 引数
+/// * `mark` - 透かしの情報
+/// * `position` - 配置位置(自動配置で選んだものを含む)
+/// * `(width, height)` - ロゴを重畳する画像の幅と高さ
+/// * `logo` - 重畳時のロゴの幅と高さ(小数点以下を含む)
+///
+/// # 戻り値
+/// ロゴの左上の座標をタプルで返す。
+///
+fn logo_exact_origin(
+    mark: &Watermark,
+    position: Position,
+    (width, height): (u32, u32),
+    logo: (f32, f32),
+) -> (f32, f32) {
+    if let Some(offset) = mark.offset() {
+        return offset.exact_position((width, height), logo);
+    }
+
+    let (margin_x, margin_y) = mark.margin().exact_pixels((width, height));
+    let right = (width as f32 - margin_x * 2.0).max(0.0) - logo.0;
+    let bottom = (height as f32 - margin_y * 2.0).max(0.0) - logo.1;
+
+    let (x, y) = match position {
+        Position::TopLeft => (0.0, 0.0),
+        Position::TopRight => (right, 0.0),
+        Position::BottomLeft => (0.0, bottom),
+        Position::BottomRight | Position::Auto => (right, bottom),
+        Position::Center => (right / 2.0, bottom / 2.0),
+        Position::TopCenter => (right / 2.0, 0.0),
+        Position::BottomCenter => (right / 2.0, bottom),
+        Position::LeftCenter => (0.0, bottom / 2.0),
+        Position::RightCenter => (right, bottom / 2.0),
+    };
+
+    (x + margin_x, y + margin_y)
+}
+
+///
+/// 配置位置に応じた座標の算出
+///
+/// # 引数
+/// * `position` - 配置位置
+/// * `outer` - 配置先の領域のサイズ(幅と高さのタプル)
+/// * `inner` - 配置する画像のサイズ(幅と高さのタプル)
+///
+/// # 戻り値
+/// 配置する画像の左上の座標をタプルで返す。
+///
+/// # 注記
+/// 自動配置(`Position::Auto`)は背景を調べずに決める場合の既定として右下
+/// に配置する。
+///
+fn anchor_position(position: Position, outer: (u32, u32), inner: (u32, u32))
+    -> (i64, i64)
+{
+    let right = outer.0 as i64 - inner.0 as i64;
+    let bottom = outer.1 as i64 - inner.1 as i64;
+
+    match position {
+        Position::TopLeft => (0, 0),
+        Position::TopRight => (right, 0),
+        Position::BottomLeft => (0, bottom),
+        Position::BottomRight | Position::Auto => (right, bottom),
+        Position::Center => (right / 2, bottom / 2),
+        Position::TopCenter => (right / 2, 0),
+        Position::BottomCenter => (right / 2, bottom),
+        Position::LeftCenter => (0, bottom / 2),
+        Position::RightCenter => (right, bottom / 2),
+    }
+}
+
+///
+/// 画像データのリサイズ　
+///
+/// # 引数
+/// * `width` - ターゲットサイズの幅(ピクセル数)
+/// * `height` - ターゲットサイズの高さ(ピクセル数) 
+/// * `image` - リサイズ元の画像データ
+///
+/// # 戻り値
+/// リサイズに成功した場合は、リサイズされた画像データを`Ok()`でラップして返す。
+/// 処理に失敗した場合はエラー情報を`Err()`でラップして返す。
+///
+fn resize_image<T>(width: u32, height: u32, image: WorkImage<T>)
+    -> Result<WorkImage<T>>
+where 
+    T: WorkPixel,
 {
-    let mut src = Image::from_vec_u8(
+    let src = Image::from_vec_u8(
         image.width(),
         image.height(),
-        image.into_raw(),
-        PixelType::U8x4
+        T::into_bytes(image.into_raw()),
+        T::PIXEL_TYPE
     )?;
 
-    let mut dst = Image::new(width, height, PixelType::U8x4);
+    let mut dst = Image::new(width, height, T::PIXEL_TYPE);
 
     let mut resizer = Resizer::new();
     let resize_opts = ResizeOptions::new()
         .resize_alg(ResizeAlg::Convolution(FilterType::Lanczos3));
 
-    resizer.resize(&mut src, &mut dst, &resize_opts)?;
+    resizer.resize(&src, &mut dst, &resize_opts)?;
 
-    Ok(RgbaImage::from_raw(width, height, dst.into_vec()).unwrap())
+    Ok(WorkImage::from_raw(width, height, T::from_bytes(dst.into_vec()))
+        .unwrap())
 }
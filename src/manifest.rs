@@ -0,0 +1,298 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 監査用の実行記録(ランマニフェスト)関連の処理をまとめたモジュール
+//!
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::Result;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::cmd_args::Options;
+use crate::Processed;
+
+///
+/// 1ファイル分の処理結果の記録
+///
+#[derive(Debug, Serialize)]
+struct FileRecord {
+    /// 入力ファイルへのパス
+    input: String,
+
+    /// 入力ファイルの内容のSHA-256ハッシュ
+    input_sha256: String,
+
+    /// 出力ファイルへのパス(タイルに分割した場合は記録しない)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+
+    /// タイルに分割した場合のタイルごとの出力ファイルへのパスのリスト
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tiles: Vec<String>,
+
+    /// 処理結果("processed", "copied", "skipped", "failed"のいずれか)
+    outcome: &'static str,
+
+    /// 処理に失敗した場合のエラー内容
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+
+    /// 処理中に発生した警告のリスト
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+
+    /// 処理に要した時間(ミリ秒)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elapsed_ms: Option<u64>,
+}
+
+///
+/// 1回の実行分の記録
+///
+#[derive(Debug, Serialize)]
+struct RunRecord {
+    /// 実行開始時刻(RFC 3339形式、UTC)
+    started_at: String,
+
+    /// 実行終了時刻(RFC 3339形式、UTC)
+    finished_at: String,
+
+    /// ツールのバージョン
+    version: &'static str,
+
+    /// 起動時のコマンドライン
+    args: Vec<String>,
+
+    /// 実効設定のSHA-256ハッシュ
+    config_sha256: String,
+
+    /// 入力ファイル集合のSHA-256ハッシュ
+    input_set_sha256: String,
+
+    /// 実行全体の結果("ok"または"failed")
+    status: &'static str,
+
+    /// 各ファイルの処理結果
+    files: Vec<FileRecord>,
+}
+
+///
+/// バイト列のSHA-256ハッシュの16進文字列表現の生成
+///
+fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+///
+/// ファイルの内容のSHA-256ハッシュの算出
+///
+/// # 引数
+/// * `path` - 対象ファイルへのパス
+///
+/// # 戻り値
+/// 算出に成功した場合はハッシュの16進文字列表現を`Ok()`でラップして返す。失
+/// 敗した場合はエラー情報を`Err()`でラップして返す。
+///
+fn file_digest(path: &Path) -> Result<String> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+
+    loop {
+        let len = reader.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+
+        hasher.update(&buf[..len]);
+    }
+
+    Ok(to_hex(&hasher.finalize()))
+}
+
+///
+/// 実行記録をJSON Lines形式のファイルに追記する構造体
+///
+pub(crate) struct RunManifest {
+    /// 書き込み先のファイルへのパス
+    path: PathBuf,
+
+    /// 実行開始時刻
+    started_at: SystemTime,
+
+    /// 実効設定のハッシュ
+    config_sha256: String,
+
+    /// 入力ファイル集合のハッシュ
+    input_set_sha256: String,
+
+    /// 入力ファイル毎の内容のハッシュ
+    digests: HashMap<PathBuf, String>,
+
+    /// 各ファイルの処理結果
+    files: Vec<FileRecord>,
+}
+
+/// RunManifestに対する実装
+impl RunManifest {
+    ///
+    /// オブジェクトの生成
+    ///
+    /// # 引数
+    /// * `path` - 書き込み先のファイルへのパス
+    /// * `opts` - オプション情報をパックしたオブジェクト
+    /// * `inputs` - 処理対象の入力ファイルのリスト
+    ///
+    /// # 戻り値
+    /// 生成したオブジェクトを`Ok()`でラップして返す。入力ファイルの読み込み
+    /// に失敗した場合はエラー情報を`Err()`でラップして返す。
+    ///
+    /// # 注記
+    /// 入力ファイル集合のハッシュは、パス順に並べた各ファイルのパスと内容の
+    /// ハッシュから算出する。
+    ///
+    pub(crate) fn new<P>(path: P, opts: &Options, inputs: &[PathBuf])
+        -> Result<Self>
+    where
+        P: AsRef<Path>
+    {
+        let started_at = SystemTime::now();
+
+        let mut hasher = Sha256::new();
+        for (key, value) in opts.effective_settings() {
+            hasher.update(format!("{}={}\n", key, value));
+        }
+        let config_sha256 = to_hex(&hasher.finalize());
+
+        let mut sorted = inputs.to_vec();
+        sorted.sort();
+
+        let mut hasher = Sha256::new();
+        let mut digests = HashMap::new();
+        for input in sorted {
+            let digest = file_digest(&input)?;
+            hasher.update(format!("{}\0{}\n", input.display(), digest));
+            digests.insert(input, digest);
+        }
+        let input_set_sha256 = to_hex(&hasher.finalize());
+
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            started_at,
+            config_sha256,
+            input_set_sha256,
+            digests,
+            files: Vec::new(),
+        })
+    }
+
+    ///
+    /// 1ファイル分の処理結果の記録
+    ///
+    /// # 引数
+    /// * `input_path` - 入力ファイルへのパス
+    /// * `result` - `proc_file()`の処理結果
+    ///
+    pub(crate) fn record(
+        &mut self,
+        input_path: &Path,
+        result: &Result<Option<Processed>>,
+    ) {
+        let (mut output, outcome, error) = match result {
+            Ok(Some(processed)) => (
+                Some(processed.output_path.to_string_lossy().into_owned()),
+                if processed.copied { "copied" } else { "processed" },
+                None,
+            ),
+
+            Ok(None) => (None, "skipped", None),
+            Err(err) => (None, "failed", Some(err.to_string())),
+        };
+
+        // タイルに分割した場合は分割前の出力先には書き出していない
+        let tiles = match result {
+            Ok(Some(processed)) if !processed.tiles.is_empty() => {
+                output = None;
+                processed.outputs()
+                    .into_iter()
+                    .map(|(path, _)| path.to_string_lossy().into_owned())
+                    .collect()
+            }
+
+            _ => Vec::new(),
+        };
+
+        let (warnings, elapsed_ms) = match result {
+            Ok(Some(processed)) => (
+                processed.warnings.clone(),
+                Some(processed.elapsed.as_millis() as u64),
+            ),
+
+            _ => (Vec::new(), None),
+        };
+
+        self.files.push(FileRecord {
+            input: input_path.to_string_lossy().into_owned(),
+            input_sha256: self.digests
+                .get(input_path)
+                .cloned()
+                .unwrap_or_default(),
+            output,
+            tiles,
+            outcome,
+            error,
+            warnings,
+            elapsed_ms,
+        });
+    }
+
+    ///
+    /// 実行記録の追記
+    ///
+    /// # 引数
+    /// * `succeeded` - 実行全体が成功したか否か
+    ///
+    /// # 戻り値
+    /// 書き込みに成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を
+    /// `Err()`でラップして返す。
+    ///
+    /// # 注記
+    /// 1回の実行を1行のJSONとしてファイルの末尾に追記する。既存の内容は変更
+    /// しない。
+    ///
+    pub(crate) fn finish(self, succeeded: bool) -> Result<()> {
+        let record = RunRecord {
+            started_at: humantime::format_rfc3339_seconds(self.started_at)
+                .to_string(),
+            finished_at: humantime::format_rfc3339_seconds(SystemTime::now())
+                .to_string(),
+            version: env!("CARGO_PKG_VERSION"),
+            args: std::env::args_os()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            config_sha256: self.config_sha256,
+            input_set_sha256: self.input_set_sha256,
+            status: if succeeded { "ok" } else { "failed" },
+            files: self.files,
+        };
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        writeln!(file, "{}", serde_json::to_string(&record)?)?;
+
+        Ok(())
+    }
+}
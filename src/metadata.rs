@@ -0,0 +1,220 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! メタデータのエクスポート関連の処理をまとめたモジュール
+//!
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use exif::{Exif, In, Tag};
+
+use crate::palette;
+
+///
+/// エクスポート対象のEXIFフィールド(列名とタグのペア)
+///
+const EXPORT_FIELDS: &[(&str, Tag)] = &[
+    ("date_time_original", Tag::DateTimeOriginal),
+    ("make", Tag::Make),
+    ("model", Tag::Model),
+    ("lens_model", Tag::LensModel),
+    ("exposure_time", Tag::ExposureTime),
+    ("f_number", Tag::FNumber),
+    ("iso", Tag::PhotographicSensitivity),
+    ("focal_length", Tag::FocalLength),
+    ("artist", Tag::Artist),
+    ("copyright", Tag::Copyright),
+];
+
+///
+/// CSV形式のフィールド値のエスケープ
+///
+/// # 引数
+/// * `value` - エスケープ対象の文字列
+///
+/// # 戻り値
+/// カンマ、ダブルクォート、改行を含む場合はダブルクォートで括った文字列を返
+/// す。それ以外の場合は引数をそのまま返す。
+///
+fn escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+///
+/// EXIF情報の読み込み
+///
+/// # 引数
+/// * `path` - 対象ファイルへのパス
+///
+/// # 戻り値
+/// EXIF情報を読み出せた場合は`Some()`でラップして返す。EXIF情報が存在しない
+/// 場合は`None`を返す。
+///
+pub(crate) fn read_exif<P>(path: P) -> Option<Exif>
+where
+    P: AsRef<Path>
+{
+    let mut buf = BufReader::new(File::open(path).ok()?);
+    exif::Reader::new().read_from_container(&mut buf).ok()
+}
+
+///
+/// EXIFフィールドの値の文字列化
+///
+/// # 引数
+/// * `exif` - EXIF情報
+/// * `tag` - 対象のタグ
+///
+/// # 戻り値
+/// フィールドが存在する場合は単位付きの表示用の文字列(前後のダブルクォー
+/// トは除く)を返す。存在しない場合は`None`を返す。
+///
+pub(crate) fn field_value(exif: &Exif, tag: Tag) -> Option<String> {
+    exif.get_field(tag, In::PRIMARY).map(|field| {
+        field.display_value()
+            .with_unit(exif)
+            .to_string()
+            .trim_matches('"')
+            .to_string()
+    })
+}
+
+///
+/// メタデータをCSVファイルに書き出す構造体
+///
+pub(crate) struct MetadataExporter {
+    /// 書き込み先
+    writer: BufWriter<File>,
+
+    /// パレットの列を出力するか否か
+    with_palette: bool,
+
+    /// 代替テキストの列を出力するか否か
+    with_alt_text: bool,
+}
+
+/// MetadataExporterに対する実装
+impl MetadataExporter {
+    ///
+    /// オブジェクトの生成
+    ///
+    /// # 引数
+    /// * `path` - 書き込み先のCSVファイルへのパス
+    /// * `with_palette` - 支配的な色とパレットの列を追加するか否か
+    /// * `with_alt_text` - 代替テキストの列を追加するか否か
+    ///
+    /// # 戻り値
+    /// ヘッダ行を書き込んだ上で生成したオブジェクトを`Ok()`でラップして返す。
+    /// 失敗した場合はエラー情報を`Err()`でラップして返す。
+    ///
+    pub(crate) fn create<P>(path: P, with_palette: bool, with_alt_text: bool)
+        -> Result<Self>
+    where
+        P: AsRef<Path>
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        let mut header = vec![
+            "input_path", "output_path", "output_width", "output_height"
+        ];
+        header.extend(EXPORT_FIELDS.iter().map(|(name, _)| *name));
+
+        if with_palette {
+            header.extend(["dominant_color", "palette"]);
+        }
+
+        if with_alt_text {
+            header.push("alt_text");
+        }
+
+        writeln!(writer, "{}", header.join(","))?;
+
+        Ok(Self {writer, with_palette, with_alt_text})
+    }
+
+    ///
+    /// 処理済み画像1件分の行の書き込み
+    ///
+    /// # 引数
+    /// * `input_path` - 入力ファイルへのパス
+    /// * `output_path` - 出力ファイルへのパス
+    /// * `(width, height)` - 出力画像の幅と高さ(ピクセル数)
+    /// * `colors` - 出力画像から抽出したパレット(画素数の多い順)
+    /// * `alt_text` - 出力画像の代替テキスト
+    ///
+    /// # 戻り値
+    /// 書き込みに成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を
+    /// `Err()`でラップして返す。
+    ///
+    /// # 注記
+    /// パレットは"#RRGGBB"形式の色を空白で区切って1列に書き出す。そのままコ
+    /// ピーした画像などパレットが空の場合は空欄にする。
+    ///
+    pub(crate) fn write<P, Q>(
+        &mut self,
+        input_path: P,
+        output_path: Q,
+        (width, height): (u32, u32),
+        colors: &[[u8; 3]],
+        alt_text: Option<&str>,
+    ) -> Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let exif = read_exif(&input_path);
+
+        let mut row = vec![
+            escape(&input_path.as_ref().display().to_string()),
+            escape(&output_path.as_ref().display().to_string()),
+            width.to_string(),
+            height.to_string(),
+        ];
+
+        for (_, tag) in EXPORT_FIELDS {
+            let value = exif
+                .as_ref()
+                .and_then(|exif| field_value(exif, *tag))
+                .unwrap_or_default();
+
+            row.push(escape(&value));
+        }
+
+        if self.with_palette {
+            row.push(colors.first().map(palette::to_hex).unwrap_or_default());
+            row.push(
+                colors.iter()
+                    .map(palette::to_hex)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            );
+        }
+
+        if self.with_alt_text {
+            row.push(escape(alt_text.unwrap_or_default()));
+        }
+
+        writeln!(self.writer, "{}", row.join(","))?;
+
+        Ok(())
+    }
+
+    ///
+    /// バッファのフラッシュ
+    ///
+    pub(crate) fn finish(mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 出力画像の支配的な色とパレットの抽出をまとめたモジュール
+//!
+
+use std::cmp::Reverse;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, RgbaImage};
+
+use crate::pixel::OutputImage;
+
+///
+/// 抽出するパレットの色数
+///
+pub(crate) const PALETTE_SIZE: usize = 5;
+
+///
+/// 解析に用いる縮小画像の長辺(ピクセル数)
+///
+const THUMBNAIL_SIZE: u32 = 64;
+
+///
+/// k-means法による色の調整の反復回数
+///
+const REFINE_ITERATIONS: usize = 4;
+
+///
+/// 解析に用いる縮小画像の生成
+///
+/// # 引数
+/// * `image` - 合成後の出力画像
+///
+/// # 戻り値
+/// 長辺を`THUMBNAIL_SIZE`に縮小したRGBA画像を返す。
+///
+pub(crate) fn thumbnail(image: &OutputImage) -> RgbaImage {
+    let image = match image {
+        OutputImage::Rgba(image) => DynamicImage::ImageRgba8(image.clone()),
+        OutputImage::Gray(image) => DynamicImage::ImageLuma8(image.clone()),
+    };
+
+    image.resize(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Triangle)
+        .to_rgba8()
+}
+
+///
+/// パレットの抽出
+///
+/// # 引数
+/// * `image` - 解析する画像(`thumbnail()`で縮小したもの)
+///
+/// # 戻り値
+/// 画素数の多い順に並べた最大`PALETTE_SIZE`色のリストを返す(先頭が支配的
+/// な色となる)。画像の色数が少ない場合は色数がそれより少なくなる。
+///
+/// # 注記
+/// メディアンカット法で初期値を求め、k-means法で各色を所属する画素の平均に
+/// 寄せてから画素数を数える。
+///
+pub(crate) fn extract(image: &RgbaImage) -> Vec<[u8; 3]> {
+    let pixels = image.pixels()
+        .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect::<Vec<_>>();
+
+    if pixels.is_empty() {
+        return Vec::new();
+    }
+
+    /*
+     * メディアンカット法による分割
+     */
+    let mut boxes = vec![pixels.clone()];
+
+    while boxes.len() < PALETTE_SIZE {
+        let Some((index, channel)) = boxes.iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(index, colors)| {
+                let (channel, range) = widest_channel(colors);
+                (index, channel, range)
+            })
+            .filter(|(_, _, range)| *range > 0)
+            .max_by_key(|(_, _, range)| *range)
+            .map(|(index, channel, _)| (index, channel))
+        else {
+            break;
+        };
+
+        let mut colors = boxes.swap_remove(index);
+        colors.sort_unstable_by_key(|color| color[channel]);
+        let upper = colors.split_off(colors.len() / 2);
+
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    let mut centers = boxes.iter()
+        .map(|colors| mean(colors.iter()))
+        .collect::<Vec<_>>();
+
+    /*
+     * k-means法による調整
+     */
+    let mut counts = vec![0usize; centers.len()];
+
+    for iteration in 0..=REFINE_ITERATIONS {
+        let mut sums = vec![([0.0f32; 3], 0usize); centers.len()];
+
+        for color in &pixels {
+            let nearest = nearest(&centers, color);
+            let (sum, count) = &mut sums[nearest];
+
+            for (total, value) in sum.iter_mut().zip(color) {
+                *total += *value as f32;
+            }
+
+            *count += 1;
+        }
+
+        counts = sums.iter().map(|(_, count)| *count).collect();
+
+        if iteration == REFINE_ITERATIONS {
+            break;
+        }
+
+        for (center, (sum, count)) in centers.iter_mut().zip(&sums) {
+            if *count > 0 {
+                *center = sum.map(|v| v / *count as f32);
+            }
+        }
+    }
+
+    /*
+     * 画素数の多い順への並べ替え
+     */
+    let mut palette = centers.into_iter()
+        .zip(counts)
+        .filter(|(_, count)| *count > 0)
+        .collect::<Vec<_>>();
+
+    palette.sort_by_key(|(_, count)| Reverse(*count));
+
+    palette.into_iter()
+        .map(|(center, _)| center.map(|v| v.round().clamp(0.0, 255.0) as u8))
+        .collect()
+}
+
+///
+/// 色の"#RRGGBB"形式の文字列への変換
+///
+pub(crate) fn to_hex(color: &[u8; 3]) -> String {
+    format!("#{:02X}{:02X}{:02X}", color[0], color[1], color[2])
+}
+
+///
+/// 値の範囲が最も広いチャネルの取得
+///
+/// # 戻り値
+/// チャネルの番号と値の範囲のタプルを返す。
+///
+fn widest_channel(colors: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|c| {
+            let min = colors.iter().map(|color| color[c]).min().unwrap_or(0);
+            let max = colors.iter().map(|color| color[c]).max().unwrap_or(0);
+
+            (c, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or((0, 0))
+}
+
+///
+/// 色の平均の算出
+///
+fn mean<'a, I>(colors: I) -> [f32; 3]
+where
+    I: Iterator<Item = &'a [u8; 3]>,
+{
+    let mut sum = [0.0f32; 3];
+    let mut count = 0;
+
+    for color in colors {
+        for (total, value) in sum.iter_mut().zip(color) {
+            *total += *value as f32;
+        }
+
+        count += 1;
+    }
+
+    sum.map(|v| v / count.max(1) as f32)
+}
+
+///
+/// 最も近い色の番号の取得
+///
+fn nearest(centers: &[[f32; 3]], color: &[u8; 3]) -> usize {
+    centers.iter()
+        .enumerate()
+        .map(|(index, center)| {
+            let distance = (0..3)
+                .map(|c| (center[c] - color[c] as f32).powi(2))
+                .sum::<f32>();
+
+            (index, distance)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
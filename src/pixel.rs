@@ -0,0 +1,337 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 作業用のピクセル形式(色深度)関連の処理をまとめたモジュール
+//!
+
+use std::fmt::Display;
+
+use clap::ValueEnum;
+use fast_image_resize::PixelType;
+use image::{DynamicImage, GrayImage, ImageBuffer, LumaA, Pixel, Rgba, RgbaImage};
+use imageproc::definitions::Clamp;
+use serde::Deserialize;
+
+///
+/// 作業用の画像データの型
+///
+pub(crate) type WorkImage<P> = ImageBuffer<P, Vec<<P as Pixel>::Subpixel>>;
+
+///
+/// 16ビット/チャネルのRGBA画像データの型
+///
+pub(crate) type Rgba16Image = WorkImage<Rgba<u16>>;
+
+///
+/// 画像処理パイプラインで使用するピクセル形式を抽象化するトレイト
+///
+pub(crate) trait WorkPixel:
+    Pixel<Subpixel: Into<f32> + Clamp<f32> + Send + Sync>
+    + Send
+    + Sync
+    + 'static
+{
+    /// リサイズ時に使用するピクセル形式
+    const PIXEL_TYPE: PixelType;
+
+    ///
+    /// 8ビット/チャネルのピクセル値からの変換
+    ///
+    fn from_rgba8(pixel: Rgba<u8>) -> Self;
+
+    ///
+    /// 8ビット/チャネルの画像データからの変換
+    ///
+    fn from_rgba8_image(image: &RgbaImage) -> WorkImage<Self>;
+
+    ///
+    /// サブピクセル列からバイト列への変換
+    ///
+    fn into_bytes(raw: Vec<Self::Subpixel>) -> Vec<u8>;
+
+    ///
+    /// バイト列からサブピクセル列への変換
+    ///
+    fn from_bytes(bytes: Vec<u8>) -> Vec<Self::Subpixel>;
+
+    ///
+    /// 汎用の画像データへの変換
+    ///
+    fn into_dynamic(image: WorkImage<Self>) -> DynamicImage;
+}
+
+// 8ビット/チャネル用の実装
+impl WorkPixel for Rgba<u8> {
+    const PIXEL_TYPE: PixelType = PixelType::U8x4;
+
+    fn from_rgba8(pixel: Rgba<u8>) -> Self {
+        pixel
+    }
+
+    fn from_rgba8_image(image: &RgbaImage) -> WorkImage<Self> {
+        image.clone()
+    }
+
+    fn into_bytes(raw: Vec<u8>) -> Vec<u8> {
+        raw
+    }
+
+    fn from_bytes(bytes: Vec<u8>) -> Vec<u8> {
+        bytes
+    }
+
+    fn into_dynamic(image: WorkImage<Self>) -> DynamicImage {
+        DynamicImage::ImageRgba8(image)
+    }
+}
+
+// 16ビット/チャネル用の実装
+impl WorkPixel for Rgba<u16> {
+    const PIXEL_TYPE: PixelType = PixelType::U16x4;
+
+    fn from_rgba8(pixel: Rgba<u8>) -> Self {
+        Rgba(pixel.0.map(|v| v as u16 * 257))
+    }
+
+    fn from_rgba8_image(image: &RgbaImage) -> WorkImage<Self> {
+        let raw = image.as_raw()
+            .iter()
+            .map(|&v| v as u16 * 257)
+            .collect();
+
+        ImageBuffer::from_raw(image.width(), image.height(), raw).unwrap()
+    }
+
+    fn into_bytes(raw: Vec<u16>) -> Vec<u8> {
+        raw.into_iter().flat_map(u16::to_ne_bytes).collect()
+    }
+
+    fn from_bytes(bytes: Vec<u8>) -> Vec<u16> {
+        bytes.chunks_exact(2)
+            .map(|pair| u16::from_ne_bytes([pair[0], pair[1]]))
+            .collect()
+    }
+
+    fn into_dynamic(image: WorkImage<Self>) -> DynamicImage {
+        DynamicImage::ImageRgba16(image)
+    }
+}
+
+// 8ビットのグレースケール(アルファ付き)用の実装
+impl WorkPixel for LumaA<u8> {
+    const PIXEL_TYPE: PixelType = PixelType::U8x2;
+
+    fn from_rgba8(pixel: Rgba<u8>) -> Self {
+        LumaA([crate::analysis::luma(&pixel.0).round() as u8, pixel[3]])
+    }
+
+    fn from_rgba8_image(image: &RgbaImage) -> WorkImage<Self> {
+        ImageBuffer::from_fn(image.width(), image.height(), |x, y| {
+            Self::from_rgba8(*image.get_pixel(x, y))
+        })
+    }
+
+    fn into_bytes(raw: Vec<u8>) -> Vec<u8> {
+        raw
+    }
+
+    fn from_bytes(bytes: Vec<u8>) -> Vec<u8> {
+        bytes
+    }
+
+    fn into_dynamic(image: WorkImage<Self>) -> DynamicImage {
+        DynamicImage::ImageLumaA8(image)
+    }
+}
+
+///
+/// 出力画像の定義
+///
+pub(crate) enum OutputImage {
+    /// RGBA形式の画像
+    Rgba(RgbaImage),
+
+    /// グレースケールの画像
+    Gray(GrayImage),
+}
+
+/// OutputImageに対する実装
+impl OutputImage {
+    ///
+    /// 画像サイズへのアクセサ
+    ///
+    pub(crate) fn dimensions(&self) -> (u32, u32) {
+        match self {
+            Self::Rgba(image) => image.dimensions(),
+            Self::Gray(image) => image.dimensions(),
+        }
+    }
+
+    ///
+    /// RGBA形式の画像への変換
+    ///
+    pub(crate) fn into_rgba8(self) -> RgbaImage {
+        match self {
+            Self::Rgba(image) => image,
+            Self::Gray(image) => DynamicImage::ImageLuma8(image).to_rgba8(),
+        }
+    }
+}
+
+///
+/// デコード済みの入力画像の定義
+///
+pub(crate) enum SourceImage {
+    /// 8ビット/チャネルのRGBA形式の画像
+    Rgba(RgbaImage),
+
+    /// 16ビット/チャネルのRGBA形式の画像
+    Rgba16(Rgba16Image),
+
+    /// グレースケールの画像
+    Gray(GrayImage),
+}
+
+/// SourceImageに対する実装
+impl SourceImage {
+    ///
+    /// 画像サイズへのアクセサ
+    ///
+    pub(crate) fn dimensions(&self) -> (u32, u32) {
+        match self {
+            Self::Rgba(image) => image.dimensions(),
+            Self::Rgba16(image) => image.dimensions(),
+            Self::Gray(image) => image.dimensions(),
+        }
+    }
+}
+
+///
+/// 色深度削減時のディザリング方式の定義
+///
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[clap(rename_all = "lower")]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Dither {
+    /// ディザリング無し(四捨五入)
+    None,
+
+    /// 8x8 Bayer行列による組織的ディザリング
+    Ordered,
+
+    /// 低相関ノイズ(Interleaved Gradient Noise)によるディザリング
+    Noise,
+}
+
+// Displayトレイトの実装
+impl Display for Dither {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Self::None => "none",
+            Self::Ordered => "ordered",
+            Self::Noise => "noise",
+        })
+    }
+}
+
+///
+/// 8x8 Bayer行列
+///
+const BAYER8: [[u8; 8]; 8] = [
+    [ 0, 32,  8, 40,  2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44,  4, 36, 14, 46,  6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [ 3, 35, 11, 43,  1, 33,  9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47,  7, 39, 13, 45,  5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+///
+/// ディザリングの閾値の算出
+///
+/// # 引数
+/// * `dither` - ディザリング方式
+/// * `x` - ピクセルのX座標
+/// * `y` - ピクセルのY座標
+///
+/// # 戻り値
+/// 0.0以上1.0未満の閾値を返す。ディザリング無しの場合は0.5を返す。
+///
+fn threshold(dither: Dither, x: u32, y: u32) -> f32 {
+    match dither {
+        Dither::None => 0.5,
+
+        Dither::Ordered => {
+            (BAYER8[(y % 8) as usize][(x % 8) as usize] as f32 + 0.5) / 64.0
+        }
+
+        // Jorge Jimenez, "Next Generation Post Processing in Call of Duty:
+        // Advanced Warfare" (SIGGRAPH 2014) のInterleaved Gradient Noise
+        Dither::Noise => {
+            let v = 0.06711056 * x as f32 + 0.00583715 * y as f32;
+            (52.982918 * v.fract()).fract()
+        }
+    }
+}
+
+///
+/// 16ビット/チャネルの画像データから8ビット/チャネルへの変換
+///
+/// # 引数
+/// * `image` - 変換元の画像データ
+/// * `dither` - 変換時に適用するディザリング方式
+///
+/// # 戻り値
+/// 8ビット/チャネルに変換した画像データを返す。
+///
+/// # 注記
+/// 階調の段差(バンディング)を抑えるため、カラーチャネルには指定された方式で
+/// ディザリングを適用する。アルファチャネルは常に四捨五入で丸める。
+///
+pub(crate) fn reduce_to_rgba8(image: &Rgba16Image, dither: Dither)
+    -> RgbaImage
+{
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let src = image.get_pixel(x, y).0;
+        let bias = threshold(dither, x, y);
+        let mut dst = [0u8; 4];
+
+        for (i, value) in src.iter().enumerate() {
+            dst[i] = if i == 3 {
+                ((*value as u32 + 128) / 257) as u8
+            } else {
+                (*value as f32 / 257.0 + bias).floor().min(255.0) as u8
+            };
+        }
+
+        Rgba(dst)
+    })
+}
+
+///
+/// sRGBの値(0.0〜1.0)からリニアな値への変換
+///
+pub(crate) fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+///
+/// リニアな値からsRGBの値(0.0〜1.0)への変換
+///
+pub(crate) fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,274 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 背景の解析に基づくロゴの配置位置の自動選択をまとめたモジュール
+//!
+
+use std::hash::{BuildHasher, RandomState};
+use std::path::Path;
+use std::sync::LazyLock;
+
+use sha2::{Digest, Sha256};
+
+use crate::analysis;
+use crate::cmd_args::Position;
+use crate::pixel::{WorkImage, WorkPixel};
+
+///
+/// 自動配置で評価する配置位置の候補
+///
+/// # 注記
+/// 評価値が同じ場合は先に並べたものを優先する(四隅を辺の中央より優先し、
+/// 四隅の中では既定の右下を優先する)。
+///
+const CANDIDATES: &[Position] = &[
+    Position::BottomRight,
+    Position::BottomLeft,
+    Position::TopRight,
+    Position::TopLeft,
+    Position::BottomCenter,
+    Position::TopCenter,
+    Position::RightCenter,
+    Position::LeftCenter,
+];
+
+///
+/// 評価値における輝度の標準偏差の重み(隣接画素間の輝度差の平均に対する)
+///
+const DEVIATION_WEIGHT: f32 = 0.5;
+
+///
+/// 領域の評価で一辺あたりに調べる画素数の目安
+///
+const SAMPLES: u32 = 48;
+
+///
+/// 乱数の種の指定が無い場合に用いる実行ごとの種
+///
+static RUN_SEED: LazyLock<u64> =
+    LazyLock::new(|| RandomState::new().hash_one("watermarker"));
+
+///
+/// 背景の解析に基づく透かし1つ分の選択結果を格納する構造体
+///
+/// # 注記
+/// 透かしのレイヤーはこの選択結果ごとに生成するので、レイヤーのキャッシュ
+/// のキーにも用いる。
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub(crate) struct LogoChoice {
+    /// 明るい背景用のロゴを用いるか否か
+    pub(crate) dark: bool,
+
+    /// 自動配置で選んだ配置位置(自動配置でない場合は`None`)
+    pub(crate) position: Option<Position>,
+
+    /// ロゴをずらす量(水平方向と垂直方向のピクセル数)
+    pub(crate) jitter: (i64, i64),
+}
+
+///
+/// 最も細部の少ない配置位置の選択
+///
+/// # 引数
+/// * `image` - ロゴを重畳する画像(リサイズ済みのもの)
+/// * `region` - 配置位置からロゴを配置する領域(左上の座標と大きさ)を求め
+///   るクロージャ
+///
+/// # 戻り値
+/// 候補のうち、領域の評価値が最も小さい配置位置を返す。
+///
+pub(crate) fn least_busy<T, F>(image: &WorkImage<T>, region: F) -> Position
+where
+    T: WorkPixel,
+    F: Fn(Position) -> (i64, i64, u32, u32),
+{
+    CANDIDATES.iter()
+        .filter_map(|position| {
+            busyness(image, region(*position)).map(|score| (*position, score))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(position, _)| position)
+        .unwrap_or(Position::BottomRight)
+}
+
+///
+/// 領域の細かさの評価
+///
+/// # 引数
+/// * `image` - 対象の画像
+/// * `(x, y, width, height)` - 領域の左上の座標と大きさ
+///
+/// # 戻り値
+/// 隣接画素間の輝度差の平均(エッジの密度)に輝度の標準偏差を重み付けして加
+/// えた値を返す。平坦な領域ほど小さくなる。領域が画像からはみ出す場合は
+/// `None`を返す(候補から外す)。
+///
+/// # 注記
+/// 大きな領域では一辺あたり`SAMPLES`画素程度に間引いて調べる。
+///
+fn busyness<T>(
+    image: &WorkImage<T>,
+    (x, y, width, height): (i64, i64, u32, u32),
+) -> Option<f32>
+where
+    T: WorkPixel,
+{
+    if x < 0
+        || y < 0
+        || x + width as i64 > image.width() as i64
+        || y + height as i64 > image.height() as i64
+        || width < 2
+        || height < 2
+    {
+        return None;
+    }
+
+    /*
+     * 輝度の標本の取得
+     */
+    let step = (width.max(height) / SAMPLES).max(1);
+    let cols = width.div_ceil(step) as usize;
+    let samples = (0..height)
+        .step_by(step as usize)
+        .flat_map(|dy| {
+            (0..width).step_by(step as usize).map(move |dx| (dx, dy))
+        })
+        .map(|(dx, dy)| {
+            let pixel = image.get_pixel(x as u32 + dx, y as u32 + dy);
+            analysis::pixel_luma(pixel)
+        })
+        .collect::<Vec<_>>();
+
+    let rows = samples.len() / cols;
+
+    /*
+     * エッジの密度(隣接する標本間の輝度差の平均)
+     */
+    let mut gradient = 0.0f32;
+    let mut count = 0usize;
+
+    for row in 0..rows {
+        for col in 0..cols {
+            let center = samples[row * cols + col];
+
+            if col + 1 < cols {
+                gradient += (center - samples[row * cols + col + 1]).abs();
+                count += 1;
+            }
+
+            if row + 1 < rows {
+                gradient += (center - samples[(row + 1) * cols + col]).abs();
+                count += 1;
+            }
+        }
+    }
+
+    let gradient = gradient / count.max(1) as f32;
+
+    /*
+     * 輝度の分散
+     */
+    let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+    let variance = samples.iter()
+        .map(|luma| (luma - mean).powi(2))
+        .sum::<f32>() / samples.len() as f32;
+
+    Some(gradient + variance.sqrt() * DEVIATION_WEIGHT)
+}
+
+///
+/// 入力ファイルに対するロゴをずらす量の鍵の算出
+///
+/// # 引数
+/// * `seed` - 乱数の種(`None`の場合は実行ごとの種を用いる)
+/// * `input_path` - 入力ファイルへのパス
+///
+/// # 戻り値
+/// 種とファイル名から求めた鍵を返す。
+///
+/// # 注記
+/// ディレクトリの位置に依らず同じ結果になるように、パスのうちファイル名の
+/// みを用いる。
+///
+pub(crate) fn jitter_key(seed: Option<u64>, input_path: &Path) -> u64 {
+    let name = input_path.file_name().unwrap_or(input_path.as_os_str());
+    let mut hasher = Sha256::new();
+
+    hasher.update(seed.unwrap_or(*RUN_SEED).to_le_bytes());
+    hasher.update(name.as_encoded_bytes());
+
+    let digest = hasher.finalize();
+    u64::from_le_bytes(digest[..8].try_into().unwrap())
+}
+
+///
+/// ロゴをずらす量の算出
+///
+/// # 引数
+/// * `key` - 入力ファイルに対する鍵(`jitter_key()`で求めたもの)
+/// * `index` - 透かしの番号
+/// * `(range_x, range_y)` - 水平方向と垂直方向にずらす範囲(ピクセル数)
+///
+/// # 戻り値
+/// `-range`以上`range`以下の範囲で、水平方向と垂直方向にずらすピクセル数を
+/// タプルで返す。
+///
+pub(crate) fn jitter(key: u64, index: usize, (range_x, range_y): (u32, u32))
+    -> (i64, i64)
+{
+    let mut hasher = Sha256::new();
+
+    hasher.update(key.to_le_bytes());
+    hasher.update((index as u64).to_le_bytes());
+
+    let digest = hasher.finalize();
+    let pick = |bytes: &[u8], range: u32| {
+        let value = u64::from_le_bytes(bytes.try_into().unwrap());
+        let span = range as u64 * 2 + 1;
+
+        (value % span) as i64 - range as i64
+    };
+
+    (pick(&digest[..8], range_x), pick(&digest[8..16], range_y))
+}
+
+///
+/// ずらした後のロゴの左上の座標の算出
+///
+/// # 引数
+/// * `origin` - ずらす前のロゴの左上の座標
+/// * `(dx, dy)` - ずらす量
+/// * `logo` - 重畳時のロゴの幅と高さ
+/// * `image` - ロゴを重畳する画像の幅と高さ
+///
+/// # 戻り値
+/// ずらした後の座標を返す。ずらす前に画像に収まっていた方向は、画像からは
+/// み出さないように座標を制限する。
+///
+pub(crate) fn jittered(
+    origin: (f32, f32),
+    (dx, dy): (i64, i64),
+    logo: (f32, f32),
+    image: (u32, u32),
+) -> (f32, f32) {
+    let shift = |pos: f32, delta: i64, size: f32, limit: u32| {
+        let limit = limit as f32 - size;
+        let moved = pos + delta as f32;
+
+        if pos >= 0.0 && pos <= limit {
+            moved.clamp(0.0, limit)
+        } else {
+            moved
+        }
+    };
+
+    (
+        shift(origin.0, dx, logo.0, image.0),
+        shift(origin.1, dy, logo.1, image.1),
+    )
+}
@@ -0,0 +1,290 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 出力ごとに実行する後処理コマンドの管理をまとめたモジュール
+//!
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::cmd_args::PostFailure;
+
+///
+/// 後処理コマンドの実行を管理する構造体
+///
+/// # 注記
+/// コマンドは出力ファイルの書き込みを終えるたびに起動し、同時に実行する数が
+/// 上限に達した場合は最も古いものの終了を待ってから次を起動する。
+///
+pub(crate) struct PostCommand {
+    /// コマンドのテンプレート
+    template: String,
+
+    /// 同時に実行するコマンドの数の上限
+    jobs: usize,
+
+    /// コマンドが失敗した場合の扱い
+    on_failure: PostFailure,
+
+    /// 実行中のコマンド(出力ファイルへのパスとプロセス)のリスト
+    running: VecDeque<(PathBuf, Child)>,
+}
+
+/// PostCommandに対する実装
+impl PostCommand {
+    ///
+    /// オブジェクトの生成
+    ///
+    /// # 引数
+    /// * `template` - コマンドのテンプレート
+    /// * `jobs` - 同時に実行するコマンドの数の上限(0は1として扱う)
+    /// * `on_failure` - コマンドが失敗した場合の扱い
+    ///
+    pub(crate) fn new(template: &str, jobs: usize, on_failure: PostFailure)
+        -> Self
+    {
+        Self {
+            template: template.to_string(),
+            jobs: jobs.max(1),
+            on_failure,
+            running: VecDeque::new(),
+        }
+    }
+
+    ///
+    /// 出力ファイル1つ分のコマンドの起動
+    ///
+    /// # 引数
+    /// * `input_path` - 入力ファイルへのパス
+    /// * `output_path` - 出力ファイルへのパス
+    ///
+    /// # 戻り値
+    /// 起動に成功した場合は`Ok(())`を返す。起動できなかった場合と、終了を待っ
+    /// たコマンドが失敗し、失敗を中断として扱う場合はエラー情報を`Err()`で
+    /// ラップして返す。
+    ///
+    pub(crate) fn spawn(&mut self, input_path: &Path, output_path: &Path)
+        -> Result<()>
+    {
+        while self.running.len() >= self.jobs {
+            self.wait_oldest()?;
+        }
+
+        let child = command(&self.template, input_path, output_path)
+            .stdin(Stdio::null())
+            .spawn()
+            .with_context(|| {
+                format!("cannot run post command: {}", self.template)
+            })?;
+
+        self.running.push_back((output_path.to_path_buf(), child));
+
+        Ok(())
+    }
+
+    ///
+    /// 実行中の全てのコマンドの終了待ち
+    ///
+    /// # 戻り値
+    /// 全てのコマンドが成功した場合(または失敗を警告として扱う場合)は`Ok(())`
+    /// を返す。失敗を中断として扱う場合は、全ての終了を待った上で最初の失敗
+    /// のエラー情報を`Err()`でラップして返す。
+    ///
+    pub(crate) fn finish(mut self) -> Result<()> {
+        let mut result = Ok(());
+
+        while !self.running.is_empty() {
+            let waited = self.wait_oldest();
+
+            if result.is_ok() {
+                result = waited;
+            }
+        }
+
+        result
+    }
+
+    ///
+    /// 最も古いコマンドの終了待ち
+    ///
+    fn wait_oldest(&mut self) -> Result<()> {
+        let Some((output_path, mut child)) = self.running.pop_front() else {
+            return Ok(());
+        };
+
+        let status = child.wait()
+            .context("cannot wait for the post command")?;
+
+        if status.success() {
+            return Ok(());
+        }
+
+        let message = format!(
+            "post command for {} failed ({})",
+            output_path.display(),
+            status
+        );
+
+        match self.on_failure {
+            PostFailure::Abort => Err(anyhow!(message)),
+            PostFailure::Warn => {
+                eprintln!("warning: {}", message);
+                Ok(())
+            }
+        }
+    }
+}
+
+///
+/// コマンドの生成
+///
+/// # 引数
+/// * `template` - コマンドのテンプレート
+/// * `input_path` - 入力ファイルへのパス
+/// * `output_path` - 出力ファイルへのパス
+///
+/// # 戻り値
+/// テンプレートをシェルを介して実行するコマンドを返す。
+///
+/// # 注記
+/// shでは`{input}`と`{output}`を`"$1"`と`"$2"`に置き換え、パスは位置パラメ
+/// ータとして渡す。パスの内容がシェルに解釈されることはない。
+///
+#[cfg(not(windows))]
+pub(crate) fn command(template: &str, input_path: &Path, output_path: &Path)
+    -> Command
+{
+    let mut shell = Command::new("sh");
+    shell
+        .arg("-c")
+        .arg(expand(template, "\"$1\"", "\"$2\""))
+        .arg("sh")
+        .arg(input_path)
+        .arg(output_path);
+
+    shell
+}
+
+///
+/// コマンドの生成
+///
+/// # 引数
+/// * `template` - コマンドのテンプレート
+/// * `input_path` - 入力ファイルへのパス
+/// * `output_path` - 出力ファイルへのパス
+///
+/// # 戻り値
+/// テンプレートをCMD.EXEを介して実行するコマンドを返す。
+///
+/// # 注記
+/// CMD.EXEには位置パラメータが無いので、`{input}`と`{output}`はクォートし
+/// たパスに置き換える。
+///
+#[cfg(windows)]
+pub(crate) fn command(template: &str, input_path: &Path, output_path: &Path)
+    -> Command
+{
+    use std::os::windows::process::CommandExt;
+
+    // cmd.exeは引数のクォートを独自に解釈するので、そのまま渡す
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").raw_arg(expand(
+        template,
+        &quote(input_path),
+        &quote(output_path),
+    ));
+
+    shell
+}
+
+///
+/// コマンドのテンプレートの展開
+///
+/// # 引数
+/// * `template` - コマンドのテンプレート
+/// * `input` - `{input}`を置き換える文字列
+/// * `output` - `{output}`を置き換える文字列
+///
+/// # 戻り値
+/// 置き換えた文字列を返す。
+///
+/// # 注記
+/// テンプレートを先頭から一度だけ走査するので、置き換えた文字列の中に
+/// `{input}`や`{output}`が含まれていても再度置き換えることはない。
+///
+fn expand(template: &str, input: &str, output: &str) -> String {
+    let mut result = String::new();
+    let mut rest = template;
+
+    while let Some(pos) = rest.find('{') {
+        result.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+
+        if let Some(tail) = rest.strip_prefix("{input}") {
+            result.push_str(input);
+            rest = tail;
+        } else if let Some(tail) = rest.strip_prefix("{output}") {
+            result.push_str(output);
+            rest = tail;
+        } else {
+            result.push('{');
+            rest = &rest[1..];
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+///
+/// パスのCMD.EXE向けのクォート
+///
+/// # 注記
+/// CMD.EXEはクォートの内側でも`%`による環境変数の展開を行うので、`%`の前
+/// 後でクォートを閉じ、`^`でエスケープする。
+///
+#[cfg(windows)]
+fn quote(path: &Path) -> String {
+    format!("\"{}\"", path.to_string_lossy().replace('%', "\"^%\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_does_not_rescan_substituted_text() {
+        let expanded = expand(
+            "cmd {input} {output} {other}",
+            "a{output}b",
+            "c{input}d",
+        );
+
+        assert_eq!(expanded, "cmd a{output}b c{input}d {other}");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn paths_are_not_interpreted_by_shell() {
+        let input_path = Path::new("a{output}$(echo PWNED)`echo X`'\"b.png");
+        let output_path = Path::new("out dir/a;echo PWNED&b.png");
+
+        let output = command("printf '%s\\n' {input} {output}",
+            input_path,
+            output_path,
+        ).output().unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            format!("{}\n{}\n", input_path.display(), output_path.display())
+        );
+    }
+}
@@ -0,0 +1,161 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 処理対象の選別に用いる前処理コマンドの実行をまとめたモジュール
+//!
+
+use std::path::Path;
+use std::process::Stdio;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{Map, Value};
+
+use crate::cmd_args::{LogoScale, Position, Quality, Spacing};
+use crate::post_cmd;
+
+///
+/// 前処理コマンドの出力で指定できるファイルごとの設定を格納する構造体
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct FileOverrides {
+    /// ロゴを配置する場所
+    pub(crate) position: Option<Position>,
+
+    /// 出力画像の大きさに対するロゴの大きさ
+    pub(crate) scale: Option<LogoScale>,
+
+    /// ロゴと画像の端との間の余白
+    pub(crate) margin: Option<Spacing>,
+
+    /// 出力品質
+    pub(crate) quality: Option<Quality>,
+}
+
+/// FileOverridesに対する実装
+impl FileOverrides {
+    ///
+    /// JSONオブジェクトからの変換
+    ///
+    /// # 引数
+    /// * `map` - 前処理コマンドが出力したJSONオブジェクト
+    ///
+    /// # 戻り値
+    /// 変換に成功した場合は設定を`Ok()`でラップして返す。未知のキーや解釈で
+    /// きない値がある場合はエラー情報を`Err()`でラップして返す。
+    ///
+    /// # 注記
+    /// 値はコマンドラインと同じ書式の文字列で指定する(数値も受け付ける)。
+    ///
+    fn from_map(map: &Map<String, Value>) -> Result<Self> {
+        let mut overrides = Self::default();
+
+        for (key, value) in map {
+            match key.as_str() {
+                "position" => overrides.position = Some(parse(key, value)?),
+                "scale" => overrides.scale = Some(parse(key, value)?),
+                "margin" => overrides.margin = Some(parse(key, value)?),
+                "quality" => overrides.quality = Some(parse(key, value)?),
+                _ => return Err(anyhow!("unknown key \"{}\"", key)),
+            }
+        }
+
+        Ok(overrides)
+    }
+}
+
+///
+/// 前処理コマンドを格納する構造体
+///
+pub(crate) struct PreFilter {
+    /// コマンドのテンプレート
+    template: String,
+}
+
+/// PreFilterに対する実装
+impl PreFilter {
+    ///
+    /// オブジェクトの生成
+    ///
+    /// # 引数
+    /// * `template` - コマンドのテンプレート
+    ///
+    pub(crate) fn new(template: &str) -> Self {
+        Self {template: template.to_string()}
+    }
+
+    ///
+    /// 入力ファイル1つ分の選別
+    ///
+    /// # 引数
+    /// * `input_path` - 入力ファイルへのパス
+    /// * `output_path` - 出力ファイルへのパス
+    ///
+    /// # 戻り値
+    /// コマンドが0で終了した場合は、標準出力で指定された設定(出力が空の場合
+    /// は空の設定)を`Ok(Some())`でラップして返す。0以外で終了した場合は
+    /// `Ok(None)`を返す。コマンドを実行できなかった場合や、標準出力をJSON
+    /// オブジェクトとして解釈できない場合はエラー情報を`Err()`でラップして返
+    /// す。
+    ///
+    pub(crate) fn check(&self, input_path: &Path, output_path: &Path)
+        -> Result<Option<FileOverrides>>
+    {
+        let output = post_cmd::command(&self.template, input_path, output_path)
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit())
+            .output()
+            .with_context(|| {
+                format!("cannot run pre filter: {}", self.template)
+            })?;
+
+        if !output.status.success() {
+            return Ok(None);
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        if stdout.trim().is_empty() {
+            return Ok(Some(FileOverrides::default()));
+        }
+
+        serde_json::from_str::<Map<String, Value>>(&stdout)
+            .map_err(|err| anyhow!(err))
+            .and_then(|map| FileOverrides::from_map(&map))
+            .map(Some)
+            .map_err(|err| {
+                anyhow!(
+                    "pre filter output for {} is invalid: {}",
+                    input_path.display(),
+                    err
+                )
+            })
+    }
+}
+
+///
+/// JSONの値の解釈
+///
+/// # 引数
+/// * `key` - キー(エラーの報告に用いる)
+/// * `value` - 解釈する値
+///
+/// # 戻り値
+/// 値を文字列として`FromStr`で解釈した結果を返す。
+///
+fn parse<T>(key: &str, value: &Value) -> Result<T>
+where
+    T: FromStr<Err = String>,
+{
+    let text = match value {
+        Value::String(text) => text.clone(),
+        Value::Number(number) => number.to_string(),
+        _ => return Err(anyhow!("\"{}\" must be a string or a number", key)),
+    };
+
+    text.parse().map_err(|err| anyhow!("\"{}\": {}", key, err))
+}
@@ -0,0 +1,227 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 画像形式毎のデコーダ・エンコーダの登録表をまとめたモジュール
+//!
+//! 専用のデコーダを持つ形式の追加は`DECODERS`に、出力形式の追加は
+//! `ENCODERS`にエントリを追加することで行う。オプショナルな形式はエントリを
+//! フィーチャで切り替える。
+//!
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::Result;
+use image::RgbaImage;
+
+use crate::cmd_args::{Options, OutputFormat};
+use crate::codec;
+use crate::encoder::{
+    AvifImageEncoder, Encoder, JpegEncoder, PngImageEncoder, WebpEncoder
+};
+
+#[cfg(any(feature = "heif", feature = "raw"))]
+use crate::decoder;
+
+///
+/// シグネチャの判定に読み込むファイル先頭のバイト数
+///
+const SIGNATURE_LENGTH: usize = 16;
+
+///
+/// デコード関数の型(第2引数は縮小デコード時に下回ってはならない画像サイズ)
+///
+pub(crate) type DecodeFn = fn(&Path, Option<(u32, u32)>) -> Result<RgbaImage>;
+
+///
+/// 専用のデコーダの登録情報
+///
+pub(crate) struct DecoderEntry {
+    /// 形式名
+    pub(crate) name: &'static str,
+
+    /// 拡張子のリスト(小文字で指定)
+    pub(crate) extensions: &'static [&'static str],
+
+    /// ファイル先頭のシグネチャの判定関数
+    pub(crate) signature: fn(&[u8]) -> bool,
+
+    /// デコード関数
+    pub(crate) decode: DecodeFn,
+
+    /// 画像サイズの読み出し関数
+    pub(crate) read_size: fn(&Path) -> Result<(u32, u32)>,
+
+    /// デコーダ自身が向きの補正を行うか否か
+    pub(crate) oriented: bool,
+
+    /// 縮小しながらのデコードに対応しているか否か
+    pub(crate) scalable: bool,
+}
+
+///
+/// 出力形式のエンコーダの登録情報
+///
+pub(crate) struct EncoderEntry {
+    /// 出力形式
+    pub(crate) format: OutputFormat,
+
+    /// エンコーダの生成関数
+    pub(crate) create: fn(&Options) -> Box<dyn Encoder>,
+}
+
+///
+/// 専用のデコーダの登録表
+///
+/// # 注記
+/// ここに無い形式は`image`クレートの汎用のデコーダで扱う。
+///
+const DECODERS: &[DecoderEntry] = &[
+    DecoderEntry {
+        name: "jpeg",
+        extensions: &["jpg", "jpeg"],
+        signature: |head| head.starts_with(&[0xff, 0xd8, 0xff]),
+        decode: |path, min_size| codec::jpeg().decode(path, min_size),
+        read_size: |path| codec::jpeg().read_size(path),
+        oriented: false,
+        scalable: true,
+    },
+
+    // libheifはデコード時に向きの補正まで行う
+    #[cfg(feature = "heif")]
+    DecoderEntry {
+        name: "heif",
+        extensions: &["heic", "heif"],
+        signature: is_heif_signature,
+        decode: |path, _| decoder::decode_heif(path),
+        read_size: decoder::read_heif_size,
+        oriented: true,
+        scalable: false,
+    },
+
+    // カメラRAWの多くはTIFFベースでシグネチャからは区別できないので拡張子の
+    // みで判定する。imagepipeも現像時に向きの補正を行う
+    #[cfg(feature = "raw")]
+    DecoderEntry {
+        name: "raw",
+        extensions: &["cr2", "nef", "arw"],
+        signature: |_| false,
+        decode: |path, _| decoder::decode_raw(path),
+        read_size: decoder::read_raw_size,
+        oriented: true,
+        scalable: false,
+    },
+];
+
+///
+/// 出力形式のエンコーダの登録表
+///
+const ENCODERS: &[EncoderEntry] = &[
+    EncoderEntry {
+        format: OutputFormat::Jpeg,
+        create: |opts| Box::new(JpegEncoder {
+            progressive: opts.is_progressive(),
+            subsampling: opts.subsampling().pixel_sizes(),
+        }),
+    },
+
+    EncoderEntry {
+        format: OutputFormat::Webp,
+        create: |opts| Box::new(WebpEncoder {
+            quality: opts.webp_quality(),
+            lossless: opts.is_webp_lossless(),
+        }),
+    },
+
+    EncoderEntry {
+        format: OutputFormat::Avif,
+        create: |opts| Box::new(AvifImageEncoder {
+            speed: opts.avif_speed(),
+        }),
+    },
+
+    EncoderEntry {
+        format: OutputFormat::Png,
+        create: |opts| Box::new(PngImageEncoder {
+            compression: opts.png_compression().into(),
+        }),
+    },
+];
+
+///
+/// HEIFファイルのシグネチャの判定
+///
+/// # 引数
+/// * `head` - ファイル先頭のバイト列
+///
+/// # 戻り値
+/// ftypボックスのメジャーブランドがHEIFのものであれば`true`を返す。
+///
+#[cfg(feature = "heif")]
+fn is_heif_signature(head: &[u8]) -> bool {
+    const BRANDS: &[&[u8]] = &[b"heic", b"heix", b"heim", b"heis", b"mif1"];
+
+    head.len() >= 12
+        && &head[4..8] == b"ftyp"
+        && BRANDS.contains(&&head[8..12])
+}
+
+///
+/// 専用のデコーダの検索
+///
+/// # 引数
+/// * `path` - デコード対象のファイルへのパス
+///
+/// # 戻り値
+/// 拡張子またはファイル先頭のシグネチャが一致するデコーダがあればその登録情
+/// 報を返す。無い場合は`None`を返す。
+///
+/// # 注記
+/// 拡張子による判定を優先し、一致しない場合のみファイルを読んでシグネチャで
+/// 判定する。
+///
+pub(crate) fn find_decoder(path: &Path) -> Option<&'static DecoderEntry> {
+    if let Some(entry) = DECODERS.iter()
+        .find(|entry| crate::has_extension(path, entry.extensions))
+    {
+        return Some(entry);
+    }
+
+    let mut head = Vec::with_capacity(SIGNATURE_LENGTH);
+    File::open(path)
+        .ok()?
+        .take(SIGNATURE_LENGTH as u64)
+        .read_to_end(&mut head)
+        .ok()?;
+
+    DECODERS.iter().find(|entry| (entry.signature)(&head))
+}
+
+///
+/// 出力形式に応じたエンコーダの生成
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `format` - 出力形式
+///
+/// # 戻り値
+/// 出力形式に対応したエンコーダを返す。
+///
+/// # 注記
+/// 登録されていない形式(入力と同じ形式での出力で形式が決まらなかった場合を
+/// 含む)はJPEGのエンコーダを返す。
+///
+pub(crate) fn create_encoder(opts: &Options, format: OutputFormat)
+    -> Box<dyn Encoder>
+{
+    let entry = ENCODERS.iter()
+        .find(|entry| entry.format == format)
+        .unwrap_or(&ENCODERS[0]);
+
+    (entry.create)(opts)
+}
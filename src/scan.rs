@@ -0,0 +1,249 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 読み取り専用の走査(カタログ化)処理をまとめたモジュール
+//!
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use image::{ColorType, ImageDecoder, ImageReader};
+use serde::Serialize;
+
+use crate::storage::LocalStorage;
+
+#[cfg(feature = "native-codecs")]
+use std::{any::Any, fs::File, io::BufReader, panic};
+
+#[cfg(feature = "native-codecs")]
+use anyhow::anyhow;
+
+#[cfg(feature = "native-codecs")]
+use mozjpeg::{ColorSpace, Decompress};
+
+///
+/// 1ファイル分の走査結果を格納する構造体
+///
+#[derive(Debug, Serialize)]
+struct ScanEntry {
+    /// ファイルへのパス
+    path: PathBuf,
+
+    /// ファイルサイズ(バイト数)
+    file_size: Option<u64>,
+
+    /// 画像の幅(ピクセル数)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<u32>,
+
+    /// 画像の高さ(ピクセル数)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<u32>,
+
+    /// EXIF Orientationの値
+    #[serde(skip_serializing_if = "Option::is_none")]
+    orientation: Option<u32>,
+
+    /// 色空間
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color_space: Option<&'static str>,
+
+    /// 処理コストの推定値(デコードする画素数をメガピクセル単位で表した値)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    estimated_cost: Option<f64>,
+
+    /// 読み取りに失敗した場合のエラー内容
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+///
+/// 色空間の名称の取得
+///
+/// # 引数
+/// * `color_space` - JPEGファイルの色空間
+///
+/// # 戻り値
+/// 色空間の名称を返す
+///
+#[cfg(feature = "native-codecs")]
+fn color_space_name(color_space: ColorSpace) -> &'static str {
+    match color_space {
+        ColorSpace::JCS_GRAYSCALE => "grayscale",
+        ColorSpace::JCS_RGB => "rgb",
+        ColorSpace::JCS_YCbCr => "ycbcr",
+        ColorSpace::JCS_CMYK => "cmyk",
+        ColorSpace::JCS_YCCK => "ycck",
+        _ => "unknown",
+    }
+}
+
+///
+/// カラータイプの名称の取得
+///
+/// # 引数
+/// * `color_type` - `image`クレートで読み取った画像のカラータイプ
+///
+/// # 戻り値
+/// 色空間の名称を返す
+///
+fn color_type_name(color_type: ColorType) -> &'static str {
+    match color_type {
+        ColorType::L8 | ColorType::La8 => "grayscale",
+        ColorType::L16 | ColorType::La16 => "grayscale16",
+        ColorType::Rgb8 | ColorType::Rgba8 => "rgb",
+        ColorType::Rgb16 | ColorType::Rgba16 => "rgb16",
+        ColorType::Rgb32F | ColorType::Rgba32F => "rgb32f",
+        _ => "unknown",
+    }
+}
+
+///
+/// パニック情報からのメッセージの取り出し
+///
+/// # 引数
+/// * `payload` - パニック時に送出された情報
+///
+/// # 戻り値
+/// メッセージ文字列を返す
+///
+#[cfg(feature = "native-codecs")]
+fn panic_message(payload: &Box<dyn Any + Send>) -> String {
+    if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else {
+        "unknown decode error".to_string()
+    }
+}
+
+///
+/// ヘッダ情報の読み取り
+///
+/// # 引数
+/// * `path` - 読み取り対象のファイルへのパス
+///
+/// # 戻り値
+/// 読み取りに成功した場合は幅、高さ、色空間の名称をタプルにパックし`Ok()`で
+/// ラップして返す。失敗した場合はエラー情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// `native-codecs`フィーチャが無効な場合はJPEGも`image`クレートで読み取るの
+/// で、CMYK(YCCK)の区別は行わない。
+///
+fn read_header(path: &Path) -> Result<(u32, u32, &'static str)> {
+    #[cfg(feature = "native-codecs")]
+    if crate::is_jpeg(path) {
+        // mozjpegはエラーをパニックとして通知するので捕捉してエラーに変換す
+        // る
+        let file = File::open(path)?;
+        let decomp = panic::catch_unwind(|| {
+            Decompress::new_reader(BufReader::new(file))
+        })
+        .map_err(|err| anyhow!("{}", panic_message(&err)))??;
+
+        let (width, height) = decomp.size();
+
+        return Ok((
+            width as u32,
+            height as u32,
+            color_space_name(decomp.color_space())
+        ));
+    }
+
+    let decoder = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()?;
+
+    let (width, height) = decoder.dimensions();
+
+    Ok((width, height, color_type_name(decoder.color_type())))
+}
+
+///
+/// 1ファイル分の走査
+///
+/// # 引数
+/// * `path` - 走査対象のファイルへのパス
+///
+/// # 戻り値
+/// 走査結果を返す。ヘッダの読み取りに失敗した場合はエラー内容を格納した走査
+/// 結果を返す。
+///
+/// # 注記
+/// 画素データのデコードは行わず、ヘッダ情報のみを読み取る。
+///
+fn scan_file<P>(path: P) -> ScanEntry
+where
+    P: AsRef<Path>
+{
+    let path = path.as_ref();
+    let mut entry = ScanEntry {
+        path: path.to_path_buf(),
+        file_size: path.metadata().ok().map(|meta| meta.len()),
+        width: None,
+        height: None,
+        orientation: None,
+        color_space: None,
+        estimated_cost: None,
+        error: None,
+    };
+
+    match read_header(path) {
+        Ok((width, height, color_space)) => {
+            entry.width = Some(width);
+            entry.height = Some(height);
+            entry.color_space = Some(color_space);
+            entry.orientation = Some(
+                crate::decoder::read_exif_orientation(path).unwrap_or(1)
+            );
+            entry.estimated_cost = Some(
+                ((width as u64 * height as u64) as f64 / 1_000_000.0 * 100.0)
+                    .round() / 100.0
+            );
+        }
+
+        Err(err) => entry.error = Some(err.to_string()),
+    }
+
+    entry
+}
+
+///
+/// 走査処理の実行
+///
+/// # 引数
+/// * `inputs` - 走査対象のファイルまたはディレクトリのリスト
+///
+/// # 戻り値
+/// 処理に成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を`Err()`で
+/// ラップして返す。
+///
+/// # 注記
+/// 走査結果はJSON形式で標準出力に出力する。
+///
+pub(crate) fn run(inputs: &[PathBuf]) -> Result<()> {
+    let mut entries = Vec::new();
+
+    for path in inputs {
+        if path.is_file() {
+            entries.push(scan_file(path));
+
+        } else if path.is_dir() {
+            entries.extend(
+                crate::image_files(&LocalStorage, path)?
+                    .into_iter()
+                    .map(scan_file)
+            );
+        }
+    }
+
+    println!("{}", serde_json::to_string_pretty(&entries)?);
+
+    Ok(())
+}
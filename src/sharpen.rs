@@ -0,0 +1,176 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! リサイズ後のシャープ化処理をまとめたモジュール
+//!
+
+use std::fmt::Display;
+
+use clap::ValueEnum;
+use image::Primitive;
+use imageproc::definitions::Clamp;
+use imageproc::filter::gaussian_blur_f32;
+use serde::Deserialize;
+
+use crate::analysis::luma;
+use crate::pixel::{WorkImage, WorkPixel};
+
+///
+/// アンシャープマスクのぼかしの標準偏差(ピクセル数)
+///
+const SIGMA: f32 = 1.0;
+
+///
+/// ロゴのシャープ化を適用する縮小率の上限(元の幅に対する比)
+///
+pub(crate) const LOGO_SHARPEN_RATIO: f32 = 0.5;
+
+///
+/// シャープ化を適用するチャネルの定義
+///
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[clap(rename_all = "lower")]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SharpenMode {
+    /// RGBの各チャネルに個別に適用する
+    Rgb,
+
+    /// 輝度(YCbCrのY)のみに適用し、色差は変えない
+    Luma,
+}
+
+// Displayトレイトの実装
+impl Display for SharpenMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            Self::Rgb => "rgb",
+            Self::Luma => "luma",
+        })
+    }
+}
+
+///
+/// アンシャープマスクによるシャープ化
+///
+/// # 引数
+/// * `image` - 対象の画像データ
+/// * `amount` - 強度(0.0で無効)
+/// * `mode` - 適用するチャネル
+///
+/// # 戻り値
+/// シャープ化した画像データを返す。
+///
+/// # 注記
+/// 輝度のみに適用する場合は、元画像とぼかした画像の輝度差をRGBの各チャネル
+/// に同じ量だけ加える。BT.601の輝度の係数の和は1なので、これは色差を保った
+/// まま輝度のみを強調することと等価になり、エッジに色の縁取りが出ない。
+/// アルファチャネル(最後のチャネル)は変更しない。
+///
+pub(crate) fn unsharp_mask<T>(
+    image: &WorkImage<T>,
+    amount: f32,
+    mode: SharpenMode,
+) -> WorkImage<T>
+where
+    T: WorkPixel,
+{
+    let blurred = gaussian_blur_f32(image, SIGMA);
+    let colors = T::CHANNEL_COUNT as usize - 1;
+    let mut result = image.clone();
+
+    for (dst, low) in result.pixels_mut().zip(blurred.pixels()) {
+        let dst = dst.channels_mut();
+        let low = low.channels();
+        let orig = dst.iter().map(|&v| v.into()).collect::<Vec<f32>>();
+        let low = low.iter().map(|&v| v.into()).collect::<Vec<f32>>();
+
+        if mode == SharpenMode::Luma && colors == 3 {
+            let delta = amount * (luma(&orig) - luma(&low));
+
+            for i in 0..colors {
+                dst[i] = Clamp::clamp((orig[i] + delta).round());
+            }
+        } else {
+            for i in 0..colors {
+                let value = orig[i] + amount * (orig[i] - low[i]);
+                dst[i] = Clamp::clamp(value.round());
+            }
+        }
+    }
+
+    result
+}
+
+///
+/// 縮小したロゴのシャープ化
+///
+/// # 引数
+/// * `logo` - 縮小後のロゴの画像データ
+/// * `amount` - 強度(0.0で無効)
+///
+/// # 戻り値
+/// シャープ化したロゴの画像データを返す。
+///
+/// # 注記
+/// 細い線の輪郭を保つためアルファチャネルも含めて強調する。透明部分の色が
+/// 縁に滲み出さないよう、乗算済みアルファに変換してから処理する。
+///
+pub(crate) fn sharpen_logo<T>(logo: &WorkImage<T>, amount: f32)
+    -> WorkImage<T>
+where
+    T: WorkPixel,
+{
+    let max: f32 = T::Subpixel::DEFAULT_MAX_VALUE.into();
+    let alpha = T::CHANNEL_COUNT as usize - 1;
+
+    /*
+     * 乗算済みアルファへの変換
+     */
+    let mut result = logo.clone();
+
+    for pixel in result.pixels_mut() {
+        let channels = pixel.channels_mut();
+        let ratio = channels[alpha].into() / max;
+
+        for value in channels.iter_mut().take(alpha) {
+            *value = Clamp::clamp(((*value).into() * ratio).round());
+        }
+    }
+
+    /*
+     * 全チャネルへのアンシャープマスクの適用と乗算済みアルファの解除
+     */
+    let blurred = gaussian_blur_f32(&result, SIGMA);
+
+    for (dst, low) in result.pixels_mut().zip(blurred.pixels()) {
+        let dst = dst.channels_mut();
+        let low = low.channels();
+        let values = dst.iter()
+            .zip(low.iter())
+            .map(|(&orig, &low)| {
+                let orig: f32 = orig.into();
+                orig + amount * (orig - low.into())
+            })
+            .collect::<Vec<f32>>();
+
+        let opacity = values[alpha].clamp(0.0, max);
+
+        for i in 0..alpha {
+            let value = if opacity > 0.0 {
+                values[i] / opacity * max
+            } else {
+                0.0
+            };
+
+            dst[i] = Clamp::clamp(value.round());
+        }
+
+        dst[alpha] = Clamp::clamp(opacity.round());
+    }
+
+    result
+}
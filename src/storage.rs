@@ -0,0 +1,115 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 入出力先のストレージを抽象化するモジュール
+//!
+//! 現在の実装はローカルファイルシステムのみ。入力画像のデコード、実行記録
+//! やメタデータの書き出し、デバッグ用の中間画像の出力はこのトレイトを介さ
+//! ずにローカルのパスを直接扱う。
+//!
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use walkdir::WalkDir;
+
+///
+/// ストレージを抽象化するトレイト
+///
+/// # 注記
+/// パスはストレージ毎の名前空間でのキーとして扱う。ローカルファイルシステム
+/// 以外のバックエンドでは、区切り文字'/'で区切ったオブジェクトキー等に読み
+/// 替えて実装すること。
+///
+pub(crate) trait Storage {
+    ///
+    /// ファイルのリストアップ
+    ///
+    /// # 引数
+    /// * `root` - 探査の起点となるフォルダへのパス
+    ///
+    /// # 戻り値
+    /// 起点以下に再帰的に存在するファイルのパスのリストを`Ok()`でラップして返
+    /// す。失敗した場合はエラー情報を`Err()`でラップして返す。
+    ///
+    fn list(&self, root: &Path) -> Result<Vec<PathBuf>>;
+
+    ///
+    /// ファイルの読み込み
+    ///
+    /// # 引数
+    /// * `path` - 読み込むファイルへのパス
+    ///
+    /// # 戻り値
+    /// 読み込みに成功した場合はファイルの内容を`Ok()`でラップして返す。失敗
+    /// した場合はエラー情報を`Err()`でラップして返す。
+    ///
+    fn read(&self, path: &Path) -> Result<Vec<u8>>;
+
+    ///
+    /// ファイルの書き込み
+    ///
+    /// # 引数
+    /// * `path` - 書き込むファイルへのパス
+    /// * `data` - 書き込む内容
+    ///
+    /// # 戻り値
+    /// 書き込みに成功した場合は`Ok(())`を返す。失敗した場合はエラー情報を
+    /// `Err()`でラップして返す。
+    ///
+    /// # 注記
+    /// 既存のファイルは上書きする。書き込み先のフォルダが存在しない場合は作
+    /// 成する。
+    ///
+    fn write(&self, path: &Path, data: &[u8]) -> Result<()>;
+
+    ///
+    /// ファイルの存在確認
+    ///
+    /// # 引数
+    /// * `path` - 確認するファイルへのパス
+    ///
+    /// # 戻り値
+    /// ファイルが存在する場合は`true`を返す。
+    ///
+    fn exists(&self, path: &Path) -> bool;
+}
+
+///
+/// ローカルファイルシステムのストレージ
+///
+pub(crate) struct LocalStorage;
+
+// Storageトレイトの実装
+impl Storage for LocalStorage {
+    fn list(&self, root: &Path) -> Result<Vec<PathBuf>> {
+        Ok(WalkDir::new(root)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.into_path())
+            .collect())
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        Ok(std::fs::read(path)?)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(path, data)?;
+
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+}
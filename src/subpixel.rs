@@ -0,0 +1,147 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! ロゴの小数点以下の精度での配置(再標本化による合成)をまとめたモジュール
+//!
+
+use image::imageops::overlay;
+use image::{Primitive, Rgba};
+use imageproc::definitions::Clamp;
+
+use crate::pixel::{WorkImage, WorkPixel};
+
+///
+/// ロゴの再標本化による重畳
+///
+/// # 引数
+/// * `image` - 重畳先の画像データ
+/// * `logo` - 重畳するロゴ画像
+/// * `origin` - ロゴの左上の座標(小数点以下を含む)
+/// * `size` - 重畳時のロゴの幅と高さ(小数点以下を含む)
+///
+/// # 注記
+/// 重畳先の各画素の中心に対応するロゴ上の位置をバイリニア補間で標本化し、
+/// ロゴの外側は透明として扱う。このためロゴの縁は画素を覆う割合に応じて半
+/// 透明になる。透明部分の色が縁に滲み出さないよう、補間は乗算済みアルファ
+/// で行う。`logo`は`size`に近い大きさにリサイズしておくこと(大きく縮小する
+/// 場合の折り返し歪みには対処しない)。
+///
+pub(crate) fn overlay_resampled<T>(
+    image: &mut WorkImage<T>,
+    logo: &WorkImage<T>,
+    origin: (f32, f32),
+    size: (f32, f32),
+)
+where
+    T: WorkPixel,
+{
+    /*
+     * 重畳先で影響を受ける範囲の決定
+     */
+    let left = origin.0.floor().max(0.0);
+    let top = origin.1.floor().max(0.0);
+    let right = (origin.0 + size.0).ceil().min(image.width() as f32);
+    let bottom = (origin.1 + size.1).ceil().min(image.height() as f32);
+
+    if right <= left || bottom <= top {
+        return;
+    }
+
+    /*
+     * 範囲内の各画素の標本化
+     */
+    let scale = (logo.width() as f32 / size.0, logo.height() as f32 / size.1);
+    let mut patch = WorkImage::<T>::from_pixel(
+        (right - left) as u32,
+        (bottom - top) as u32,
+        T::from_rgba8(Rgba([0, 0, 0, 0]))
+    );
+
+    for (x, y, pixel) in patch.enumerate_pixels_mut() {
+        let u = (left + x as f32 + 0.5 - origin.0) * scale.0 - 0.5;
+        let v = (top + y as f32 + 0.5 - origin.1) * scale.1 - 0.5;
+
+        sample_bilinear(logo, u, v, pixel);
+    }
+
+    overlay(image, &patch, left as i64, top as i64);
+}
+
+///
+/// 乗算済みアルファでのバイリニア補間
+///
+/// # 引数
+/// * `logo` - 標本化するロゴ画像
+/// * `u` - 標本化する位置のX座標(画素の中心が整数になる座標系)
+/// * `v` - 標本化する位置のY座標(同上)
+/// * `pixel` - 結果の格納先
+///
+fn sample_bilinear<T>(logo: &WorkImage<T>, u: f32, v: f32, pixel: &mut T)
+where
+    T: WorkPixel,
+{
+    let max: f32 = T::Subpixel::DEFAULT_MAX_VALUE.into();
+    let count = T::CHANNEL_COUNT as usize;
+    let alpha = count - 1;
+
+    let x0 = u.floor();
+    let y0 = v.floor();
+    let fx = u - x0;
+    let fy = v - y0;
+
+    /*
+     * 近傍4画素の乗算済みアルファでの加重和(ロゴの外側は透明)
+     */
+    let mut sum = [0.0f32; 4];
+
+    for (dx, dy, weight) in [
+        (0, 0, (1.0 - fx) * (1.0 - fy)),
+        (1, 0, fx * (1.0 - fy)),
+        (0, 1, (1.0 - fx) * fy),
+        (1, 1, fx * fy),
+    ] {
+        let x = x0 as i64 + dx;
+        let y = y0 as i64 + dy;
+
+        if weight <= 0.0
+            || x < 0
+            || y < 0
+            || x >= logo.width() as i64
+            || y >= logo.height() as i64
+        {
+            continue;
+        }
+
+        let channels = logo.get_pixel(x as u32, y as u32).channels();
+        let opacity: f32 = channels[alpha].into();
+
+        for i in 0..alpha {
+            let value: f32 = channels[i].into();
+            sum[i] += value * opacity / max * weight;
+        }
+
+        sum[alpha] += opacity * weight;
+    }
+
+    /*
+     * 乗算済みアルファの解除
+     */
+    let channels = pixel.channels_mut();
+    let opacity = sum[alpha].clamp(0.0, max);
+
+    for i in 0..alpha {
+        let value = if opacity > 0.0 {
+            sum[i] / opacity * max
+        } else {
+            0.0
+        };
+
+        channels[i] = Clamp::clamp(value.round());
+    }
+
+    channels[alpha] = Clamp::clamp(opacity.round());
+}
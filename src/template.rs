@@ -0,0 +1,783 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 文字列による透かしのテンプレート変数の展開をまとめたモジュール
+//!
+
+use std::collections::HashMap;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use exif::{Exif, In, Tag, Value};
+
+use crate::datetime::{self, DateTime, TimeZone};
+use crate::metadata;
+use crate::text_table::TextTable;
+
+///
+/// テンプレート変数の定義
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Variable {
+    /// 入力ファイルのファイル名
+    Filename,
+
+    /// 入力ファイルのファイル名(拡張子を除く)
+    Stem,
+
+    /// 処理日(タイムゾーンの指定が無い場合はUTC)
+    Date,
+
+    /// キャプション(キャプションの対応表、無ければ画像の説明)
+    Caption,
+
+    /// 撮影日時
+    ExifDateTime,
+
+    /// 撮影日
+    ExifDate,
+
+    /// カメラのメーカー名と機種名
+    ExifCamera,
+
+    /// レンズの機種名
+    ExifLens,
+
+    /// 画像の説明(キャプション)
+    ExifCaption,
+
+    /// 処理対象全体の中での通し番号(1始まり)
+    Seq,
+
+    /// 処理対象全体のファイル数
+    SeqTotal,
+
+    /// 同じディレクトリの中での通し番号(1始まり)
+    DirSeq,
+
+    /// 同じディレクトリのファイル数
+    DirTotal,
+}
+
+/// Variableに対する実装
+impl Variable {
+    ///
+    /// 変数名からの変換
+    ///
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "filename" => Some(Self::Filename),
+            "stem" => Some(Self::Stem),
+            "date" => Some(Self::Date),
+            "caption" => Some(Self::Caption),
+            "exif.datetime" | "exif.DateTimeOriginal" => {
+                Some(Self::ExifDateTime)
+            }
+
+            "exif.date" => Some(Self::ExifDate),
+            "exif.camera" => Some(Self::ExifCamera),
+            "exif.lens" => Some(Self::ExifLens),
+            "exif.caption" => Some(Self::ExifCaption),
+            "seq" => Some(Self::Seq),
+            "seq_total" => Some(Self::SeqTotal),
+            "dir_seq" => Some(Self::DirSeq),
+            "dir_total" => Some(Self::DirTotal),
+            _ => None,
+        }
+    }
+
+    ///
+    /// 日時を表す変数か否かの判定
+    ///
+    fn is_date(self) -> bool {
+        matches!(self, Self::Date | Self::ExifDateTime | Self::ExifDate)
+    }
+
+    ///
+    /// 番号を表す変数か否かの判定
+    ///
+    fn is_counter(self) -> bool {
+        matches!(
+            self,
+            Self::Seq | Self::SeqTotal | Self::DirSeq | Self::DirTotal
+        )
+    }
+
+    ///
+    /// 日時を表す変数の既定の書式
+    ///
+    fn default_format(self) -> &'static str {
+        match self {
+            Self::ExifDateTime => "%Y-%m-%d %H:%M:%S",
+            _ => "%Y-%m-%d",
+        }
+    }
+
+    ///
+    /// EXIF情報を参照する変数か否かの判定
+    ///
+    fn needs_exif(self) -> bool {
+        matches!(
+            self,
+            Self::Caption | Self::ExifDateTime | Self::ExifDate
+                | Self::ExifCamera | Self::ExifLens | Self::ExifCaption
+        )
+    }
+}
+
+///
+/// テンプレートの構成要素
+///
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    /// そのまま出力する文字列
+    Literal(String),
+
+    /// 展開する変数と日時の書式(指定が無い場合は`None`)
+    Variable(Variable, Option<String>),
+}
+
+///
+/// 処理対象の中でのファイルの番号を格納する構造体
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Sequence {
+    /// 処理対象全体の中での通し番号(1始まり)
+    index: usize,
+
+    /// 処理対象全体のファイル数
+    total: usize,
+
+    /// 同じディレクトリの中での通し番号(1始まり)
+    dir_index: usize,
+
+    /// 同じディレクトリのファイル数
+    dir_total: usize,
+}
+
+// Defaultトレイトの実装
+impl Default for Sequence {
+    fn default() -> Self {
+        Self {index: 1, total: 1, dir_index: 1, dir_total: 1}
+    }
+}
+
+/// Sequenceに対する実装
+impl Sequence {
+    ///
+    /// 処理対象のファイルへの番号の割り当て
+    ///
+    /// # 引数
+    /// * `paths` - 処理する順に並べた入力ファイルへのパスのリスト
+    ///
+    /// # 戻り値
+    /// 入力ファイルへのパスと番号の対応表を返す。
+    ///
+    /// # 注記
+    /// ディレクトリごとの番号は、入力ファイルのパスの親ディレクトリが同じも
+    /// のの中で処理する順に割り当てる。
+    ///
+    pub(crate) fn assign(paths: &[PathBuf]) -> HashMap<PathBuf, Self> {
+        let mut dir_totals = HashMap::new();
+
+        for path in paths {
+            *dir_totals.entry(path.parent()).or_insert(0) += 1;
+        }
+
+        let mut dir_indices = HashMap::new();
+
+        paths.iter()
+            .enumerate()
+            .map(|(index, path)| {
+                let dir_index = dir_indices.entry(path.parent()).or_insert(0);
+                *dir_index += 1;
+
+                (path.clone(), Self {
+                    index: index + 1,
+                    total: paths.len(),
+                    dir_index: *dir_index,
+                    dir_total: dir_totals[&path.parent()],
+                })
+            })
+            .collect()
+    }
+}
+
+///
+/// テンプレート変数を含むか否かの判定
+///
+/// # 引数
+/// * `text` - 透かしとして描画する文字列
+///
+/// # 戻り値
+/// 展開が必要な変数を含む場合は`true`を返す。
+///
+pub(crate) fn has_variables(text: &str) -> bool {
+    parse(text).is_ok_and(|segments| {
+        segments.iter().any(|seg| matches!(seg, Segment::Variable(..)))
+    })
+}
+
+///
+/// テンプレートの書式の確認
+///
+/// # 引数
+/// * `text` - 透かしとして描画する文字列
+///
+/// # 戻り値
+/// 書式に問題が無い場合は`Ok(())`を返す。問題がある場合はその内容を
+/// `Err()`でラップして返す。
+///
+pub(crate) fn check(text: &str) -> Result<(), String> {
+    parse(text).map(|_| ())
+}
+
+///
+/// EXIF情報が無いために変数を展開できないか否かの判定
+///
+/// # 引数
+/// * `text` - 透かしとして描画する文字列
+/// * `input_path` - 入力ファイルへのパス
+///
+/// # 戻り値
+/// EXIF情報を参照する変数を含み、入力ファイルにEXIF情報が無い場合は`true`
+/// を返す。
+///
+/// # 注記
+/// 展開結果が空になった理由を警告で示すためのもので、展開のたびには呼び出
+/// さない。
+///
+pub(crate) fn lacks_exif(text: &str, input_path: &Path) -> bool {
+    let needs_exif = parse(text).is_ok_and(|segments| {
+        segments.iter().any(|seg| {
+            matches!(seg, Segment::Variable(var, _) if var.needs_exif())
+        })
+    });
+
+    needs_exif && metadata::read_exif(input_path).is_none()
+}
+
+///
+/// テンプレート変数の展開
+///
+/// # 引数
+/// * `text` - 透かしとして描画する文字列
+/// * `input_path` - 入力ファイルへのパス
+/// * `captions` - ファイル名とキャプションの対応表(指定が無い場合は`None`)
+/// * `timezone` - 日時を表す変数に用いるタイムゾーン(指定が無い場合は
+///   `None`)
+/// * `sequence` - 処理対象の中での入力ファイルの番号
+///
+/// # 戻り値
+/// 変数を展開した文字列を`Ok()`でラップして返す。書式に問題がある場合はそ
+/// の内容を`Err()`でラップして返す。
+///
+/// # 注記
+/// 入力ファイルにEXIF情報が無い場合や該当するフィールドが無い場合、EXIF
+/// 情報を参照する変数は空文字列に展開する。`{caption}`は対応表に記述が無い
+/// 場合は`{exif.caption}`と同じ値に展開する。撮影日時はEXIF情報に時差
+/// (OffsetTimeOriginal)が記録されている場合のみ`timezone`の地方時に変換し、
+/// 記録されていない場合は記録されたままの時刻を用いる。
+///
+pub(crate) fn expand(
+    text: &str,
+    input_path: &Path,
+    captions: Option<&TextTable>,
+    timezone: Option<&TimeZone>,
+    sequence: Sequence,
+) -> Result<String, String> {
+    let segments = parse(text)?;
+    let needs_exif = segments.iter().any(|seg| {
+        matches!(seg, Segment::Variable(var, _) if var.needs_exif())
+    });
+
+    let exif = if needs_exif {
+        metadata::read_exif(input_path)
+    } else {
+        None
+    };
+
+    let mut result = String::new();
+
+    for segment in segments {
+        match segment {
+            Segment::Literal(literal) => result.push_str(&literal),
+            Segment::Variable(var, format) => {
+                result.push_str(&resolve(
+                    var,
+                    format.as_deref(),
+                    input_path,
+                    captions,
+                    exif.as_ref(),
+                    timezone,
+                    sequence
+                ));
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+///
+/// テンプレートの解析
+///
+/// # 注記
+/// `{名前}`を変数として扱う。日時を表す変数は`{名前:書式}`の形式で書式を、
+/// 番号を表す変数は`{seq:3}`の形式で0で揃える桁数を指定できる。`{{`と`}}`
+/// はそれぞれ`{`と`}`を表す。
+///
+fn parse(text: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+
+                for ch in chars.by_ref() {
+                    if ch == '}' {
+                        closed = true;
+                        break;
+                    }
+
+                    name.push(ch);
+                }
+
+                if !closed {
+                    return Err(format!("unclosed '{{' in \"{}\"", text));
+                }
+
+                let (var_name, format) = match name.split_once(':') {
+                    Some((var_name, format)) => (var_name, Some(format)),
+                    None => (name.as_str(), None),
+                };
+
+                let var = Variable::from_name(var_name.trim()).ok_or_else(|| {
+                    format!("unknown template variable {{{}}}", var_name)
+                })?;
+
+                match format {
+                    Some(format) if var.is_date() => {
+                        datetime::check_format(format)?;
+                    }
+
+                    Some(format) if var.is_counter() => {
+                        let width = format.trim().parse::<u8>();
+
+                        if !matches!(width, Ok(1..=9)) {
+                            return Err(format!(
+                                "invalid number width \"{}\" for {{{}}}",
+                                format,
+                                var_name.trim()
+                            ));
+                        }
+                    }
+
+                    Some(_) => {
+                        return Err(format!(
+                            "template variable {{{}}} does not take a format",
+                            var_name.trim()
+                        ));
+                    }
+
+                    None => {}
+                }
+
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(mem::take(&mut literal)));
+                }
+
+                segments.push(
+                    Segment::Variable(var, format.map(str::to_string))
+                );
+            }
+
+            '}' => {
+                return Err(format!("unmatched '}}' in \"{}\"", text));
+            }
+
+            _ => literal.push(ch),
+        }
+    }
+
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+
+    Ok(segments)
+}
+
+///
+/// 変数の値の取得
+///
+fn resolve(
+    var: Variable,
+    format: Option<&str>,
+    input_path: &Path,
+    captions: Option<&TextTable>,
+    exif: Option<&Exif>,
+    timezone: Option<&TimeZone>,
+    sequence: Sequence,
+) -> String {
+    let field = |tag| exif.and_then(|exif| metadata::field_value(exif, tag));
+
+    match var {
+        Variable::Filename => input_path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+
+        Variable::Stem => input_path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+
+        Variable::Caption => match captions.and_then(|t| t.get(input_path)) {
+            Some(caption) => caption.to_string(),
+            None => resolve(
+                Variable::ExifCaption,
+                None,
+                input_path,
+                None,
+                exif,
+                None,
+                sequence
+            ),
+        },
+
+        Variable::ExifCamera => {
+            let make = field(Tag::Make).unwrap_or_default();
+            let model = field(Tag::Model).unwrap_or_default();
+
+            // 機種名がメーカー名から始まる場合は重複させない
+            if model.starts_with(&make) {
+                model
+            } else {
+                format!("{} {}", make, model).trim().to_string()
+            }
+        }
+
+        Variable::ExifLens => field(Tag::LensModel).unwrap_or_default(),
+
+        Variable::ExifCaption => field(Tag::ImageDescription)
+            .map(|caption| caption.trim().to_string())
+            .unwrap_or_default(),
+
+        Variable::Date | Variable::ExifDateTime | Variable::ExifDate => {
+            resolve_date(var, format, exif, timezone)
+        }
+
+        Variable::Seq | Variable::SeqTotal | Variable::DirSeq
+            | Variable::DirTotal => {
+            resolve_counter(var, format, sequence)
+        }
+    }
+}
+
+///
+/// 番号を表す変数の値の取得
+///
+/// # 引数
+/// * `var` - 番号を表す変数
+/// * `format` - 0で揃える桁数(指定が無い場合は`None`)
+/// * `sequence` - 入力ファイルの番号
+///
+fn resolve_counter(var: Variable, format: Option<&str>, sequence: Sequence)
+    -> String
+{
+    let value = match var {
+        Variable::Seq => sequence.index,
+        Variable::SeqTotal => sequence.total,
+        Variable::DirSeq => sequence.dir_index,
+        _ => sequence.dir_total,
+    };
+
+    let width = format
+        .and_then(|format| format.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+
+    format!("{:0width$}", value, width = width)
+}
+
+///
+/// 日時を表す変数の値の取得
+///
+/// # 引数
+/// * `var` - 日時を表す変数
+/// * `format` - 書式(指定が無い場合は`None`)
+/// * `exif` - EXIF情報
+/// * `timezone` - 変換先のタイムゾーン(指定が無い場合は`None`)
+///
+/// # 戻り値
+/// 書式に従って文字列化した日時を返す。撮影日時が無い場合は空文字列を返
+/// す。
+///
+fn resolve_date(
+    var: Variable,
+    format: Option<&str>,
+    exif: Option<&Exif>,
+    timezone: Option<&TimeZone>,
+) -> String {
+    let datetime = match var {
+        Variable::Date => {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs() as i64);
+
+            Some(match timezone {
+                Some(timezone) => timezone.to_local(now),
+                None => DateTime::from_unix(now),
+            })
+        }
+
+        _ => exif.and_then(|exif| exif_datetime(exif, timezone)),
+    };
+
+    datetime
+        .map(|datetime| {
+            datetime.format(format.unwrap_or(var.default_format()))
+        })
+        .unwrap_or_default()
+}
+
+///
+/// EXIF情報からの撮影日時の取得
+///
+/// # 戻り値
+/// 撮影日時(無ければ更新日時)を`Some()`でラップして返す。時差が記録され
+/// ていて`timezone`の指定がある場合は、その地方時に変換する。日時が無い場
+/// 合は`None`を返す。
+///
+fn exif_datetime(exif: &Exif, timezone: Option<&TimeZone>) -> Option<DateTime> {
+    let ascii = |tag| {
+        exif.get_field(tag, In::PRIMARY).and_then(|field| match &field.value {
+            Value::Ascii(ascii) => ascii.first().cloned(),
+            _ => None,
+        })
+    };
+
+    let (raw, offset_tag) = match ascii(Tag::DateTimeOriginal) {
+        Some(raw) => (raw, Tag::OffsetTimeOriginal),
+        None => (ascii(Tag::DateTime)?, Tag::OffsetTime),
+    };
+
+    let dt = exif::DateTime::from_ascii(&raw).ok()?;
+    let local = DateTime::new(
+        dt.year as i64,
+        dt.month as u32,
+        dt.day as u32,
+        dt.hour as u32,
+        dt.minute as u32,
+        dt.second as u32,
+    );
+
+    let offset = ascii(offset_tag).and_then(|offset| {
+        datetime::parse_offset(&String::from_utf8_lossy(&offset))
+    });
+
+    match (timezone, offset) {
+        (Some(timezone), Some(offset)) => {
+            Some(timezone.to_local(local.to_unix() - offset))
+        }
+
+        _ => Some(local),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_literal_only() {
+        assert_eq!(
+            parse("Hello").unwrap(),
+            vec![Segment::Literal("Hello".to_string())]
+        );
+        assert!(parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_variables() {
+        assert_eq!(
+            parse("© {stem} / { exif.date }").unwrap(),
+            vec![
+                Segment::Literal("© ".to_string()),
+                Segment::Variable(Variable::Stem, None),
+                Segment::Literal(" / ".to_string()),
+                Segment::Variable(Variable::ExifDate, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_escaped_braces() {
+        assert_eq!(
+            parse("{{stem}} {{{filename}}}").unwrap(),
+            vec![
+                Segment::Literal("{stem} {".to_string()),
+                Segment::Variable(Variable::Filename, None),
+                Segment::Literal("}".to_string()),
+            ]
+        );
+        assert!(!has_variables("{{stem}}"));
+    }
+
+    #[test]
+    fn parse_rejects_unclosed_variable() {
+        let err = parse("shot on {exif.date").unwrap_err();
+        assert!(err.starts_with("unclosed '{'"), "{}", err);
+    }
+
+    #[test]
+    fn parse_rejects_unknown_variable() {
+        assert_eq!(
+            parse("{exif.iso}").unwrap_err(),
+            "unknown template variable {exif.iso}"
+        );
+        assert!(check("{}").is_err());
+    }
+
+    #[test]
+    fn parse_rejects_stray_closing_brace() {
+        let err = parse("a } b").unwrap_err();
+        assert!(err.starts_with("unmatched '}'"), "{}", err);
+    }
+
+    #[test]
+    fn parse_date_formats() {
+        assert_eq!(
+            parse("{exif.datetime:%Y年%m月%d日}").unwrap(),
+            vec![Segment::Variable(
+                Variable::ExifDateTime,
+                Some("%Y年%m月%d日".to_string())
+            )]
+        );
+        assert_eq!(
+            parse("{exif.DateTimeOriginal:%H:%M}").unwrap(),
+            vec![Segment::Variable(
+                Variable::ExifDateTime,
+                Some("%H:%M".to_string())
+            )]
+        );
+        assert!(check("{date:%Q}").is_err());
+        assert_eq!(
+            parse("{stem:%Y}").unwrap_err(),
+            "template variable {stem} does not take a format"
+        );
+    }
+
+    #[test]
+    fn parse_counter_widths() {
+        assert_eq!(
+            parse("{seq:3}/{seq_total}").unwrap(),
+            vec![
+                Segment::Variable(Variable::Seq, Some("3".to_string())),
+                Segment::Literal("/".to_string()),
+                Segment::Variable(Variable::SeqTotal, None),
+            ]
+        );
+        assert!(check("{dir_seq:2} of {dir_total}").is_ok());
+        assert!(check("{seq:0}").is_err());
+        assert!(check("{seq:10}").is_err());
+        assert!(check("{seq:%Y}").is_err());
+    }
+
+    #[test]
+    fn assign_sequences() {
+        let paths = [
+            PathBuf::from("a/1.jpg"),
+            PathBuf::from("b/1.jpg"),
+            PathBuf::from("a/2.jpg"),
+        ];
+        let sequences = Sequence::assign(&paths);
+
+        assert_eq!(sequences[&paths[0]], Sequence {
+            index: 1, total: 3, dir_index: 1, dir_total: 2
+        });
+        assert_eq!(sequences[&paths[1]], Sequence {
+            index: 2, total: 3, dir_index: 1, dir_total: 1
+        });
+        assert_eq!(sequences[&paths[2]], Sequence {
+            index: 3, total: 3, dir_index: 2, dir_total: 2
+        });
+    }
+
+    #[test]
+    fn expand_counters() {
+        let seq = Sequence {index: 12, total: 240, dir_index: 3, dir_total: 9};
+        let path = Path::new("IMG_0001.jpg");
+
+        assert_eq!(
+            expand("{seq} / {seq_total}", path, None, None, seq).unwrap(),
+            "12 / 240"
+        );
+        assert_eq!(
+            expand("{seq:3} ({dir_seq:2}/{dir_total})", path, None, None, seq)
+                .unwrap(),
+            "012 (03/9)"
+        );
+        assert_eq!(
+            expand("{seq:1}", path, None, None, seq).unwrap(),
+            "12"
+        );
+    }
+
+    #[test]
+    fn expand_date_with_timezone() {
+        let tz = TimeZone::load("+09:00").unwrap();
+        let seq = Sequence::default();
+        let utc = expand("{date:%F}", Path::new(""), None, None, seq).unwrap();
+        let local = expand("{date:%F}", Path::new(""), None, Some(&tz), seq)
+            .unwrap();
+
+        assert_eq!(utc.len(), 10);
+        assert_eq!(local.len(), 10);
+        assert!(local >= utc);
+    }
+
+    #[test]
+    fn expand_file_variables() {
+        let path = Path::new("photos/IMG_0001.jpg");
+        let seq = Sequence::default();
+
+        assert_eq!(
+            expand("{filename} ({stem})", path, None, None, seq).unwrap(),
+            "IMG_0001.jpg (IMG_0001)"
+        );
+    }
+
+    #[test]
+    fn expand_exif_variables_without_exif() {
+        let path = Path::new("no-such-dir/IMG_0001.jpg");
+        let seq = Sequence::default();
+
+        assert_eq!(
+            expand("{exif.datetime}", path, None, None, seq).unwrap(),
+            ""
+        );
+        assert_eq!(
+            expand("{exif.camera} {exif.lens}", path, None, None, seq).unwrap(),
+            " "
+        );
+        assert!(lacks_exif("{exif.date}", path));
+        assert!(!lacks_exif("{stem}", path));
+    }
+}
@@ -0,0 +1,122 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 文字列による透かし(テキストスタンプ)の描画をまとめたモジュール
+//!
+
+use std::fs;
+use std::path::Path;
+
+use ab_glyph::{Font, FontVec, PxScale, Rect, ScaleFont, point};
+use anyhow::{anyhow, Context, Result};
+use image::{Rgba, RgbaImage};
+
+///
+/// 文字列の描画
+///
+/// # 引数
+/// * `font_path` - フォントファイル(TrueType/OpenType)へのパス
+/// * `text` - 描画する文字列
+/// * `size` - 描画サイズ(ピクセル数)
+/// * `color` - 文字の色
+///
+/// # 戻り値
+/// 処理に成功した場合は、透明な背景に文字列を描画した画像を`Ok()`でラップし
+/// て返す。失敗した場合はエラー情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// 画像の大きさはグリフの外形を包む矩形に合わせる。文字の色は一様とし、グ
+/// リフの被覆率をアルファ値として用いるので、縁が背景色で濁ることはない。
+///
+pub(crate) fn render_text(
+    font_path: &Path,
+    text: &str,
+    size: f32,
+    color: Rgba<u8>,
+) -> Result<RgbaImage> {
+    /*
+     * フォントの読み込み
+     */
+    let data = fs::read(font_path)
+        .with_context(|| format!("cannot read {}", font_path.display()))?;
+    let font = FontVec::try_from_vec(data)
+        .map_err(|_| anyhow!("{} is not a font", font_path.display()))?;
+
+    /*
+     * グリフの配置(1行で左から順に並べる)
+     */
+    let scale = PxScale::from(size);
+    let scaled = font.as_scaled(scale);
+    let mut caret = 0.0;
+    let mut prev = None;
+    let mut outlines = Vec::new();
+
+    for ch in text.chars() {
+        let id = scaled.glyph_id(ch);
+
+        if let Some(prev) = prev {
+            caret += scaled.kern(prev, id);
+        }
+
+        let glyph = id.with_scale_and_position(
+            scale,
+            point(caret, scaled.ascent())
+        );
+
+        if let Some(outline) = font.outline_glyph(glyph) {
+            outlines.push(outline);
+        }
+
+        caret += scaled.h_advance(id);
+        prev = Some(id);
+    }
+
+    /*
+     * 描画範囲の決定
+     */
+    let bounds = outlines.iter()
+        .map(|outline| outline.px_bounds())
+        .reduce(|a, b| {
+            Rect {
+                min: point(a.min.x.min(b.min.x), a.min.y.min(b.min.y)),
+                max: point(a.max.x.max(b.max.x), a.max.y.max(b.max.y)),
+            }
+        })
+        .ok_or_else(|| anyhow!("text \"{}\" has no visible glyphs", text))?;
+
+    let width = (bounds.max.x - bounds.min.x).ceil() as u32;
+    let height = (bounds.max.y - bounds.min.y).ceil() as u32;
+    let mut image = RgbaImage::from_pixel(
+        width.max(1),
+        height.max(1),
+        Rgba([color[0], color[1], color[2], 0])
+    );
+
+    /*
+     * グリフの描画(重なる部分は被覆率の大きい方を採る)
+     */
+    for outline in &outlines {
+        let glyph = outline.px_bounds();
+        let left = (glyph.min.x - bounds.min.x) as i64;
+        let top = (glyph.min.y - bounds.min.y) as i64;
+
+        outline.draw(|x, y, coverage| {
+            let x = left + x as i64;
+            let y = top + y as i64;
+
+            if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+                return;
+            }
+
+            let alpha = (coverage.clamp(0.0, 1.0) * color[3] as f32).round();
+            let pixel = image.get_pixel_mut(x as u32, y as u32);
+            pixel[3] = pixel[3].max(alpha as u8);
+        });
+    }
+
+    Ok(image)
+}
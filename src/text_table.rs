@@ -0,0 +1,238 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! ファイル名と文字列の対応表(CSVファイル)の読み込みをまとめたモジュール
+//!
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+
+///
+/// ファイル名と文字列の対応表を格納する構造体
+///
+/// # 注記
+/// 1列目にファイル名、2列目に文字列を記述したCSVファイルから読み込む。1行目
+/// の1列目が"filename"の場合は見出しの行として読み飛ばす。
+///
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TextTable {
+    /// ファイル名(またはパス)をキーとする文字列の表
+    entries: HashMap<String, String>,
+}
+
+/// TextTableに対する実装
+impl TextTable {
+    ///
+    /// CSVファイルからの読み込み
+    ///
+    /// # 引数
+    /// * `path` - 読み込むCSVファイルへのパス
+    ///
+    /// # 戻り値
+    /// 読み込みに成功した場合は対応表を`Ok()`でラップして返す。失敗した場合
+    /// はエラー情報を`Err()`でラップして返す。
+    ///
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("cannot read {}", path.display()))?;
+
+        Self::from_csv(&text, path)
+    }
+
+    ///
+    /// CSV形式の文字列からの構築
+    ///
+    /// # 引数
+    /// * `text` - CSVファイルの内容
+    /// * `path` - 読み込んだCSVファイルへのパス(エラー表示用)
+    ///
+    /// # 戻り値
+    /// 構築に成功した場合は対応表を`Ok()`でラップして返す。失敗した場合はエ
+    /// ラー情報を`Err()`でラップして返す。
+    ///
+    fn from_csv(text: &str, path: &Path) -> Result<Self> {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let mut entries = HashMap::new();
+
+        for (index, row) in parse(text)?.into_iter().enumerate() {
+            // 空行は読み飛ばす
+            if row.iter().all(|field| field.trim().is_empty()) {
+                continue;
+            }
+
+            // 見出しの行は読み飛ばす
+            if index == 0 && row[0].trim().eq_ignore_ascii_case("filename") {
+                continue;
+            }
+
+            let [name, value, ..] = row.as_slice() else {
+                return Err(anyhow!(
+                    "row {} of {} has no text column",
+                    index + 1,
+                    path.display()
+                ));
+            };
+
+            entries.insert(name.trim().to_string(), value.clone());
+        }
+
+        Ok(Self {entries})
+    }
+
+    ///
+    /// 入力ファイルに対応する文字列の取得
+    ///
+    /// # 引数
+    /// * `input_path` - 入力ファイルへのパス
+    ///
+    /// # 戻り値
+    /// 表に入力ファイルのパスそのもの、またはファイル名が記述されている場合
+    /// は対応する文字列を返す。記述されていない場合は`None`を返す。
+    ///
+    pub(crate) fn get(&self, input_path: &Path) -> Option<&str> {
+        let path = input_path.to_string_lossy();
+        let name = input_path.file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+
+        self.entries.get(path.as_ref())
+            .or_else(|| self.entries.get(name.as_ref()))
+            .map(String::as_str)
+    }
+
+    ///
+    /// 記述されているファイルの数の取得
+    ///
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+///
+/// CSV形式の文字列の解析
+///
+/// # 引数
+/// * `text` - CSV形式の文字列
+///
+/// # 戻り値
+/// 解析に成功した場合は行ごとのフィールドのリストを`Ok()`でラップして返す。
+/// 書式に問題がある場合はエラー情報を`Err()`でラップして返す。
+///
+/// # 注記
+/// ダブルクォートで括ったフィールドはカンマと改行を含むことができ、`""`は
+/// `"`を表す。
+///
+fn parse(text: &str) -> Result<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+
+            _ => field.push(ch),
+        }
+    }
+
+    if quoted {
+        return Err(anyhow!("unclosed '\"' in the last row"));
+    }
+
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|field| field.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_quoted_fields() {
+        assert_eq!(
+            parse("a.jpg,\"Hello, world\"\nb.jpg,\"two\nlines\"\n").unwrap(),
+            vec![
+                row(&["a.jpg", "Hello, world"]),
+                row(&["b.jpg", "two\nlines"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_escaped_quotes() {
+        assert_eq!(
+            parse("a.jpg,\"say \"\"cheese\"\"\"\nb.jpg,\"\"").unwrap(),
+            vec![row(&["a.jpg", "say \"cheese\""]), row(&["b.jpg", ""])]
+        );
+    }
+
+    #[test]
+    fn parse_crlf_line_endings() {
+        assert_eq!(
+            parse("a.jpg,one\r\nb.jpg,\"x\r\ny\"\r\n").unwrap(),
+            vec![row(&["a.jpg", "one"]), row(&["b.jpg", "x\r\ny"])]
+        );
+    }
+
+    #[test]
+    fn parse_rejects_unclosed_quote() {
+        assert!(parse("a.jpg,\"open\nb.jpg,text\n").is_err());
+    }
+
+    #[test]
+    fn from_csv_skips_bom_and_header() {
+        let table = TextTable::from_csv(
+            "\u{feff}filename,caption\r\na.jpg,Alpha\r\n\r\nb.jpg,Beta\r\n",
+            Path::new("captions.csv")
+        ).unwrap();
+
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.get(Path::new("photos/a.jpg")), Some("Alpha"));
+        assert_eq!(table.get(Path::new("b.jpg")), Some("Beta"));
+        assert_eq!(table.get(Path::new("filename")), None);
+    }
+
+    #[test]
+    fn from_csv_rejects_missing_text_column() {
+        let err = TextTable::from_csv(
+            "a.jpg,Alpha\nb.jpg\n",
+            Path::new("captions.csv")
+        ).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "row 2 of captions.csv has no text column"
+        );
+    }
+}
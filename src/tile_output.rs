@@ -0,0 +1,188 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! 大きな出力画像のタイルへの分割をまとめたモジュール
+//!
+
+use std::path::{Path, PathBuf};
+
+///
+/// タイル1枚分の範囲を格納する構造体
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TileRect {
+    /// 行番号(0始まり)
+    pub(crate) row: u32,
+
+    /// 列番号(0始まり)
+    pub(crate) col: u32,
+
+    /// 左端のX座標(重なり部分を含む)
+    pub(crate) x: u32,
+
+    /// 上端のY座標(重なり部分を含む)
+    pub(crate) y: u32,
+
+    /// 幅(重なり部分を含む)
+    pub(crate) width: u32,
+
+    /// 高さ(重なり部分を含む)
+    pub(crate) height: u32,
+}
+
+/// TileRectに対する実装
+impl TileRect {
+    ///
+    /// タイルの出力先の決定
+    ///
+    /// # 引数
+    /// * `output_path` - 分割しない場合の出力ファイルへのパス
+    ///
+    /// # 戻り値
+    /// 出力ファイル名の末尾に"_行_列"を付けたパスを返す。
+    ///
+    pub(crate) fn path(&self, output_path: &Path) -> PathBuf {
+        tile_path(output_path, self.row, self.col)
+    }
+}
+
+///
+/// タイルの出力先の決定
+///
+/// # 引数
+/// * `output_path` - 分割しない場合の出力ファイルへのパス
+/// * `row` - 行番号
+/// * `col` - 列番号
+///
+/// # 戻り値
+/// 出力ファイル名の末尾に"_行_列"を付けたパスを返す。
+///
+pub(crate) fn tile_path(output_path: &Path, row: u32, col: u32) -> PathBuf {
+    let stem = output_path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match output_path.extension() {
+        Some(ext) => {
+            format!("{}_{}_{}.{}", stem, row, col, ext.to_string_lossy())
+        }
+
+        None => format!("{}_{}_{}", stem, row, col),
+    };
+
+    output_path.with_file_name(name)
+}
+
+///
+/// タイルの格子の算出
+///
+/// # 引数
+/// * `(width, height)` - 出力画像の幅と高さ
+/// * `tile` - タイルの一辺の長さ(重なり部分を除くピクセル数)
+/// * `overlap` - 隣のタイルと重ねるピクセル数
+///
+/// # 戻り値
+/// 行優先の順に並べたタイルの範囲のリストを返す。画像が1枚のタイルに収まる
+/// 場合は画像全体を表す1件のみを返す。
+///
+/// # 注記
+/// Deep Zoom形式と同様に、各タイルは隣のタイルがある辺の側にのみ`overlap`
+/// だけ広げる(画像の端の側には広げない)。重なり部分の画素は隣り合うタイル
+/// で同じ内容になる。
+///
+pub(crate) fn grid((width, height): (u32, u32), tile: u32, overlap: u32)
+    -> Vec<TileRect>
+{
+    let tile = tile.max(1);
+    let cols = width.div_ceil(tile).max(1);
+    let rows = height.div_ceil(tile).max(1);
+    let mut tiles = Vec::new();
+
+    for row in 0..rows {
+        for col in 0..cols {
+            let left = (col * tile).saturating_sub(overlap);
+            let top = (row * tile).saturating_sub(overlap);
+            let right = (col + 1).saturating_mul(tile)
+                .saturating_add(overlap)
+                .min(width);
+            let bottom = (row + 1).saturating_mul(tile)
+                .saturating_add(overlap)
+                .min(height);
+
+            tiles.push(TileRect {
+                row,
+                col,
+                x: left,
+                y: top,
+                width: right - left,
+                height: bottom - top,
+            });
+        }
+    }
+
+    tiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(row: u32, col: u32, x: u32, y: u32, width: u32, height: u32)
+        -> TileRect
+    {
+        TileRect {row, col, x, y, width, height}
+    }
+
+    #[test]
+    fn grid_single_tile() {
+        assert_eq!(grid((200, 100), 256, 1), vec![rect(0, 0, 0, 0, 200, 100)]);
+        assert_eq!(grid((256, 256), 256, 1), vec![rect(0, 0, 0, 0, 256, 256)]);
+    }
+
+    #[test]
+    fn grid_overlap_only_inside() {
+        assert_eq!(
+            grid((512, 256), 256, 2),
+            vec![
+                rect(0, 0, 0, 0, 258, 256),
+                rect(0, 1, 254, 0, 258, 256),
+            ]
+        );
+    }
+
+    #[test]
+    fn grid_non_multiple_size() {
+        let tiles = grid((600, 300), 256, 1);
+
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles[0], rect(0, 0, 0, 0, 257, 257));
+        assert_eq!(tiles[1], rect(0, 1, 255, 0, 258, 257));
+        assert_eq!(tiles[2], rect(0, 2, 511, 0, 89, 257));
+        assert_eq!(tiles[5], rect(1, 2, 511, 255, 89, 45));
+
+        // 各行・各列が画像全体を覆い、端の外に出ないこと
+        for tile in &tiles {
+            assert!(tile.x + tile.width <= 600);
+            assert!(tile.y + tile.height <= 300);
+        }
+    }
+
+    #[test]
+    fn grid_without_overlap() {
+        let tiles = grid((300, 10), 100, 0);
+
+        assert_eq!(
+            tiles.iter().map(|t| (t.x, t.width)).collect::<Vec<_>>(),
+            vec![(0, 100), (100, 100), (200, 100)]
+        );
+    }
+
+    #[test]
+    fn tile_path_appends_row_and_col() {
+        assert_eq!(
+            rect(1, 2, 0, 0, 1, 1).path(Path::new("out/photo.jpg")),
+            Path::new("out/photo_1_2.jpg")
+        );
+    }
+}
@@ -0,0 +1,238 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! ロゴの回転と敷き詰めによる合成処理をまとめたモジュール
+//!
+
+use image::imageops::{crop_imm, overlay};
+use image::{Primitive, Rgba};
+use imageproc::definitions::Clamp;
+use imageproc::geometric_transformations::{rotate_about_center, Interpolation};
+
+use crate::pixel::{WorkImage, WorkPixel};
+
+///
+/// 回転後の外接矩形の大きさの算出
+///
+/// # 引数
+/// * `(width, height)` - 回転前の幅と高さ
+/// * `degrees` - 回転角(度)
+///
+/// # 戻り値
+/// 回転した矩形に外接する矩形の幅と高さ(小数点以下を含む)を返す。
+///
+pub(crate) fn rotated_exact_size((width, height): (f32, f32), degrees: f32)
+    -> (f32, f32)
+{
+    let (sin, cos) = degrees.to_radians().sin_cos();
+
+    (
+        width * cos.abs() + height * sin.abs(),
+        width * sin.abs() + height * cos.abs()
+    )
+}
+
+///
+/// 回転後の外接矩形の大きさの算出(ピクセル数)
+///
+/// # 引数
+/// * `size` - 回転前の幅と高さ
+/// * `degrees` - 回転角(度)
+///
+/// # 戻り値
+/// `rotate_expanded()`が返す画像の幅と高さを返す。
+///
+pub(crate) fn rotated_size(size: (u32, u32), degrees: f32) -> (u32, u32) {
+    let (width, height) =
+        rotated_exact_size((size.0 as f32, size.1 as f32), degrees);
+
+    /*
+     * 直角の回転で浮動小数点の誤差により1ピクセル広がらないよう丸めてから
+     * 切り上げる
+     */
+    (
+        ((width * 1000.0).round() / 1000.0).ceil() as u32,
+        ((height * 1000.0).round() / 1000.0).ceil() as u32
+    )
+}
+
+///
+/// 画像の回転
+///
+/// # 引数
+/// * `image` - 回転する画像データ
+/// * `degrees` - 回転角(度、反時計回り)
+///
+/// # 戻り値
+/// 回転後の画像データを返す。回転後の画像が欠けないように、回転した矩形に
+/// 外接する大きさに広げ、広げた部分は透明にする。
+///
+/// # 注記
+/// 透明部分の色が縁に滲み出さないよう、補間は乗算済みアルファで行う。
+///
+pub(crate) fn rotate_expanded<T>(image: &WorkImage<T>, degrees: f32)
+    -> WorkImage<T>
+where
+    T: WorkPixel,
+{
+    let (width, height) = image.dimensions();
+    let transparent = T::from_rgba8(Rgba([0, 0, 0, 0]));
+
+    /*
+     * 回転後の外接矩形の大きさのキャンバスの中央に配置
+     */
+    let (outer_width, outer_height) = rotated_size((width, height), degrees);
+
+    let canvas_width = outer_width.max(width);
+    let canvas_height = outer_height.max(height);
+    let mut canvas = WorkImage::<T>::from_pixel(
+        canvas_width,
+        canvas_height,
+        transparent
+    );
+
+    overlay(
+        &mut canvas,
+        image,
+        ((canvas_width - width) / 2) as i64,
+        ((canvas_height - height) / 2) as i64
+    );
+
+    premultiply(&mut canvas);
+
+    /*
+     * 中心を軸に回転(画像の座標系はY軸が下向きなので符号を反転する)
+     */
+    let rotated = rotate_about_center(
+        &canvas,
+        -degrees.to_radians(),
+        Interpolation::Bilinear,
+        transparent
+    );
+
+    // 元の画像の大きさに合わせて広げすぎた分を切り詰める
+    let mut cropped = crop_imm(
+        &rotated,
+        (canvas_width - outer_width) / 2,
+        (canvas_height - outer_height) / 2,
+        outer_width,
+        outer_height
+    ).to_image();
+
+    unpremultiply(&mut cropped);
+    cropped
+}
+
+///
+/// 乗算済みアルファへの変換
+///
+/// # 引数
+/// * `image` - 変換する画像データ(その場で書き換える)
+///
+fn premultiply<T>(image: &mut WorkImage<T>)
+where
+    T: WorkPixel,
+{
+    let max: f32 = T::Subpixel::DEFAULT_MAX_VALUE.into();
+    let alpha = T::CHANNEL_COUNT as usize - 1;
+
+    for pixel in image.pixels_mut() {
+        let channels = pixel.channels_mut();
+        let opacity: f32 = channels[alpha].into();
+
+        for value in &mut channels[..alpha] {
+            let v: f32 = (*value).into();
+            *value = Clamp::clamp((v * opacity / max).round());
+        }
+    }
+}
+
+///
+/// 乗算済みアルファの解除
+///
+/// # 引数
+/// * `image` - 変換する画像データ(その場で書き換える)
+///
+fn unpremultiply<T>(image: &mut WorkImage<T>)
+where
+    T: WorkPixel,
+{
+    let max: f32 = T::Subpixel::DEFAULT_MAX_VALUE.into();
+    let alpha = T::CHANNEL_COUNT as usize - 1;
+
+    for pixel in image.pixels_mut() {
+        let channels = pixel.channels_mut();
+        let opacity: f32 = channels[alpha].into();
+
+        if opacity <= 0.0 {
+            continue;
+        }
+
+        for value in &mut channels[..alpha] {
+            let v: f32 = (*value).into();
+            *value = Clamp::clamp((v * max / opacity).round());
+        }
+    }
+}
+
+///
+/// ロゴの斜めの敷き詰め
+///
+/// # 引数
+/// * `image` - 敷き詰める先の画像データ
+/// * `logo` - 敷き詰めるロゴ画像
+/// * `degrees` - 並びの角度(度、反時計回り)
+/// * `gap` - ロゴ同士の水平方向と垂直方向の間隔(ピクセル数)
+///
+/// # 注記
+/// ロゴの並びを格子状に配置し、格子全体を中心を軸に回転させる。個々のロゴも
+/// 同じ角度で回転させるので、ロゴは並びの方向に沿って傾く。
+///
+pub(crate) fn overlay_tiled<T>(
+    image: &mut WorkImage<T>,
+    logo: &WorkImage<T>,
+    degrees: f32,
+    gap: (u32, u32),
+)
+where
+    T: WorkPixel,
+{
+    let rotated = rotate_expanded(logo, degrees);
+    let (sin, cos) = degrees.to_radians().sin_cos();
+
+    /*
+     * 格子の基底ベクトルの算出(画像の座標系に合わせてY成分を反転する)
+     */
+    let step_x = (logo.width() + gap.0) as f32;
+    let step_y = (logo.height() + gap.1) as f32;
+    let u = (cos * step_x, -sin * step_x);
+    let v = (sin * step_y, cos * step_y);
+
+    /*
+     * 画像全体を覆う範囲の格子点にロゴを配置
+     */
+    let center = (image.width() as f32 / 2.0, image.height() as f32 / 2.0);
+    let reach = (center.0.hypot(center.1)
+        + rotated.width().max(rotated.height()) as f32)
+        .ceil();
+    let cols = (reach / step_x).ceil() as i64;
+    let rows = (reach / step_y).ceil() as i64;
+
+    for row in -rows..=rows {
+        for col in -cols..=cols {
+            let x = center.0 + u.0 * col as f32 + v.0 * row as f32;
+            let y = center.1 + u.1 * col as f32 + v.1 * row as f32;
+
+            overlay(
+                image,
+                &rotated,
+                (x - rotated.width() as f32 / 2.0).round() as i64,
+                (y - rotated.height() as f32 / 2.0).round() as i64
+            );
+        }
+    }
+}
@@ -0,0 +1,361 @@
+/*
+ * Watermarker
+ *
+ *  Copyright (C) 2025 Hiroshi KUWAGATA <kgt9221@gmail.com>
+ */
+
+//!
+//! Web向けの出力一式(縮小版の画像とJSON形式のメタデータ)の生成をまとめた
+//! モジュール
+//!
+
+use std::f32::consts::PI;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use image::{DynamicImage, RgbaImage};
+use serde::Serialize;
+
+use crate::cmd_args::Options;
+use crate::context::ProcessingContext;
+use crate::palette;
+use crate::pixel::{linear_to_srgb, srgb_to_linear, OutputImage};
+
+///
+/// 縮小版の幅の既定値(ピクセル数)
+///
+pub(crate) const DEFAULT_WIDTHS: &[u32] = &[640, 1280];
+
+///
+/// BlurHashの水平方向の成分数
+///
+const BLURHASH_X: usize = 4;
+
+///
+/// BlurHashの垂直方向の成分数
+///
+const BLURHASH_Y: usize = 3;
+
+///
+/// BlurHashで用いる83進数の文字
+///
+const BASE83: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ\
+    abcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+///
+/// srcsetの1エントリ
+///
+#[derive(Debug, Serialize)]
+struct SrcsetEntry {
+    /// 画像ファイルの名前(メタデータファイルからの相対パス)
+    src: String,
+
+    /// 幅(ピクセル数)
+    width: u32,
+
+    /// 高さ(ピクセル数)
+    height: u32,
+}
+
+///
+/// メタデータファイルの内容
+///
+#[derive(Debug, Serialize)]
+struct BundleMetadata {
+    /// 出力画像のファイル名
+    src: String,
+
+    /// 出力画像の幅(ピクセル数)
+    width: u32,
+
+    /// 出力画像の高さ(ピクセル数)
+    height: u32,
+
+    /// 支配的な色("#RRGGBB"形式)
+    dominant_color: String,
+
+    /// 画素数の多い順に並べたパレット("#RRGGBB"形式、抽出の指定がある場合
+    /// のみ)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    palette: Vec<String>,
+
+    /// 代替テキスト(指定がある場合のみ)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alt: Option<String>,
+
+    /// プレースホルダ用のBlurHash
+    blurhash: String,
+
+    /// 幅の小さい順に並べたsrcsetのエントリ(出力画像自身を含む)
+    srcset: Vec<SrcsetEntry>,
+}
+
+///
+/// Web向けの出力一式の生成
+///
+/// # 引数
+/// * `opts` - オプション情報をパックしたオブジェクト
+/// * `ctx` - 処理中のコンテキスト(出力先・出力形式・品質・パレット・代替
+///   テキストを参照する)
+/// * `image` - 合成後の出力画像
+///
+/// # 戻り値
+/// 処理に成功した場合は、縮小版の画像とメタデータファイルの出力先とデータの
+/// リストを`Ok()`でラップして返す。失敗した場合はエラー情報を`Err()`でラッ
+/// プして返す。
+///
+/// # 注記
+/// 縮小版は指定された幅のうち出力画像より小さいものについてのみ生成し、
+/// "<出力ファイル名>-<幅>w.<拡張子>"に書き出す。メタデータは出力ファイルの
+/// 拡張子を".json"に替えたパスに書き出す。
+///
+pub(crate) fn build(
+    opts: &Arc<Options>,
+    ctx: &ProcessingContext,
+    image: &OutputImage,
+) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+    let (width, height) = image.dimensions();
+    let mut files = Vec::new();
+    let mut srcset = Vec::new();
+
+    /*
+     * 縮小版の生成
+     */
+    let mut widths = opts.web_widths()
+        .into_iter()
+        .filter(|w| *w > 0 && *w < width)
+        .collect::<Vec<_>>();
+
+    widths.sort_unstable();
+    widths.dedup();
+
+    for w in widths {
+        let h = ((height as f32 * w as f32 / width as f32).round() as u32)
+            .max(1);
+        let path = variant_path(&ctx.output_path, w);
+        let mut buf = Vec::new();
+
+        crate::encode_image(
+            opts,
+            ctx.output_format,
+            &mut buf,
+            resize_output(image, w, h)?,
+            ctx.quality
+        )?;
+
+        srcset.push(SrcsetEntry {src: file_name(&path), width: w, height: h});
+        files.push((path, buf));
+    }
+
+    srcset.push(SrcsetEntry {
+        src: file_name(&ctx.output_path),
+        width,
+        height,
+    });
+
+    /*
+     * 色の解析とメタデータの生成
+     */
+    let thumbnail = palette::thumbnail(image);
+
+    let dominant = match ctx.palette.first() {
+        Some(color) => *color,
+        None => palette::extract(&thumbnail)
+            .first()
+            .copied()
+            .unwrap_or_default(),
+    };
+
+    let metadata = BundleMetadata {
+        src: file_name(&ctx.output_path),
+        width,
+        height,
+        dominant_color: palette::to_hex(&dominant),
+        palette: ctx.palette.iter().map(palette::to_hex).collect(),
+        alt: ctx.alt_text.clone(),
+        blurhash: blurhash(&thumbnail),
+        srcset,
+    };
+
+    files.push((
+        ctx.output_path.with_extension("json"),
+        serde_json::to_vec_pretty(&metadata)?
+    ));
+
+    Ok(files)
+}
+
+///
+/// 縮小版の出力先の決定
+///
+/// # 引数
+/// * `output_path` - 出力ファイルへのパス
+/// * `width` - 縮小版の幅
+///
+/// # 戻り値
+/// 出力ファイル名の末尾に"-<幅>w"を付けたパスを返す。
+///
+fn variant_path(output_path: &Path, width: u32) -> PathBuf {
+    let stem = output_path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match output_path.extension() {
+        Some(ext) => format!("{}-{}w.{}", stem, width, ext.to_string_lossy()),
+        None => format!("{}-{}w", stem, width),
+    };
+
+    output_path.with_file_name(name)
+}
+
+///
+/// パスのファイル名部分の取得
+///
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+///
+/// 出力画像の縮小
+///
+/// # 引数
+/// * `image` - 合成後の出力画像
+/// * `width` - 縮小後の幅
+/// * `height` - 縮小後の高さ
+///
+/// # 戻り値
+/// 処理に成功した場合は縮小した画像を`Ok()`でラップして返す。失敗した場合は
+/// エラー情報を`Err()`でラップして返す。
+///
+fn resize_output(image: &OutputImage, width: u32, height: u32)
+    -> Result<OutputImage>
+{
+    Ok(match image {
+        OutputImage::Rgba(image) => OutputImage::Rgba(
+            crate::resize_image(width, height, image.clone())?
+        ),
+
+        OutputImage::Gray(image) => {
+            let image = DynamicImage::ImageLuma8(image.clone())
+                .to_luma_alpha8();
+            let image = crate::resize_image(width, height, image)?;
+
+            OutputImage::Gray(DynamicImage::ImageLumaA8(image).to_luma8())
+        }
+    })
+}
+
+///
+/// BlurHashの算出
+///
+/// # 引数
+/// * `image` - 解析する画像(縮小済みのもの)
+///
+/// # 戻り値
+/// 水平4×垂直3成分のBlurHash文字列を返す。
+///
+fn blurhash(image: &RgbaImage) -> String {
+    let (width, height) = image.dimensions();
+    let linear = image.pixels()
+        .map(|pixel| pixel.0.map(|v| srgb_to_linear(v as f32 / 255.0)))
+        .collect::<Vec<_>>();
+
+    /*
+     * 各成分の係数の算出
+     */
+    let mut factors = Vec::with_capacity(BLURHASH_X * BLURHASH_Y);
+
+    for j in 0..BLURHASH_Y {
+        for i in 0..BLURHASH_X {
+            let norm = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+            let mut sum = [0.0f32; 3];
+
+            for y in 0..height {
+                let basis_y = (PI * j as f32 * y as f32 / height as f32).cos();
+
+                for x in 0..width {
+                    let basis = basis_y
+                        * (PI * i as f32 * x as f32 / width as f32).cos();
+                    let pixel = &linear[(y * width + x) as usize];
+
+                    for c in 0..3 {
+                        sum[c] += basis * pixel[c];
+                    }
+                }
+            }
+
+            let scale = norm / (width * height) as f32;
+            factors.push(sum.map(|v| v * scale));
+        }
+    }
+
+    /*
+     * 83進数での符号化
+     */
+    let mut hash = String::new();
+    let size_flag = (BLURHASH_X - 1) + (BLURHASH_Y - 1) * 9;
+    encode83(&mut hash, size_flag as u32, 1);
+
+    let max_ac = factors[1..].iter()
+        .flat_map(|factor| factor.iter())
+        .fold(0.0f32, |max, v| max.max(v.abs()));
+    let quantised = ((max_ac * 166.0 - 0.5).floor()).clamp(0.0, 82.0) as u32;
+    let max_value = (quantised + 1) as f32 / 166.0;
+    encode83(&mut hash, quantised, 1);
+
+    let dc = factors[0].map(|v| {
+        (linear_to_srgb(v.clamp(0.0, 1.0)) * 255.0 + 0.5).floor() as u32
+    });
+    encode83(&mut hash, (dc[0] << 16) | (dc[1] << 8) | dc[2], 4);
+
+    for factor in &factors[1..] {
+        let [r, g, b] = factor.map(|v| {
+            let v = v / max_value;
+            let v = v.signum() * v.abs().sqrt();
+
+            (v * 9.0 + 9.5).floor().clamp(0.0, 18.0) as u32
+        });
+
+        encode83(&mut hash, r * 19 * 19 + g * 19 + b, 2);
+    }
+
+    hash
+}
+
+///
+/// 83進数での符号化
+///
+/// # 引数
+/// * `hash` - 符号化結果の追加先
+/// * `value` - 符号化する値
+/// * `length` - 桁数
+///
+fn encode83(hash: &mut String, value: u32, length: u32) {
+    for digit in 1..=length {
+        let index = value / 83u32.pow(length - digit) % 83;
+        hash.push(BASE83[index as usize] as char);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn blurhash_of_solid_images() {
+        let white = RgbaImage::from_pixel(8, 8, Rgba([255, 255, 255, 255]));
+        let blue = RgbaImage::from_pixel(8, 8, Rgba([40, 90, 200, 255]));
+
+        assert_eq!(blurhash(&white), "LfTSUA~qfQ~q~qt7fQt7fQfQfQfQ");
+        assert_eq!(blurhash(&blue), "LN4p@pp3fQp3p3j^fQj^fQfQfQfQ");
+    }
+
+    #[test]
+    fn blurhash_of_gradient() {
+        let image = RgbaImage::from_fn(16, 8, |x, y| {
+            Rgba([(x * 17) as u8, (y * 36) as u8, 128, 255])
+        });
+
+        assert_eq!(blurhash(&image), "L$Hx+i2?wxoyuvR-jtjIf7fQfQfQ");
+    }
+}